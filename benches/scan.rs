@@ -0,0 +1,111 @@
+//! Walker benchmarks on a synthetic tree
+//!
+//! The tree is built once under the system temp dir and reused across runs.
+//! Set `TIDYMAC_BENCH_FILES` to change its size (default: one million files):
+//!
+//! ```sh
+//! TIDYMAC_BENCH_FILES=100000 cargo bench --bench scan
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use tidymac::common::cancel::CancelToken;
+use tidymac::common::exclusions::ExclusionMatcher;
+use tidymac::scanner::aggregate::DEFAULT_TOP_FILES;
+use tidymac::scanner::events::EventSink;
+use tidymac::scanner::limits::ScanBudget;
+use tidymac::scanner::targets::{Category, SafetyLevel, ScanTarget};
+use tidymac::scanner::walker;
+
+const DEFAULT_FILES: usize = 1_000_000;
+const FILES_PER_DIR: usize = 500;
+const DIRS_PER_DIR: usize = 20;
+
+fn file_count() -> usize {
+    std::env::var("TIDYMAC_BENCH_FILES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_FILES)
+}
+
+/// Build (or reuse) a tree of `files` small files spread over nested dirs
+fn synthetic_tree(files: usize) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("tidymac-bench-{}", files));
+    let marker = root.join(".complete");
+    if marker.exists() {
+        return root;
+    }
+    let _ = fs::remove_dir_all(&root);
+
+    let dirs = files.div_ceil(FILES_PER_DIR);
+    for d in 0..dirs {
+        let dir = root
+            .join(format!("d{}", d / (DIRS_PER_DIR * DIRS_PER_DIR)))
+            .join(format!("d{}", (d / DIRS_PER_DIR) % DIRS_PER_DIR))
+            .join(format!("d{}", d % DIRS_PER_DIR));
+        fs::create_dir_all(&dir).unwrap();
+        let start = d * FILES_PER_DIR;
+        for i in start..(start + FILES_PER_DIR).min(files) {
+            // Vary sizes so the top-K heap has real work to do
+            write_file(&dir.join(format!("f{}.cache", i)), i % 4096);
+        }
+    }
+    fs::write(&marker, b"").unwrap();
+    root
+}
+
+fn write_file(path: &Path, len: usize) {
+    fs::write(path, vec![0u8; len]).unwrap();
+}
+
+fn target(root: &Path) -> ScanTarget {
+    ScanTarget {
+        name: "Synthetic".to_string(),
+        category: Category::UserCache,
+        paths: vec![root.display().to_string()],
+        safety: SafetyLevel::Safe,
+        recursive: true,
+        min_age_days: None,
+        reason: "benchmark".to_string(),
+        trim: None,
+    }
+}
+
+fn bench_walk(c: &mut Criterion) {
+    let files = file_count();
+    let target = target(&synthetic_tree(files));
+    let excludes = ExclusionMatcher::empty();
+    let events = EventSink::none();
+    let cancel = CancelToken::new();
+
+    let mut group = c.benchmark_group(format!("walk_{}_files", files));
+    group.sample_size(10);
+    group.bench_function("full_file_list", |b| {
+        b.iter(|| {
+            let budget = ScanBudget::unlimited();
+            walker::walk_target_streaming(&target, &excludes, &events, &cancel, &budget, None)
+                .unwrap()
+        })
+    });
+    group.bench_function("top_files", |b| {
+        b.iter(|| {
+            let budget = ScanBudget::unlimited();
+            walker::walk_target_streaming(
+                &target,
+                &excludes,
+                &events,
+                &cancel,
+                &budget,
+                Some(DEFAULT_TOP_FILES),
+            )
+            .unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_walk);
+criterion_main!(benches);
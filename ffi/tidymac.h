@@ -10,10 +10,32 @@
 // Memory management - caller must free all returned strings
 void tidymac_free_string(char *ptr);
 
+// Cancellable operations. Create a handle, pass it as `operation` to one
+// call, cancel it from any thread, and free it after that call returns.
+// Cancelled calls return {"error":"CANCELLED"} (clean reports
+// "cancelled": true instead). Pass 0 as `operation` when not needed.
+uint64_t tidymac_operation_new(void);
+void tidymac_operation_cancel(uint64_t operation);
+void tidymac_operation_free(uint64_t operation);
+
 // Scan with a profile. Returns JSON string.
 // profile_name: "quick", "developer", "creative", "deep"
+// Targets cut short by the profile's [limits] are listed under "truncated".
 char *tidymac_scan(const char *profile_name);
 
+// Receives one JSON-encoded scan event ({"event": "target_started", ...}).
+// event_json is only valid during the call and must not be freed.
+typedef void (*tidymac_scan_event_cb)(const char *event_json, void *user_data);
+
+// Scan like tidymac_scan, streaming events to callback as they happen.
+// The callback may run on worker threads; all calls finish before this returns.
+char *tidymac_scan_stream(const char *profile_name, tidymac_scan_event_cb callback,
+                          void *user_data, uint64_t operation);
+
+// Find duplicate files under path. Returns JSON string.
+// min_size: smallest file size in bytes to consider.
+char *tidymac_dup_scan(const char *path, uint64_t min_size, uint64_t operation);
+
 // Get disk usage breakdown. Returns JSON string.
 char *tidymac_disk_usage(void);
 
@@ -29,28 +51,93 @@ char *tidymac_app_clean_leftovers(const char *app_name);
 // selected_names_json: JSON array of item names to clean, e.g. '["npm
 // Cache","pip Cache"]'
 //                      Pass NULL to clean ALL items.
+// Only "safe" items are cleaned; riskier ones are skipped and reported under
+// "held_back" (see tidymac_clean_with_safety).
+// Targets with a profile trim policy only lose what exceeds it; "trimmed"
+// lists kept and evicted bytes for each.
+// Files that changed or were swapped for something else since the scan are
+// left alone and listed under "skipped" with a reason.
+// A soft delete stages files as the profile's [staging] store or the
+// staging_store setting says; "staged_bytes" is what they take up in
+// staging, less than "bytes_freed" when compressed or deduplicated.
 char *tidymac_clean(const char *profile_name, const char *mode,
                     const char *selected_names_json);
 
+// tidymac_clean, cleaning items up to max_safety: "safe", "caution" or
+// "dangerous". Pass NULL for "safe". Riskier items are skipped and reported
+// under "held_back".
+char *tidymac_clean_with_safety(const char *profile_name, const char *mode,
+                                const char *selected_names_json,
+                                const char *max_safety);
+
+// tidymac_clean, cancellable between files. A cancelled soft delete keeps
+// its session, so what was staged can still be undone.
+char *tidymac_clean_cancellable(const char *profile_name, const char *mode,
+                                const char *selected_names_json,
+                                const char *max_safety, uint64_t operation);
+
+// Clean exactly the files in a plan saved by `tidymac scan --save-plan`,
+// without scanning. Returns JSON like tidymac_clean, plus "drifted": planned
+// files skipped because their size or mtime changed since. mode and
+// max_safety as for tidymac_clean; operation as for tidymac_clean_cancellable
+// (0 if not cancellable).
+char *tidymac_clean_plan(const char *plan_path, const char *mode,
+                         const char *max_safety, uint64_t operation);
+
 // Run privacy audit. Returns JSON string.
 char *tidymac_privacy_scan(void);
 
 // Get Docker usage. Returns JSON string.
 char *tidymac_docker_usage(void);
 
-// List undo sessions. Returns JSON string.
+// List undo sessions. Returns JSON string; each session has "total_bytes"
+// (original size) and "staged_size" (bytes used in staging).
 char *tidymac_undo_list(void);
 
-// Restore a session by ID. Returns JSON string.
+// Restore a session by ID. Returns JSON string with "restored_count",
+// "errors", "skipped" and "remaining" (items still in staging).
 char *tidymac_undo_session(const char *session_id);
 
+// Restore part of a session, or to another place. options_json is an object
+// with optional "only" (array of globs over original paths), "to" (directory
+// to restore under, keeping full original paths) and "on_conflict" ("error",
+// "skip", "rename" or "overwrite"); NULL restores everything in place.
+// Returns JSON like tidymac_undo_session.
+char *tidymac_undo_session_with_options(const char *session_id,
+                                        const char *options_json);
+
+// Search staging sessions and the clean logs for cleaned files. query_json
+// is an object with optional "pattern" (glob over original paths, names if
+// it has no '/'), "min_size", "max_size" (bytes), "session" and "status"
+// ("restorable", "restored", "purged", "hard_deleted", "failed" or
+// "skipped"); NULL matches everything. Returns a JSON array of items with
+// session_id, timestamp, profile, mode, path, size_bytes and status.
+char *tidymac_undo_find(const char *query_json);
+
+// Check that a session's staged files are all present and that
+// deduplicated ones still match their SHA-256. Returns JSON with
+// session_id, checked, verified, and the original paths of "missing" and
+// "corrupted" files.
+char *tidymac_undo_verify(const char *session_id);
+
+// List soft deletes interrupted before saving their manifest ("incomplete",
+// each with session_id and the journaled "moves") and staged files no
+// session records ("orphaned"). Returns JSON string.
+char *tidymac_recover_list(void);
+
+// Recover an interrupted soft delete. action is "rollback" (move staged
+// files back; JSON like tidymac_undo_session) or "finalize" (write the
+// manifest so it can be undone; JSON with total_files and total_bytes).
+char *tidymac_recover_session(const char *session_id, const char *action);
+
 // List available profiles. Returns JSON string.
 char *tidymac_profiles_list(void);
 
 // Get version string.
 char *tidymac_version(void);
 
-// Signal the current scan to cancel.
+// Cancel every running tidymac_scan / tidymac_clean call. Operations with a
+// handle are unaffected; use tidymac_operation_cancel for those.
 void tidymac_cancel_scan(void);
 
 // Initialize logging and crash reporting.
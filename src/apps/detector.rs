@@ -325,10 +325,7 @@ fn check_associated(path: &Path, kind: AssociatedKind, files: &mut Vec<Associate
             crate::scanner::walker::dir_size(path)
         } else {
             std::fs::metadata(path)
-                .map(|m| {
-                    use std::os::darwin::fs::MetadataExt;
-                    m.st_blocks() * 512
-                })
+                .map(|m| crate::common::sizes::allocated_bytes(&m))
                 .unwrap_or(0)
         }
     } else {
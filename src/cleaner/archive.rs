@@ -0,0 +1,246 @@
+//! Compressed staging
+//!
+//! With `staging_store = "compress"` (or `clean --stage compress`, or a
+//! profile's `[staging] store`), staged files that are likely to compress
+//! well (logs, crash reports, caches of text) are packed into one zstd zip
+//! archive per session, `staging/<id>/archive.zip`, once they have all been
+//! moved. Each entry is named after the staged file it replaces. Restores
+//! extract the entry, which the zip CRC checks, and leave the archive for
+//! the rest of the session until it is purged.
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, Local, TimeZone, Timelike};
+use std::collections::HashMap;
+use std::fs::File;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use super::journal::Journal;
+use super::manifest::CleanManifest;
+
+pub const ARCHIVE_FILE: &str = "archive.zip";
+
+/// Formats that are compressed already, so packing them only costs time
+const COMPRESSED_EXTENSIONS: &[&str] = &[
+    "7z", "aac", "avi", "br", "bz2", "dmg", "gz", "heic", "ipa", "jar", "jpeg", "jpg", "lz4",
+    "m4a", "m4v", "mkv", "mov", "mp3", "mp4", "pdf", "pkg", "png", "rar", "tgz", "webp", "xip",
+    "xz", "zip", "zst",
+];
+
+/// Whether a file is worth packing, going by its name
+pub fn is_compressible(path: &Path) -> bool {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    !COMPRESSED_EXTENSIONS.contains(&ext.as_str())
+}
+
+/// Pack the compressible files `manifest` staged into its session archive
+///
+/// The archive is written and synced before any staged file is removed, and
+/// each removal is journaled first, so a crash at any point leaves every
+/// file either loose in `files/` or in the archive. If the archive can't be
+/// written, everything stays loose.
+pub fn pack(manifest: &mut CleanManifest, journal: &mut Journal) -> Result<()> {
+    let candidates: Vec<(usize, usize, PathBuf)> = manifest
+        .items
+        .iter()
+        .enumerate()
+        .filter(|(_, i)| i.success && i.content_hash.is_none() && is_compressible(&i.original_path))
+        .filter_map(|(index, i)| {
+            let staged = i.staged_path.clone()?;
+            // Staged files are named after their journal sequence number
+            let seq = staged.file_name()?.to_str()?.parse().ok()?;
+            let meta = std::fs::symlink_metadata(&staged).ok()?;
+            meta.is_file().then_some((index, seq, staged))
+        })
+        .collect();
+    if candidates.is_empty() {
+        return Ok(());
+    }
+
+    let archive = manifest.staging_session_dir().join(ARCHIVE_FILE);
+    let files: Vec<&Path> = candidates.iter().map(|(_, _, p)| p.as_path()).collect();
+    let sizes = match write_archive(&archive, &files) {
+        Ok(sizes) => sizes,
+        Err(e) => {
+            tracing::warn!("Failed to compress staged files: {:#}", e);
+            return Ok(());
+        }
+    };
+
+    for (index, seq, staged) in candidates {
+        let entry = entry_name(&staged);
+        journal.record_packed(seq)?;
+        std::fs::remove_file(&staged)
+            .with_context(|| format!("Failed to remove packed file: {}", staged.display()))?;
+        let item = &mut manifest.items[index];
+        item.staged_bytes = sizes.get(&entry).copied();
+        item.staged_path = Some(archive.clone());
+        item.archive_entry = Some(entry);
+    }
+    Ok(())
+}
+
+/// Write `files` into a new archive at `path`; returns each entry's
+/// compressed size
+fn write_archive(path: &Path, files: &[&Path]) -> Result<HashMap<String, u64>> {
+    let partial = path.with_extension("zip.partial");
+    let mut zip = ZipWriter::new(
+        File::create(&partial)
+            .with_context(|| format!("Failed to create archive: {}", partial.display()))?,
+    );
+    for file in files {
+        let meta = std::fs::metadata(file)
+            .with_context(|| format!("Failed to read: {}", file.display()))?;
+        let mut options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Zstd)
+            .unix_permissions(meta.permissions().mode())
+            .large_file(meta.len() > u32::MAX as u64);
+        if let Some(time) = meta.modified().ok().and_then(dos_time) {
+            options = options.last_modified_time(time);
+        }
+        zip.start_file(entry_name(file), options)?;
+        let mut src =
+            File::open(file).with_context(|| format!("Failed to read: {}", file.display()))?;
+        std::io::copy(&mut src, &mut zip)
+            .with_context(|| format!("Failed to compress: {}", file.display()))?;
+    }
+    zip.finish()?.sync_all()?;
+    std::fs::rename(&partial, path)
+        .with_context(|| format!("Failed to finish archive: {}", path.display()))?;
+
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let mut sizes = HashMap::new();
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i)?;
+        sizes.insert(entry.name().to_string(), entry.compressed_size());
+    }
+    Ok(sizes)
+}
+
+/// Extract `entry` of `archive` to `dest`, which must not exist yet; a
+/// failed CRC check leaves nothing behind at `dest`
+pub fn extract(archive: &Path, entry: &str, dest: &Path) -> Result<()> {
+    let mut zip = open(archive)?;
+    let mut file = zip
+        .by_name(entry)
+        .with_context(|| format!("'{}' is missing from {}", entry, archive.display()))?;
+    let mut out = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dest)
+        .with_context(|| format!("Failed to create: {}", dest.display()))?;
+
+    if let Err(e) = std::io::copy(&mut file, &mut out) {
+        drop(out);
+        let _ = std::fs::remove_file(dest);
+        return Err(e).with_context(|| {
+            format!(
+                "Staged copy is corrupted in {} ('{}')",
+                archive.display(),
+                entry
+            )
+        });
+    }
+    if let Some(mode) = file.unix_mode() {
+        let _ = out.set_permissions(std::fs::Permissions::from_mode(mode));
+    }
+    if let Some(time) = file.last_modified().and_then(system_time) {
+        let _ = out.set_modified(time);
+    }
+    Ok(())
+}
+
+/// Read `entry` through, so the zip CRC check runs
+pub fn check(archive: &Path, entry: &str) -> Result<()> {
+    let mut zip = open(archive)?;
+    let mut file = zip
+        .by_name(entry)
+        .with_context(|| format!("'{}' is missing from {}", entry, archive.display()))?;
+    std::io::copy(&mut file, &mut std::io::sink())
+        .with_context(|| format!("'{}' is corrupted in {}", entry, archive.display()))?;
+    Ok(())
+}
+
+/// Whether `archive` exists and has `entry`
+pub fn contains(archive: &Path, entry: &str) -> bool {
+    open(archive).is_ok_and(|zip| zip.index_for_name(entry).is_some())
+}
+
+fn open(archive: &Path) -> Result<ZipArchive<File>> {
+    let file = File::open(archive)
+        .with_context(|| format!("Staged archive is missing: {}", archive.display()))?;
+    ZipArchive::new(file).with_context(|| format!("Unreadable archive: {}", archive.display()))
+}
+
+/// The archive entry for a staged file: its name
+pub(super) fn entry_name(staged: &Path) -> String {
+    staged
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Local time, to the 2 seconds zip can hold
+fn dos_time(time: SystemTime) -> Option<zip::DateTime> {
+    let t = chrono::DateTime::<Local>::from(time);
+    zip::DateTime::from_date_and_time(
+        u16::try_from(t.year()).ok()?,
+        t.month() as u8,
+        t.day() as u8,
+        t.hour() as u8,
+        t.minute() as u8,
+        t.second() as u8,
+    )
+    .ok()
+}
+
+fn system_time(time: zip::DateTime) -> Option<SystemTime> {
+    Local
+        .with_ymd_and_hms(
+            time.year().into(),
+            time.month().into(),
+            time.day().into(),
+            time.hour().into(),
+            time.minute().into(),
+            time.second().into(),
+        )
+        .single()
+        .map(SystemTime::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_round_trip_keeps_contents_and_mode() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("000001");
+        std::fs::write(&log, "line\n".repeat(10_000)).unwrap();
+        std::fs::set_permissions(&log, std::fs::Permissions::from_mode(0o600)).unwrap();
+
+        let archive = dir.path().join(ARCHIVE_FILE);
+        let sizes = write_archive(&archive, &[log.as_path()]).unwrap();
+        assert!(sizes["000001"] < 50_000, "{:?}", sizes);
+        assert!(contains(&archive, "000001"));
+        check(&archive, "000001").unwrap();
+
+        let dest = dir.path().join("restored.log");
+        extract(&archive, "000001", &dest).unwrap();
+        assert_eq!(std::fs::read(&dest).unwrap(), std::fs::read(&log).unwrap());
+        let mode = std::fs::metadata(&dest).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // Never overwrites
+        assert!(extract(&archive, "000001", &dest).is_err());
+        assert!(!is_compressible(Path::new("/Downloads/movie.MP4")));
+        assert!(is_compressible(Path::new("/Library/Logs/app.log")));
+    }
+}
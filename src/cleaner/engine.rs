@@ -2,11 +2,18 @@ use anyhow::{Context, Result};
 use indicatif::{ProgressBar, ProgressStyle};
 use std::path::Path;
 
-use super::manifest::{CleanManifest, ManifestItem};
+use super::guard::PathGuard;
+use super::journal::Journal;
+use super::manifest::{CleanManifest, ManifestItem, SkippedPath};
 use super::staging;
-use crate::common::config::Config;
+use crate::common::cancel::CancelToken;
+use crate::common::config::{Config, StagingStore};
+use crate::common::exclusions::ExclusionMatcher;
 use crate::common::format;
-use crate::scanner::targets::ScanItem;
+use crate::profiles::loader::Profile;
+use crate::scanner::cache::ScanCache;
+use crate::scanner::targets::{self, FileEntry, SafetyLevel, ScanItem, TrimPolicy};
+use std::time::{Duration, SystemTime};
 
 /// Clean mode determines how files are removed
 #[derive(Debug, Clone, PartialEq)]
@@ -35,8 +42,222 @@ pub struct CleanReport {
     pub mode: CleanMode,
     pub files_removed: usize,
     pub bytes_freed: u64,
+    /// What a soft delete's files take up in staging; less than
+    /// `bytes_freed` when compressed or already in the dedup store
+    pub staged_bytes: u64,
     pub session_id: Option<String>,
     pub errors: Vec<String>,
+    /// Items skipped because they exceed the requested safety level
+    pub held_back: Vec<HeldBackItem>,
+    /// Stopped early by a `CancelToken`; the counts cover what was done
+    pub cancelled: bool,
+    /// Targets only partly cleaned because of their trim policy
+    pub trimmed: Vec<TrimmedItem>,
+    /// Files left alone because they changed since the scan
+    pub skipped: Vec<SkippedPath>,
+}
+
+/// A scan item that was not cleaned because it is riskier than allowed
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct HeldBackItem {
+    pub name: String,
+    pub path: std::path::PathBuf,
+    pub size_bytes: u64,
+    pub file_count: usize,
+    pub safety: SafetyLevel,
+    pub reason: String,
+}
+
+/// What a trim policy keeps of a target and what cleaning it evicts
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TrimmedItem {
+    pub name: String,
+    pub path: std::path::PathBuf,
+    pub policy: TrimPolicy,
+    pub kept_bytes: u64,
+    pub kept_files: usize,
+    pub evicted_bytes: u64,
+    pub evicted_files: usize,
+}
+
+/// Split items into those at or below `max_safety` and those held back
+///
+/// The held-back list records why each item was skipped so callers can
+/// show it to the user before (or instead of) cleaning.
+pub fn partition_by_safety(
+    items: &[ScanItem],
+    max_safety: &SafetyLevel,
+) -> (Vec<ScanItem>, Vec<HeldBackItem>) {
+    let mut allowed = Vec::new();
+    let mut held_back = Vec::new();
+
+    for item in items {
+        if &item.safety <= max_safety {
+            allowed.push(item.clone());
+        } else {
+            held_back.push(HeldBackItem {
+                name: item.name.clone(),
+                path: item.path.clone(),
+                size_bytes: item.size_bytes,
+                file_count: item.file_count,
+                safety: item.safety.clone(),
+                reason: format!(
+                    "safety level '{}' exceeds --max-safety '{}' ({})",
+                    item.safety, max_safety, item.reason
+                ),
+            });
+        }
+    }
+
+    (allowed, held_back)
+}
+
+/// Drop anything matched by `exclude_paths`, recording it as held back
+///
+/// The scanner already prunes excluded paths; this is the last line of
+/// defence for stale cache entries, saved selections and FFI callers.
+pub fn drop_excluded(
+    items: Vec<ScanItem>,
+    excludes: &ExclusionMatcher,
+) -> (Vec<ScanItem>, Vec<HeldBackItem>) {
+    if excludes.is_empty() {
+        return (items, Vec::new());
+    }
+
+    let is_blocked = |path: &Path| excludes.is_excluded(path) || excludes.contains_excluded(path);
+    let mut allowed = Vec::new();
+    let mut held_back = Vec::new();
+
+    for mut item in items {
+        let (kept, blocked): (Vec<_>, Vec<_>) = if item.files.is_empty() {
+            if is_blocked(&item.path) {
+                (Vec::new(), Vec::new())
+            } else {
+                allowed.push(item);
+                continue;
+            }
+        } else {
+            item.files.drain(..).partition(|f| !is_blocked(&f.path))
+        };
+        if blocked.is_empty() && !kept.is_empty() {
+            item.files = kept;
+            allowed.push(item);
+            continue;
+        }
+
+        let blocked_bytes: u64 = blocked.iter().map(|f| f.size_bytes).sum();
+        let whole_item = kept.is_empty();
+        held_back.push(HeldBackItem {
+            name: item.name.clone(),
+            path: blocked
+                .first()
+                .map(|f| f.path.clone())
+                .unwrap_or_else(|| item.path.clone()),
+            size_bytes: if whole_item {
+                item.size_bytes
+            } else {
+                blocked_bytes
+            },
+            file_count: if whole_item {
+                item.file_count
+            } else {
+                blocked.len()
+            },
+            safety: item.safety.clone(),
+            reason: "matches a pattern in exclude_paths".to_string(),
+        });
+
+        if !whole_item {
+            let usage = targets::file_totals(&kept);
+            item.size_bytes = usage.allocated;
+            item.apparent_bytes = usage.apparent;
+            item.freeable_bytes = usage.freeable;
+            item.file_count = kept.len();
+            item.files = kept;
+            allowed.push(item);
+        }
+    }
+
+    (allowed, held_back)
+}
+
+/// Cut items with a trim policy down to the files the policy evicts
+///
+/// Files not used within `keep_days` go first, then the least recently
+/// used until what is left fits in `max_gb`. Items the policy keeps whole
+/// are dropped, as are items that list no files (they can only be removed
+/// whole). Returned items carry no policy, so trimming twice is harmless.
+pub fn apply_trim(items: Vec<ScanItem>) -> (Vec<ScanItem>, Vec<TrimmedItem>) {
+    let now = SystemTime::now();
+    let mut selected = Vec::new();
+    let mut trimmed = Vec::new();
+
+    for mut item in items {
+        // Partial lists can't be trimmed; `clean` refuses them anyway
+        let Some(policy) = item.trim.take().filter(|_| !item.files_partial) else {
+            selected.push(item);
+            continue;
+        };
+        let (kept, evicted) = select_evictions(std::mem::take(&mut item.files), &policy, now);
+        let evicted_usage = targets::file_totals(&evicted);
+        // An item without a file list is kept whole
+        let (kept_bytes, kept_files) = if kept.is_empty() && evicted.is_empty() {
+            (item.size_bytes, item.file_count)
+        } else {
+            (targets::file_totals(&kept).allocated, kept.len())
+        };
+        trimmed.push(TrimmedItem {
+            name: item.name.clone(),
+            path: item.path.clone(),
+            policy,
+            kept_bytes,
+            kept_files,
+            evicted_bytes: evicted_usage.allocated,
+            evicted_files: evicted.len(),
+        });
+
+        if !evicted.is_empty() {
+            item.size_bytes = evicted_usage.allocated;
+            item.apparent_bytes = evicted_usage.apparent;
+            item.freeable_bytes = evicted_usage.freeable;
+            item.file_count = evicted.len();
+            item.files = evicted;
+            selected.push(item);
+        }
+    }
+
+    (selected, trimmed)
+}
+
+/// Split `files` into those `policy` keeps and those it evicts, by last use
+fn select_evictions(
+    mut files: Vec<FileEntry>,
+    policy: &TrimPolicy,
+    now: SystemTime,
+) -> (Vec<FileEntry>, Vec<FileEntry>) {
+    // Most recently used first; files with no usable time are evicted first
+    files.sort_by_key(|f| std::cmp::Reverse(f.last_used()));
+    let cutoff = policy
+        .keep_days
+        .map(|days| now - Duration::from_secs(u64::from(days) * 86400));
+    let max_bytes = policy.max_bytes();
+
+    let mut kept = Vec::new();
+    let mut evicted = Vec::new();
+    let mut kept_bytes = 0u64;
+    let mut full = false;
+    for file in files {
+        let fresh = cutoff.is_none_or(|cutoff| file.last_used().is_some_and(|t| t >= cutoff));
+        // Once something doesn't fit, everything used less recently goes too
+        full = full || max_bytes.is_some_and(|max| kept_bytes + file.size_bytes > max);
+        if fresh && !full {
+            kept_bytes += file.size_bytes;
+            kept.push(file);
+        } else {
+            evicted.push(file);
+        }
+    }
+    (kept, evicted)
 }
 
 /// Execute a cleaning operation on the given scan items
@@ -45,19 +266,65 @@ pub struct CleanReport {
 /// - DryRun: just reports what would be done
 /// - SoftDelete: moves files to staging area with manifest for undo
 /// - HardDelete: permanently removes files
+///
+/// Items riskier than `max_safety` or matched by `exclude_paths` are never
+/// touched; they are returned in `CleanReport::held_back` instead.
+///
+/// Each file is checked again right before it is moved or deleted (see
+/// `guard`); files that changed since the scan are left alone and returned
+/// in `CleanReport::skipped`.
+///
+/// Cancelling stops between files. Whatever was already removed or staged is
+/// still recorded in the session manifest, so a cancelled soft delete can be
+/// undone like any other.
 pub fn clean(
     items: &[ScanItem],
     mode: CleanMode,
     profile_name: &str,
+    max_safety: &SafetyLevel,
+    show_progress: bool,
+    cancel: &CancelToken,
+) -> Result<CleanReport> {
+    clean_with_store(
+        items,
+        mode,
+        profile_name,
+        max_safety,
+        None,
+        show_progress,
+        cancel,
+    )
+}
+
+/// `clean`, choosing how a soft delete stages files; `None` uses the
+/// profile's `[staging] store`, or failing that the `staging_store` setting
+pub fn clean_with_store(
+    items: &[ScanItem],
+    mode: CleanMode,
+    profile_name: &str,
+    max_safety: &SafetyLevel,
+    staging_store: Option<StagingStore>,
     show_progress: bool,
+    cancel: &CancelToken,
 ) -> Result<CleanReport> {
     let config = Config::load()?;
+    let (items, mut held_back) = partition_by_safety(items, max_safety);
+    let (items, excluded) = drop_excluded(items, &config.exclusions()?);
+    held_back.extend(excluded);
+    let (items, trimmed) = apply_trim(items);
+    let items = items.as_slice();
 
     // Ensure TidyMac directories exist
     Config::init_dirs()?;
 
     // Safety check: validate no protected paths are being cleaned
     for item in items {
+        if item.files_partial && mode != CleanMode::DryRun {
+            anyhow::bail!(
+                "'{}' only lists its largest files; scan with full file lists before cleaning",
+                item.name
+            );
+        }
         if crate::common::safety::is_protected(&item.path) {
             anyhow::bail!(
                 "SAFETY: Refusing to clean protected path: {}",
@@ -74,30 +341,77 @@ pub fn clean(
         }
     }
 
-    match mode {
+    let mut report = match mode {
         CleanMode::DryRun => clean_dry_run(items),
         CleanMode::SoftDelete => clean_soft_delete(
             items,
             profile_name,
             config.staging_retention_days,
+            staging_store
+                .or_else(|| profile_staging_store(profile_name))
+                .unwrap_or(config.staging_store),
             show_progress,
+            cancel,
         ),
-        CleanMode::HardDelete => clean_hard_delete(items, profile_name, show_progress),
+        CleanMode::HardDelete => clean_hard_delete(items, profile_name, show_progress, cancel),
+    }?;
+    report.held_back = held_back;
+    report.trimmed = trimmed;
+
+    if mode != CleanMode::DryRun {
+        forget_cached(profile_name, items);
+    }
+
+    Ok(report)
+}
+
+/// The profile's `[staging] store`, if it sets one
+///
+/// A profile that can't be loaded falls back to the `staging_store`
+/// setting, with a warning rather than failing the clean.
+fn profile_staging_store(profile_name: &str) -> Option<StagingStore> {
+    match Profile::load(profile_name) {
+        Ok(profile) => profile.staging.store,
+        Err(e) => {
+            tracing::warn!("Using the default staging store: {:#}", e);
+            None
+        }
+    }
+}
+
+/// Drop cleaned targets from the scan cache so the next scan walks them again
+///
+/// Removals deeper than the cache's mtime depth would otherwise go unnoticed.
+fn forget_cached(profile_name: &str, items: &[ScanItem]) {
+    let Some(mut cache) = ScanCache::load(profile_name) else {
+        return;
+    };
+    for item in items {
+        cache.invalidate(&item.path);
+    }
+    if let Err(e) = cache.save() {
+        tracing::warn!("Failed to update scan cache after cleaning: {}", e);
     }
 }
 
 /// Dry run — just tally up what would be cleaned
+///
+/// `bytes_freed` is what deleting would actually give back, so hard-linked
+/// files whose other links survive don't count.
 fn clean_dry_run(items: &[ScanItem]) -> Result<CleanReport> {
     let mut total_files = 0usize;
     let mut total_bytes = 0u64;
 
     for item in items {
-        if item.files.is_empty() {
+        if item.files_partial {
+            total_files += item.file_count;
+            total_bytes += item.freeable_bytes;
+        } else if item.files.is_empty() {
             total_files += 1;
-            total_bytes += item.size_bytes;
+            total_bytes += item.freeable_bytes;
         } else {
             total_files += item.files.len();
-            total_bytes += item.files.iter().map(|f| f.size_bytes).sum::<u64>();
+            total_bytes += targets::file_totals(&item.files).freeable;
         }
     }
 
@@ -105,8 +419,13 @@ fn clean_dry_run(items: &[ScanItem]) -> Result<CleanReport> {
         mode: CleanMode::DryRun,
         files_removed: total_files,
         bytes_freed: total_bytes,
+        staged_bytes: 0,
         session_id: None,
         errors: Vec::new(),
+        held_back: Vec::new(),
+        cancelled: false,
+        trimmed: Vec::new(),
+        skipped: Vec::new(),
     })
 }
 
@@ -115,23 +434,39 @@ fn clean_soft_delete(
     items: &[ScanItem],
     profile_name: &str,
     retention_days: u32,
+    staging_store: StagingStore,
     show_progress: bool,
+    cancel: &CancelToken,
 ) -> Result<CleanReport> {
     let mut manifest = CleanManifest::new(profile_name, "soft_delete", retention_days);
 
-    // Stage all files
-    staging::stage_files(items, &mut manifest, show_progress)?;
+    // Stage all files, journaling each move until the manifest is saved
+    let mut journal = Journal::create(&manifest)?;
+    staging::stage_files(
+        items,
+        &mut manifest,
+        &mut journal,
+        staging_store,
+        show_progress,
+        cancel,
+    )?;
 
     // Save the manifest
     manifest.save().context("Failed to save clean manifest")?;
+    journal.finish()?;
 
     let session_id = manifest.session_id.clone();
     let report = CleanReport {
         mode: CleanMode::SoftDelete,
         files_removed: manifest.total_files,
         bytes_freed: manifest.total_bytes,
+        staged_bytes: manifest.staged_bytes(),
         session_id: Some(session_id),
         errors: manifest.errors.clone(),
+        held_back: Vec::new(),
+        cancelled: manifest.cancelled,
+        trimmed: Vec::new(),
+        skipped: manifest.skipped.clone(),
     };
 
     Ok(report)
@@ -142,6 +477,7 @@ fn clean_hard_delete(
     items: &[ScanItem],
     profile_name: &str,
     show_progress: bool,
+    cancel: &CancelToken,
 ) -> Result<CleanReport> {
     let mut manifest = CleanManifest::new(profile_name, "hard_delete", 0);
 
@@ -170,8 +506,24 @@ fn clean_hard_delete(
         None
     };
 
-    for item in items {
+    'items: for item in items {
         if item.files.is_empty() {
+            if cancel.is_cancelled() {
+                manifest.cancelled = true;
+                break 'items;
+            }
+            if let Some(reason) = PathGuard::check_whole(&item.path) {
+                manifest.add_skipped(SkippedPath {
+                    path: item.path.clone(),
+                    size_bytes: item.size_bytes,
+                    category: format!("{}", item.category),
+                    reason,
+                });
+                if let Some(ref pb) = pb {
+                    pb.inc(1);
+                }
+                continue;
+            }
             // Delete the item path directly
             let result = hard_delete_path(&item.path);
             manifest.add_item(ManifestItem {
@@ -183,17 +535,39 @@ fn clean_hard_delete(
                 is_dir: item.path.is_dir(),
                 success: result.is_ok(),
                 error: result.err().map(|e| e.to_string()),
+                restored: None,
+                content_hash: None,
+                archive_entry: None,
+                staged_bytes: None,
             });
 
             if let Some(ref pb) = pb {
                 pb.inc(1);
             }
         } else {
+            let guard = PathGuard::for_item(item);
             for file_entry in &item.files {
+                if cancel.is_cancelled() {
+                    manifest.cancelled = true;
+                    break 'items;
+                }
                 if let Some(ref pb) = pb {
                     pb.set_message(format::truncate(&format::format_path(&file_entry.path), 40));
                 }
 
+                if let Some(reason) = guard.check(file_entry) {
+                    manifest.add_skipped(SkippedPath {
+                        path: file_entry.path.clone(),
+                        size_bytes: file_entry.size_bytes,
+                        category: format!("{}", item.category),
+                        reason,
+                    });
+                    if let Some(ref pb) = pb {
+                        pb.inc(1);
+                    }
+                    continue;
+                }
+
                 let result = hard_delete_path(&file_entry.path);
                 manifest.add_item(ManifestItem {
                     original_path: file_entry.path.clone(),
@@ -204,6 +578,10 @@ fn clean_hard_delete(
                     is_dir: file_entry.path.is_dir(),
                     success: result.is_ok(),
                     error: result.err().map(|e| e.to_string()),
+                    restored: None,
+                    content_hash: None,
+                    archive_entry: None,
+                    staged_bytes: None,
                 });
 
                 if let Some(ref pb) = pb {
@@ -226,8 +604,13 @@ fn clean_hard_delete(
         mode: CleanMode::HardDelete,
         files_removed: manifest.total_files,
         bytes_freed: manifest.total_bytes,
+        staged_bytes: 0,
         session_id: None,
         errors: manifest.errors.clone(),
+        held_back: Vec::new(),
+        cancelled: manifest.cancelled,
+        trimmed: Vec::new(),
+        skipped: manifest.skipped.clone(),
     })
 }
 
@@ -251,18 +634,21 @@ fn hard_delete_path(path: &Path) -> Result<()> {
 /// Check if the staging area is getting too large and warn
 pub fn check_staging_health() -> Result<StagingHealth> {
     let staging_dir = Config::staging_dir();
+    let (store_blobs, store_size) = super::store::usage();
     if !staging_dir.exists() {
         return Ok(StagingHealth {
-            total_size: 0,
+            total_size: store_size,
             session_count: 0,
             expired_count: 0,
             expired_size: 0,
+            store_blobs,
+            store_size,
             warning: None,
         });
     }
 
     let sessions = CleanManifest::list_sessions()?;
-    let total_size: u64 = sessions.iter().map(|s| s.staged_size).sum();
+    let total_size: u64 = sessions.iter().map(|s| s.staged_size).sum::<u64>() + store_size;
     let expired: Vec<_> = sessions.iter().filter(|s| s.is_expired).collect();
     let expired_size: u64 = expired.iter().map(|s| s.staged_size).sum();
 
@@ -286,6 +672,8 @@ pub fn check_staging_health() -> Result<StagingHealth> {
         session_count: sessions.len(),
         expired_count: expired.len(),
         expired_size,
+        store_blobs,
+        store_size,
         warning,
     })
 }
@@ -293,9 +681,13 @@ pub fn check_staging_health() -> Result<StagingHealth> {
 /// Health status of the staging area
 #[derive(Debug)]
 pub struct StagingHealth {
+    /// Includes `store_size`
     pub total_size: u64,
     pub session_count: usize,
     pub expired_count: usize,
     pub expired_size: u64,
+    /// Deduplicated files in the shared store
+    pub store_blobs: usize,
+    pub store_size: u64,
     pub warning: Option<String>,
 }
@@ -0,0 +1,198 @@
+//! Last-moment checks before a path is removed
+//!
+//! Files can change between a scan and the clean that acts on it: seconds
+//! for a normal clean, days for a cached scan or a saved plan. Right before
+//! moving or deleting a file the engine stats it again, without following
+//! symlinks, and compares device, inode, size and mtime with what the scan
+//! saw. It also resolves the file's parent directories, so a directory
+//! swapped for a symlink can't redirect the removal outside the target.
+
+use std::path::{Path, PathBuf};
+
+use crate::common::sizes::FileId;
+use crate::scanner::targets::{FileEntry, ScanItem};
+
+/// Checks files against the scan for the roots of one target
+pub struct PathGuard {
+    /// Each root that still exists, with its symlinks resolved
+    roots: Vec<(PathBuf, PathBuf)>,
+}
+
+impl PathGuard {
+    /// Guard for the files under any of `roots`; a pattern like
+    /// `~/**/node_modules` guards the directory before its first wildcard
+    pub fn new(roots: &[PathBuf]) -> Self {
+        let roots = roots
+            .iter()
+            .map(|root| {
+                root.components()
+                    .take_while(|c| !c.as_os_str().to_string_lossy().contains('*'))
+                    .collect::<PathBuf>()
+            })
+            .filter_map(|root| {
+                let real_root = std::fs::canonicalize(&root).ok()?;
+                Some((root, real_root))
+            })
+            .collect();
+        Self { roots }
+    }
+
+    /// Guard for the files of `item`, under the roots it was scanned from
+    pub fn for_item(item: &ScanItem) -> Self {
+        if item.roots.is_empty() {
+            Self::new(std::slice::from_ref(&item.path))
+        } else {
+            Self::new(&item.roots)
+        }
+    }
+
+    /// Why an item removed as a whole must not be removed now, if anything
+    ///
+    /// Such items list no files, so the entry is recorded here and checked
+    /// against the item's parent directory.
+    pub fn check_whole(path: &Path) -> Option<String> {
+        let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) else {
+            return Some("has no parent directory".to_string());
+        };
+        let entry = match std::fs::symlink_metadata(path) {
+            Ok(meta) => FileEntry::from_metadata(path.to_path_buf(), &meta),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Some("no longer exists".to_string())
+            }
+            Err(e) => return Some(format!("can't be checked: {}", e)),
+        };
+        Self::new(&[parent.to_path_buf()]).check(&entry)
+    }
+
+    /// Why `entry` must not be removed now, if anything
+    pub fn check(&self, entry: &FileEntry) -> Option<String> {
+        let meta = match std::fs::symlink_metadata(&entry.path) {
+            Ok(meta) => meta,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Some("no longer exists".to_string())
+            }
+            Err(e) => return Some(format!("can't be checked: {}", e)),
+        };
+
+        if let Some(reason) = self.escapes_root(&entry.path) {
+            return Some(reason);
+        }
+        if entry.file_id.is_some_and(|id| id != FileId::of(&meta)) {
+            return Some("was replaced by a different file since the scan".to_string());
+        }
+        // A directory's recorded size covers everything inside it
+        if !meta.is_dir() && meta.len() != entry.apparent_bytes {
+            return Some(format!(
+                "size changed from {} to {} bytes since the scan",
+                entry.apparent_bytes,
+                meta.len()
+            ));
+        }
+        if entry.modified.is_some() && meta.modified().ok() != entry.modified {
+            return Some("was modified since the scan".to_string());
+        }
+        None
+    }
+
+    /// Whether `path` lies outside every root, or a symlinked parent
+    /// directory now leads it out of the one it is under
+    fn escapes_root(&self, path: &Path) -> Option<String> {
+        let Some((root, real_root)) = self
+            .roots
+            .iter()
+            .filter(|(root, _)| path.starts_with(root))
+            .max_by_key(|(root, _)| root.as_os_str().len())
+        else {
+            return Some("is outside the directories the target scans".to_string());
+        };
+        if path == root {
+            return None;
+        }
+        let parent = path.parent()?;
+        match std::fs::canonicalize(parent) {
+            Ok(real_parent) if real_parent.starts_with(real_root) => None,
+            Ok(real_parent) => Some(format!(
+                "resolves outside {} through a symlink (to {})",
+                root.display(),
+                real_parent.display()
+            )),
+            Err(_) => Some("its parent directory no longer resolves".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &Path) -> FileEntry {
+        FileEntry::from_metadata(
+            path.to_path_buf(),
+            &std::fs::symlink_metadata(path).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_guard_accepts_unchanged_and_rejects_replaced_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.cache");
+        std::fs::write(&path, "cached").unwrap();
+        let scanned = entry(&path);
+        let guard = PathGuard::new(&[dir.path().to_path_buf()]);
+        assert_eq!(guard.check(&scanned), None);
+
+        // Same size, different file
+        let other = dir.path().join("b.cache");
+        std::fs::write(&other, "CACHED").unwrap();
+        std::fs::rename(&other, &path).unwrap();
+        let reason = guard.check(&scanned).unwrap();
+        assert!(reason.contains("replaced"), "{}", reason);
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(guard.check(&scanned).unwrap(), "no longer exists");
+    }
+
+    #[test]
+    fn test_guard_rejects_symlinked_parent_outside_root() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let sub = root.path().join("sub");
+        std::fs::create_dir(&sub).unwrap();
+        let path = sub.join("keep.txt");
+        std::fs::write(&path, "data").unwrap();
+        let scanned = entry(&path);
+
+        // Swap the directory for a symlink to a look-alike elsewhere
+        std::fs::write(outside.path().join("keep.txt"), "data").unwrap();
+        std::fs::remove_dir_all(&sub).unwrap();
+        std::os::unix::fs::symlink(outside.path(), &sub).unwrap();
+
+        let reason = PathGuard::new(&[root.path().to_path_buf()])
+            .check(&scanned)
+            .unwrap();
+        assert!(reason.contains("through a symlink"), "{}", reason);
+    }
+
+    #[test]
+    fn test_guard_rejects_paths_outside_root_and_checks_whole_items() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let path = outside.path().join("stray.txt");
+        std::fs::write(&path, "data").unwrap();
+
+        let reason = PathGuard::new(&[root.path().to_path_buf()])
+            .check(&entry(&path))
+            .unwrap();
+        assert!(reason.contains("is outside"), "{}", reason);
+
+        // Wildcards in an item path guard the directory above them
+        let pattern = outside.path().join("**/node_modules");
+        assert_eq!(PathGuard::new(&[pattern]).check(&entry(&path)), None);
+
+        let dir = root.path().join("build");
+        std::fs::create_dir(&dir).unwrap();
+        assert_eq!(PathGuard::check_whole(&dir), None);
+        std::fs::remove_dir(&dir).unwrap();
+        assert_eq!(PathGuard::check_whole(&dir).unwrap(), "no longer exists");
+    }
+}
@@ -0,0 +1,338 @@
+//! Searching past cleans for a file
+//!
+//! Every clean appends its manifest to the daily `clean-*.jsonl` log, and
+//! a soft delete also keeps one in its staging session until purged. The
+//! staging copy is current; the last log line for a session is the next
+//! best thing once the session is gone, and the only record of a hard
+//! delete.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::manifest::{CleanManifest, ManifestItem};
+use crate::common::config::Config;
+
+/// Where a cleaned file is now
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemStatus {
+    /// Still in staging; `undo` can bring it back
+    Restorable,
+    /// Already put back by `undo`
+    Restored,
+    /// Staged, but the session has since been purged
+    Purged,
+    /// Deleted without staging
+    HardDeleted,
+    /// The clean tried and failed, so it should still be in place
+    Failed,
+    /// Left in place because it changed after the scan
+    Skipped,
+}
+
+impl ItemStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ItemStatus::Restorable => "restorable",
+            ItemStatus::Restored => "restored",
+            ItemStatus::Purged => "purged",
+            ItemStatus::HardDeleted => "hard deleted",
+            ItemStatus::Failed => "failed",
+            ItemStatus::Skipped => "skipped",
+        }
+    }
+}
+
+/// A glob over paths, as taken by `undo find` and `undo --only`
+///
+/// A leading `~` is expanded. A pattern with a `/` is matched against the
+/// full path and its ancestors, so a directory selects everything under it;
+/// one without is matched against names.
+#[derive(Debug, Clone)]
+pub struct PathPattern {
+    pattern: glob::Pattern,
+    by_name: bool,
+}
+
+impl PathPattern {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let expanded = match pattern.strip_prefix('~') {
+            Some(rest) => format!("{}{}", dirs::home_dir().unwrap_or_default().display(), rest),
+            None => pattern.to_string(),
+        };
+        Ok(Self {
+            by_name: !expanded.contains('/'),
+            pattern: glob::Pattern::new(&expanded)
+                .with_context(|| format!("Invalid pattern: {}", pattern))?,
+        })
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        path.ancestors().any(|p| {
+            if self.by_name {
+                p.file_name()
+                    .is_some_and(|n| self.pattern.matches(&n.to_string_lossy()))
+            } else {
+                self.pattern.matches_path(p)
+            }
+        })
+    }
+}
+
+/// What to look for
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryQuery {
+    /// A `PathPattern`
+    pub pattern: Option<String>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    /// Only this session
+    pub session: Option<String>,
+    pub status: Option<ItemStatus>,
+}
+
+/// A cleaned (or skipped) path and what became of it
+#[derive(Debug, Clone, Serialize)]
+pub struct FoundItem {
+    pub session_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub profile: String,
+    pub mode: String,
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub category: String,
+    pub status: ItemStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub staged_path: Option<PathBuf>,
+    /// Where `undo` put it, if not back in place
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restored_to: Option<PathBuf>,
+    /// Why it failed or was skipped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Every recorded item matching `query`, newest session first
+///
+/// Fails if `query.session` names a session that isn't recorded anywhere.
+pub fn find(query: &HistoryQuery) -> Result<Vec<FoundItem>> {
+    let pattern = query.pattern.as_deref().map(PathPattern::new).transpose()?;
+    let mut sessions = load_sessions(&Config::staging_dir(), &Config::logs_dir())?;
+    if let Some(ref id) = query.session {
+        sessions.retain(|(m, _)| &m.session_id == id);
+        if sessions.is_empty() {
+            anyhow::bail!("Session '{}' not found in staging or the clean logs", id);
+        }
+    }
+
+    let matches = |path: &Path, size: u64, status: ItemStatus| {
+        pattern.as_ref().is_none_or(|p| p.matches(path))
+            && query.min_size.is_none_or(|min| size >= min)
+            && query.max_size.is_none_or(|max| size <= max)
+            && query.status.is_none_or(|s| s == status)
+    };
+
+    let mut found = Vec::new();
+    for (manifest, in_staging) in &sessions {
+        let found_item = |path: &Path, size_bytes, category: &str, status| FoundItem {
+            session_id: manifest.session_id.clone(),
+            timestamp: manifest.timestamp,
+            profile: manifest.profile.clone(),
+            mode: manifest.mode.clone(),
+            path: path.to_path_buf(),
+            size_bytes,
+            category: category.to_string(),
+            status,
+            staged_path: None,
+            restored_to: None,
+            detail: None,
+        };
+        for item in &manifest.items {
+            let status = status_of(manifest, item, *in_staging);
+            if !matches(&item.original_path, item.size_bytes, status) {
+                continue;
+            }
+            found.push(FoundItem {
+                staged_path: item
+                    .staged_path
+                    .clone()
+                    .filter(|_| status == ItemStatus::Restorable),
+                restored_to: item
+                    .restored
+                    .as_ref()
+                    .map(|r| r.path.clone())
+                    .filter(|p| p != &item.original_path),
+                detail: item.error.clone(),
+                ..found_item(&item.original_path, item.size_bytes, &item.category, status)
+            });
+        }
+        for skipped in &manifest.skipped {
+            if matches(&skipped.path, skipped.size_bytes, ItemStatus::Skipped) {
+                found.push(FoundItem {
+                    detail: Some(skipped.reason.clone()),
+                    ..found_item(
+                        &skipped.path,
+                        skipped.size_bytes,
+                        &skipped.category,
+                        ItemStatus::Skipped,
+                    )
+                });
+            }
+        }
+    }
+    Ok(found)
+}
+
+fn status_of(manifest: &CleanManifest, item: &ManifestItem, in_staging: bool) -> ItemStatus {
+    if !item.success {
+        ItemStatus::Failed
+    } else if item.restored.is_some() {
+        ItemStatus::Restored
+    } else if manifest.mode == "hard_delete" {
+        ItemStatus::HardDeleted
+    } else if in_staging && item.staged_path.as_ref().is_some_and(|p| p.exists()) {
+        ItemStatus::Restorable
+    } else {
+        ItemStatus::Purged
+    }
+}
+
+/// Every recorded session, newest first, and whether it is still staged
+fn load_sessions(staging_dir: &Path, logs_dir: &Path) -> Result<Vec<(CleanManifest, bool)>> {
+    let mut sessions: HashMap<String, (CleanManifest, bool)> = HashMap::new();
+
+    // Oldest log first, so the last line for a session wins
+    if let Ok(entries) = std::fs::read_dir(logs_dir) {
+        let mut logs: Vec<PathBuf> = entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.starts_with("clean-") && n.ends_with(".jsonl"))
+            })
+            .collect();
+        logs.sort();
+        for log in logs {
+            let contents = std::fs::read_to_string(&log)
+                .with_context(|| format!("Failed to read log: {}", log.display()))?;
+            // A line cut short by a crash is skipped, not fatal
+            for manifest in contents
+                .lines()
+                .filter_map(|l| serde_json::from_str::<CleanManifest>(l).ok())
+            {
+                sessions.insert(manifest.session_id.clone(), (manifest.migrated(), false));
+            }
+        }
+    }
+
+    if let Ok(entries) = std::fs::read_dir(staging_dir) {
+        for entry in entries.flatten() {
+            let Ok(contents) = std::fs::read_to_string(entry.path().join("manifest.json")) else {
+                continue;
+            };
+            if let Ok(manifest) = serde_json::from_str::<CleanManifest>(&contents) {
+                sessions.insert(manifest.session_id.clone(), (manifest.migrated(), true));
+            }
+        }
+    }
+
+    let mut sessions: Vec<_> = sessions.into_values().collect();
+    sessions.sort_by_key(|(m, _)| Reverse(m.timestamp));
+    Ok(sessions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleaner::manifest::SkippedPath;
+
+    fn item(path: &str, size: u64, staged: Option<PathBuf>) -> ManifestItem {
+        ManifestItem {
+            original_path: path.into(),
+            staged_path: staged,
+            size_bytes: size,
+            category: "Logs".to_string(),
+            safety: "safe".to_string(),
+            is_dir: false,
+            success: true,
+            error: None,
+            restored: None,
+            content_hash: None,
+            archive_entry: None,
+            staged_bytes: None,
+        }
+    }
+
+    fn write_log(logs: &Path, manifests: &[&CleanManifest]) {
+        std::fs::create_dir_all(logs).unwrap();
+        let lines: Vec<String> = manifests
+            .iter()
+            .map(|m| serde_json::to_string(m).unwrap())
+            .collect();
+        std::fs::write(logs.join("clean-2026-01-01.jsonl"), lines.join("\n") + "\n").unwrap();
+    }
+
+    #[test]
+    fn test_pattern_matches_names_paths_and_directories() {
+        let by_name = PathPattern::new("*.psd").unwrap();
+        assert!(by_name.matches(Path::new("/Users/me/art/cover.psd")));
+        assert!(!by_name.matches(Path::new("/Users/me/art/cover.png")));
+
+        let by_dir = PathPattern::new("/Users/*/art").unwrap();
+        assert!(by_dir.matches(Path::new("/Users/me/art/cover.png")));
+        assert!(!by_dir.matches(Path::new("/Users/me/music/song.mp3")));
+    }
+
+    #[test]
+    fn test_sessions_combine_staging_and_logs() {
+        let root = tempfile::tempdir().unwrap();
+        let (staging, logs) = (root.path().join("staging"), root.path().join("logs"));
+
+        // A staged session whose log line is out of date
+        let staged_file = root.path().join("000001");
+        std::fs::write(&staged_file, "x").unwrap();
+        let mut soft = CleanManifest::new("dev", "soft_delete", 7);
+        soft.session_id = "soft".to_string();
+        soft.items.push(item("/a/kept.log", 10, Some(staged_file)));
+        write_log(&logs, &[&soft]);
+        soft.items.push(item("/a/later.log", 20, Some(root.path().join("gone"))));
+        std::fs::create_dir_all(staging.join("soft")).unwrap();
+        std::fs::write(
+            staging.join("soft/manifest.json"),
+            serde_json::to_string(&soft).unwrap(),
+        )
+        .unwrap();
+
+        // Only in the logs
+        let mut hard = CleanManifest::new("dev", "hard_delete", 0);
+        hard.session_id = "hard".to_string();
+        hard.items.push(item("/b/big.log", 5000, None));
+        hard.skipped.push(SkippedPath {
+            path: "/b/changed.log".into(),
+            size_bytes: 30,
+            category: "Logs".to_string(),
+            reason: "was modified since the scan".to_string(),
+        });
+        let mut purged = CleanManifest::new("dev", "soft_delete", 7);
+        purged.session_id = "purged".to_string();
+        purged.items.push(item("/c/old.log", 40, Some("/nowhere".into())));
+        write_log(&logs, &[&soft, &hard, &purged]);
+
+        let sessions = load_sessions(&staging, &logs).unwrap();
+        assert_eq!(sessions.len(), 3);
+        let status = |id: &str, n: usize| {
+            let (m, staged) = sessions.iter().find(|(m, _)| m.session_id == id).unwrap();
+            status_of(m, &m.items[n], *staged)
+        };
+        assert_eq!(status("soft", 0), ItemStatus::Restorable);
+        assert_eq!(status("soft", 1), ItemStatus::Purged);
+        assert_eq!(status("hard", 0), ItemStatus::HardDeleted);
+        assert_eq!(status("purged", 0), ItemStatus::Purged);
+    }
+}
@@ -0,0 +1,658 @@
+//! Write-ahead journal for soft deletes
+//!
+//! `stage_files` moves files one at a time, and the manifest is only written
+//! once they are all done. So that a crash, kill or panic in between can't
+//! strand staged files, each move is journaled to `staging/<id>/journal.jsonl`
+//! before it happens and confirmed after. The journal is removed once the
+//! manifest is saved: a session directory with a journal but no manifest
+//! was interrupted, and `tidymac recover` either moves its files back or
+//! writes the manifest the clean never got to.
+//!
+//! Every entry is one append to the file, so it survives the process dying
+//! (though not a power cut before the OS flushes it).
+//!
+//! The clean holds an exclusive `flock` on the journal until it is done, so
+//! a session whose journal is still locked belongs to a clean that is
+//! running (in another terminal, or the app) and isn't offered for recovery.
+//! The lock goes with the process, however it dies.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::archive;
+use super::manifest::{CleanManifest, ManifestItem};
+use super::staging::{self, RestoreReport};
+use super::store;
+use crate::common::config::Config;
+
+const JOURNAL_FILE: &str = "journal.jsonl";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum JournalEntry {
+    Begin {
+        session_id: String,
+        profile: String,
+        timestamp: DateTime<Utc>,
+        expires_at: Option<DateTime<Utc>>,
+    },
+    /// About to move `item.original_path` to `item.staged_path`
+    Move {
+        seq: usize,
+        item: ManifestItem,
+    },
+    Done {
+        seq: usize,
+    },
+    Failed {
+        seq: usize,
+        error: String,
+    },
+    /// About to move the staged file into the store as blob `hash`
+    Stored {
+        seq: usize,
+        hash: String,
+    },
+    /// The staged file is in the session archive and about to be removed
+    Packed {
+        seq: usize,
+    },
+}
+
+/// The journal of a soft delete in progress
+pub struct Journal {
+    path: PathBuf,
+    file: std::fs::File,
+}
+
+impl Journal {
+    /// Start the journal in the manifest's session directory
+    pub fn create(manifest: &CleanManifest) -> Result<Self> {
+        let dir = manifest.staging_session_dir();
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create session dir: {}", dir.display()))?;
+        let path = dir.join(JOURNAL_FILE);
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open journal: {}", path.display()))?;
+        if !try_lock(&file, libc::LOCK_EX) {
+            anyhow::bail!("Journal is in use by another clean: {}", path.display());
+        }
+        let mut journal = Self { path, file };
+        journal.append(&JournalEntry::Begin {
+            session_id: manifest.session_id.clone(),
+            profile: manifest.profile.clone(),
+            timestamp: manifest.timestamp,
+            expires_at: manifest.expires_at,
+        })?;
+        Ok(journal)
+    }
+
+    /// Record that `item` is about to be moved to its staged path
+    pub fn record_move(&mut self, seq: usize, item: &ManifestItem) -> Result<()> {
+        self.append(&JournalEntry::Move {
+            seq,
+            item: item.clone(),
+        })
+    }
+
+    pub fn record_done(&mut self, seq: usize) -> Result<()> {
+        self.append(&JournalEntry::Done { seq })
+    }
+
+    pub fn record_failed(&mut self, seq: usize, error: &str) -> Result<()> {
+        self.append(&JournalEntry::Failed {
+            seq,
+            error: error.to_string(),
+        })
+    }
+
+    pub fn record_stored(&mut self, seq: usize, hash: &str) -> Result<()> {
+        self.append(&JournalEntry::Stored {
+            seq,
+            hash: hash.to_string(),
+        })
+    }
+
+    pub fn record_packed(&mut self, seq: usize) -> Result<()> {
+        self.append(&JournalEntry::Packed { seq })
+    }
+
+    /// Drop the journal once the manifest is safely saved
+    pub fn finish(self) -> Result<()> {
+        std::fs::remove_file(&self.path)
+            .with_context(|| format!("Failed to remove journal: {}", self.path.display()))
+    }
+
+    fn append(&mut self, entry: &JournalEntry) -> Result<()> {
+        let line =
+            serde_json::to_string(entry).context("Failed to serialize journal entry")? + "\n";
+        self.file
+            .write_all(line.as_bytes())
+            .with_context(|| format!("Failed to write journal: {}", self.path.display()))
+    }
+}
+
+/// Where a journaled move got to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MoveState {
+    /// In staging, gone from the original path
+    Staged,
+    /// Still at the original path
+    NotMoved,
+    /// At both: cut short while copying across filesystems
+    Interrupted,
+    /// At neither
+    Missing,
+}
+
+/// One move from an interrupted session's journal
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingMove {
+    pub item: ManifestItem,
+    pub state: MoveState,
+}
+
+/// A soft delete that stopped before writing its manifest
+#[derive(Debug, Clone, Serialize)]
+pub struct IncompleteSession {
+    pub session_id: String,
+    pub profile: String,
+    pub timestamp: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub moves: Vec<PendingMove>,
+}
+
+impl IncompleteSession {
+    /// Files sitting in staging that only the journal knows about
+    pub fn staged_count(&self) -> usize {
+        self.moves
+            .iter()
+            .filter(|m| matches!(m.state, MoveState::Staged | MoveState::Interrupted))
+            .count()
+    }
+
+    pub fn staged_bytes(&self) -> u64 {
+        self.moves
+            .iter()
+            .filter(|m| matches!(m.state, MoveState::Staged | MoveState::Interrupted))
+            .map(|m| m.item.size_bytes)
+            .sum()
+    }
+
+    fn dir(&self) -> PathBuf {
+        Config::staging_dir().join(&self.session_id)
+    }
+
+    /// A manifest for this session with no items yet
+    fn empty_manifest(&self) -> CleanManifest {
+        let mut manifest = CleanManifest::new(&self.profile, "soft_delete", 0);
+        manifest.session_id = self.session_id.clone();
+        manifest.timestamp = self.timestamp;
+        manifest.expires_at = self.expires_at;
+        manifest.cancelled = true;
+        manifest
+    }
+}
+
+/// Staged files no manifest or journal accounts for
+#[derive(Debug, Clone, Serialize)]
+pub struct OrphanedFiles {
+    pub session_id: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Every session in the staging area that was interrupted, oldest first
+pub fn incomplete_sessions() -> Result<Vec<IncompleteSession>> {
+    incomplete_in(&Config::staging_dir())
+}
+
+fn incomplete_in(staging_dir: &Path) -> Result<Vec<IncompleteSession>> {
+    let Ok(entries) = std::fs::read_dir(staging_dir) else {
+        return Ok(Vec::new());
+    };
+    let mut sessions = Vec::new();
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        // A manifest means the clean finished and only the journal's
+        // removal was cut short
+        if dir.join("manifest.json").exists() || !dir.join(JOURNAL_FILE).exists() {
+            continue;
+        }
+        if is_live(&dir.join(JOURNAL_FILE)) {
+            continue;
+        }
+        if let Some(session) = replay(&dir.join(JOURNAL_FILE))? {
+            sessions.push(session);
+        }
+    }
+    sessions.sort_by_key(|s| s.timestamp);
+    Ok(sessions)
+}
+
+/// Whether a running clean still holds the journal's lock
+fn is_live(journal: &Path) -> bool {
+    std::fs::File::open(journal).is_ok_and(|file| !try_lock(&file, libc::LOCK_SH))
+}
+
+/// Take a `flock` without waiting; it is released when `file` is closed
+fn try_lock(file: &std::fs::File, operation: libc::c_int) -> bool {
+    use std::os::unix::io::AsRawFd;
+    unsafe { libc::flock(file.as_raw_fd(), operation | libc::LOCK_NB) == 0 }
+}
+
+/// Rebuild a session from its journal, checking each unconfirmed move
+/// against the disk
+fn replay(path: &Path) -> Result<Option<IncompleteSession>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read journal: {}", path.display()))?;
+    let mut session = None;
+    let mut moves: Vec<(usize, ManifestItem)> = Vec::new();
+    let mut outcomes: HashMap<usize, Option<String>> = HashMap::new();
+    let mut stored: HashMap<usize, String> = HashMap::new();
+    let mut packed: HashSet<usize> = HashSet::new();
+
+    // The last line may have been cut short by the crash
+    for entry in contents
+        .lines()
+        .filter_map(|l| serde_json::from_str::<JournalEntry>(l).ok())
+    {
+        match entry {
+            JournalEntry::Begin {
+                session_id,
+                profile,
+                timestamp,
+                expires_at,
+            } => {
+                session = Some(IncompleteSession {
+                    session_id,
+                    profile,
+                    timestamp,
+                    expires_at,
+                    moves: Vec::new(),
+                })
+            }
+            JournalEntry::Move { seq, item } => moves.push((seq, item)),
+            JournalEntry::Done { seq } => {
+                outcomes.insert(seq, None);
+            }
+            JournalEntry::Failed { seq, error } => {
+                outcomes.insert(seq, Some(error));
+            }
+            JournalEntry::Stored { seq, hash } => {
+                stored.insert(seq, hash);
+            }
+            JournalEntry::Packed { seq } => {
+                packed.insert(seq);
+            }
+        }
+    }
+    let Some(mut session) = session else {
+        return Ok(None);
+    };
+
+    for (seq, mut item) in moves {
+        let at_original = std::fs::symlink_metadata(&item.original_path).is_ok();
+        let mut in_staging = item
+            .staged_path
+            .as_ref()
+            .is_some_and(|p| std::fs::symlink_metadata(p).is_ok());
+        // Moved on into the store
+        if let Some(hash) = stored.remove(&seq) {
+            let blob = store::blob_path(&hash);
+            if !in_staging && blob.exists() {
+                item.staged_path = Some(blob);
+                item.content_hash = Some(hash);
+                in_staging = true;
+            }
+        }
+        // Packed into the session archive
+        if let (true, false, Some(staged)) = (packed.contains(&seq), in_staging, &item.staged_path)
+        {
+            let entry = archive::entry_name(staged);
+            let archive_path = path.with_file_name(archive::ARCHIVE_FILE);
+            if archive::contains(&archive_path, &entry) {
+                item.staged_path = Some(archive_path);
+                item.archive_entry = Some(entry);
+                in_staging = true;
+            }
+        }
+        let state = match outcomes.remove(&seq) {
+            Some(Some(error)) => {
+                item.error = Some(error);
+                MoveState::NotMoved
+            }
+            Some(None) if in_staging => MoveState::Staged,
+            _ => match (at_original, in_staging) {
+                (false, true) => MoveState::Staged,
+                (true, false) => MoveState::NotMoved,
+                (true, true) => MoveState::Interrupted,
+                (false, false) => MoveState::Missing,
+            },
+        };
+        session.moves.push(PendingMove { item, state });
+    }
+    Ok(Some(session))
+}
+
+/// Write the manifest an interrupted session never got, so what it staged
+/// can be undone as usual
+///
+/// A file caught mid-copy is left at its original path and its partial
+/// staged copy removed; a directory caught mid-copy keeps both, since either
+/// may be incomplete.
+pub fn finalize(session: &IncompleteSession) -> Result<CleanManifest> {
+    let mut manifest = session.empty_manifest();
+    for pending in &session.moves {
+        let item = pending.item.clone();
+        match pending.state {
+            MoveState::Staged => manifest.add_item(item),
+            MoveState::Interrupted if item.is_dir => {
+                manifest.add_error(format!(
+                    "'{}' was interrupted while being copied to staging; both copies were kept",
+                    item.original_path.display()
+                ));
+                manifest.add_item(item);
+            }
+            MoveState::Interrupted => {
+                if let Some(ref staged) = item.staged_path {
+                    let _ = std::fs::remove_file(staged);
+                }
+                manifest.add_item(unmoved(item, "interrupted while copying; left in place"));
+            }
+            MoveState::NotMoved => {
+                manifest.add_item(unmoved(item, "not moved before the clean was interrupted"))
+            }
+            MoveState::Missing => {
+                let path = item.original_path.display().to_string();
+                manifest.add_item(unmoved(item, "missing from both its path and staging"));
+                manifest.add_error(format!("Lost track of '{}'", path));
+            }
+        }
+    }
+
+    manifest
+        .save()
+        .context("Failed to save recovered manifest")?;
+    let _ = std::fs::remove_file(session.dir().join(JOURNAL_FILE));
+    Ok(manifest)
+}
+
+/// `item` as a failed entry, keeping any error the journal recorded
+fn unmoved(item: ManifestItem, reason: &str) -> ManifestItem {
+    ManifestItem {
+        staged_path: None,
+        success: false,
+        error: Some(item.error.clone().unwrap_or_else(|| reason.to_string())),
+        ..item
+    }
+}
+
+/// Move everything an interrupted session staged back where it came from
+///
+/// Anything that can't go back (including directories caught mid-copy) is
+/// kept in a manifest for the session, so `undo` can still reach it.
+pub fn rollback(session: &IncompleteSession) -> Result<RestoreReport> {
+    let mut report = RestoreReport {
+        session_id: session.session_id.clone(),
+        restored_count: 0,
+        restored_bytes: 0,
+        errors: Vec::new(),
+        skipped: Vec::new(),
+        remaining: 0,
+    };
+    let mut kept = session.empty_manifest();
+
+    for pending in &session.moves {
+        let item = &pending.item;
+        let Some(ref staged) = item.staged_path else {
+            continue;
+        };
+        match pending.state {
+            MoveState::Staged => {
+                if let Some(parent) = item.original_path.parent() {
+                    let _ = std::fs::create_dir_all(parent);
+                }
+                match staging::restore_item(item, staged, &item.original_path) {
+                    Ok(()) => {
+                        report.restored_count += 1;
+                        report.restored_bytes += item.size_bytes;
+                    }
+                    Err(e) => {
+                        report.errors.push(format!(
+                            "Failed to restore '{}': {}",
+                            item.original_path.display(),
+                            e
+                        ));
+                        kept.add_item(item.clone());
+                    }
+                }
+            }
+            MoveState::Interrupted if item.is_dir => {
+                report.errors.push(format!(
+                    "'{}' was interrupted while being copied; the staged copy was kept",
+                    item.original_path.display()
+                ));
+                kept.add_item(item.clone());
+            }
+            MoveState::Interrupted => {
+                let _ = std::fs::remove_file(staged);
+            }
+            MoveState::NotMoved | MoveState::Missing => {}
+        }
+    }
+
+    report.remaining = kept.items.len();
+    if kept.items.is_empty() {
+        std::fs::remove_dir_all(session.dir())
+            .with_context(|| format!("Failed to remove session: {}", session.session_id))?;
+    } else {
+        kept.save()
+            .context("Failed to save manifest for kept files")?;
+        let _ = std::fs::remove_file(session.dir().join(JOURNAL_FILE));
+    }
+    Ok(report)
+}
+
+/// Sessions holding staged files that nothing records, e.g. from a crash
+/// before the journal existed
+pub fn orphaned_files() -> Result<Vec<OrphanedFiles>> {
+    orphans_in(&Config::staging_dir())
+}
+
+fn orphans_in(staging_dir: &Path) -> Result<Vec<OrphanedFiles>> {
+    let Ok(entries) = std::fs::read_dir(staging_dir) else {
+        return Ok(Vec::new());
+    };
+    let mut orphans = Vec::new();
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        let Ok(files) = std::fs::read_dir(dir.join("files")) else {
+            continue;
+        };
+        let known = recorded_paths(&dir)?;
+        let (mut count, mut bytes) = (0, 0);
+        for file in files.flatten() {
+            let path = file.path();
+            if known.contains(&path) {
+                continue;
+            }
+            count += 1;
+            bytes += if path.is_dir() {
+                crate::scanner::walker::dir_size(&path)
+            } else {
+                file.metadata().map(|m| m.len()).unwrap_or(0)
+            };
+        }
+        if count > 0 {
+            orphans.push(OrphanedFiles {
+                session_id: entry.file_name().to_string_lossy().to_string(),
+                files: count,
+                bytes,
+            });
+        }
+    }
+    orphans.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+    Ok(orphans)
+}
+
+/// Blobs a session directory still needs: those of its manifest's items
+/// not yet restored, or for a session without a manifest, every blob its
+/// journal mentions
+///
+/// A manifest that can't be read is an error, so nothing it might reference
+/// is swept.
+pub(super) fn referenced_hashes(dir: &Path) -> Result<Vec<String>> {
+    let manifest_path = dir.join("manifest.json");
+    if manifest_path.exists() {
+        let manifest: CleanManifest = std::fs::read_to_string(&manifest_path)
+            .ok()
+            .and_then(|c| serde_json::from_str(&c).ok())
+            .with_context(|| format!("Failed to read manifest: {}", manifest_path.display()))?;
+        return Ok(manifest
+            .items
+            .into_iter()
+            .filter(|i| i.is_restorable())
+            .filter_map(|i| i.content_hash)
+            .collect());
+    }
+    let Ok(contents) = std::fs::read_to_string(dir.join(JOURNAL_FILE)) else {
+        return Ok(Vec::new());
+    };
+    Ok(contents
+        .lines()
+        .filter_map(|l| serde_json::from_str::<JournalEntry>(l).ok())
+        .filter_map(|e| match e {
+            JournalEntry::Stored { hash, .. } => Some(hash),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Staged paths the session's manifest, or failing that its journal, knows
+fn recorded_paths(dir: &Path) -> Result<HashSet<PathBuf>> {
+    if let Ok(contents) = std::fs::read_to_string(dir.join("manifest.json")) {
+        if let Ok(manifest) = serde_json::from_str::<CleanManifest>(&contents) {
+            return Ok(manifest
+                .items
+                .into_iter()
+                .filter_map(|i| i.staged_path)
+                .collect());
+        }
+    }
+    let journal = dir.join(JOURNAL_FILE);
+    if journal.exists() {
+        if let Some(session) = replay(&journal)? {
+            return Ok(session
+                .moves
+                .into_iter()
+                .filter_map(|m| m.item.staged_path)
+                .collect());
+        }
+    }
+    Ok(HashSet::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(original: &Path, staged: &Path) -> ManifestItem {
+        ManifestItem {
+            original_path: original.to_path_buf(),
+            staged_path: Some(staged.to_path_buf()),
+            size_bytes: 4,
+            category: "Temp Files".to_string(),
+            safety: "Safe".to_string(),
+            is_dir: false,
+            success: true,
+            error: None,
+            restored: None,
+            content_hash: None,
+            archive_entry: None,
+            staged_bytes: None,
+        }
+    }
+
+    #[test]
+    fn test_replay_classifies_moves_from_the_disk() {
+        let root = tempfile::tempdir().unwrap();
+        let session_dir = root.path().join("staging/s1");
+        let files = session_dir.join("files");
+        std::fs::create_dir_all(&files).unwrap();
+        let original = |n: &str| root.path().join(n);
+
+        // Confirmed, unconfirmed but done, not started, cut short mid-copy
+        std::fs::write(files.join("000001"), "data").unwrap();
+        std::fs::write(files.join("000002"), "data").unwrap();
+        std::fs::write(original("c"), "data").unwrap();
+        std::fs::write(original("d"), "data").unwrap();
+        std::fs::write(files.join("000004"), "da").unwrap();
+        // Unreferenced by the journal
+        std::fs::write(files.join("000009"), "lost").unwrap();
+
+        let entries = [
+            JournalEntry::Begin {
+                session_id: "s1".to_string(),
+                profile: "quick".to_string(),
+                timestamp: Utc::now(),
+                expires_at: None,
+            },
+            JournalEntry::Move {
+                seq: 1,
+                item: item(&original("a"), &files.join("000001")),
+            },
+            JournalEntry::Done { seq: 1 },
+            JournalEntry::Move {
+                seq: 2,
+                item: item(&original("b"), &files.join("000002")),
+            },
+            JournalEntry::Move {
+                seq: 3,
+                item: item(&original("c"), &files.join("000003")),
+            },
+            JournalEntry::Move {
+                seq: 4,
+                item: item(&original("d"), &files.join("000004")),
+            },
+            JournalEntry::Move {
+                seq: 5,
+                item: item(&original("e"), &files.join("000005")),
+            },
+        ];
+        let mut lines: String = entries
+            .iter()
+            .map(|e| serde_json::to_string(e).unwrap() + "\n")
+            .collect();
+        lines.push_str("{\"op\":\"do");
+        std::fs::write(session_dir.join(JOURNAL_FILE), lines).unwrap();
+
+        let sessions = incomplete_in(&root.path().join("staging")).unwrap();
+        assert_eq!(sessions.len(), 1);
+        let states: Vec<MoveState> = sessions[0].moves.iter().map(|m| m.state).collect();
+        assert_eq!(
+            states,
+            vec![
+                MoveState::Staged,
+                MoveState::Staged,
+                MoveState::NotMoved,
+                MoveState::Interrupted,
+                MoveState::Missing
+            ]
+        );
+        assert_eq!(sessions[0].staged_count(), 3);
+
+        let orphans = orphans_in(&root.path().join("staging")).unwrap();
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].files, 1);
+        assert_eq!(orphans[0].bytes, 4);
+    }
+}
@@ -33,14 +33,23 @@ pub struct CleanManifest {
     /// When staged files expire (soft-delete only)
     pub expires_at: Option<DateTime<Utc>>,
 
-    /// Whether this session has been restored via undo
+    /// Whether every staged item of this session has been restored via undo;
+    /// see `ManifestItem::restored` for partial restores
     pub restored: bool,
 
+    /// The clean was cancelled partway; only the listed items were touched
+    #[serde(default)]
+    pub cancelled: bool,
+
     /// Individual items that were cleaned
     pub items: Vec<ManifestItem>,
 
     /// Errors encountered
     pub errors: Vec<String>,
+
+    /// Paths left alone because they changed since the scan
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<SkippedPath>,
 }
 
 /// A single file/directory entry in the manifest
@@ -69,8 +78,61 @@ pub struct ManifestItem {
 
     /// Error message if processing failed
     pub error: Option<String>,
+
+    /// SHA-256 of the contents, for items kept in the content-addressed
+    /// store; `staged_path` is then the shared blob
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+
+    /// Name of the item's entry in the session archive, for items packed by
+    /// compressed staging; `staged_path` is then the archive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_entry: Option<String>,
+
+    /// Bytes the item takes up in staging, when not `size_bytes`: its
+    /// compressed size, or 0 for a file the store already held
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub staged_bytes: Option<u64>,
+
+    /// Set once this item has been restored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restored: Option<ItemRestore>,
+}
+
+/// When and where a staged item was restored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemRestore {
+    pub at: DateTime<Utc>,
+
+    /// Where it was put back: the original path, a renamed copy next to it,
+    /// or a path under `undo --to`
+    pub path: PathBuf,
 }
 
+impl ManifestItem {
+    /// Still in staging and not restored yet
+    pub fn is_restorable(&self) -> bool {
+        self.success && self.staged_path.is_some() && self.restored.is_none()
+    }
+}
+
+/// A path the clean refused to touch, and why
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedPath {
+    pub path: PathBuf,
+
+    /// Size recorded by the scan
+    pub size_bytes: u64,
+
+    /// Category label
+    pub category: String,
+
+    pub reason: String,
+}
+
+/// Schema written by this release; 2 added per-item restore state
+const SCHEMA_VERSION: u32 = 2;
+
 fn default_schema_version() -> u32 {
     1
 }
@@ -87,7 +149,7 @@ impl CleanManifest {
         };
 
         Self {
-            schema_version: default_schema_version(),
+            schema_version: SCHEMA_VERSION,
             session_id,
             timestamp: now,
             profile: profile.to_string(),
@@ -96,8 +158,10 @@ impl CleanManifest {
             total_files: 0,
             expires_at,
             restored: false,
+            cancelled: false,
             items: Vec::new(),
             errors: Vec::new(),
+            skipped: Vec::new(),
         }
     }
 
@@ -110,11 +174,25 @@ impl CleanManifest {
         self.items.push(item);
     }
 
+    /// Bytes the successful items take up in staging
+    pub fn staged_bytes(&self) -> u64 {
+        self.items
+            .iter()
+            .filter(|i| i.success)
+            .map(|i| i.staged_bytes.unwrap_or(i.size_bytes))
+            .sum()
+    }
+
     /// Add an error
     pub fn add_error(&mut self, error: String) {
         self.errors.push(error);
     }
 
+    /// Record a path that was left alone
+    pub fn add_skipped(&mut self, skipped: SkippedPath) {
+        self.skipped.push(skipped);
+    }
+
     /// Check if this session's staged files have expired
     pub fn is_expired(&self) -> bool {
         if let Some(expires_at) = self.expires_at {
@@ -183,12 +261,39 @@ impl CleanManifest {
         let contents = std::fs::read_to_string(&manifest_path)
             .with_context(|| format!("Failed to read manifest: {}", manifest_path.display()))?;
 
-        let manifest: CleanManifest = serde_json::from_str(&contents)
+        let mut manifest: CleanManifest = serde_json::from_str(&contents)
             .with_context(|| format!("Failed to parse manifest: {}", manifest_path.display()))?;
+        manifest.migrate();
 
         Ok(manifest)
     }
 
+    /// Bring a manifest written by an older release up to date
+    fn migrate(&mut self) {
+        // Before per-item state, a restore always covered the whole session
+        // and put everything back where it came from
+        if self.schema_version < 2 && self.restored {
+            for item in self.items.iter_mut().filter(|i| i.is_restorable()) {
+                item.restored = Some(ItemRestore {
+                    at: self.timestamp,
+                    path: item.original_path.clone(),
+                });
+            }
+        }
+        self.schema_version = self.schema_version.max(SCHEMA_VERSION);
+    }
+
+    /// `self`, brought up to date as by `migrate`
+    pub(crate) fn migrated(mut self) -> Self {
+        self.migrate();
+        self
+    }
+
+    /// Items still waiting in staging
+    pub fn restorable_items(&self) -> impl Iterator<Item = &ManifestItem> {
+        self.items.iter().filter(|i| i.is_restorable())
+    }
+
     /// List all available sessions in the staging area
     pub fn list_sessions() -> Result<Vec<SessionSummary>> {
         let staging_dir = Config::staging_dir();
@@ -217,6 +322,11 @@ impl CleanManifest {
                 if let Ok(manifest) = serde_json::from_str::<CleanManifest>(&contents) {
                     let staged_size = crate::scanner::walker::dir_size(&path);
                     let is_expired = manifest.is_expired();
+                    let restored_files = manifest
+                        .items
+                        .iter()
+                        .filter(|i| i.restored.is_some())
+                        .count();
                     sessions.push(SessionSummary {
                         session_id: manifest.session_id.clone(),
                         timestamp: manifest.timestamp,
@@ -227,6 +337,7 @@ impl CleanManifest {
                         staged_size,
                         expires_at: manifest.expires_at,
                         restored: manifest.restored,
+                        restored_files,
                         is_expired,
                     });
                 }
@@ -244,9 +355,11 @@ impl CleanManifest {
         Ok(sessions.first().map(|s| s.session_id.clone()))
     }
 
-    /// Mark this session as restored
-    pub fn mark_restored(&mut self) -> Result<()> {
-        self.restored = true;
+    /// Record restored items, marking the session restored once nothing
+    /// is left in staging
+    pub fn save_restore_state(&mut self) -> Result<()> {
+        let remaining = self.restorable_items().count();
+        self.restored = remaining == 0;
         self.save()?;
         Ok(())
     }
@@ -264,5 +377,8 @@ pub struct SessionSummary {
     pub staged_size: u64,
     pub expires_at: Option<DateTime<Utc>>,
     pub restored: bool,
+    /// Files restored so far; some but not all for a partly restored session
+    #[serde(default)]
+    pub restored_files: usize,
     pub is_expired: bool,
 }
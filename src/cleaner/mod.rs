@@ -1,9 +1,26 @@
+pub mod archive;
 pub mod engine;
+pub mod guard;
+pub mod history;
+pub mod journal;
 pub mod manifest;
+pub mod planner;
 pub mod purger;
+pub mod saved_plan;
 pub mod staging;
+pub mod store;
 
-pub use engine::{check_staging_health, clean, CleanMode, CleanReport, StagingHealth};
-pub use manifest::{CleanManifest, ManifestItem, SessionSummary};
+pub use engine::{
+    apply_trim, check_staging_health, clean, clean_with_store, drop_excluded, partition_by_safety,
+    CleanMode, CleanReport, HeldBackItem, StagingHealth, TrimmedItem,
+};
+pub use history::{FoundItem, HistoryQuery, ItemStatus, PathPattern};
+pub use journal::{IncompleteSession, MoveState, OrphanedFiles};
+pub use manifest::{CleanManifest, ItemRestore, ManifestItem, SessionSummary, SkippedPath};
+pub use planner::{plan_for_goal, CleanPlan};
 pub use purger::{purge_all, purge_expired, purge_session, PurgeReport};
-pub use staging::{restore_session, RestoreReport};
+pub use saved_plan::{DriftedFile, SavedPlan};
+pub use staging::{
+    restore_session, restore_session_with_options, select_for_restore, verify_session, OnConflict,
+    RestoreOptions, RestoreReport, VerifyReport,
+};
@@ -0,0 +1,280 @@
+//! Planning a clean that frees a byte goal
+//!
+//! Instead of cleaning everything a profile found, `plan_for_goal` picks
+//! files one at a time, least risky first, until the goal is met:
+//! `Safe` before `Caution` before `Dangerous`, regenerable caches before
+//! logs before user data, and within those the oldest files first.
+
+use serde::Serialize;
+use std::time::SystemTime;
+
+use crate::scanner::targets::{self, Category, DevTool, FileEntry, SafetyLevel, ScanItem};
+
+/// How costly losing a category is, cheapest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Disposability {
+    /// Rebuilt or re-downloaded on demand (caches, build output, temp files)
+    Regenerable,
+    /// Not rebuilt, but only of diagnostic value (logs, crash reports)
+    Diagnostic,
+    /// Something the user made or downloaded
+    UserData,
+}
+
+impl Disposability {
+    pub fn of(category: &Category) -> Self {
+        match category {
+            Category::SystemCache | Category::UserCache | Category::TempFiles => {
+                Disposability::Regenerable
+            }
+            // Xcode archives are signed builds someone may still need
+            Category::DevCache(DevTool::XcodeArchives) => Disposability::UserData,
+            Category::DevCache(_) => Disposability::Regenerable,
+            Category::Logs | Category::CrashReports => Disposability::Diagnostic,
+            Category::LargeFile
+            | Category::Duplicate
+            | Category::MailAttachment
+            | Category::Trash
+            | Category::BrowserData
+            | Category::AppLeftover
+            | Category::StartupItem
+            | Category::DownloadedDmg
+            | Category::OldDownload => Disposability::UserData,
+        }
+    }
+}
+
+/// What a goal-driven clean would remove
+#[derive(Debug, Clone, Serialize)]
+pub struct CleanPlan {
+    pub goal_bytes: u64,
+    /// Bytes the planned items free
+    pub planned_bytes: u64,
+    /// Everything the allowed items could free at most
+    pub available_bytes: u64,
+    /// Whether `planned_bytes` meets the goal
+    pub reachable: bool,
+    /// The selection, in the order it was picked; each item keeps only its
+    /// chosen files and its totals cover just those
+    pub items: Vec<ScanItem>,
+}
+
+impl CleanPlan {
+    pub fn file_count(&self) -> usize {
+        self.items.iter().map(|i| i.file_count).sum()
+    }
+}
+
+/// One removable unit: a file, or a whole item that lists no files
+struct Candidate<'a> {
+    item: usize,
+    file: Option<&'a FileEntry>,
+    freeable: u64,
+    safety: &'a SafetyLevel,
+    disposability: Disposability,
+    modified: Option<SystemTime>,
+}
+
+/// Pick the least risky files from `items` until `goal_bytes` would be freed
+///
+/// `items` should already be filtered by `--max-safety` and exclusions and
+/// carry full file lists. Files that free nothing (hard-linked elsewhere)
+/// are never picked. If the goal is out of reach, the plan holds everything
+/// and `reachable` is false.
+pub fn plan_for_goal(items: &[ScanItem], goal_bytes: u64) -> CleanPlan {
+    let mut candidates: Vec<Candidate> = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        let disposability = Disposability::of(&item.category);
+        if item.files.is_empty() {
+            candidates.push(Candidate {
+                item: index,
+                file: None,
+                freeable: item.freeable_bytes,
+                safety: &item.safety,
+                disposability,
+                modified: None,
+            });
+        }
+        for file in &item.files {
+            candidates.push(Candidate {
+                item: index,
+                file: Some(file),
+                freeable: file.freeable_bytes,
+                safety: &item.safety,
+                disposability,
+                modified: file.modified,
+            });
+        }
+    }
+    candidates.retain(|c| c.freeable > 0);
+
+    // Unknown ages sort after every known one, as if brand new
+    candidates.sort_by(|a, b| {
+        a.safety
+            .cmp(b.safety)
+            .then(a.disposability.cmp(&b.disposability))
+            .then_with(|| match (a.modified, b.modified) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            })
+            .then(b.freeable.cmp(&a.freeable))
+    });
+
+    let available_bytes: u64 = candidates.iter().map(|c| c.freeable).sum();
+    let mut planned_bytes = 0;
+    // Chosen files per item, with the order items were first touched
+    let mut chosen: Vec<(usize, Vec<FileEntry>, bool)> = Vec::new();
+    for candidate in &candidates {
+        if planned_bytes >= goal_bytes {
+            break;
+        }
+        planned_bytes += candidate.freeable;
+        let slot = match chosen.iter().position(|(i, _, _)| *i == candidate.item) {
+            Some(slot) => slot,
+            None => {
+                chosen.push((candidate.item, Vec::new(), false));
+                chosen.len() - 1
+            }
+        };
+        match candidate.file {
+            Some(file) => chosen[slot].1.push(file.clone()),
+            None => chosen[slot].2 = true,
+        }
+    }
+
+    let planned: Vec<ScanItem> = chosen
+        .into_iter()
+        .map(|(index, files, whole)| {
+            let item = &items[index];
+            if whole || files.len() == item.files.len() {
+                return item.clone();
+            }
+            let usage = targets::file_totals(&files);
+            ScanItem {
+                size_bytes: usage.allocated,
+                apparent_bytes: usage.apparent,
+                freeable_bytes: usage.freeable,
+                file_count: files.len(),
+                files,
+                ..item.clone()
+            }
+        })
+        .collect();
+
+    CleanPlan {
+        goal_bytes,
+        planned_bytes,
+        available_bytes,
+        reachable: planned_bytes >= goal_bytes,
+        items: planned,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn file(path: &str, size: u64, days_old: u64) -> FileEntry {
+        FileEntry {
+            path: path.into(),
+            size_bytes: size,
+            modified: Some(SystemTime::now() - Duration::from_secs(days_old * 86400)),
+            apparent_bytes: size,
+            freeable_bytes: size,
+            hard_link: None,
+            accessed: None,
+            file_id: None,
+        }
+    }
+
+    fn item(
+        name: &str,
+        category: Category,
+        safety: SafetyLevel,
+        files: Vec<FileEntry>,
+    ) -> ScanItem {
+        let usage = targets::file_totals(&files);
+        ScanItem {
+            name: name.to_string(),
+            category,
+            path: format!("/data/{}", name).into(),
+            size_bytes: usage.allocated,
+            apparent_bytes: usage.apparent,
+            freeable_bytes: usage.freeable,
+            file_count: files.len(),
+            safety,
+            reason: String::new(),
+            files,
+            files_partial: false,
+            trim: None,
+            roots: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_plan_takes_least_risky_oldest_files_first() {
+        let items = vec![
+            item(
+                "Downloads",
+                Category::OldDownload,
+                SafetyLevel::Safe,
+                vec![file("/d/old.dmg", 500, 400)],
+            ),
+            item(
+                "Logs",
+                Category::Logs,
+                SafetyLevel::Safe,
+                vec![file("/l/a.log", 100, 90)],
+            ),
+            item(
+                "npm",
+                Category::DevCache(DevTool::Npm),
+                SafetyLevel::Safe,
+                vec![file("/n/new", 100, 1), file("/n/old", 100, 60)],
+            ),
+            item(
+                "Xcode",
+                Category::DevCache(DevTool::Xcode),
+                SafetyLevel::Caution,
+                vec![file("/x/derived", 1000, 300)],
+            ),
+        ];
+
+        let plan = plan_for_goal(&items, 50);
+        assert!(plan.reachable);
+        assert_eq!(plan.planned_bytes, 100);
+        assert_eq!(plan.items.len(), 1);
+        assert_eq!(plan.items[0].name, "npm");
+        assert_eq!(plan.items[0].size_bytes, 100);
+        assert_eq!(
+            plan.items[0].files[0].path,
+            std::path::PathBuf::from("/n/old")
+        );
+
+        // Logs, then user data, before anything that needs caution
+        let plan = plan_for_goal(&items, 800);
+        let names: Vec<&str> = plan.items.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec!["npm", "Logs", "Downloads"]);
+        assert_eq!(plan.planned_bytes, 800);
+    }
+
+    #[test]
+    fn test_plan_reports_unreachable_goal() {
+        let mut linked = file("/c/linked", 300, 10);
+        linked.freeable_bytes = 0;
+        let items = vec![item(
+            "Caches",
+            Category::UserCache,
+            SafetyLevel::Safe,
+            vec![file("/c/a", 100, 10), linked],
+        )];
+        let plan = plan_for_goal(&items, 1000);
+        assert!(!plan.reachable);
+        assert_eq!(plan.available_bytes, 100);
+        assert_eq!(plan.file_count(), 1);
+    }
+}
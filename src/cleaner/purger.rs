@@ -2,6 +2,7 @@ use anyhow::{Context, Result};
 use std::path::PathBuf;
 
 use super::manifest::CleanManifest;
+use super::store;
 use crate::common::config::Config;
 
 /// Purge expired staging sessions
@@ -15,6 +16,7 @@ pub fn purge_expired() -> Result<PurgeReport> {
     let mut report = PurgeReport {
         purged_sessions: Vec::new(),
         total_bytes_freed: 0,
+        store_bytes_freed: 0,
         errors: Vec::new(),
     };
 
@@ -59,10 +61,13 @@ pub fn purge_expired() -> Result<PurgeReport> {
         }
     }
 
+    report.sweep_store();
     Ok(report)
 }
 
 /// Purge a specific session by ID
+///
+/// The bytes freed include store blobs only this session referenced.
 pub fn purge_session(session_id: &str) -> Result<u64> {
     let session_dir = Config::staging_dir().join(session_id);
 
@@ -75,7 +80,11 @@ pub fn purge_session(session_id: &str) -> Result<u64> {
     std::fs::remove_dir_all(&session_dir)
         .with_context(|| format!("Failed to purge session: {}", session_id))?;
 
-    Ok(size)
+    let swept = store::sweep().unwrap_or_else(|e| {
+        tracing::warn!("Failed to sweep the staging store: {}", e);
+        0
+    });
+    Ok(size + swept)
 }
 
 /// Purge ALL sessions (nuclear option)
@@ -84,10 +93,12 @@ pub fn purge_all() -> Result<PurgeReport> {
     let mut report = PurgeReport {
         purged_sessions: Vec::new(),
         total_bytes_freed: 0,
+        store_bytes_freed: 0,
         errors: Vec::new(),
     };
 
     if !staging_dir.exists() {
+        report.sweep_store();
         return Ok(report);
     }
 
@@ -124,6 +135,7 @@ pub fn purge_all() -> Result<PurgeReport> {
         }
     }
 
+    report.sweep_store();
     Ok(report)
 }
 
@@ -179,10 +191,28 @@ pub fn purge_plist_path() -> PathBuf {
 #[derive(Debug)]
 pub struct PurgeReport {
     pub purged_sessions: Vec<PurgedSession>,
+    /// Includes `store_bytes_freed`
     pub total_bytes_freed: u64,
+    /// Store blobs no remaining session references
+    pub store_bytes_freed: u64,
     pub errors: Vec<String>,
 }
 
+impl PurgeReport {
+    /// Drop store blobs the purged sessions were the last to reference
+    fn sweep_store(&mut self) {
+        match store::sweep() {
+            Ok(freed) => {
+                self.store_bytes_freed = freed;
+                self.total_bytes_freed += freed;
+            }
+            Err(e) => self
+                .errors
+                .push(format!("Failed to sweep the staging store: {}", e)),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct PurgedSession {
     pub session_id: String,
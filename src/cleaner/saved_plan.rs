@@ -0,0 +1,319 @@
+//! Saved clean plans: scan once, review, clean later
+//!
+//! `tidymac scan --save-plan <file>` writes every file a clean would remove,
+//! with its size and mtime, as pretty-printed JSON that can be reviewed,
+//! diffed and edited by hand. `tidymac clean --plan <file>` removes exactly
+//! those files, skipping any whose size or mtime no longer match.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::scanner::targets::{self, Category, FileEntry, SafetyLevel, ScanItem, ScanResults};
+
+/// Format version written by this release; newer plans are rejected
+pub const PLAN_VERSION: u32 = 1;
+
+/// An explicit list of files to clean
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedPlan {
+    pub plan_version: u32,
+    /// Release of tidymac that wrote the plan
+    pub tidymac_version: String,
+    pub profile: String,
+    pub created_at: DateTime<Utc>,
+    pub items: Vec<PlannedItem>,
+}
+
+/// One scan item and the files of it to remove
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedItem {
+    pub name: String,
+    pub category: Category,
+    pub safety: SafetyLevel,
+    #[serde(default)]
+    pub reason: String,
+    pub path: PathBuf,
+    /// `ScanItem::roots`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roots: Vec<PathBuf>,
+    pub files: Vec<PlannedFile>,
+}
+
+/// A file (or directory removed whole) as it was when planned
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedFile {
+    pub path: PathBuf,
+    /// Logical size; for directories, of everything inside
+    pub size_bytes: u64,
+    pub modified: Option<DateTime<Utc>>,
+}
+
+/// A planned file that is skipped because it changed
+#[derive(Debug, Clone, Serialize)]
+pub struct DriftedFile {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+impl SavedPlan {
+    /// Plan to remove everything in `results`
+    ///
+    /// Items that only list their largest files can't be planned; scan with
+    /// full file lists first.
+    pub fn from_results(results: &ScanResults, profile: &str) -> Result<Self> {
+        let mut items = Vec::new();
+        for item in &results.items {
+            if item.files_partial {
+                bail!(
+                    "'{}' only lists its largest files; scan with full file lists to save a plan",
+                    item.name
+                );
+            }
+            let files = if item.files.is_empty() {
+                // Removed whole, so the item path is the one file; measure it
+                // the way `verify` will
+                let Ok(current) = restat(&item.path) else {
+                    continue;
+                };
+                vec![PlannedFile {
+                    path: item.path.clone(),
+                    size_bytes: current.apparent_bytes,
+                    modified: current.modified.map(DateTime::from),
+                }]
+            } else {
+                item.files
+                    .iter()
+                    .map(|f| PlannedFile {
+                        path: f.path.clone(),
+                        size_bytes: f.apparent_bytes,
+                        modified: f.modified.map(DateTime::from),
+                    })
+                    .collect()
+            };
+            items.push(PlannedItem {
+                name: item.name.clone(),
+                category: item.category.clone(),
+                safety: item.safety.clone(),
+                reason: item.reason.clone(),
+                path: item.path.clone(),
+                roots: item.roots.clone(),
+                files,
+            });
+        }
+
+        Ok(Self {
+            plan_version: PLAN_VERSION,
+            tidymac_version: env!("CARGO_PKG_VERSION").to_string(),
+            profile: profile.to_string(),
+            created_at: Utc::now(),
+            items,
+        })
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.items.iter().map(|i| i.files.len()).sum()
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.items
+            .iter()
+            .flat_map(|i| &i.files)
+            .map(|f| f.size_bytes)
+            .sum()
+    }
+
+    /// Write the plan, replacing `path` only once it is complete
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+        }
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize plan")?;
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, json + "\n")
+            .with_context(|| format!("Failed to write plan: {}", tmp.display()))?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to write plan: {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read plan: {}", path.display()))?;
+        let value: serde_json::Value = serde_json::from_str(&contents)
+            .with_context(|| format!("Not a JSON file: {}", path.display()))?;
+        let Some(version) = value.get("plan_version") else {
+            bail!("Not a tidymac clean plan: {}", path.display());
+        };
+        let version = version.as_u64().unwrap_or(u64::MAX);
+        if version > u64::from(PLAN_VERSION) {
+            bail!(
+                "Plan {} has format version {}, this tidymac reads up to {}; upgrade tidymac to run it",
+                path.display(),
+                version,
+                PLAN_VERSION
+            );
+        }
+        serde_json::from_value(value)
+            .with_context(|| format!("Failed to parse plan: {}", path.display()))
+    }
+
+    /// Check every planned file against the disk
+    ///
+    /// Returns scan items holding the files that still match, with fresh
+    /// sizes, and the files that changed or disappeared. The engine checks
+    /// each file again right before removing it.
+    pub fn verify(&self) -> (Vec<ScanItem>, Vec<DriftedFile>) {
+        let mut items = Vec::new();
+        let mut drifted = Vec::new();
+
+        for planned in &self.items {
+            let mut files = Vec::new();
+            for file in &planned.files {
+                let expected = FileEntry {
+                    path: file.path.clone(),
+                    size_bytes: file.size_bytes,
+                    modified: file.modified.map(Into::into),
+                    accessed: None,
+                    apparent_bytes: file.size_bytes,
+                    freeable_bytes: file.size_bytes,
+                    hard_link: None,
+                    file_id: None,
+                };
+                match recheck(&expected) {
+                    Ok(current) => files.push(current),
+                    Err(reason) => drifted.push(DriftedFile {
+                        path: file.path.clone(),
+                        reason,
+                    }),
+                }
+            }
+            if files.is_empty() {
+                continue;
+            }
+            let usage = targets::file_totals(&files);
+            items.push(ScanItem {
+                name: planned.name.clone(),
+                category: planned.category.clone(),
+                path: planned.path.clone(),
+                size_bytes: usage.allocated,
+                apparent_bytes: usage.apparent,
+                freeable_bytes: usage.freeable,
+                file_count: files.len(),
+                safety: planned.safety.clone(),
+                reason: planned.reason.clone(),
+                files,
+                files_partial: false,
+                trim: None,
+                roots: planned.roots.clone(),
+            });
+        }
+
+        (items, drifted)
+    }
+}
+
+/// A fresh entry for what is at `path`, without following a symlink there
+fn restat(path: &Path) -> std::io::Result<FileEntry> {
+    let meta = std::fs::symlink_metadata(path)?;
+    Ok(if meta.is_dir() {
+        FileEntry::from_dir(path.to_path_buf(), meta.modified().ok())
+    } else {
+        FileEntry::from_metadata(path.to_path_buf(), &meta)
+    })
+}
+
+/// A fresh entry for `expected.path`, or why it no longer matches
+fn recheck(expected: &FileEntry) -> std::result::Result<FileEntry, String> {
+    let current = match restat(&expected.path) {
+        Ok(current) => current,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err("no longer exists".to_string())
+        }
+        Err(e) => return Err(format!("can't be checked: {}", e)),
+    };
+
+    if current.apparent_bytes != expected.apparent_bytes {
+        return Err(format!(
+            "size changed from {} to {} bytes",
+            expected.apparent_bytes, current.apparent_bytes
+        ));
+    }
+    if current.modified != expected.modified {
+        return Err("modified since it was planned".to_string());
+    }
+    Ok(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_round_trips_and_skips_changed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut files = Vec::new();
+        for name in ["a.log", "b.log", "c.log"] {
+            let path = dir.path().join(name);
+            std::fs::write(&path, name).unwrap();
+            files.push(FileEntry::from_metadata(
+                path.clone(),
+                &std::fs::metadata(&path).unwrap(),
+            ));
+        }
+        let usage = targets::file_totals(&files);
+        let mut results = ScanResults::new();
+        results.items.push(ScanItem {
+            name: "Logs".to_string(),
+            category: Category::Logs,
+            path: dir.path().to_path_buf(),
+            size_bytes: usage.allocated,
+            apparent_bytes: usage.apparent,
+            freeable_bytes: usage.freeable,
+            file_count: files.len(),
+            safety: SafetyLevel::Safe,
+            reason: "test".to_string(),
+            files,
+            files_partial: false,
+            trim: None,
+            roots: Vec::new(),
+        });
+
+        let plan_path = dir.path().join("plan.json");
+        SavedPlan::from_results(&results, "quick")
+            .unwrap()
+            .save(&plan_path)
+            .unwrap();
+        let plan = SavedPlan::load(&plan_path).unwrap();
+        assert_eq!(plan.file_count(), 3);
+        assert_eq!(plan.total_bytes(), 15);
+
+        std::fs::write(dir.path().join("b.log"), "grown since").unwrap();
+        std::fs::remove_file(dir.path().join("c.log")).unwrap();
+
+        let (items, drifted) = plan.verify();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].files.len(), 1);
+        assert_eq!(items[0].files[0].path, dir.path().join("a.log"));
+
+        let reasons: Vec<&str> = drifted.iter().map(|d| d.reason.as_str()).collect();
+        assert_eq!(
+            reasons,
+            vec!["size changed from 5 to 11 bytes", "no longer exists"]
+        );
+    }
+
+    #[test]
+    fn test_load_rejects_newer_and_foreign_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("plan.json");
+        std::fs::write(&path, r#"{"plan_version": 99, "items": []}"#).unwrap();
+        let err = SavedPlan::load(&path).unwrap_err();
+        assert!(err.to_string().contains("upgrade tidymac"));
+
+        std::fs::write(&path, r#"{"items": []}"#).unwrap();
+        assert!(SavedPlan::load(&path).is_err());
+    }
+}
@@ -1,18 +1,41 @@
 use anyhow::{Context, Result};
+use chrono::Utc;
 use indicatif::{ProgressBar, ProgressStyle};
 use rayon::prelude::*;
-use std::path::Path;
-
-use super::manifest::{CleanManifest, ManifestItem};
-use crate::common::config::Config;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use super::archive;
+use super::guard::PathGuard;
+use super::history::PathPattern;
+use super::journal::Journal;
+use super::manifest::{CleanManifest, ItemRestore, ManifestItem, SkippedPath};
+use super::store;
+use crate::common::cancel::CancelToken;
+use crate::common::config::{Config, StagingStore};
 use crate::common::format;
 use crate::scanner::targets::ScanItem;
 
 /// Move files to the staging area for soft-delete with recovery
+///
+/// Each path is moved whole before `cancel` is checked again, so a cancelled
+/// run leaves every listed item either staged or untouched and sets
+/// `manifest.cancelled`.
+///
+/// Files that changed since the scan are left in place and recorded in
+/// `manifest.skipped`. Every move is written to `journal` first, so a crash
+/// before the manifest is saved can be recovered from.
+///
+/// With `StagingStore::Dedup`, each staged regular file is then moved on
+/// into the content-addressed store; with `StagingStore::Compress`, the
+/// compressible ones are packed into the session archive at the end.
 pub fn stage_files(
     items: &[ScanItem],
     manifest: &mut CleanManifest,
+    journal: &mut Journal,
+    staging_store: StagingStore,
     show_progress: bool,
+    cancel: &CancelToken,
 ) -> Result<()> {
     let files_dir = manifest.staging_files_dir();
     std::fs::create_dir_all(&files_dir).with_context(|| {
@@ -49,81 +72,83 @@ pub fn stage_files(
 
     let mut file_counter: usize = 0;
 
-    for item in items {
+    'items: for item in items {
         if item.files.is_empty() {
+            if cancel.is_cancelled() {
+                manifest.cancelled = true;
+                break 'items;
+            }
             // The item path itself is the target (e.g., a directory)
-            file_counter += 1;
-            let staged_name = format!("{:06}", file_counter);
-            let staged_path = files_dir.join(&staged_name);
-
-            match stage_single_path(&item.path, &staged_path) {
-                Ok(()) => {
-                    manifest.add_item(ManifestItem {
-                        original_path: item.path.clone(),
-                        staged_path: Some(staged_path),
-                        size_bytes: item.size_bytes,
-                        category: item.category.to_string(),
-                        safety: format!("{:?}", item.safety),
-                        is_dir: item.path.is_dir(),
-                        success: true,
-                        error: None,
-                    });
-                }
-                Err(e) => {
-                    let err_msg = format!("Failed to stage '{}': {}", item.path.display(), e);
-                    manifest.add_item(ManifestItem {
-                        original_path: item.path.clone(),
-                        staged_path: None,
-                        size_bytes: item.size_bytes,
-                        category: item.category.to_string(),
-                        safety: format!("{:?}", item.safety),
-                        is_dir: item.path.is_dir(),
-                        success: false,
-                        error: Some(err_msg.clone()),
-                    });
-                    manifest.add_error(err_msg);
+            if let Some(reason) = PathGuard::check_whole(&item.path) {
+                manifest.add_skipped(SkippedPath {
+                    path: item.path.clone(),
+                    size_bytes: item.size_bytes,
+                    category: item.category.to_string(),
+                    reason,
+                });
+                if let Some(ref pb) = pb {
+                    pb.inc(1);
                 }
+                continue;
             }
+            file_counter += 1;
+            let staged_path = files_dir.join(format!("{:06}", file_counter));
+            let entry = ManifestItem {
+                original_path: item.path.clone(),
+                staged_path: Some(staged_path),
+                size_bytes: item.size_bytes,
+                category: item.category.to_string(),
+                safety: format!("{:?}", item.safety),
+                is_dir: item.path.is_dir(),
+                success: true,
+                error: None,
+                restored: None,
+                content_hash: None,
+                archive_entry: None,
+                staged_bytes: None,
+            };
+            stage_journaled(entry, file_counter, manifest, journal, staging_store)?;
 
             if let Some(ref pb) = pb {
                 pb.inc(1);
             }
         } else {
             // Process individual files within the item
+            let guard = PathGuard::for_item(item);
             for file_entry in &item.files {
-                file_counter += 1;
-                let staged_name = format!("{:06}", file_counter);
-                let staged_path = files_dir.join(&staged_name);
-
-                match stage_single_path(&file_entry.path, &staged_path) {
-                    Ok(()) => {
-                        manifest.add_item(ManifestItem {
-                            original_path: file_entry.path.clone(),
-                            staged_path: Some(staged_path),
-                            size_bytes: file_entry.size_bytes,
-                            category: item.category.to_string(),
-                            safety: format!("{:?}", item.safety),
-                            is_dir: file_entry.path.is_dir(),
-                            success: true,
-                            error: None,
-                        });
-                    }
-                    Err(e) => {
-                        let err_msg =
-                            format!("Failed to stage '{}': {}", file_entry.path.display(), e);
-                        manifest.add_item(ManifestItem {
-                            original_path: file_entry.path.clone(),
-                            staged_path: None,
-                            size_bytes: file_entry.size_bytes,
-                            category: item.category.to_string(),
-                            safety: format!("{:?}", item.safety),
-                            is_dir: file_entry.path.is_dir(),
-                            success: false,
-                            error: Some(err_msg.clone()),
-                        });
-                        manifest.add_error(err_msg);
+                if cancel.is_cancelled() {
+                    manifest.cancelled = true;
+                    break 'items;
+                }
+                if let Some(reason) = guard.check(file_entry) {
+                    manifest.add_skipped(SkippedPath {
+                        path: file_entry.path.clone(),
+                        size_bytes: file_entry.size_bytes,
+                        category: item.category.to_string(),
+                        reason,
+                    });
+                    if let Some(ref pb) = pb {
+                        pb.inc(1);
                     }
+                    continue;
                 }
+                file_counter += 1;
+                let staged_path = files_dir.join(format!("{:06}", file_counter));
+                let entry = ManifestItem {
+                    original_path: file_entry.path.clone(),
+                    staged_path: Some(staged_path),
+                    size_bytes: file_entry.size_bytes,
+                    category: item.category.to_string(),
+                    safety: format!("{:?}", item.safety),
+                    is_dir: file_entry.path.is_dir(),
+                    success: true,
+                    error: None,
+                    restored: None,
+                    content_hash: None,
+                    archive_entry: None,
+                    staged_bytes: None,
+                };
+                stage_journaled(entry, file_counter, manifest, journal, staging_store)?;
 
                 if let Some(ref pb) = pb {
                     pb.set_message(format::truncate(&format::format_path(&file_entry.path), 40));
@@ -137,9 +162,69 @@ pub fn stage_files(
         pb.finish_and_clear();
     }
 
+    if staging_store == StagingStore::Compress {
+        archive::pack(manifest, journal)?;
+    }
+
     Ok(())
 }
 
+/// Journal `entry`, move it to its staged path and record how that went
+///
+/// Only a failure to write the journal is returned as an error; a failed
+/// move is recorded in the manifest.
+fn stage_journaled(
+    mut entry: ManifestItem,
+    seq: usize,
+    manifest: &mut CleanManifest,
+    journal: &mut Journal,
+    staging_store: StagingStore,
+) -> Result<()> {
+    journal.record_move(seq, &entry)?;
+    let staged_path = entry.staged_path.clone().unwrap();
+    match stage_single_path(&entry.original_path, &staged_path) {
+        Ok(()) => {
+            journal.record_done(seq)?;
+            if staging_store == StagingStore::Dedup {
+                // Not fatal: the file just stays in the session
+                if let Ok((hash, new_blob)) = store_blob(&staged_path, seq, journal) {
+                    entry.staged_path = Some(store::blob_path(&hash));
+                    entry.content_hash = Some(hash);
+                    if !new_blob {
+                        entry.staged_bytes = Some(0);
+                    }
+                }
+            }
+            manifest.add_item(entry);
+        }
+        Err(e) => {
+            let err_msg = format!("Failed to stage '{}': {}", entry.original_path.display(), e);
+            journal.record_failed(seq, &err_msg)?;
+            manifest.add_item(ManifestItem {
+                staged_path: None,
+                success: false,
+                error: Some(err_msg.clone()),
+                ..entry
+            });
+            manifest.add_error(err_msg);
+        }
+    }
+    Ok(())
+}
+
+/// Hash a staged regular file and hand it to the store, journaling the
+/// hash first so recovery knows where it went; also returns whether the
+/// store didn't have it yet
+fn store_blob(staged: &Path, seq: usize, journal: &mut Journal) -> Result<(String, bool)> {
+    if !std::fs::symlink_metadata(staged)?.is_file() {
+        anyhow::bail!("Only regular files are deduplicated");
+    }
+    let hash = store::hash_file(staged)?;
+    journal.record_stored(seq, &hash)?;
+    let new_blob = store::put(staged, &hash)?;
+    Ok((hash, new_blob))
+}
+
 /// Move a single file or directory to the staging area
 fn stage_single_path(original: &Path, staged: &Path) -> Result<()> {
     if !original.exists() {
@@ -214,26 +299,106 @@ fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<()> {
     Ok(())
 }
 
+/// What to do when something already exists where an item would be restored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnConflict {
+    /// Leave both alone and report an error
+    #[default]
+    Error,
+    /// Leave both alone and report the item as skipped
+    Skip,
+    /// Restore next to it as `<name>.restored`, `<name>.restored-2`, ...
+    Rename,
+    /// Replace what is there
+    Overwrite,
+}
+
+/// Which items of a session to restore, and where
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RestoreOptions {
+    /// `PathPattern`s for the original paths to restore; empty restores
+    /// everything
+    pub only: Vec<String>,
+    /// Restore under this directory instead, keeping each item's full
+    /// original path below it
+    pub to: Option<PathBuf>,
+    pub on_conflict: OnConflict,
+}
+
+impl RestoreOptions {
+    /// Where `original` goes back to
+    pub fn destination(&self, original: &Path) -> PathBuf {
+        match &self.to {
+            Some(root) => root.join(original.strip_prefix("/").unwrap_or(original)),
+            None => original.to_path_buf(),
+        }
+    }
+}
+
+/// The items of `manifest` that a restore with `options` would put back
+pub fn select_for_restore<'a>(
+    manifest: &'a CleanManifest,
+    options: &RestoreOptions,
+) -> Result<Vec<&'a ManifestItem>> {
+    Ok(selected_indices(manifest, options)?
+        .into_iter()
+        .map(|i| &manifest.items[i])
+        .collect())
+}
+
+fn selected_indices(manifest: &CleanManifest, options: &RestoreOptions) -> Result<Vec<usize>> {
+    let patterns = options
+        .only
+        .iter()
+        .map(|p| PathPattern::new(p))
+        .collect::<Result<Vec<_>>>()?;
+    let selected =
+        |path: &Path| patterns.is_empty() || patterns.iter().any(|p| p.matches(path));
+    Ok(manifest
+        .items
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| item.is_restorable() && selected(&item.original_path))
+        .map(|(i, _)| i)
+        .collect())
+}
+
 /// Restore files from staging back to their original locations
 pub fn restore_session(session_id: &str, show_progress: bool) -> Result<RestoreReport> {
+    restore_session_with_options(session_id, &RestoreOptions::default(), show_progress)
+}
+
+/// Restore the items of a session selected by `options`
+///
+/// Each restored item is recorded in the manifest, so a session can be
+/// restored piece by piece; it counts as restored once nothing is left.
+pub fn restore_session_with_options(
+    session_id: &str,
+    options: &RestoreOptions,
+    show_progress: bool,
+) -> Result<RestoreReport> {
     let mut manifest = CleanManifest::load_from_session(session_id)?;
 
     if manifest.restored {
         anyhow::bail!("Session '{}' has already been restored", session_id);
     }
 
-    let restorable_items: Vec<&ManifestItem> = manifest
-        .items
-        .iter()
-        .filter(|i| i.success && i.staged_path.is_some())
-        .collect();
-
-    if restorable_items.is_empty() {
-        anyhow::bail!("No restorable items in session '{}'", session_id);
+    let selected = selected_indices(&manifest, options)?;
+    if selected.is_empty() {
+        if options.only.is_empty() {
+            anyhow::bail!("No restorable items in session '{}'", session_id);
+        }
+        anyhow::bail!(
+            "No restorable items in session '{}' match {}",
+            session_id,
+            options.only.join(", ")
+        );
     }
 
     let pb = if show_progress {
-        let pb = ProgressBar::new(restorable_items.len() as u64);
+        let pb = ProgressBar::new(selected.len() as u64);
         pb.set_style(
             ProgressStyle::default_bar()
                 .template("{spinner:.green} [{bar:40.green/blue}] {pos}/{len} Restoring... {msg}")
@@ -248,17 +413,37 @@ pub fn restore_session(session_id: &str, show_progress: bool) -> Result<RestoreR
     let mut restored_count = 0usize;
     let mut restored_bytes = 0u64;
     let mut errors = Vec::new();
+    let mut skipped = Vec::new();
 
-    for item in &restorable_items {
-        let staged_path = item.staged_path.as_ref().unwrap();
+    for index in selected {
+        let item = &manifest.items[index];
+        let staged_path = item.staged_path.clone().unwrap();
         let original_path = &item.original_path;
+        let mut destination = options.destination(original_path);
+        let mut replace = false;
 
         if let Some(ref pb) = pb {
             pb.set_message(format::truncate(&format::format_path(original_path), 40));
+            pb.inc(1);
+        }
+
+        // Something is already there
+        if std::fs::symlink_metadata(&destination).is_ok() {
+            match options.on_conflict {
+                // `restore_single_path` refuses and says why
+                OnConflict::Error => {}
+                OnConflict::Skip => {
+                    skipped.push(destination);
+                    continue;
+                }
+                OnConflict::Rename => destination = free_name(&destination),
+                // Restored next to it first, so a bad staged copy costs nothing
+                OnConflict::Overwrite => replace = true,
+            }
         }
 
         // Ensure parent directory exists for restoration
-        if let Some(parent) = original_path.parent() {
+        if let Some(parent) = destination.parent() {
             if !parent.exists() {
                 if let Err(e) = std::fs::create_dir_all(parent) {
                     errors.push(format!(
@@ -266,42 +451,44 @@ pub fn restore_session(session_id: &str, show_progress: bool) -> Result<RestoreR
                         parent.display(),
                         e
                     ));
-                    if let Some(ref pb) = pb {
-                        pb.inc(1);
-                    }
                     continue;
                 }
             }
         }
 
-        // Move from staging back to original location
-        match restore_single_path(staged_path, original_path) {
+        // Move from staging back
+        let result = if replace {
+            restore_over(item, &staged_path, &destination)
+        } else {
+            restore_item(item, &staged_path, &destination)
+        };
+        match result {
             Ok(()) => {
                 restored_count += 1;
                 restored_bytes += item.size_bytes;
+                manifest.items[index].restored = Some(ItemRestore {
+                    at: Utc::now(),
+                    path: destination,
+                });
             }
             Err(e) => {
                 errors.push(format!(
                     "Failed to restore '{}': {}",
-                    original_path.display(),
+                    destination.display(),
                     e
                 ));
             }
         }
-
-        if let Some(ref pb) = pb {
-            pb.inc(1);
-        }
     }
 
     if let Some(ref pb) = pb {
         pb.finish_and_clear();
     }
 
-    // Mark session as restored
-    manifest.mark_restored()?;
+    manifest.save_restore_state()?;
+    let remaining = manifest.restorable_items().count();
 
-    // Clean up the now-empty staging session directory
+    // Clean up staging directories emptied by the restore
     let session_dir = Config::staging_dir().join(session_id);
     let _ = cleanup_empty_dirs(&session_dir);
 
@@ -310,21 +497,117 @@ pub fn restore_session(session_id: &str, show_progress: bool) -> Result<RestoreR
         restored_count,
         restored_bytes,
         errors,
+        skipped,
+        remaining,
     })
 }
 
-/// Move a single file/directory back from staging to original path
-fn restore_single_path(staged: &Path, original: &Path) -> Result<()> {
+/// `path` with `.restored` (then `.restored-2`, ...) appended until unused
+fn free_name(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    (1..)
+        .map(|n| {
+            let suffix = if n == 1 {
+                "restored".to_string()
+            } else {
+                format!("restored-{}", n)
+            };
+            path.with_file_name(format!("{}.{}", name, suffix))
+        })
+        .find(|p| std::fs::symlink_metadata(p).is_err())
+        .unwrap()
+}
+
+/// Restore `item` beside `dest`, then swap it in for what is there
+///
+/// The existing file is only moved aside once the restore has succeeded,
+/// and put back if the swap fails, so a missing or corrupted staged copy
+/// leaves `dest` untouched.
+fn restore_over(item: &ManifestItem, staged: &Path, dest: &Path) -> Result<()> {
+    if crate::common::safety::is_protected(dest) {
+        anyhow::bail!("SAFETY: Refusing to overwrite protected path");
+    }
+    let incoming = sibling(dest, "restore");
+    restore_item(item, staged, &incoming)?;
+
+    let aside = sibling(dest, "replaced");
+    let swapped = std::fs::rename(dest, &aside).and_then(|()| {
+        std::fs::rename(&incoming, dest).inspect_err(|_| {
+            let _ = std::fs::rename(&aside, dest);
+        })
+    });
+    if let Err(e) = swapped {
+        // Give the staged copy back so the restore can be retried
+        if item.content_hash.is_none() && item.archive_entry.is_none() {
+            let _ = std::fs::rename(&incoming, staged);
+        } else {
+            let _ = remove_existing(&incoming);
+        }
+        return Err(e).with_context(|| format!("Failed to replace '{}'", dest.display()));
+    }
+    if let Err(e) = remove_existing(&aside) {
+        tracing::warn!("Failed to remove replaced '{}': {:#}", aside.display(), e);
+    }
+    Ok(())
+}
+
+/// A hidden, unused name next to `path` for an in-progress restore
+fn sibling(path: &Path, tag: &str) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    (1..)
+        .map(|n| path.with_file_name(format!(".{}.tidymac-{}-{}", name, tag, n)))
+        .find(|p| std::fs::symlink_metadata(p).is_err())
+        .unwrap()
+}
+
+/// Delete whatever is at `path` so a restore can take its place
+fn remove_existing(path: &Path) -> Result<()> {
+    if crate::common::safety::is_protected(path) {
+        anyhow::bail!("SAFETY: Refusing to overwrite protected path");
+    }
+    let meta = std::fs::symlink_metadata(path)?;
+    if meta.is_dir() {
+        std::fs::remove_dir_all(path)?;
+    } else {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Put one staged item back at `dest`
+///
+/// Deduplicated files are copied out of the store, since other sessions
+/// may share the blob, and packed ones extracted from the session archive;
+/// everything else is moved back.
+pub(super) fn restore_item(item: &ManifestItem, staged: &Path, dest: &Path) -> Result<()> {
+    if item.content_hash.is_none() && item.archive_entry.is_none() {
+        return restore_single_path(staged, dest);
+    }
+    if std::fs::symlink_metadata(dest).is_ok() {
+        anyhow::bail!(
+            "Original path already exists (won't overwrite): {}. \
+             Use --conflict rename or --conflict overwrite to restore anyway.",
+            dest.display()
+        );
+    }
+    match (&item.content_hash, &item.archive_entry) {
+        (Some(hash), _) => store::copy_out(staged, hash, dest),
+        (None, Some(entry)) => archive::extract(staged, entry, dest),
+        (None, None) => unreachable!(),
+    }
+}
+
+pub(super) fn restore_single_path(staged: &Path, original: &Path) -> Result<()> {
     if !staged.exists() {
         anyhow::bail!("Staged file no longer exists: {}", staged.display());
     }
 
-    // Don't overwrite if something already exists at the original path.
-    // Tip: use `tidymac undo --force` (future) to overwrite.
+    // Don't overwrite if something already exists at the original path;
+    // `--conflict overwrite` clears it first
     if original.exists() {
         anyhow::bail!(
             "Original path already exists (won't overwrite): {}. \
-             If you want to force-restore, delete the existing file first.",
+             Use --conflict rename or --conflict overwrite to restore anyway.",
             original.display()
         );
     }
@@ -371,6 +654,61 @@ fn cleanup_empty_dirs(dir: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Check that every restorable item of a session is still in staging, that
+/// deduplicated files still match their recorded hash, and that packed ones
+/// pass the archive's CRC check
+pub fn verify_session(session_id: &str) -> Result<VerifyReport> {
+    let manifest = CleanManifest::load_from_session(session_id)?;
+    let mut report = VerifyReport {
+        session_id: session_id.to_string(),
+        checked: 0,
+        verified: 0,
+        missing: Vec::new(),
+        corrupted: Vec::new(),
+    };
+
+    for item in manifest.restorable_items() {
+        report.checked += 1;
+        let staged = item.staged_path.as_deref().unwrap();
+        if std::fs::symlink_metadata(staged).is_err() {
+            report.missing.push(item.original_path.clone());
+            continue;
+        }
+        let intact = match (&item.content_hash, &item.archive_entry) {
+            (Some(hash), _) => store::hash_file(staged).ok().as_ref() == Some(hash),
+            (None, Some(entry)) if !archive::contains(staged, entry) => {
+                report.missing.push(item.original_path.clone());
+                continue;
+            }
+            (None, Some(entry)) => archive::check(staged, entry).is_ok(),
+            (None, None) => true,
+        };
+        if intact {
+            report.verified += 1;
+        } else {
+            report.corrupted.push(item.original_path.clone());
+        }
+    }
+
+    Ok(report)
+}
+
+/// Report from `verify_session`; paths are where the files came from
+#[derive(Debug, Serialize)]
+pub struct VerifyReport {
+    pub session_id: String,
+    pub checked: usize,
+    pub verified: usize,
+    pub missing: Vec<PathBuf>,
+    pub corrupted: Vec<PathBuf>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.corrupted.is_empty()
+    }
+}
+
 /// Report from a restore operation
 #[derive(Debug)]
 pub struct RestoreReport {
@@ -378,4 +716,8 @@ pub struct RestoreReport {
     pub restored_count: usize,
     pub restored_bytes: u64,
     pub errors: Vec<String>,
+    /// Destinations left alone under `OnConflict::Skip`
+    pub skipped: Vec<PathBuf>,
+    /// Items of the session still in staging afterwards
+    pub remaining: usize,
 }
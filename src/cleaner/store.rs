@@ -0,0 +1,206 @@
+//! Content-addressed staging store
+//!
+//! With `staging_store = "dedup"` (or `clean --stage dedup`), each staged
+//! file is moved on from its session into `~/.tidymac/store/<aa>/<sha256>`,
+//! so the same cache contents cleaned in several sessions are kept once.
+//! The manifest records the hash, and restores copy the blob back out,
+//! checking the hash as they go.
+//!
+//! A blob's references are counted from the live session manifests (and
+//! the journals of interrupted sessions) rather than kept in an index of
+//! their own, so a crash can't leave a count wrong. `purge` removes blobs
+//! once nothing references them.
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use super::journal;
+use crate::common::config::Config;
+
+/// Where the blob for `hash` lives
+pub fn blob_path(hash: &str) -> PathBuf {
+    blob_path_in(&Config::store_dir(), hash)
+}
+
+fn blob_path_in(store_dir: &Path, hash: &str) -> PathBuf {
+    store_dir.join(&hash[..2.min(hash.len())]).join(hash)
+}
+
+/// SHA-256 of a file, as lowercase hex
+pub fn hash_file(path: &Path) -> Result<String> {
+    crate::duplicates::hasher::full_hash(path)
+        .with_context(|| format!("Failed to hash: {}", path.display()))
+}
+
+/// Move a staged file into the store as the blob for `hash`
+///
+/// If the blob already exists, the staged file is a duplicate and is
+/// removed instead. Returns whether a new blob was written.
+pub fn put(staged: &Path, hash: &str) -> Result<bool> {
+    let blob = blob_path(hash);
+    if blob.exists() {
+        std::fs::remove_file(staged)
+            .with_context(|| format!("Failed to remove duplicate: {}", staged.display()))?;
+        return Ok(false);
+    }
+    if let Some(dir) = blob.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create store dir: {}", dir.display()))?;
+    }
+    std::fs::rename(staged, &blob)
+        .with_context(|| format!("Failed to move '{}' into the store", staged.display()))?;
+    Ok(true)
+}
+
+/// Copy a blob to `dest`, which must not exist yet, checking it against
+/// `hash` on the way; a corrupted blob leaves nothing behind at `dest`
+pub fn copy_out(blob: &Path, hash: &str, dest: &Path) -> Result<()> {
+    let mut src = std::fs::File::open(blob)
+        .with_context(|| format!("Staged blob is missing: {}", blob.display()))?;
+    let mut out = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dest)
+        .with_context(|| format!("Failed to create: {}", dest.display()))?;
+
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
+    let copied = (|| -> std::io::Result<()> {
+        loop {
+            let n = src.read(&mut buffer)?;
+            if n == 0 {
+                return Ok(());
+            }
+            hasher.update(&buffer[..n]);
+            out.write_all(&buffer[..n])?;
+        }
+    })();
+    drop(out);
+
+    if let Err(e) = copied {
+        let _ = std::fs::remove_file(dest);
+        return Err(e).with_context(|| format!("Failed to copy from store: {}", blob.display()));
+    }
+    if format!("{:x}", hasher.finalize()) != hash {
+        let _ = std::fs::remove_file(dest);
+        anyhow::bail!(
+            "Staged copy is corrupted (SHA-256 mismatch): {}",
+            blob.display()
+        );
+    }
+    if let Ok(meta) = std::fs::metadata(blob) {
+        let _ = std::fs::set_permissions(dest, meta.permissions());
+    }
+    Ok(())
+}
+
+/// How many live session items reference each blob
+pub fn references() -> Result<HashMap<String, usize>> {
+    references_in(&Config::staging_dir())
+}
+
+fn references_in(staging_dir: &Path) -> Result<HashMap<String, usize>> {
+    let mut counts = HashMap::new();
+    let Ok(entries) = std::fs::read_dir(staging_dir) else {
+        return Ok(counts);
+    };
+    for entry in entries.flatten() {
+        for hash in journal::referenced_hashes(&entry.path())? {
+            *counts.entry(hash).or_insert(0) += 1;
+        }
+    }
+    Ok(counts)
+}
+
+/// Blob count and total size of the store
+pub fn usage() -> (usize, u64) {
+    let mut count = 0;
+    let mut bytes = 0;
+    for (_, size) in blobs(&Config::store_dir()) {
+        count += 1;
+        bytes += size;
+    }
+    (count, bytes)
+}
+
+/// Remove every blob no live session references; returns bytes freed
+pub fn sweep() -> Result<u64> {
+    sweep_in(&Config::store_dir(), &references()?)
+}
+
+fn sweep_in(store_dir: &Path, references: &HashMap<String, usize>) -> Result<u64> {
+    let mut freed = 0;
+    for (path, size) in blobs(store_dir) {
+        let hash = path.file_name().unwrap_or_default().to_string_lossy();
+        if references.contains_key(hash.as_ref()) {
+            continue;
+        }
+        std::fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove blob: {}", path.display()))?;
+        freed += size;
+        if let Some(dir) = path.parent() {
+            // Only succeeds once the fan-out directory is empty
+            let _ = std::fs::remove_dir(dir);
+        }
+    }
+    Ok(freed)
+}
+
+/// Every blob in the store with its size
+fn blobs(store_dir: &Path) -> Vec<(PathBuf, u64)> {
+    let Ok(dirs) = std::fs::read_dir(store_dir) else {
+        return Vec::new();
+    };
+    dirs.flatten()
+        .filter_map(|d| std::fs::read_dir(d.path()).ok())
+        .flat_map(|files| files.flatten())
+        .filter_map(|f| {
+            let meta = f.metadata().ok()?;
+            meta.is_file().then(|| (f.path(), meta.len()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_out_verifies_the_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let blob = dir.path().join("blob");
+        std::fs::write(&blob, "cached bytes").unwrap();
+        let hash = hash_file(&blob).unwrap();
+
+        let dest = dir.path().join("restored");
+        copy_out(&blob, &hash, &dest).unwrap();
+        assert_eq!(std::fs::read_to_string(&dest).unwrap(), "cached bytes");
+
+        // Never overwrites
+        assert!(copy_out(&blob, &hash, &dest).is_err());
+
+        std::fs::write(&blob, "bit rot").unwrap();
+        let other = dir.path().join("other");
+        let err = copy_out(&blob, &hash, &other).unwrap_err();
+        assert!(err.to_string().contains("corrupted"), "{}", err);
+        assert!(!other.exists());
+    }
+
+    #[test]
+    fn test_sweep_keeps_referenced_blobs() {
+        let store = tempfile::tempdir().unwrap();
+        for hash in ["aa11", "bb22"] {
+            let path = blob_path_in(store.path(), hash);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, hash).unwrap();
+        }
+        let references = HashMap::from([("aa11".to_string(), 2)]);
+
+        assert_eq!(sweep_in(store.path(), &references).unwrap(), 4);
+        assert!(blob_path_in(store.path(), "aa11").exists());
+        assert!(!store.path().join("bb").exists());
+    }
+}
@@ -1,4 +1,10 @@
 use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+use crate::cleaner::{ItemStatus, OnConflict};
+use crate::common::config::StagingStore;
+use crate::common::format;
+use crate::scanner::targets::SafetyLevel;
 
 /// TidyMac — A developer-aware, privacy-first Mac cleanup utility
 #[derive(Parser, Debug)]
@@ -12,8 +18,11 @@ use clap::{Parser, Subcommand, ValueEnum};
         tidymac scan                           Quick scan with default targets\n  \
         tidymac scan --profile developer       Developer-focused scan\n  \
         tidymac scan --profile deep --json     Deep scan with JSON output\n  \
+        tidymac scan --save before.json        Save a snapshot to compare later\n  \
+        tidymac scan --compare before.json     What changed since the snapshot\n  \
         tidymac clean --profile developer      Clean with soft delete (default)\n  \
         tidymac clean --profile quick --hard   Permanent deletion\n  \
+        tidymac clean --free 20GB -y           Free just 20 GB, least risky first\n  \
         tidymac dup ~/Pictures --perceptual    Find similar photos\n  \
         tidymac apps list --sort size          List apps by total size\n  \
         tidymac apps remove Slack --dry-run    Preview app removal\n  \
@@ -21,6 +30,8 @@ use clap::{Parser, Subcommand, ValueEnum};
         tidymac privacy scan                   Privacy audit\n  \
         tidymac viz                            Storage visualization\n  \
         tidymac undo --last                    Restore last cleanup\n  \
+        tidymac watch --profile developer      Keep scan results warm\n  \
+        tidymac trend --days 7                 Growth of caches over the last week\n  \
         tidymac status                         Show cleanup history"
 )]
 pub struct Cli {
@@ -56,7 +67,7 @@ pub enum Commands {
         #[arg(long)]
         detailed: bool,
 
-        /// Only scan specific categories
+        /// Only scan specific categories (e.g. user_cache,dev_cache.cargo,dev_cache.*)
         #[arg(long, value_delimiter = ',')]
         categories: Option<Vec<String>>,
 
@@ -67,6 +78,47 @@ pub enum Commands {
         /// Skip cache and force a fresh scan
         #[arg(long)]
         no_cache: bool,
+
+        /// Stop walking after this many seconds and report partial results
+        #[arg(long, value_name = "SECS")]
+        time_budget: Option<u64>,
+
+        /// Stop walking a target after this many files
+        #[arg(long, value_name = "N")]
+        max_files: Option<usize>,
+
+        /// Don't descend more than this many directory levels below each root
+        #[arg(long, value_name = "N")]
+        max_depth: Option<usize>,
+
+        /// Don't cross mount points or scan network filesystems
+        #[arg(long)]
+        one_file_system: bool,
+
+        /// Save the results as a snapshot for later comparison
+        #[arg(long, value_name = "FILE")]
+        save: Option<PathBuf>,
+
+        /// Show what changed since a saved snapshot instead of the results
+        #[arg(long, value_name = "SNAPSHOT")]
+        compare: Option<PathBuf>,
+
+        /// Write every file a clean would remove to a reviewable plan
+        #[arg(long, value_name = "FILE")]
+        save_plan: Option<PathBuf>,
+    },
+
+    /// Compare two saved scan snapshots
+    Diff {
+        /// Earlier snapshot (from `scan --save` or `scan --format json`)
+        old: PathBuf,
+
+        /// Later snapshot
+        new: PathBuf,
+
+        /// Show changed files within each item
+        #[arg(long)]
+        detailed: bool,
     },
 
     /// Remove selected files
@@ -79,17 +131,30 @@ pub enum Commands {
         #[arg(long, short = 'y')]
         yes: bool,
 
-        /// Only clean specific categories
+        /// Only clean specific categories (e.g. dev_cache.npm,dev_cache.cargo)
         #[arg(long, value_delimiter = ',')]
         categories: Option<Vec<String>>,
 
-        /// Only clean items above this safety level
+        /// Only clean items at or below this safety level
         #[arg(long, default_value = "safe")]
         max_safety: SafetyFilter,
 
         /// Simulate — show what would be cleaned
         #[arg(long)]
         dry_run: bool,
+
+        /// Only free this much (e.g. 20GB), taking the least risky files first
+        #[arg(long, value_name = "SIZE", value_parser = format::parse_size)]
+        free: Option<u64>,
+
+        /// Clean exactly the files in a plan from `scan --save-plan`, skipping
+        /// any that changed since
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["categories", "free"])]
+        plan: Option<PathBuf>,
+
+        /// How to stage files for undo (default: the `staging_store` setting)
+        #[arg(long, value_enum, conflicts_with = "hard")]
+        stage: Option<StageMode>,
     },
 
     /// Find duplicate files
@@ -168,6 +233,22 @@ pub enum Commands {
         /// List all available sessions
         #[arg(long)]
         list: bool,
+
+        /// Only restore original paths matching this glob (repeatable); a
+        /// directory selects everything under it, a bare name matches names
+        #[arg(long, value_name = "GLOB")]
+        only: Vec<String>,
+
+        /// Restore under this directory instead of the original locations
+        #[arg(long, value_name = "DIR")]
+        to: Option<PathBuf>,
+
+        /// What to do when something already exists at a restore destination
+        #[arg(long, value_enum, default_value = "error")]
+        conflict: ConflictMode,
+
+        #[command(subcommand)]
+        action: Option<UndoAction>,
     },
 
     /// Manage configuration
@@ -176,6 +257,32 @@ pub enum Commands {
         action: ConfigAction,
     },
 
+    /// Keep scan results warm by watching the filesystem for changes
+    Watch {
+        /// Minutes between full rescans of every target
+        #[arg(long, default_value = "60", value_name = "MINUTES")]
+        rescan_interval: u64,
+
+        /// Stop the daemon running for this profile
+        #[arg(long)]
+        stop: bool,
+    },
+
+    /// Show how scan targets have grown over recorded scans
+    Trend {
+        /// Only look at scans from the last N days
+        #[arg(long, default_value = "30", value_name = "N")]
+        days: u32,
+
+        /// How many of the fastest-growing targets to show
+        #[arg(long, default_value = "10", value_name = "N")]
+        top: usize,
+
+        /// Flag targets that grew by more than this many GB within a day
+        #[arg(long, default_value = "5", value_name = "GB")]
+        runaway_gb: f64,
+    },
+
     /// Show cleanup history and staging status
     Status {
         /// Generate a diagnostics zip file for support
@@ -212,6 +319,22 @@ pub enum Commands {
         #[arg(long)]
         install_auto: bool,
     },
+
+    /// Recover cleans that were interrupted before saving their manifest
+    Recover {
+        /// Move the staged files back where they came from
+        #[arg(long, conflicts_with = "finalize")]
+        rollback: bool,
+
+        /// Keep the staged files and write the missing manifest, so `undo`
+        /// and `purge` work as usual
+        #[arg(long)]
+        finalize: bool,
+
+        /// Only recover this session
+        #[arg(long)]
+        session: Option<String>,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -305,6 +428,51 @@ pub enum PrivacyAction {
     },
 }
 
+#[derive(Subcommand, Debug)]
+pub enum UndoAction {
+    /// Search every session and clean log for files, and say where they went
+    Find {
+        /// Glob over original paths; without a `/` it matches names
+        pattern: Option<String>,
+
+        /// Only files at least this large (e.g. 100MB)
+        #[arg(long, value_name = "SIZE", value_parser = format::parse_size)]
+        min_size: Option<u64>,
+
+        /// Only files at most this large
+        #[arg(long, value_name = "SIZE", value_parser = format::parse_size)]
+        max_size: Option<u64>,
+    },
+
+    /// List the items of one session
+    Show {
+        /// Session ID, from `undo --list` or `undo find`
+        session: String,
+
+        /// Only items whose original path matches this glob
+        #[arg(long, value_name = "GLOB")]
+        only: Option<String>,
+
+        /// Only files at least this large (e.g. 100MB)
+        #[arg(long, value_name = "SIZE", value_parser = format::parse_size)]
+        min_size: Option<u64>,
+
+        /// Only files at most this large
+        #[arg(long, value_name = "SIZE", value_parser = format::parse_size)]
+        max_size: Option<u64>,
+
+        /// Only items in this state
+        #[arg(long, value_enum)]
+        status: Option<StatusFilter>,
+    },
+
+    /// Check a session's staged files are all there and intact
+    Verify {
+        /// Session ID, from `undo --list`
+        session: String,
+    },
+}
+
 #[derive(Subcommand, Debug)]
 pub enum ConfigAction {
     /// Show current configuration
@@ -332,6 +500,8 @@ pub enum ConfigAction {
 pub enum OutputFormat {
     Human,
     Json,
+    /// One JSON event per line while the scan runs (`scan` only)
+    Jsonl,
     Quiet,
 }
 
@@ -342,6 +512,83 @@ pub enum SafetyFilter {
     Dangerous,
 }
 
+impl SafetyFilter {
+    /// The most risky `SafetyLevel` this filter allows
+    pub fn level(&self) -> SafetyLevel {
+        match self {
+            SafetyFilter::Safe => SafetyLevel::Safe,
+            SafetyFilter::Caution => SafetyLevel::Caution,
+            SafetyFilter::Dangerous => SafetyLevel::Dangerous,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum StageMode {
+    /// Move each file into the session
+    Move,
+    /// Keep one copy of identical files across sessions, by SHA-256
+    Dedup,
+    /// Pack logs and other compressible files into a zstd archive
+    Compress,
+}
+
+impl StageMode {
+    pub fn store(&self) -> StagingStore {
+        match self {
+            StageMode::Move => StagingStore::Move,
+            StageMode::Dedup => StagingStore::Dedup,
+            StageMode::Compress => StagingStore::Compress,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum ConflictMode {
+    /// Leave the existing file and report an error
+    Error,
+    /// Leave the existing file and skip the item
+    Skip,
+    /// Restore next to it as `<name>.restored`
+    Rename,
+    /// Replace the existing file
+    Overwrite,
+}
+
+impl ConflictMode {
+    pub fn policy(&self) -> OnConflict {
+        match self {
+            ConflictMode::Error => OnConflict::Error,
+            ConflictMode::Skip => OnConflict::Skip,
+            ConflictMode::Rename => OnConflict::Rename,
+            ConflictMode::Overwrite => OnConflict::Overwrite,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum StatusFilter {
+    Restorable,
+    Restored,
+    Purged,
+    HardDeleted,
+    Failed,
+    Skipped,
+}
+
+impl StatusFilter {
+    pub fn status(&self) -> ItemStatus {
+        match self {
+            StatusFilter::Restorable => ItemStatus::Restorable,
+            StatusFilter::Restored => ItemStatus::Restored,
+            StatusFilter::Purged => ItemStatus::Purged,
+            StatusFilter::HardDeleted => ItemStatus::HardDeleted,
+            StatusFilter::Failed => ItemStatus::Failed,
+            StatusFilter::Skipped => ItemStatus::Skipped,
+        }
+    }
+}
+
 #[derive(Debug, Clone, ValueEnum)]
 pub enum AppSort {
     Name,
@@ -2,6 +2,7 @@ use colored::*;
 use serde_json;
 
 use crate::common::format::{self, format_path, format_size, format_size_colored};
+use crate::scanner::cache::CacheStats;
 use crate::scanner::targets::{SafetyLevel, ScanItem, ScanResults};
 
 /// Print scan results in human-readable format
@@ -101,6 +102,31 @@ pub fn print_scan_results(results: &ScanResults, detailed: bool) {
         println!();
     }
 
+    if !results.truncated.is_empty() {
+        println!(
+            "  {} {}",
+            "⏱".yellow(),
+            format!(
+                "Partial results — {} target(s) stopped early by scan limits:",
+                results.truncated.len()
+            )
+            .yellow()
+        );
+        for truncation in &results.truncated {
+            println!(
+                "    {} {}: {}",
+                "→".dimmed(),
+                truncation.target,
+                truncation.reason.to_string().dimmed()
+            );
+        }
+        println!();
+    }
+
+    if let Some(ref stats) = results.cache {
+        print_cache_stats(stats);
+    }
+
     // Summary
     println!("{}", "─".repeat(60).dimmed());
     println!(
@@ -108,6 +134,21 @@ pub fn print_scan_results(results: &ScanResults, detailed: bool) {
         "💾".to_string(),
         format_size_colored(results.total_reclaimable)
     );
+    if results.total_apparent != results.total_reclaimable
+        || results.total_freeable != results.total_reclaimable
+    {
+        println!(
+            "  {} {}",
+            "📐".to_string(),
+            format!(
+                "{} apparent size, {} on disk, {} actually freed by cleaning",
+                format_size(results.total_apparent),
+                format_size(results.total_reclaimable),
+                format_size(results.total_freeable)
+            )
+            .dimmed()
+        );
+    }
     println!(
         "  {} Run {} to clean safely",
         "💡".to_string(),
@@ -140,6 +181,24 @@ fn print_scan_item(item: &ScanItem, detailed: bool) {
         format::format_count(item.file_count).dimmed()
     );
 
+    // Hard links kept elsewhere or blocks shared with clones stay on disk
+    if item.freeable_bytes != item.size_bytes {
+        println!(
+            "      {} {}",
+            "↳".dimmed(),
+            format!(
+                "frees {} (the rest is shared with hard links or clones)",
+                format_size(item.freeable_bytes)
+            )
+            .dimmed()
+        );
+    }
+
+    // Large files are never regenerated, so always show what to do with them
+    if !detailed && item.category == crate::scanner::targets::Category::LargeFile {
+        println!("      {} {}", "↳".dimmed(), item.reason.dimmed());
+    }
+
     if detailed {
         println!(
             "      {} {}",
@@ -152,18 +211,23 @@ fn print_scan_item(item: &ScanItem, detailed: bool) {
         let mut sorted_files = item.files.clone();
         sorted_files.sort_by(|a, b| b.size_bytes.cmp(&a.size_bytes));
         for file in sorted_files.iter().take(5) {
+            let link = match file.hard_link {
+                Some(link) => format!(", {} hard links", link.nlink),
+                None => String::new(),
+            };
             println!(
-                "        {} {} ({})",
+                "        {} {} ({}{})",
                 "•".dimmed(),
                 format_path(&file.path).dimmed(),
-                format_size(file.size_bytes).dimmed()
+                format_size(file.size_bytes).dimmed(),
+                link.dimmed()
             );
         }
-        if item.files.len() > 5 {
+        if item.file_count > 5 {
             println!(
                 "        {} ... and {} more",
                 "•".dimmed(),
-                (item.files.len() - 5).to_string().dimmed()
+                (item.file_count - 5).to_string().dimmed()
             );
         }
         println!();
@@ -178,6 +242,287 @@ pub fn print_scan_json(results: &ScanResults) {
     }
 }
 
+/// Print scan cache hit statistics
+fn print_cache_stats(stats: &CacheStats) {
+    if stats.hits + stats.watched + stats.misses + stats.invalidated == 0 {
+        return;
+    }
+    let watched = if stats.watched > 0 {
+        format!("{} from watch daemon, ", stats.watched)
+    } else {
+        String::new()
+    };
+    println!(
+        "  {} {}",
+        "⚡",
+        format!(
+            "Cache: {}{} hits, {} misses, {} invalidated ({:.0}% hit rate)",
+            watched,
+            stats.hits,
+            stats.misses,
+            stats.invalidated,
+            stats.hit_rate()
+        )
+        .dimmed()
+    );
+    println!();
+}
+
+/// Print each watch daemon and how current its per-target results are
+pub fn print_watch_states(states: &[crate::watch::state::WatchState]) {
+    for state in states {
+        let status = if state.is_live() {
+            format!("running (pid {}, {})", state.pid, state.backend).green()
+        } else {
+            "stopped".yellow()
+        };
+        println!("  {} Watch [{}]: {}", "👀", state.profile, status);
+        for target in &state.targets {
+            println!(
+                "    {} {:<32} {:>10}  {}",
+                "•".dimmed(),
+                target.item.name,
+                format_size(target.item.size_bytes),
+                crate::watch::state::staleness(state, target).dimmed()
+            );
+        }
+    }
+}
+
+/// Print growth of scan targets over recorded history
+pub fn print_trend(report: &crate::scanner::history::TrendReport) {
+    println!();
+    println!("{}  TidyMac Trends", "📈".to_string());
+    println!("{}", "─".repeat(60).dimmed());
+    println!(
+        "  {} scans since {}",
+        report.scans,
+        report.since.format("%Y-%m-%d").to_string().cyan()
+    );
+    println!("{}", "─".repeat(60).dimmed());
+    println!();
+
+    if report.scans < 2 {
+        println!(
+            "  {} Not enough history yet — trends appear after a few {} runs",
+            "💡".to_string(),
+            "tidymac scan".cyan()
+        );
+        println!();
+        return;
+    }
+
+    println!("  {}", "By category".bold());
+    for category in &report.categories {
+        println!(
+            "    {} {:<32} {:>10}  {}",
+            "•".dimmed(),
+            category.category.to_string(),
+            format_size(category.last_bytes),
+            format_growth(category.growth_bytes)
+        );
+    }
+    println!();
+
+    if !report.fastest_growing.is_empty() {
+        println!("  {}", "Fastest growing".bold());
+        for (i, target) in report.fastest_growing.iter().enumerate() {
+            println!(
+                "    {:>2}. {:<32} {:>10}  {}  {}",
+                i + 1,
+                target.name,
+                format_size(target.last_bytes),
+                format_growth(target.growth_bytes),
+                format!("({}/day)", format_growth(target.growth_per_day)).dimmed()
+            );
+        }
+        println!();
+    }
+
+    if !report.runaway.is_empty() {
+        println!(
+            "  {} {}",
+            "🚨".to_string(),
+            format!(
+                "Runaway writers — grew by more than {} within a day:",
+                format_size(report.runaway_threshold_bytes)
+            )
+            .red()
+            .bold()
+        );
+        for writer in &report.runaway {
+            println!(
+                "    {} {:<32} {}  {}",
+                "→".red(),
+                writer.name,
+                format!("+{}", format_size(writer.growth_bytes)).red(),
+                format!(
+                    "{} → {}",
+                    writer.from.format("%m-%d %H:%M"),
+                    writer.to.format("%m-%d %H:%M")
+                )
+                .dimmed()
+            );
+        }
+        println!();
+    }
+}
+
+/// Print a trend report as JSON
+pub fn print_trend_json(report: &crate::scanner::history::TrendReport) {
+    match serde_json::to_string_pretty(report) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("Error serializing trends: {}", e),
+    }
+}
+
+/// Signed size change, e.g. `+1.2 GB` in yellow or `-300 MB` in green
+fn format_growth(bytes: i64) -> ColoredString {
+    if bytes > 0 {
+        format!("+{}", format_size(bytes.unsigned_abs())).yellow()
+    } else if bytes < 0 {
+        format!("-{}", format_size(bytes.unsigned_abs())).green()
+    } else {
+        "±0".dimmed()
+    }
+}
+
+/// Confirm where a scan snapshot was written
+pub fn print_snapshot_saved(path: &std::path::Path) {
+    println!(
+        "  {} Snapshot saved to {} — compare later with {}",
+        "📸",
+        format_path(path).cyan(),
+        format!("tidymac scan --compare {}", path.display()).cyan()
+    );
+}
+
+/// Confirm where a clean plan was written
+pub fn print_plan_saved(path: &std::path::Path, plan: &crate::cleaner::SavedPlan) {
+    println!(
+        "  {} Clean plan saved to {}: {} ({}) — run it with {}",
+        "📝",
+        format_path(path).cyan(),
+        format::format_count(plan.file_count()),
+        format_size(plan.total_bytes()),
+        format!("tidymac clean --plan {}", path.display()).cyan()
+    );
+}
+
+/// Describe the saved plan a clean runs from
+pub fn print_plan_info(path: &std::path::Path, plan: &crate::cleaner::SavedPlan) {
+    println!();
+    println!("  {} Plan: {}", "📝".to_string(), format_path(path).bold());
+    println!(
+        "  {} {} ({}) planned {} with profile {}",
+        "  ".to_string(),
+        format::format_count(plan.file_count()),
+        format_size(plan.total_bytes()),
+        plan.created_at
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M"),
+        plan.profile
+    );
+    println!();
+}
+
+/// Print what changed between two scans
+pub fn print_diff(diff: &crate::scanner::diff::ScanDiff, detailed: bool) {
+    use crate::scanner::diff::Change;
+
+    println!();
+    println!("{}  TidyMac Scan Diff", "🔀".to_string());
+    println!("{}", "─".repeat(60).dimmed());
+    println!(
+        "  {} → {}  •  {} → {}  ({})",
+        diff.old.taken_at.format("%Y-%m-%d %H:%M").to_string().cyan(),
+        diff.new.taken_at.format("%Y-%m-%d %H:%M").to_string().cyan(),
+        format_size(diff.old_bytes),
+        format_size(diff.new_bytes),
+        format_growth(diff.delta_bytes)
+    );
+    println!(
+        "  {} added  •  {} removed  •  {} grown  •  {} shrunk",
+        diff.count(Change::Added),
+        diff.count(Change::Removed),
+        diff.count(Change::Grown),
+        diff.count(Change::Shrunk)
+    );
+    println!("{}", "─".repeat(60).dimmed());
+    println!();
+
+    if diff.items.is_empty() {
+        println!("  {} Nothing changed between the two scans", "✨".to_string());
+        println!();
+        return;
+    }
+
+    println!("  {}", "By category".bold());
+    for category in &diff.categories {
+        println!(
+            "    {} {:<32} {:>10} → {:<10}  {}",
+            "•".dimmed(),
+            category.category.to_string(),
+            format_size(category.old_bytes),
+            format_size(category.new_bytes),
+            format_growth(category.delta_bytes)
+        );
+    }
+    println!();
+
+    println!("  {}", "Items".bold());
+    for item in &diff.items {
+        let marker = match item.change {
+            Change::Added => "+".yellow(),
+            Change::Removed => "-".green(),
+            Change::Grown => "▲".yellow(),
+            Change::Shrunk => "▼".green(),
+        };
+        println!(
+            "    {} {:<40} {}  ({} → {} files)",
+            marker,
+            item.name,
+            format_growth(item.delta_bytes),
+            item.old_files,
+            item.new_files
+        );
+
+        if detailed {
+            if !item.files_compared {
+                println!(
+                    "        {} {}",
+                    "•".dimmed(),
+                    "file list was cut to the largest files; save snapshots with full lists to compare files".dimmed()
+                );
+            }
+            for file in item.files.iter().take(10) {
+                println!(
+                    "        {} {} ({})",
+                    "•".dimmed(),
+                    format_path(&file.path).dimmed(),
+                    format_growth(file.delta_bytes)
+                );
+            }
+            if item.files.len() > 10 {
+                println!(
+                    "        {} ... and {} more",
+                    "•".dimmed(),
+                    (item.files.len() - 10).to_string().dimmed()
+                );
+            }
+        }
+    }
+    println!();
+}
+
+/// Print a scan diff as JSON
+pub fn print_diff_json(diff: &crate::scanner::diff::ScanDiff) {
+    match serde_json::to_string_pretty(diff) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("Error serializing diff: {}", e),
+    }
+}
+
 /// Print a minimal summary
 pub fn print_scan_quiet(results: &ScanResults) {
     println!(
@@ -231,6 +576,23 @@ pub fn print_clean_report(report: &crate::cleaner::CleanReport) {
         format_size_colored(report.bytes_freed),
     );
 
+    if report.cancelled {
+        println!(
+            "  {} {}",
+            "⚠".yellow(),
+            "Cancelled partway — only the files above were touched".yellow()
+        );
+    }
+
+    if report.mode == crate::cleaner::CleanMode::SoftDelete && report.staged_bytes < report.bytes_freed {
+        println!(
+            "  {} Staged in {} ({} saved by compression or dedup)",
+            "🗜️",
+            format_size(report.staged_bytes).cyan(),
+            format_size(report.bytes_freed - report.staged_bytes)
+        );
+    }
+
     if let Some(ref sid) = report.session_id {
         println!("  {} Session: {}", "💾", sid.cyan());
         println!(
@@ -240,6 +602,29 @@ pub fn print_clean_report(report: &crate::cleaner::CleanReport) {
         );
     }
 
+    if !report.skipped.is_empty() {
+        println!();
+        println!(
+            "  {} Left {} alone because they changed since the scan:",
+            "⚠".yellow(),
+            format::format_count(report.skipped.len())
+        );
+        for skipped in report.skipped.iter().take(10) {
+            println!(
+                "    {} {}  {}",
+                "•".dimmed(),
+                format_path(&skipped.path),
+                skipped.reason.dimmed()
+            );
+        }
+        if report.skipped.len() > 10 {
+            println!(
+                "    ... and {} more",
+                (report.skipped.len() - 10).to_string().dimmed()
+            );
+        }
+    }
+
     if !report.errors.is_empty() {
         println!();
         println!("  {} {} errors:", "⚠".yellow(), report.errors.len());
@@ -270,19 +655,24 @@ pub fn print_sessions(sessions: &[crate::cleaner::SessionSummary]) {
     }
 
     println!(
-        "  {:<24} {:<12} {:<10} {:>10} {:>8}  {}",
+        "  {:<24} {:<12} {:<10} {:>10} {:>10} {:>8}  {}",
         "Session ID".dimmed(),
         "Profile".dimmed(),
         "Mode".dimmed(),
-        "Size".dimmed(),
+        "Original".dimmed(),
+        "Staged".dimmed(),
         "Files".dimmed(),
         "Status".dimmed(),
     );
-    println!("  {}", "─".repeat(76).dimmed());
+    println!("  {}", "─".repeat(87).dimmed());
 
     for session in sessions {
         let status = if session.restored {
             "Restored".green().to_string()
+        } else if session.restored_files > 0 {
+            format!("{} restored", session.restored_files)
+                .cyan()
+                .to_string()
         } else if session.is_expired {
             "Expired".red().to_string()
         } else {
@@ -304,10 +694,11 @@ pub fn print_sessions(sessions: &[crate::cleaner::SessionSummary]) {
         };
 
         println!(
-            "  {:<24} {:<12} {:<10} {:>10} {:>8}  {}",
+            "  {:<24} {:<12} {:<10} {:>10} {:>10} {:>8}  {}",
             session.session_id,
             session.profile,
             session.mode,
+            format_size(session.total_bytes),
             format_size(session.staged_size),
             session.total_files,
             status,
@@ -328,6 +719,170 @@ pub fn print_sessions(sessions: &[crate::cleaner::SessionSummary]) {
     println!();
 }
 
+/// Print items found in past sessions, grouped by session
+pub fn print_history(heading: &str, items: &[crate::cleaner::FoundItem]) {
+    use crate::cleaner::ItemStatus;
+
+    println!();
+    println!("  {} {}", "🔍", heading.bold());
+    println!("{}", "─".repeat(80).dimmed());
+
+    if items.is_empty() {
+        println!();
+        println!("  No matching files in staging or the clean logs.");
+        println!();
+        return;
+    }
+
+    let mut session = "";
+    for item in items {
+        if item.session_id != session {
+            session = &item.session_id;
+            println!();
+            println!(
+                "  {} {}  {}",
+                "📦",
+                item.session_id.cyan(),
+                format!(
+                    "{} · {} · {}",
+                    item.profile,
+                    item.mode.replace('_', " "),
+                    item.timestamp.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+                )
+                .dimmed()
+            );
+        }
+
+        let label = format!("{:<12}", item.status.label());
+        let status = match item.status {
+            ItemStatus::Restorable => label.green(),
+            ItemStatus::Restored | ItemStatus::Skipped | ItemStatus::Failed => label.cyan(),
+            ItemStatus::Purged | ItemStatus::HardDeleted => label.red(),
+        };
+        println!(
+            "    {} {:>10}  {}",
+            status,
+            format_size(item.size_bytes),
+            format_path(&item.path)
+        );
+        if let Some(ref to) = item.restored_to {
+            println!("      {} restored to {}", "→".dimmed(), format_path(to).dimmed());
+        }
+        if let Some(ref detail) = item.detail {
+            println!("      {} {}", "→".dimmed(), detail.dimmed());
+        }
+    }
+
+    let restorable = items
+        .iter()
+        .filter(|i| i.status == ItemStatus::Restorable)
+        .count();
+    println!();
+    println!(
+        "  {} files, {} still restorable",
+        items.len().to_string().cyan(),
+        restorable.to_string().green()
+    );
+    if restorable > 0 {
+        println!(
+            "  {} Restore: {}",
+            "💡",
+            "tidymac undo --session <ID> --only '<glob>'".cyan()
+        );
+    }
+    println!();
+}
+
+/// Print the result of `undo verify`
+pub fn print_verify_report(report: &crate::cleaner::VerifyReport) {
+    println!();
+    if report.is_ok() {
+        println!(
+            "  {} All {} staged files of {} are intact",
+            "✅",
+            report.checked.to_string().cyan(),
+            report.session_id.cyan()
+        );
+        println!();
+        return;
+    }
+
+    println!(
+        "  {} {} of {} staged files of {} can't be restored",
+        "⚠️",
+        (report.missing.len() + report.corrupted.len()).to_string().red(),
+        report.checked,
+        report.session_id.cyan()
+    );
+    for path in &report.missing {
+        println!("    {} {}  {}", "✗".red(), "missing".red(), format_path(path));
+    }
+    for path in &report.corrupted {
+        println!("    {} {}  {}", "✗".red(), "corrupted".red(), format_path(path));
+    }
+    println!();
+}
+
+/// Print cleans that stopped before saving their manifest
+pub fn print_incomplete_sessions(sessions: &[crate::cleaner::IncompleteSession]) {
+    println!();
+    if sessions.is_empty() {
+        println!("  {} No interrupted sessions.", "✓".green());
+        return;
+    }
+    println!(
+        "  {} {} clean(s) were interrupted before saving a manifest:",
+        "⚠".yellow(),
+        sessions.len()
+    );
+    for session in sessions {
+        println!(
+            "    {} {} ({}) — {} files ({}) in staging",
+            "•".dimmed(),
+            session.session_id.cyan(),
+            session.profile,
+            session.staged_count(),
+            format_size(session.staged_bytes())
+        );
+    }
+    println!();
+    println!(
+        "  {} {} moves them back, {} keeps them undoable",
+        "💡",
+        "tidymac recover --rollback".cyan(),
+        "tidymac recover --finalize".cyan()
+    );
+    println!();
+}
+
+/// Print staged files no session records
+pub fn print_orphaned_files(orphans: &[crate::cleaner::OrphanedFiles]) {
+    if orphans.is_empty() {
+        return;
+    }
+    println!(
+        "  {} Orphaned staged files no manifest or journal records:",
+        "⚠".yellow()
+    );
+    for orphan in orphans {
+        println!(
+            "    {} {} — {} files ({})",
+            "•".dimmed(),
+            orphan.session_id,
+            orphan.files,
+            format_size(orphan.bytes)
+        );
+    }
+    println!();
+    println!(
+        "  {} Inspect them under {}, or free them with {}",
+        "💡",
+        format_path(&crate::common::config::Config::staging_dir()),
+        "tidymac purge --session <ID>".cyan()
+    );
+    println!();
+}
+
 /// Print restore report
 pub fn print_restore_report(report: &crate::cleaner::RestoreReport) {
     println!();
@@ -339,6 +894,25 @@ pub fn print_restore_report(report: &crate::cleaner::RestoreReport) {
     );
     println!("  {} Session: {}", "📦", report.session_id.cyan());
 
+    if !report.skipped.is_empty() {
+        println!();
+        println!(
+            "  {} Skipped {} files that already exist:",
+            "⏭".yellow(),
+            report.skipped.len()
+        );
+        for path in report.skipped.iter().take(5) {
+            println!("    {} {}", "→".dimmed(), format_path(path).dimmed());
+        }
+    }
+    if report.remaining > 0 {
+        println!(
+            "  {} {} files remain in staging",
+            "ℹ️",
+            report.remaining.to_string().cyan()
+        );
+    }
+
     if !report.errors.is_empty() {
         println!();
         println!(
@@ -374,6 +948,13 @@ pub fn print_purge_report(report: &crate::cleaner::PurgeReport) {
             );
         }
     }
+    if report.store_bytes_freed > 0 {
+        println!(
+            "    {} {} of deduplicated files no session uses any more",
+            "✗".red(),
+            format_size(report.store_bytes_freed),
+        );
+    }
 
     if !report.errors.is_empty() {
         println!();
@@ -384,6 +965,124 @@ pub fn print_purge_report(report: &crate::cleaner::PurgeReport) {
     println!();
 }
 
+/// Print the items skipped by `--max-safety` or `exclude_paths`, with the reason for each
+pub fn print_held_back(held_back: &[crate::cleaner::HeldBackItem]) {
+    if held_back.is_empty() {
+        return;
+    }
+
+    let total: u64 = held_back.iter().map(|h| h.size_bytes).sum();
+    println!(
+        "  {} Held back {} items ({}):",
+        "🛡".yellow(),
+        held_back.len().to_string().cyan(),
+        format_size(total)
+    );
+    for item in held_back {
+        println!(
+            "    {} {:<40} {:>10}  [{}]",
+            "•".dimmed(),
+            item.name,
+            format_size(item.size_bytes),
+            item.safety.to_string().yellow()
+        );
+        println!("      {} {}", "↳".dimmed(), item.reason.dimmed());
+    }
+    println!();
+}
+
+/// Print the items and files picked to reach a `clean --free` goal
+pub fn print_clean_plan(plan: &crate::cleaner::CleanPlan) {
+    println!();
+    println!("{}  TidyMac Clean Plan", "🎯".to_string());
+    println!("{}", "─".repeat(60).dimmed());
+    println!(
+        "  Goal {}  •  plan frees {}  •  {} available",
+        format_size(plan.goal_bytes).cyan(),
+        format_size_colored(plan.planned_bytes),
+        format_size(plan.available_bytes)
+    );
+    println!("{}", "─".repeat(60).dimmed());
+    println!();
+
+    for item in &plan.items {
+        println!(
+            "    {} {:<40} {:>10}  ({})  [{}]",
+            "•".dimmed(),
+            item.name,
+            format_size(item.freeable_bytes),
+            format::format_count(item.file_count).dimmed(),
+            format::format_safety(&item.safety)
+        );
+    }
+    println!();
+
+    if !plan.reachable {
+        println!(
+            "  {} {}",
+            "✗".red(),
+            format!(
+                "Goal unreachable: everything allowed frees only {}",
+                format_size(plan.available_bytes)
+            )
+            .red()
+        );
+        println!();
+    }
+}
+
+/// Print what each trim policy keeps and what cleaning evicts
+pub fn print_trimmed(trimmed: &[crate::cleaner::TrimmedItem]) {
+    if trimmed.is_empty() {
+        return;
+    }
+
+    let kept: u64 = trimmed.iter().map(|t| t.kept_bytes).sum();
+    let evicted: u64 = trimmed.iter().map(|t| t.evicted_bytes).sum();
+    println!(
+        "  {} Trimming {} targets: keeping {}, evicting {}",
+        "✂".cyan(),
+        trimmed.len().to_string().cyan(),
+        format_size(kept),
+        format_size(evicted)
+    );
+    for item in trimmed {
+        println!(
+            "    {} {:<40} keep {:>10} ({})  •  evict {:>10} ({})",
+            "•".dimmed(),
+            item.name,
+            format_size(item.kept_bytes),
+            format::format_count(item.kept_files).dimmed(),
+            format_size(item.evicted_bytes),
+            format::format_count(item.evicted_files).dimmed()
+        );
+        println!("      {} {}", "↳".dimmed(), item.policy.to_string().dimmed());
+    }
+    println!();
+}
+
+/// List planned files skipped because they changed since the plan was saved
+pub fn print_drifted(drifted: &[crate::cleaner::DriftedFile]) {
+    if drifted.is_empty() {
+        return;
+    }
+
+    println!(
+        "  {} Skipping {} changed since the plan was saved:",
+        "⚠".yellow(),
+        format::format_count(drifted.len())
+    );
+    for file in drifted {
+        println!(
+            "    {} {}  {}",
+            "•".dimmed(),
+            format_path(&file.path),
+            file.reason.dimmed()
+        );
+    }
+    println!();
+}
+
 /// Print staging health warning if needed
 pub fn print_staging_health(health: &crate::cleaner::StagingHealth) {
     if let Some(ref warning) = health.warning {
@@ -536,7 +1235,15 @@ pub fn print_dup_results(results: &crate::duplicates::DupResults, detailed: bool
 
 /// Print duplicate results as JSON
 pub fn print_dup_json(results: &crate::duplicates::DupResults) {
-    let json = serde_json::json!({
+    match serde_json::to_string_pretty(&dup_json(results)) {
+        Ok(s) => println!("{}", s),
+        Err(e) => eprintln!("Error serializing: {}", e),
+    }
+}
+
+/// JSON form of duplicate results, shared with the FFI
+pub fn dup_json(results: &crate::duplicates::DupResults) -> serde_json::Value {
+    serde_json::json!({
         "files_scanned": results.files_scanned,
         "duration_secs": results.duration_secs,
         "total_groups": results.total_groups,
@@ -569,11 +1276,7 @@ pub fn print_dup_json(results: &crate::duplicates::DupResults) {
             })
         }).collect::<Vec<_>>(),
         "errors": results.errors,
-    });
-    match serde_json::to_string_pretty(&json) {
-        Ok(s) => println!("{}", s),
-        Err(e) => eprintln!("Error serializing: {}", e),
-    }
+    })
 }
 
 /// Print list of installed applications
@@ -0,0 +1,65 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use super::errors::TidyError;
+
+/// Cooperative cancellation for one long-running operation
+///
+/// Clones share the same flag, so a token handed to a scan can be cancelled
+/// from another thread without affecting any other operation. Work loops
+/// poll `is_cancelled` between files and stop at the next safe point.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    flag: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask every holder of this token to stop
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+
+    /// `Err(TidyError::Cancelled)` once the token has been cancelled
+    pub fn check(&self) -> anyhow::Result<()> {
+        if self.is_cancelled() {
+            return Err(TidyError::Cancelled.into());
+        }
+        Ok(())
+    }
+
+    /// The underlying flag, e.g. for `signal_hook::flag::register`
+    pub fn flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.flag)
+    }
+}
+
+/// Whether `err` came from a cancelled operation
+pub fn is_cancelled(err: &anyhow::Error) -> bool {
+    matches!(err.downcast_ref::<TidyError>(), Some(TidyError::Cancelled))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clones_share_state() {
+        let token = CancelToken::new();
+        let other = CancelToken::new();
+        let clone = token.clone();
+        assert!(token.check().is_ok());
+
+        clone.cancel();
+        assert!(token.is_cancelled());
+        assert!(!other.is_cancelled(), "independent tokens are unaffected");
+        assert!(is_cancelled(&token.check().unwrap_err()));
+    }
+}
@@ -2,6 +2,8 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
+use super::exclusions::ExclusionMatcher;
+
 /// Global TidyMac configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -21,6 +23,10 @@ pub struct Config {
     #[serde(default = "default_retention_days")]
     pub staging_retention_days: u32,
 
+    /// How soft deletes keep staged files
+    #[serde(default)]
+    pub staging_store: StagingStore,
+
     /// Large file threshold in MB
     #[serde(default = "default_large_file_mb")]
     pub large_file_threshold_mb: u64,
@@ -29,7 +35,7 @@ pub struct Config {
     #[serde(default = "default_stale_days")]
     pub stale_days: u32,
 
-    /// Paths to exclude from scanning
+    /// Gitignore-style patterns never scanned or cleaned (see `ExclusionMatcher`)
     #[serde(default)]
     pub exclude_paths: Vec<String>,
 
@@ -46,6 +52,31 @@ pub enum CleanMode {
     HardDelete,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StagingStore {
+    /// Each session keeps its own files
+    #[default]
+    Move,
+    /// Files are stored once by content hash and shared across sessions
+    Dedup,
+    /// Compressible files are packed into a zstd archive per session
+    Compress,
+}
+
+impl std::str::FromStr for StagingStore {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "move" => Ok(StagingStore::Move),
+            "dedup" => Ok(StagingStore::Dedup),
+            "compress" => Ok(StagingStore::Compress),
+            _ => anyhow::bail!("Unknown staging store '{}': use move, dedup or compress", s),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum OutputFormat {
@@ -81,6 +112,7 @@ impl Default for Config {
             default_mode: default_clean_mode(),
             default_profile: default_profile(),
             staging_retention_days: default_retention_days(),
+            staging_store: StagingStore::Move,
             large_file_threshold_mb: default_large_file_mb(),
             stale_days: default_stale_days(),
             exclude_paths: Vec::new(),
@@ -107,6 +139,11 @@ impl Config {
         Self::data_dir().join("staging")
     }
 
+    /// Get the content-addressed store shared by staging sessions
+    pub fn store_dir() -> PathBuf {
+        Self::data_dir().join("store")
+    }
+
     /// Get the logs directory
     pub fn logs_dir() -> PathBuf {
         Self::data_dir().join("logs")
@@ -117,6 +154,11 @@ impl Config {
         Self::data_dir().join("profiles")
     }
 
+    /// Get the cache directory
+    pub fn cache_dir() -> PathBuf {
+        Self::data_dir().join("cache")
+    }
+
     /// Load config from file, or create default if not exists
     pub fn load() -> Result<Self> {
         let path = Self::config_path();
@@ -175,6 +217,7 @@ impl Config {
             Self::staging_dir(),
             Self::logs_dir(),
             Self::profiles_dir(),
+            Self::cache_dir(),
         ];
         for dir in &dirs {
             std::fs::create_dir_all(dir)
@@ -188,10 +231,16 @@ impl Config {
         self.large_file_threshold_mb * 1024 * 1024
     }
 
-    /// Check if a path should be excluded
+    /// Build the matcher for `exclude_paths`
+    pub fn exclusions(&self) -> Result<ExclusionMatcher> {
+        ExclusionMatcher::new(&self.exclude_paths).context("Invalid exclude_paths in config")
+    }
+
+    /// Check if a path (or one of its parents) should be excluded
     pub fn is_excluded(&self, path: &Path) -> bool {
-        let path_str = path.display().to_string();
-        self.exclude_paths.iter().any(|p| path_str.contains(p))
+        self.exclusions()
+            .map(|m| m.is_excluded(path))
+            .unwrap_or(false)
     }
 }
 
@@ -33,6 +33,9 @@ pub enum TidyError {
     /// App bundle is invalid or unreadable
     AppError { name: String, message: String },
 
+    /// The operation was cancelled through its `CancelToken`
+    Cancelled,
+
     /// Generic error with context
     Other { message: String },
 }
@@ -68,6 +71,7 @@ impl std::fmt::Display for TidyError {
             TidyError::AppError { name, message } => {
                 write!(f, "App error for '{}': {}", name, message)
             }
+            TidyError::Cancelled => write!(f, "Operation cancelled"),
             TidyError::Other { message } => {
                 write!(f, "{}", message)
             }
@@ -0,0 +1,169 @@
+use anyhow::{Context, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Gitignore-style matcher for `Config.exclude_paths`
+///
+/// Patterns follow `.gitignore` rules, rooted at `/`:
+/// - `~/Library/Caches/JetBrains` or `/opt/big` — anchored to that path
+/// - `*.vmdk`, `DerivedData` — match a file or directory name anywhere
+/// - `**/fixtures/**` — globs across any depth
+/// - `!~/Library/Caches/JetBrains/tmp` — re-include a previously excluded path
+///
+/// Excluding a directory excludes everything beneath it.
+#[derive(Debug, Clone)]
+pub struct ExclusionMatcher {
+    inner: Arc<Gitignore>,
+    patterns: Vec<String>,
+}
+
+impl Default for ExclusionMatcher {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl ExclusionMatcher {
+    /// A matcher that excludes nothing
+    pub fn empty() -> Self {
+        Self {
+            inner: Arc::new(Gitignore::empty()),
+            patterns: Vec::new(),
+        }
+    }
+
+    /// Build a matcher from gitignore-style patterns, expanding a leading `~`
+    pub fn new(patterns: &[String]) -> Result<Self> {
+        let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
+        let mut builder = GitignoreBuilder::new("/");
+
+        for raw in patterns {
+            let line = expand_home(raw.trim(), &home);
+            if line.is_empty() {
+                continue;
+            }
+            builder
+                .add_line(None, &line)
+                .with_context(|| format!("Invalid exclude pattern '{}'", raw))?;
+        }
+
+        let inner = builder
+            .build()
+            .context("Failed to build exclude_paths matcher")?;
+
+        Ok(Self {
+            inner: Arc::new(inner),
+            patterns: patterns.to_vec(),
+        })
+    }
+
+    /// The patterns this matcher was built from
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Whether no patterns are configured
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Whether `path` or any of its parent directories is excluded
+    ///
+    /// Use this for paths reached without walking from an unexcluded root.
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        self.inner
+            .matched_path_or_any_parents(path, path.is_dir())
+            .is_ignore()
+    }
+
+    /// Whether a single directory entry is excluded, ignoring its parents
+    ///
+    /// Cheaper than `is_excluded` and correct while walking, since excluded
+    /// parents are pruned before their children are visited.
+    pub fn is_entry_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        self.inner.matched(path, is_dir).is_ignore()
+    }
+
+    /// Whether anything inside the directory `root` is excluded
+    ///
+    /// Used before removing a whole directory so an excluded descendant is
+    /// never taken down with it.
+    pub fn contains_excluded(&self, root: &Path) -> bool {
+        if self.is_empty() || !root.is_dir() {
+            return false;
+        }
+        walkdir::WalkDir::new(root)
+            .follow_links(false)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .any(|e| self.is_entry_excluded(e.path(), e.file_type().is_dir()))
+    }
+}
+
+/// Expand `~` at the start of a pattern, keeping any `!` negation prefix
+fn expand_home(pattern: &str, home: &Path) -> String {
+    let (negate, rest) = match pattern.strip_prefix('!') {
+        Some(rest) => ("!", rest),
+        None => ("", pattern),
+    };
+
+    let expanded = if rest == "~" {
+        home.display().to_string()
+    } else if let Some(tail) = rest.strip_prefix("~/") {
+        home.join(tail).display().to_string()
+    } else {
+        rest.to_string()
+    };
+
+    format!("{}{}", negate, expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(patterns: &[&str]) -> ExclusionMatcher {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+        ExclusionMatcher::new(&patterns).unwrap()
+    }
+
+    #[test]
+    fn test_anchored_path_excludes_descendants() {
+        let m = matcher(&["/data/keep"]);
+        assert!(m.is_excluded(Path::new("/data/keep")));
+        assert!(m.is_excluded(Path::new("/data/keep/a/b.bin")));
+        assert!(!m.is_excluded(Path::new("/data/other")));
+        assert!(!m.is_excluded(Path::new("/elsewhere/data/keep")));
+    }
+
+    #[test]
+    fn test_globs_and_negation() {
+        let m = matcher(&["*.vmdk", "**/fixtures/**", "/cache/*", "!/cache/tmp"]);
+        assert!(m.is_excluded(Path::new("/vm/disk.vmdk")));
+        assert!(m.is_excluded(Path::new("/src/app/fixtures/big.json")));
+        assert!(m.is_excluded(Path::new("/cache/a")));
+        assert!(!m.is_excluded(Path::new("/cache/tmp")));
+    }
+
+    #[test]
+    fn test_home_expansion() {
+        let home = dirs::home_dir().unwrap();
+        let m = matcher(&["~/Library/Caches/JetBrains"]);
+        assert!(m.is_excluded(&home.join("Library/Caches/JetBrains/idea/x")));
+        assert!(!m.is_excluded(&home.join("Library/Caches/Other")));
+    }
+
+    #[test]
+    fn test_empty_matcher_excludes_nothing() {
+        let m = ExclusionMatcher::empty();
+        assert!(m.is_empty());
+        assert!(!m.is_excluded(Path::new("/anything")));
+    }
+}
@@ -20,6 +20,29 @@ pub fn format_size(bytes: u64) -> String {
     }
 }
 
+/// Parse a size like `20GB`, `1.5 GiB`, `500M` or `4096`
+///
+/// Units are binary, matching `format_size`: `1GB` is 1024³ bytes.
+pub fn parse_size(s: &str) -> anyhow::Result<u64> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid size '{}': expected e.g. 20GB or 500MB", s))?;
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        "t" | "tb" | "tib" => 1 << 40,
+        other => anyhow::bail!("Unknown size unit '{}' in '{}': use B, KB, MB, GB or TB", other, s),
+    };
+    Ok((number * multiplier as f64).round() as u64)
+}
+
 /// Format size with color based on magnitude
 pub fn format_size_colored(bytes: u64) -> ColoredString {
     let s = format_size(bytes);
@@ -125,6 +148,16 @@ mod tests {
         assert_eq!(format_size(1099511627776), "1.00 TB");
     }
 
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096").unwrap(), 4096);
+        assert_eq!(parse_size("20GB").unwrap(), 20 * 1073741824);
+        assert_eq!(parse_size("1.5 GiB").unwrap(), 1610612736);
+        assert_eq!(parse_size("500m").unwrap(), 500 * 1048576);
+        assert!(parse_size("GB").is_err());
+        assert!(parse_size("12 parsecs").is_err());
+    }
+
     #[test]
     fn test_format_count() {
         assert_eq!(format_count(0), "0 files");
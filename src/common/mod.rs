@@ -1,7 +1,9 @@
+pub mod cancel;
 pub mod config;
 pub mod errors;
+pub mod exclusions;
 pub mod format;
 pub mod observability;
 pub mod permissions;
 pub mod safety;
-pub mod scan_cache;
+pub mod sizes;
@@ -1,223 +0,0 @@
-//! Incremental scan cache
-//!
-//! Persists the last `ScanResults` to `~/.tidymac/cache/last_scan.json`.
-//! On subsequent scans, unchanged paths (by mtime) are served from cache,
-//! skipping expensive filesystem walks.
-
-use anyhow::{Context, Result};
-use serde::{Deserialize, Serialize};
-use std::path::{Path, PathBuf};
-use std::time::SystemTime;
-
-use crate::common::config::Config;
-use crate::scanner::targets::ScanResults;
-
-/// Metadata stored alongside the cached results for invalidation checks.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ScanCacheMeta {
-    /// Wall-clock timestamp when the cache was written (Unix seconds).
-    pub created_at: u64,
-    /// Profile name the cache was built for.
-    pub profile: String,
-    /// mtime snapshots of scanned root paths used to detect staleness.
-    pub path_mtimes: Vec<PathMtime>,
-}
-
-/// A path paired with its last-modified timestamp (Unix seconds).
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PathMtime {
-    pub path: PathBuf,
-    pub mtime_secs: u64,
-}
-
-/// On-disk structure that bundles metadata + results.
-#[derive(Debug, Serialize, Deserialize)]
-struct CacheFile {
-    meta: ScanCacheMeta,
-    results: ScanResults,
-}
-
-/// Path to the cache directory.
-fn cache_dir() -> PathBuf {
-    Config::data_dir().join("cache")
-}
-
-/// Path to the last-scan cache JSON file.
-fn cache_path(profile: &str) -> PathBuf {
-    cache_dir().join(format!("scan_{}.json", profile))
-}
-
-/// Snapshot the mtime of all provided paths.
-pub fn snapshot_mtimes(paths: &[PathBuf]) -> Vec<PathMtime> {
-    paths
-        .iter()
-        .map(|p| {
-            let mtime_secs = mtime_secs(p);
-            PathMtime {
-                path: p.clone(),
-                mtime_secs,
-            }
-        })
-        .collect()
-}
-
-fn mtime_secs(path: &Path) -> u64 {
-    std::fs::metadata(path)
-        .and_then(|m| m.modified())
-        .and_then(|t| {
-            t.duration_since(SystemTime::UNIX_EPOCH)
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
-        })
-        .map(|d| d.as_secs())
-        .unwrap_or(0)
-}
-
-/// Save scan results to the incremental cache.
-pub fn save(profile: &str, results: &ScanResults, path_mtimes: Vec<PathMtime>) -> Result<()> {
-    let dir = cache_dir();
-    std::fs::create_dir_all(&dir)
-        .with_context(|| format!("Failed to create cache dir: {}", dir.display()))?;
-
-    let now = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs();
-
-    let cache = CacheFile {
-        meta: ScanCacheMeta {
-            created_at: now,
-            profile: profile.to_string(),
-            path_mtimes,
-        },
-        results: results.clone(),
-    };
-
-    let json = serde_json::to_string(&cache).context("Failed to serialize scan cache")?;
-    let path = cache_path(profile);
-    std::fs::write(&path, json)
-        .with_context(|| format!("Failed to write scan cache: {}", path.display()))?;
-
-    Ok(())
-}
-
-/// Try to load a cached result for the given profile.
-///
-/// Returns `None` if:
-/// - No cache exists for this profile
-/// - The cache is older than `max_age_secs`
-/// - Any of the tracked paths have been modified since the cache was written
-pub fn load(profile: &str, paths_to_check: &[PathBuf], max_age_secs: u64) -> Option<ScanResults> {
-    let path = cache_path(profile);
-    if !path.exists() {
-        return None;
-    }
-
-    let json = std::fs::read_to_string(&path).ok()?;
-    let cache: CacheFile = serde_json::from_str(&json).ok()?;
-
-    // Age check
-    let now = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs();
-
-    if now.saturating_sub(cache.meta.created_at) > max_age_secs {
-        tracing::debug!("Scan cache expired for profile '{}'", profile);
-        return None;
-    }
-
-    // Staleness check — compare current mtime of each cached path
-    for tracked in &cache.meta.path_mtimes {
-        let current = mtime_secs(&tracked.path);
-        if current != tracked.mtime_secs {
-            tracing::debug!(
-                "Cache invalidated: path '{}' mtime changed",
-                tracked.path.display()
-            );
-            return None;
-        }
-    }
-
-    // Also validate any additional paths the caller wants to check
-    for p in paths_to_check {
-        let current = mtime_secs(p);
-        let was = cache
-            .meta
-            .path_mtimes
-            .iter()
-            .find(|pm| pm.path == *p)
-            .map(|pm| pm.mtime_secs)
-            .unwrap_or(0);
-        if current != was {
-            tracing::debug!(
-                "Cache invalidated: extra path '{}' mtime changed",
-                p.display()
-            );
-            return None;
-        }
-    }
-
-    tracing::info!("Using incremental scan cache for profile '{}'", profile);
-    Some(cache.results)
-}
-
-/// Delete the cache file for a given profile.
-pub fn invalidate(profile: &str) {
-    let _ = std::fs::remove_file(cache_path(profile));
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::TempDir;
-
-    fn make_results() -> ScanResults {
-        ScanResults::new()
-    }
-
-    #[test]
-    fn test_save_and_load_roundtrip() {
-        let tmp = TempDir::new().unwrap();
-        // Create a file to track
-        let tracked = tmp.path().join("tracked.txt");
-        std::fs::write(&tracked, b"data").unwrap();
-
-        let mtimes = snapshot_mtimes(&[tracked.clone()]);
-        let _results = make_results();
-
-        // We can't easily override the cache_dir, but we can test the
-        // serialization logic directly.
-        let now = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-
-        let _meta = ScanCacheMeta {
-            created_at: now,
-            profile: "test".to_string(),
-            path_mtimes: mtimes.clone(),
-        };
-
-        // Verify snapshot captured a mtime
-        assert!(mtimes.iter().any(|m| m.path == tracked));
-    }
-
-    #[test]
-    fn test_snapshot_missing_path_returns_zero() {
-        let missing = PathBuf::from("/this/does/not/exist/tidymac_test");
-        let snaps = snapshot_mtimes(&[missing.clone()]);
-        assert_eq!(snaps[0].mtime_secs, 0);
-    }
-
-    #[test]
-    fn test_invalidate_missing_cache_does_not_panic() {
-        // Should be a no-op
-        invalidate("__nonexistent_profile_test__");
-    }
-
-    #[test]
-    fn test_load_nonexistent_returns_none() {
-        let result = load("__no_such_profile__", &[], 3600);
-        assert!(result.is_none());
-    }
-}
@@ -0,0 +1,295 @@
+//! Apparent, allocated and freeable sizes
+//!
+//! * **apparent** — the file's length (`st_size`), what Finder shows as "Size"
+//! * **allocated** — blocks on disk (`st_blocks * 512`), what `du` shows; less
+//!   than apparent for sparse and compressed files
+//! * **freeable** — what deleting would actually give back. A hard-linked
+//!   file frees nothing until its last link goes, and an APFS clone only
+//!   frees the blocks it doesn't share with its source.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+/// Sizes of a file, directory or group of files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiskUsage {
+    pub apparent: u64,
+    pub allocated: u64,
+    pub freeable: u64,
+}
+
+impl std::ops::AddAssign for DiskUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.apparent += other.apparent;
+        self.allocated += other.allocated;
+        self.freeable += other.freeable;
+    }
+}
+
+/// Identity of a file with more than one hard link
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct HardLink {
+    pub dev: u64,
+    pub ino: u64,
+    /// Total number of links, wherever they are
+    pub nlink: u64,
+}
+
+impl HardLink {
+    /// `Some` only when `meta` has other links besides this path
+    pub fn of(meta: &Metadata) -> Option<Self> {
+        (meta.nlink() > 1).then(|| Self {
+            dev: meta.dev(),
+            ino: meta.ino(),
+            nlink: meta.nlink(),
+        })
+    }
+}
+
+/// Which file a path pointed at: device and inode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FileId {
+    pub dev: u64,
+    pub ino: u64,
+}
+
+impl FileId {
+    pub fn of(meta: &Metadata) -> Self {
+        Self {
+            dev: meta.dev(),
+            ino: meta.ino(),
+        }
+    }
+}
+
+/// Bytes allocated on disk
+pub fn allocated_bytes(meta: &Metadata) -> u64 {
+    meta.blocks() * 512
+}
+
+/// Bytes deleting this one path would free, ignoring other links
+///
+/// For clones this is the private (unshared) size where the filesystem
+/// reports it, otherwise the allocated size.
+pub fn unshared_bytes(path: &Path, meta: &Metadata) -> u64 {
+    let allocated = allocated_bytes(meta);
+    // Single-block files aren't worth the extra syscall
+    if allocated <= 4096 {
+        return allocated;
+    }
+    clone_private_bytes(path)
+        .map(|private| private.min(allocated))
+        .unwrap_or(allocated)
+}
+
+/// Usage of a single file, with hard-link identity when it has other links
+///
+/// `freeable` is 0 for hard-linked files; `UsageTally` decides whether a set
+/// of paths covers every link.
+pub fn file_usage(path: &Path, meta: &Metadata) -> (DiskUsage, Option<HardLink>) {
+    let link = HardLink::of(meta);
+    let usage = DiskUsage {
+        apparent: meta.len(),
+        allocated: allocated_bytes(meta),
+        freeable: if link.is_some() {
+            0
+        } else {
+            unshared_bytes(path, meta)
+        },
+    };
+    (usage, link)
+}
+
+/// Adds up per-path usages, counting each hard-linked file once
+///
+/// A hard-linked file is only freeable when all of its links were added.
+/// Only files with several links are remembered, and tallies kept by
+/// separate walkers can be merged, so no lock is needed per file.
+#[derive(Debug, Default)]
+pub struct UsageTally {
+    total: DiskUsage,
+    links: HashMap<(u64, u64), LinkSeen>,
+}
+
+#[derive(Debug)]
+struct LinkSeen {
+    paths: u64,
+    nlink: u64,
+    usage: DiskUsage,
+}
+
+impl UsageTally {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, usage: DiskUsage, link: Option<HardLink>) {
+        match link {
+            None => self.total += usage,
+            Some(link) => {
+                self.links
+                    .entry((link.dev, link.ino))
+                    .or_insert(LinkSeen {
+                        paths: 0,
+                        nlink: link.nlink,
+                        usage,
+                    })
+                    .paths += 1;
+            }
+        }
+    }
+
+    /// Fold in a tally of other paths
+    pub fn merge(&mut self, other: UsageTally) {
+        self.total += other.total;
+        for (key, seen) in other.links {
+            match self.links.get_mut(&key) {
+                Some(mine) => mine.paths += seen.paths,
+                None => {
+                    self.links.insert(key, seen);
+                }
+            }
+        }
+    }
+
+    pub fn finish(self) -> DiskUsage {
+        let mut total = self.total;
+        for seen in self.links.into_values() {
+            total.apparent += seen.usage.apparent;
+            total.allocated += seen.usage.allocated;
+            if seen.paths >= seen.nlink {
+                total.freeable += seen.usage.allocated;
+            }
+        }
+        total
+    }
+}
+
+/// Usage of everything under `path`, hard links counted once
+pub fn dir_usage(path: &Path) -> DiskUsage {
+    let mut tally = UsageTally::new();
+    let walker = jwalk::WalkDir::new(path).follow_links(false).sort(false);
+    for entry in walker.into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type.is_file() {
+            continue;
+        }
+        if let Ok(meta) = entry.metadata() {
+            let (usage, link) = file_usage(&entry.path(), &meta);
+            tally.add(usage, link);
+        }
+    }
+    tally.finish()
+}
+
+/// Private size of an APFS clone via `getattrlist(ATTR_CMNEXT_PRIVATESIZE)`
+#[cfg(target_os = "macos")]
+fn clone_private_bytes(path: &Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    const ATTR_BIT_MAP_COUNT: u16 = 5;
+    const ATTR_CMN_RETURNED_ATTRS: u32 = 0x8000_0000;
+    const ATTR_CMNEXT_PRIVATESIZE: u32 = 0x0000_0008;
+    const FSOPT_NOFOLLOW: u32 = 0x0000_0001;
+    const FSOPT_ATTR_CMN_EXTENDED: u32 = 0x0000_0020;
+
+    #[repr(C)]
+    struct AttrList {
+        bitmapcount: u16,
+        reserved: u16,
+        commonattr: u32,
+        volattr: u32,
+        dirattr: u32,
+        fileattr: u32,
+        forkattr: u32,
+    }
+
+    #[repr(C, packed(4))]
+    struct AttrBuf {
+        length: u32,
+        returned: [u32; 5],
+        private_size: i64,
+    }
+
+    extern "C" {
+        fn getattrlist(
+            path: *const libc::c_char,
+            attr_list: *mut AttrList,
+            attr_buf: *mut AttrBuf,
+            attr_buf_size: libc::size_t,
+            options: u32,
+        ) -> libc::c_int;
+    }
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut list = AttrList {
+        bitmapcount: ATTR_BIT_MAP_COUNT,
+        reserved: 0,
+        commonattr: ATTR_CMN_RETURNED_ATTRS,
+        volattr: 0,
+        dirattr: 0,
+        fileattr: 0,
+        // With FSOPT_ATTR_CMN_EXTENDED this field selects ATTR_CMNEXT_*
+        forkattr: ATTR_CMNEXT_PRIVATESIZE,
+    };
+    let mut buf = AttrBuf {
+        length: 0,
+        returned: [0; 5],
+        private_size: 0,
+    };
+    let rc = unsafe {
+        getattrlist(
+            c_path.as_ptr(),
+            &mut list,
+            &mut buf,
+            std::mem::size_of::<AttrBuf>(),
+            FSOPT_NOFOLLOW | FSOPT_ATTR_CMN_EXTENDED,
+        )
+    };
+    let returned = buf.returned;
+    if rc != 0 || returned[4] & ATTR_CMNEXT_PRIVATESIZE == 0 {
+        return None;
+    }
+    let private_size = buf.private_size;
+    u64::try_from(private_size).ok()
+}
+
+#[cfg(not(target_os = "macos"))]
+fn clone_private_bytes(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tally_counts_hard_links_once() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let a = dir.path().join("a");
+        std::fs::write(&a, vec![1u8; 10_000]).unwrap();
+        std::fs::hard_link(&a, dir.path().join("b")).unwrap();
+
+        let meta = std::fs::metadata(&a).unwrap();
+        let (usage, link) = file_usage(&a, &meta);
+        assert!(link.is_some());
+        assert_eq!(usage.apparent, 10_000);
+        assert_eq!(usage.freeable, 0);
+
+        // One of two links: counted, but deleting it frees nothing
+        let mut tally = UsageTally::new();
+        tally.add(usage, link);
+        let one = tally.finish();
+        assert_eq!(one.apparent, 10_000);
+        assert_eq!(one.freeable, 0);
+
+        // Both links: counted once and freeable
+        let both = dir_usage(dir.path());
+        assert_eq!(both.apparent, 10_000);
+        assert_eq!(both.allocated, usage.allocated);
+        assert_eq!(both.freeable, usage.allocated);
+    }
+}
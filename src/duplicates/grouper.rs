@@ -7,6 +7,9 @@ use walkdir::WalkDir;
 
 use super::hasher;
 use super::perceptual::{self, MatchType, SimilarFile, SimilarGroup};
+use crate::common::cancel::CancelToken;
+use crate::common::exclusions::ExclusionMatcher;
+use crate::common::sizes;
 
 /// Configuration for duplicate scanning
 #[derive(Debug, Clone)]
@@ -21,6 +24,10 @@ pub struct DupConfig {
     pub threshold: f64,
     /// Show progress bars
     pub show_progress: bool,
+    /// Paths never collected (from `Config.exclude_paths`)
+    pub excludes: ExclusionMatcher,
+    /// Stops the scan early; a cancelled scan returns `TidyError::Cancelled`
+    pub cancel: CancelToken,
 }
 
 /// Complete results from a duplicate scan
@@ -60,7 +67,10 @@ pub fn find_duplicates(config: &DupConfig) -> Result<DupResults> {
 
     // ── Step 0: Collect all files in parallel ─────────────────────────────
     let pb = make_spinner(config.show_progress, "Collecting files...");
-    let all_files = collect_files_parallel(&config.root, config.min_size);
+    let cancel = &config.cancel;
+    let all_files =
+        collect_files_parallel(&config.root, config.min_size, &config.excludes, cancel);
+    cancel.check()?;
     results.files_scanned = all_files.len();
     finish_spinner(pb, &format!("Found {} files", all_files.len()));
 
@@ -71,7 +81,8 @@ pub fn find_duplicates(config: &DupConfig) -> Result<DupResults> {
 
     // ── Pass 1: Group by file size ────────────────────────────────────────
     let pb = make_spinner(config.show_progress, "Pass 1: Grouping by file size...");
-    let size_groups = hasher::group_by_size(&all_files);
+    let size_groups = hasher::group_by_size(&all_files, cancel);
+    cancel.check()?;
     let candidates: usize = size_groups.values().map(|v| v.len()).sum();
     finish_spinner(
         pb,
@@ -96,7 +107,7 @@ pub fn find_duplicates(config: &DupConfig) -> Result<DupResults> {
     let mut quick_candidates: Vec<Vec<PathBuf>> = Vec::new();
 
     for (_size, paths) in &size_groups {
-        let quick_groups = hasher::group_by_quick_hash(paths);
+        let quick_groups = hasher::group_by_quick_hash(paths, cancel);
         for (_hash, group) in quick_groups {
             quick_candidates.push(group);
         }
@@ -108,6 +119,7 @@ pub fn find_duplicates(config: &DupConfig) -> Result<DupResults> {
         pb,
         &format!("Pass 2: {} candidate groups", quick_candidates.len()),
     );
+    cancel.check()?;
 
     if quick_candidates.is_empty() {
         results.duration_secs = start.elapsed().as_secs_f64();
@@ -122,25 +134,15 @@ pub fn find_duplicates(config: &DupConfig) -> Result<DupResults> {
     );
 
     for candidate_group in &quick_candidates {
-        let full_groups = hasher::group_by_full_hash(candidate_group);
+        let full_groups = hasher::group_by_full_hash(candidate_group, cancel);
         for (_hash, paths) in full_groups {
             let mut members: Vec<SimilarFile> = paths
                 .iter()
                 .map(|p| {
                     // Use physical disk usage (st_blocks) for consistency
-                    let size = {
-                        #[cfg(target_os = "macos")]
-                        {
-                            use std::os::darwin::fs::MetadataExt;
-                            std::fs::metadata(p)
-                                .map(|m| m.st_blocks() * 512)
-                                .unwrap_or(0)
-                        }
-                        #[cfg(not(target_os = "macos"))]
-                        {
-                            std::fs::metadata(p).map(|m| m.len()).unwrap_or(0)
-                        }
-                    };
+                    let size = std::fs::metadata(p)
+                        .map(|m| sizes::allocated_bytes(&m))
+                        .unwrap_or(0);
                     SimilarFile {
                         path: p.clone(),
                         size_bytes: size,
@@ -175,6 +177,7 @@ pub fn find_duplicates(config: &DupConfig) -> Result<DupResults> {
             results.exact_groups.len()
         ),
     );
+    cancel.check()?;
 
     // ── Pass 4 (optional): Perceptual image hashing ──────────────────────
     if config.perceptual {
@@ -201,25 +204,20 @@ pub fn find_duplicates(config: &DupConfig) -> Result<DupResults> {
             let phashes: Vec<perceptual::PerceptualHash> = image_files
                 .par_iter()
                 .filter_map(|path| {
+                    if cancel.is_cancelled() {
+                        return None;
+                    }
                     let result = perceptual::compute_perceptual_hash(path);
                     if let Some(ref pb) = pb {
                         pb.inc(1);
                     }
                     match result {
                         Ok(hash) => {
-                            let size = {
-                                #[cfg(target_os = "macos")]
-                                {
-                                    use std::os::darwin::fs::MetadataExt;
-                                    std::fs::metadata(path)
-                                        .map(|m| m.st_blocks() * 512)
-                                        .unwrap_or(0)
-                                }
-                                #[cfg(not(target_os = "macos"))]
-                                {
-                                    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
-                                }
-                            };
+                            let size = std::fs::metadata(path)
+
+                                .map(|m| sizes::allocated_bytes(&m))
+
+                                .unwrap_or(0);
                             Some(perceptual::PerceptualHash {
                                 path: path.clone(),
                                 hash,
@@ -247,6 +245,7 @@ pub fn find_duplicates(config: &DupConfig) -> Result<DupResults> {
             );
 
             finish_progress(pb, &format!("Hashed {} images", phashes.len()));
+            cancel.check()?;
 
             // Find similar groups
             if phashes.len() >= 2 {
@@ -304,7 +303,16 @@ pub fn find_duplicates(config: &DupConfig) -> Result<DupResults> {
 }
 
 /// Collect all files in a directory tree in parallel, filtered by minimum size
-fn collect_files_parallel(root: &PathBuf, min_size: u64) -> Vec<PathBuf> {
+fn collect_files_parallel(
+    root: &PathBuf,
+    min_size: u64,
+    excludes: &ExclusionMatcher,
+    cancel: &CancelToken,
+) -> Vec<PathBuf> {
+    if excludes.is_excluded(root) {
+        return Vec::new();
+    }
+
     // First, collect entries from the walkdir (single-threaded walk needed for
     // deterministic ordering), then filter in parallel for speed.
     let raw: Vec<PathBuf> = WalkDir::new(root)
@@ -312,9 +320,13 @@ fn collect_files_parallel(root: &PathBuf, min_size: u64) -> Vec<PathBuf> {
         .into_iter()
         .filter_entry(|e| {
             let name = e.file_name().to_string_lossy();
-            // Skip hidden dirs, node_modules, .git, Library
-            !name.starts_with('.') && name != "node_modules" && name != "Library"
+            // Skip hidden dirs, node_modules, .git, Library, and excluded paths
+            !name.starts_with('.')
+                && name != "node_modules"
+                && name != "Library"
+                && !excludes.is_entry_excluded(e.path(), e.file_type().is_dir())
         })
+        .take_while(|_| !cancel.is_cancelled())
         .filter_map(|e| e.ok())
         .filter(|e| e.file_type().is_file())
         .map(|e| e.path().to_path_buf())
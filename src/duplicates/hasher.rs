@@ -5,9 +5,8 @@ use std::io::{BufReader, Read};
 use std::path::{Path, PathBuf};
 use rayon::prelude::*;
 use std::sync::{Arc, Mutex, Condvar};
-use std::sync::atomic::Ordering;
 
-use crate::ffi::CANCEL_FLAG;
+use crate::common::cancel::CancelToken;
 
 /// Size of the quick hash prefix (first 4KB)
 const QUICK_HASH_SIZE: usize = 4096;
@@ -79,11 +78,14 @@ pub fn full_hash(path: &Path) -> Result<String> {
 /// Group file paths by their file size
 /// This is Pass 1: instantly eliminates ~95% of files since
 /// files with unique sizes cannot be duplicates.
-pub fn group_by_size(files: &[PathBuf]) -> std::collections::HashMap<u64, Vec<PathBuf>> {
+pub fn group_by_size(
+    files: &[PathBuf],
+    cancel: &CancelToken,
+) -> std::collections::HashMap<u64, Vec<PathBuf>> {
     let groups = Arc::new(Mutex::new(std::collections::HashMap::<u64, Vec<PathBuf>>::new()));
 
     files.par_iter().for_each(|path| {
-        if CANCEL_FLAG.load(Ordering::Relaxed) {
+        if cancel.is_cancelled() {
             return;
         }
         if let Ok(meta) = std::fs::metadata(path) {
@@ -102,11 +104,14 @@ pub fn group_by_size(files: &[PathBuf]) -> std::collections::HashMap<u64, Vec<Pa
 
 /// Group files by quick hash (first 4KB)
 /// This is Pass 2: eliminates most remaining false positives cheaply.
-pub fn group_by_quick_hash(files: &[PathBuf]) -> std::collections::HashMap<String, Vec<PathBuf>> {
+pub fn group_by_quick_hash(
+    files: &[PathBuf],
+    cancel: &CancelToken,
+) -> std::collections::HashMap<String, Vec<PathBuf>> {
     let groups = Arc::new(Mutex::new(std::collections::HashMap::<String, Vec<PathBuf>>::new()));
 
     files.par_iter().for_each(|path| {
-        if CANCEL_FLAG.load(Ordering::Relaxed) {
+        if cancel.is_cancelled() {
             return;
         }
         match quick_hash(path) {
@@ -125,12 +130,15 @@ pub fn group_by_quick_hash(files: &[PathBuf]) -> std::collections::HashMap<Strin
 
 /// Group files by full SHA-256 hash
 /// This is Pass 3: confirms exact byte-for-byte duplicates.
-pub fn group_by_full_hash(files: &[PathBuf]) -> std::collections::HashMap<String, Vec<PathBuf>> {
+pub fn group_by_full_hash(
+    files: &[PathBuf],
+    cancel: &CancelToken,
+) -> std::collections::HashMap<String, Vec<PathBuf>> {
     let groups = Arc::new(Mutex::new(std::collections::HashMap::<String, Vec<PathBuf>>::new()));
     let sem = Arc::new(Semaphore::new(MAX_CONCURRENT_HASHES));
 
     files.par_iter().for_each(|path| {
-        if CANCEL_FLAG.load(Ordering::Relaxed) {
+        if cancel.is_cancelled() {
             return;
         }
         
@@ -7,16 +7,20 @@
 //! All `extern "C"` functions are wrapped in `std::panic::catch_unwind` to prevent
 //! Rust panics from crossing the C ABI boundary (which is undefined behavior).
 
+use std::collections::HashMap;
 use std::ffi::{CStr, CString};
-use std::os::raw::c_char;
-use std::sync::atomic::{AtomicBool, Ordering};
-
-pub static CANCEL_FLAG: AtomicBool = AtomicBool::new(false);
+use std::os::raw::{c_char, c_void};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
 
+use crate::common::cancel::{self, CancelToken};
 use crate::common::config::Config;
 use crate::common::format;
 use crate::profiles::loader::Profile;
-use crate::scanner;
+use crate::scanner::aggregate::DEFAULT_TOP_FILES;
+use crate::scanner::events::EventSink;
+use crate::scanner::{self, ScanOptions};
+use crate::scanner::targets::SafetyLevel;
 
 // ─── Panic Safety Macro ──────────────────────────────────────────────────────
 
@@ -89,6 +93,96 @@ pub extern "C" fn tidymac_init_observability(verbose: bool, sentry_dsn: *const c
     }));
 }
 
+// ─── Operations ──────────────────────────────────────────────────────────────
+
+/// A cancellable operation started through the FFI
+struct Operation {
+    token: CancelToken,
+    /// Started by an entry point without a handle; `tidymac_cancel_scan`
+    /// cancels these
+    legacy: bool,
+}
+
+/// Live operations by handle; handles are never reused
+fn operations() -> &'static Mutex<HashMap<u64, Operation>> {
+    static OPERATIONS: OnceLock<Mutex<HashMap<u64, Operation>>> = OnceLock::new();
+    OPERATIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+static NEXT_OPERATION: AtomicU64 = AtomicU64::new(1);
+
+fn register_operation(legacy: bool) -> (u64, CancelToken) {
+    let handle = NEXT_OPERATION.fetch_add(1, Ordering::Relaxed);
+    let token = CancelToken::new();
+    operations().lock().unwrap().insert(
+        handle,
+        Operation {
+            token: token.clone(),
+            legacy,
+        },
+    );
+    (handle, token)
+}
+
+/// Token for `handle`; 0 means the caller doesn't need to cancel
+fn operation_token(handle: u64) -> Option<CancelToken> {
+    if handle == 0 {
+        return Some(CancelToken::new());
+    }
+    operations()
+        .lock()
+        .unwrap()
+        .get(&handle)
+        .map(|op| op.token.clone())
+}
+
+/// Registers a legacy operation for the duration of one call
+struct LegacyOperation {
+    handle: u64,
+    token: CancelToken,
+}
+
+impl LegacyOperation {
+    fn start() -> Self {
+        let (handle, token) = register_operation(true);
+        Self { handle, token }
+    }
+}
+
+impl Drop for LegacyOperation {
+    fn drop(&mut self) {
+        operations().lock().unwrap().remove(&self.handle);
+    }
+}
+
+/// Create a handle for one cancellable operation. Pass it to a function
+/// taking an `operation` argument, cancel it from any thread with
+/// `tidymac_operation_cancel`, and release it with `tidymac_operation_free`
+/// once that function has returned. Handles are never 0.
+#[no_mangle]
+pub extern "C" fn tidymac_operation_new() -> u64 {
+    std::panic::catch_unwind(|| register_operation(false).0).unwrap_or(0)
+}
+
+/// Cancel the operation behind `handle`. Other operations are unaffected.
+/// Unknown or freed handles are ignored.
+#[no_mangle]
+pub extern "C" fn tidymac_operation_cancel(handle: u64) {
+    let _ = std::panic::catch_unwind(|| {
+        if let Some(op) = operations().lock().unwrap().get(&handle) {
+            op.token.cancel();
+        }
+    });
+}
+
+/// Release an operation handle.
+#[no_mangle]
+pub extern "C" fn tidymac_operation_free(handle: u64) {
+    let _ = std::panic::catch_unwind(|| {
+        operations().lock().unwrap().remove(&handle);
+    });
+}
+
 // ─── Scan ────────────────────────────────────────────────────────────────────
 
 /// Run a scan with the given profile name. Returns JSON string.
@@ -96,7 +190,65 @@ pub extern "C" fn tidymac_init_observability(verbose: bool, sentry_dsn: *const c
 #[no_mangle]
 pub extern "C" fn tidymac_scan(profile_name: *const c_char) -> *mut c_char {
     ffi_safe!({
-    CANCEL_FLAG.store(false, Ordering::Relaxed);
+    let op = LegacyOperation::start();
+    scan_with_events(profile_name, EventSink::none(), &op.token)
+    })
+}
+
+/// Callback receiving one JSON-encoded scan event. The string is only valid
+/// for the duration of the call and must not be freed.
+pub type ScanEventCallback = extern "C" fn(event_json: *const c_char, user_data: *mut c_void);
+
+/// Caller-owned context pointer handed back to the callback
+struct UserData(*mut c_void);
+
+// The caller promises the callback may be invoked from any thread
+unsafe impl Send for UserData {}
+unsafe impl Sync for UserData {}
+
+impl UserData {
+    // A method rather than `.0` so closures capture the whole wrapper
+    fn ptr(&self) -> *mut c_void {
+        self.0
+    }
+}
+
+/// Run a scan, calling `callback` with each event as it happens (see
+/// `scanner::events::ScanEvent`). Callbacks may arrive from worker threads
+/// and all finish before this returns. Returns the same JSON as `tidymac_scan`.
+/// operation: handle from `tidymac_operation_new`, or 0 if not cancellable.
+#[no_mangle]
+pub extern "C" fn tidymac_scan_stream(
+    profile_name: *const c_char,
+    callback: Option<ScanEventCallback>,
+    user_data: *mut c_void,
+    operation: u64,
+) -> *mut c_char {
+    ffi_safe!({
+    let Some(cancel) = operation_token(operation) else {
+        return error_c("Unknown operation handle");
+    };
+    let events = match callback {
+        Some(callback) => {
+            let user_data = UserData(user_data);
+            EventSink::callback(move |event| {
+                if let Ok(json) = serde_json::to_string(&event) {
+                    let json = CString::new(json).unwrap_or_default();
+                    callback(json.as_ptr(), user_data.ptr());
+                }
+            })
+        }
+        None => EventSink::none(),
+    };
+    scan_with_events(profile_name, events, &cancel)
+    })
+}
+
+fn scan_with_events(
+    profile_name: *const c_char,
+    events: EventSink,
+    cancel: &CancelToken,
+) -> *mut c_char {
     let profile_name = if profile_name.is_null() {
         "quick".to_string()
     } else {
@@ -117,34 +269,32 @@ pub extern "C" fn tidymac_scan(profile_name: *const c_char) -> *mut c_char {
     };
 
     let scan_targets = profile.enabled_targets();
-    let results = match scanner::run_scan_with_cache(
-        &scan_targets,
-        false,
-        profile.includes_dev_projects(),
-        profile.thresholds.stale_days,
-        config.large_file_threshold_bytes(),
-        true,
-        &profile_name,
-    ) {
+    let opts = match ScanOptions::for_profile(&profile, &config) {
+        Ok(o) => ScanOptions {
+            profile_name: profile_name.clone(),
+            events,
+            cancel: cancel.clone(),
+            top_files: Some(DEFAULT_TOP_FILES),
+            record_history: true,
+            ..o
+        },
+        Err(e) => return error_c(&format!("Failed to load config: {}", e)),
+    };
+    let results = match scanner::run_scan_with_options(&scan_targets, &opts) {
         Ok(r) => r,
-        Err(e) => {
-            if CANCEL_FLAG.load(Ordering::Relaxed) {
-                return error_c("CANCELLED");
-            }
-            return error_c(&format!("Scan failed: {}", e));
-        }
+        Err(e) if cancel::is_cancelled(&e) => return error_c("CANCELLED"),
+        Err(e) => return error_c(&format!("Scan failed: {}", e)),
     };
 
-    if CANCEL_FLAG.load(Ordering::Relaxed) {
-        return error_c("CANCELLED");
-    }
-
     // Build a JSON-friendly response
     let response = serde_json::json!({
         "profile": profile_name,
         "duration_secs": results.duration_secs,
         "total_reclaimable": results.total_reclaimable,
         "total_reclaimable_formatted": format::format_size(results.total_reclaimable),
+        "total_apparent": results.total_apparent,
+        "total_freeable": results.total_freeable,
+        "total_freeable_formatted": format::format_size(results.total_freeable),
         "total_files": results.total_files,
         "items": results.items.iter().map(|item| {
             serde_json::json!({
@@ -153,15 +303,69 @@ pub extern "C" fn tidymac_scan(profile_name: *const c_char) -> *mut c_char {
                 "path": item.path.display().to_string(),
                 "size_bytes": item.size_bytes,
                 "size_formatted": format::format_size(item.size_bytes),
+                "apparent_bytes": item.apparent_bytes,
+                "freeable_bytes": item.freeable_bytes,
                 "file_count": item.file_count,
                 "safety": format!("{:?}", item.safety),
                 "reason": item.reason,
             })
         }).collect::<Vec<_>>(),
         "errors": results.errors,
+        "truncated": results.truncated,
     });
 
     json_to_c(&response)
+}
+
+// ─── Duplicates ──────────────────────────────────────────────────────────────
+
+/// Find duplicate files under `path` (`~` is expanded). Returns JSON string
+/// in the same shape as `tidymac dup --format json`.
+/// min_size: smallest file size in bytes to consider.
+/// operation: handle from `tidymac_operation_new`, or 0 if not cancellable.
+#[no_mangle]
+pub extern "C" fn tidymac_dup_scan(
+    path: *const c_char,
+    min_size: u64,
+    operation: u64,
+) -> *mut c_char {
+    ffi_safe!({
+    if path.is_null() {
+        return error_c("path is required");
+    }
+    let path = unsafe { CStr::from_ptr(path) }.to_str().unwrap_or("");
+    let Some(root) = crate::scanner::walker::expand_paths(&[path.to_string()])
+        .into_iter()
+        .next()
+    else {
+        return error_c("path is required");
+    };
+    if !root.exists() {
+        return error_c(&format!("Path does not exist: {}", root.display()));
+    }
+    let Some(cancel) = operation_token(operation) else {
+        return error_c("Unknown operation handle");
+    };
+
+    let excludes = match Config::load().and_then(|c| c.exclusions()) {
+        Ok(e) => e,
+        Err(e) => return error_c(&format!("Failed to load config: {}", e)),
+    };
+    let config = crate::duplicates::DupConfig {
+        root,
+        min_size,
+        perceptual: false,
+        threshold: 0.85,
+        show_progress: false,
+        excludes,
+        cancel,
+    };
+
+    match crate::duplicates::find_duplicates(&config) {
+        Ok(results) => json_to_c(&crate::cli::output::dup_json(&results)),
+        Err(e) if cancel::is_cancelled(&e) => error_c("CANCELLED"),
+        Err(e) => error_c(&format!("Duplicate scan failed: {}", e)),
+    }
     })
 }
 
@@ -334,6 +538,12 @@ pub extern "C" fn tidymac_app_clean_leftovers(app_name: *const c_char) -> *mut c
 /// mode: "dry_run", "soft", "hard"
 /// selected_names_json: JSON array of item names to clean, e.g. ["User Cache Files","npm Cache"]
 ///                      If NULL or empty, cleans ALL items from the scan.
+/// Only "safe" items are cleaned; riskier ones are listed under "held_back"
+/// (see `tidymac_clean_with_safety`).
+/// Targets with a profile trim policy only lose what exceeds it; "trimmed"
+/// lists kept and evicted bytes for each.
+/// Files that changed or were swapped for something else since the scan are
+/// left alone and listed under "skipped" with a reason.
 #[no_mangle]
 pub extern "C" fn tidymac_clean(
     profile_name: *const c_char,
@@ -341,6 +551,54 @@ pub extern "C" fn tidymac_clean(
     selected_names_json: *const c_char,
 ) -> *mut c_char {
     ffi_safe!({
+    let op = LegacyOperation::start();
+    clean_with_cancel(profile_name, mode, selected_names_json, std::ptr::null(), &op.token)
+    })
+}
+
+/// Like `tidymac_clean`, cleaning items up to `max_safety`: "safe",
+/// "caution" or "dangerous". NULL means "safe". Riskier items are never
+/// cleaned and are listed under "held_back".
+#[no_mangle]
+pub extern "C" fn tidymac_clean_with_safety(
+    profile_name: *const c_char,
+    mode: *const c_char,
+    selected_names_json: *const c_char,
+    max_safety: *const c_char,
+) -> *mut c_char {
+    ffi_safe!({
+    let op = LegacyOperation::start();
+    clean_with_cancel(profile_name, mode, selected_names_json, max_safety, &op.token)
+    })
+}
+
+/// Like `tidymac_clean`, cancellable through `operation` (see
+/// `tidymac_operation_new`; 0 if not cancellable). Cancelling stops between
+/// files: the report has "cancelled": true and covers only what was done, and
+/// a soft delete's session can be undone as usual.
+#[no_mangle]
+pub extern "C" fn tidymac_clean_cancellable(
+    profile_name: *const c_char,
+    mode: *const c_char,
+    selected_names_json: *const c_char,
+    max_safety: *const c_char,
+    operation: u64,
+) -> *mut c_char {
+    ffi_safe!({
+    let Some(cancel) = operation_token(operation) else {
+        return error_c("Unknown operation handle");
+    };
+    clean_with_cancel(profile_name, mode, selected_names_json, max_safety, &cancel)
+    })
+}
+
+fn clean_with_cancel(
+    profile_name: *const c_char,
+    mode: *const c_char,
+    selected_names_json: *const c_char,
+    max_safety: *const c_char,
+    cancel: &CancelToken,
+) -> *mut c_char {
     let profile_name = if profile_name.is_null() {
         "quick".to_string()
     } else {
@@ -350,15 +608,6 @@ pub extern "C" fn tidymac_clean(
             .to_string()
     };
 
-    let mode_str = if mode.is_null() {
-        "soft".to_string()
-    } else {
-        unsafe { CStr::from_ptr(mode) }
-            .to_str()
-            .unwrap_or("soft")
-            .to_string()
-    };
-
     // Parse selected item names filter
     let selected_names: Option<Vec<String>> = if selected_names_json.is_null() {
         None
@@ -369,11 +618,11 @@ pub extern "C" fn tidymac_clean(
         serde_json::from_str(json_str).ok()
     };
 
-    let clean_mode = match mode_str.as_str() {
-        "dry_run" => crate::cleaner::CleanMode::DryRun,
-        "hard" => crate::cleaner::CleanMode::HardDelete,
-        _ => crate::cleaner::CleanMode::SoftDelete,
+    let max_safety = match max_safety_arg(max_safety) {
+        Ok(l) => l,
+        Err(e) => return error_c(&e),
     };
+    let clean_mode = clean_mode_arg(mode);
 
     let profile = match Profile::load(&profile_name) {
         Ok(p) => p,
@@ -386,14 +635,18 @@ pub extern "C" fn tidymac_clean(
     };
 
     let scan_targets = profile.enabled_targets();
-    let results = match scanner::run_scan(
-        &scan_targets,
-        false,
-        profile.includes_dev_projects(),
-        profile.thresholds.stale_days,
-        config.large_file_threshold_bytes(),
-    ) {
+    let opts = match ScanOptions::for_profile(&profile, &config) {
+        Ok(o) => ScanOptions {
+            use_cache: false,
+            cancel: cancel.clone(),
+            record_history: true,
+            ..o
+        },
+        Err(e) => return error_c(&format!("Failed to load config: {}", e)),
+    };
+    let results = match scanner::run_scan_with_options(&scan_targets, &opts) {
         Ok(r) => r,
+        Err(e) if cancel::is_cancelled(&e) => return error_c("CANCELLED"),
         Err(e) => return error_c(&format!("Scan failed: {}", e)),
     };
 
@@ -424,21 +677,112 @@ pub extern "C" fn tidymac_clean(
         );
     }
 
-    let report = match crate::cleaner::clean(&items_to_clean, clean_mode, &profile_name, false) {
+    let report = match crate::cleaner::clean(
+        &items_to_clean,
+        clean_mode,
+        &profile_name,
+        &max_safety,
+        false,
+        cancel,
+    ) {
         Ok(r) => r,
         Err(e) => return error_c(&format!("Clean failed: {}", e)),
     };
 
-    let response = serde_json::json!({
+    json_to_c(&clean_response(&report, &max_safety))
+}
+
+/// Clean exactly the files in a plan saved by `tidymac scan --save-plan`,
+/// without scanning. Returns JSON like `tidymac_clean`, plus "drifted":
+/// planned files skipped because their size or mtime changed since.
+/// mode and max_safety are as for `tidymac_clean`; operation as for
+/// `tidymac_clean_cancellable`.
+#[no_mangle]
+pub extern "C" fn tidymac_clean_plan(
+    plan_path: *const c_char,
+    mode: *const c_char,
+    max_safety: *const c_char,
+    operation: u64,
+) -> *mut c_char {
+    ffi_safe!({
+    if plan_path.is_null() {
+        return error_c("plan_path is required");
+    }
+    let Some(cancel) = operation_token(operation) else {
+        return error_c("Unknown operation handle");
+    };
+    let plan_path = unsafe { CStr::from_ptr(plan_path) }.to_str().unwrap_or("");
+    let max_safety = match max_safety_arg(max_safety) {
+        Ok(l) => l,
+        Err(e) => return error_c(&e),
+    };
+
+    let plan = match crate::cleaner::SavedPlan::load(std::path::Path::new(plan_path)) {
+        Ok(p) => p,
+        Err(e) => return error_c(&format!("Failed to load plan: {}", e)),
+    };
+    let (items, drifted) = plan.verify();
+
+    let report = match crate::cleaner::clean(
+        &items,
+        clean_mode_arg(mode),
+        &plan.profile,
+        &max_safety,
+        false,
+        &cancel,
+    ) {
+        Ok(r) => r,
+        Err(e) => return error_c(&format!("Clean failed: {}", e)),
+    };
+
+    let mut response = clean_response(&report, &max_safety);
+    response["drifted"] = serde_json::json!(drifted);
+    json_to_c(&response)
+    })
+}
+
+/// "dry_run", "soft" or "hard"; anything else (or NULL) means "soft"
+fn clean_mode_arg(mode: *const c_char) -> crate::cleaner::CleanMode {
+    let mode = if mode.is_null() {
+        "soft"
+    } else {
+        unsafe { CStr::from_ptr(mode) }.to_str().unwrap_or("soft")
+    };
+    match mode {
+        "dry_run" => crate::cleaner::CleanMode::DryRun,
+        "hard" => crate::cleaner::CleanMode::HardDelete,
+        _ => crate::cleaner::CleanMode::SoftDelete,
+    }
+}
+
+/// A safety level name; NULL means "safe"
+fn max_safety_arg(max_safety: *const c_char) -> Result<SafetyLevel, String> {
+    if max_safety.is_null() {
+        return Ok(SafetyLevel::Safe);
+    }
+    let level = unsafe { CStr::from_ptr(max_safety) }
+        .to_str()
+        .unwrap_or("safe");
+    level.parse().map_err(|e: anyhow::Error| e.to_string())
+}
+
+fn clean_response(
+    report: &crate::cleaner::CleanReport,
+    max_safety: &SafetyLevel,
+) -> serde_json::Value {
+    serde_json::json!({
         "mode": format!("{}", report.mode),
         "files_removed": report.files_removed,
         "bytes_freed": report.bytes_freed,
         "bytes_freed_formatted": format::format_size(report.bytes_freed),
+        "staged_bytes": report.staged_bytes,
         "session_id": report.session_id,
         "errors": report.errors,
-    });
-
-    json_to_c(&response)
+        "max_safety": max_safety.to_string(),
+        "held_back": report.held_back,
+        "cancelled": report.cancelled,
+        "trimmed": report.trimmed,
+        "skipped": report.skipped,
     })
 }
 
@@ -544,8 +888,10 @@ pub extern "C" fn tidymac_undo_list() -> *mut c_char {
                 "total_files": s.total_files,
                 "total_bytes": s.total_bytes,
                 "total_bytes_formatted": format::format_size(s.total_bytes),
+                "staged_size": s.staged_size,
                 "expires_at": s.expires_at.map(|e| e.to_rfc3339()),
                 "restored": s.restored,
+                "restored_files": s.restored_files,
                 "is_expired": s.is_expired,
             })
         })
@@ -565,20 +911,162 @@ pub extern "C" fn tidymac_undo_session(session_id: *const c_char) -> *mut c_char
 
     let session_id = unsafe { CStr::from_ptr(session_id) }.to_str().unwrap_or("");
 
-    let report = match crate::cleaner::restore_session(session_id, false) {
-        Ok(r) => r,
-        Err(e) => return error_c(&format!("Restore failed: {}", e)),
+    match crate::cleaner::restore_session(session_id, false) {
+        Ok(report) => json_to_c(&restore_response(&report)),
+        Err(e) => error_c(&format!("Restore failed: {}", e)),
+    }
+    })
+}
+
+/// Restore part of a session, or to another place. `options_json` is an
+/// object with optional "only" (array of globs), "to" (directory) and
+/// "on_conflict" ("error", "skip", "rename" or "overwrite"); NULL restores
+/// everything in place. Returns JSON string.
+#[no_mangle]
+pub extern "C" fn tidymac_undo_session_with_options(
+    session_id: *const c_char,
+    options_json: *const c_char,
+) -> *mut c_char {
+    ffi_safe!({
+    if session_id.is_null() {
+        return error_c("session_id is required");
+    }
+
+    let session_id = unsafe { CStr::from_ptr(session_id) }.to_str().unwrap_or("");
+
+    let options: crate::cleaner::RestoreOptions = if options_json.is_null() {
+        Default::default()
+    } else {
+        let json = unsafe { CStr::from_ptr(options_json) }.to_str().unwrap_or("{}");
+        match serde_json::from_str(json) {
+            Ok(o) => o,
+            Err(e) => return error_c(&format!("Invalid restore options: {}", e)),
+        }
     };
 
-    let response = serde_json::json!({
+    match crate::cleaner::restore_session_with_options(session_id, &options, false) {
+        Ok(report) => json_to_c(&restore_response(&report)),
+        Err(e) => error_c(&format!("Restore failed: {}", e)),
+    }
+    })
+}
+
+/// Search staging sessions and clean logs for cleaned files. `query_json`
+/// is an object with optional "pattern", "min_size", "max_size", "session"
+/// and "status"; NULL matches everything. Returns JSON array string.
+#[no_mangle]
+pub extern "C" fn tidymac_undo_find(query_json: *const c_char) -> *mut c_char {
+    ffi_safe!({
+    let query: crate::cleaner::HistoryQuery = if query_json.is_null() {
+        Default::default()
+    } else {
+        let json = unsafe { CStr::from_ptr(query_json) }.to_str().unwrap_or("{}");
+        match serde_json::from_str(json) {
+            Ok(q) => q,
+            Err(e) => return error_c(&format!("Invalid query: {}", e)),
+        }
+    };
+
+    match crate::cleaner::history::find(&query) {
+        Ok(found) => json_to_c(&found),
+        Err(e) => error_c(&format!("Search failed: {}", e)),
+    }
+    })
+}
+
+/// Check a session's staged files are present and, for deduplicated ones,
+/// still match their SHA-256. Returns JSON string.
+#[no_mangle]
+pub extern "C" fn tidymac_undo_verify(session_id: *const c_char) -> *mut c_char {
+    ffi_safe!({
+    if session_id.is_null() {
+        return error_c("session_id is required");
+    }
+    let session_id = unsafe { CStr::from_ptr(session_id) }.to_str().unwrap_or("");
+
+    match crate::cleaner::verify_session(session_id) {
+        Ok(report) => json_to_c(&report),
+        Err(e) => error_c(&format!("Verify failed: {}", e)),
+    }
+    })
+}
+
+/// List cleans interrupted before saving their manifest, and staged files
+/// no session records. Returns JSON string.
+#[no_mangle]
+pub extern "C" fn tidymac_recover_list() -> *mut c_char {
+    ffi_safe!({
+    let incomplete = match crate::cleaner::journal::incomplete_sessions() {
+        Ok(s) => s,
+        Err(e) => return error_c(&format!("Failed to read journals: {}", e)),
+    };
+    let orphaned = match crate::cleaner::journal::orphaned_files() {
+        Ok(o) => o,
+        Err(e) => return error_c(&format!("Failed to check staging: {}", e)),
+    };
+
+    json_to_c(&serde_json::json!({
+        "incomplete": incomplete,
+        "orphaned": orphaned,
+    }))
+    })
+}
+
+/// Recover an interrupted clean. action: "rollback" moves its staged files
+/// back, "finalize" writes its manifest so it can be undone. Returns JSON
+/// string.
+#[no_mangle]
+pub extern "C" fn tidymac_recover_session(
+    session_id: *const c_char,
+    action: *const c_char,
+) -> *mut c_char {
+    ffi_safe!({
+    if session_id.is_null() || action.is_null() {
+        return error_c("session_id and action are required");
+    }
+
+    let session_id = unsafe { CStr::from_ptr(session_id) }.to_str().unwrap_or("");
+    let action = unsafe { CStr::from_ptr(action) }.to_str().unwrap_or("");
+
+    let session = match crate::cleaner::journal::incomplete_sessions() {
+        Ok(sessions) => sessions.into_iter().find(|s| s.session_id == session_id),
+        Err(e) => return error_c(&format!("Failed to read journals: {}", e)),
+    };
+    let Some(session) = session else {
+        return error_c(&format!("No interrupted session '{}'", session_id));
+    };
+
+    match action {
+        "rollback" => match crate::cleaner::journal::rollback(&session) {
+            Ok(report) => json_to_c(&restore_response(&report)),
+            Err(e) => error_c(&format!("Rollback failed: {}", e)),
+        },
+        "finalize" => match crate::cleaner::journal::finalize(&session) {
+            Ok(manifest) => json_to_c(&serde_json::json!({
+                "session_id": manifest.session_id,
+                "total_files": manifest.total_files,
+                "total_bytes": manifest.total_bytes,
+                "errors": manifest.errors,
+            })),
+            Err(e) => error_c(&format!("Finalize failed: {}", e)),
+        },
+        other => error_c(&format!(
+            "Unknown action '{}': use rollback or finalize",
+            other
+        )),
+    }
+    })
+}
+
+fn restore_response(report: &crate::cleaner::RestoreReport) -> serde_json::Value {
+    serde_json::json!({
         "session_id": report.session_id,
         "restored_count": report.restored_count,
         "restored_bytes": report.restored_bytes,
         "restored_bytes_formatted": format::format_size(report.restored_bytes),
         "errors": report.errors,
-    });
-
-    json_to_c(&response)
+        "skipped": report.skipped,
+        "remaining": report.remaining,
     })
 }
 
@@ -679,10 +1167,16 @@ pub extern "C" fn tidymac_version() -> *mut c_char {
     })
 }
 
-/// Signal the current scan to cancel.
+/// Cancel every running `tidymac_scan` and `tidymac_clean` call. Operations
+/// started with a handle are unaffected; cancel those with
+/// `tidymac_operation_cancel`.
 #[no_mangle]
 pub extern "C" fn tidymac_cancel_scan() {
     let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-        CANCEL_FLAG.store(true, Ordering::SeqCst);
+        for op in operations().lock().unwrap().values() {
+            if op.legacy {
+                op.token.cancel();
+            }
+        }
     }));
 }
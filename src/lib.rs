@@ -24,3 +24,4 @@ pub mod profiles;
 pub mod scanner;
 pub mod startup;
 pub mod viz;
+pub mod watch;
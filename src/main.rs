@@ -1,16 +1,23 @@
 use anyhow::Result;
 use clap::Parser;
 use colored::Colorize;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
-use tidymac::cleaner::{self, CleanManifest, CleanMode};
-use tidymac::cli::args::{Cli, Commands, ConfigAction, OutputFormat};
+use tidymac::cleaner::{self, CleanManifest, CleanMode, SavedPlan};
+use tidymac::cli::args::{Cli, Commands, ConfigAction, OutputFormat, UndoAction};
 use tidymac::cli::output;
-use tidymac::common::config::Config;
+use tidymac::common::cancel::CancelToken;
+use tidymac::common::config::{Config, StagingStore};
 use tidymac::common::format;
 use tidymac::profiles::loader::Profile;
-use tidymac::scanner;
+use tidymac::scanner::targets::{CategoryFilter, SafetyLevel, ScanResults};
+use tidymac::scanner::events::EventSink;
+use tidymac::scanner::aggregate::DEFAULT_TOP_FILES;
+use tidymac::scanner::limits::ScanLimits;
+use tidymac::scanner::snapshot::Snapshot;
+use tidymac::scanner::diff;
+use tidymac::scanner::{self, ScanOptions};
 
 use tidymac::common::observability;
 
@@ -29,27 +36,102 @@ fn main() -> Result<()> {
         eprintln!("Failed to initialize logging: {}", e);
     });
 
+    if matches!(cli.format, OutputFormat::Jsonl) && !matches!(cli.command, Commands::Scan { .. }) {
+        anyhow::bail!("--format jsonl is only supported by scan");
+    }
+
+    if !matches!(cli.command, Commands::Recover { .. } | Commands::Completions { .. }) {
+        // Never worth failing the command the user actually asked for
+        if let Err(e) = check_interrupted_sessions(&cli) {
+            eprintln!(
+                "  {} Couldn't check for interrupted cleans: {:#}",
+                "⚠".yellow(),
+                e
+            );
+        }
+    }
+
     match cli.command {
         Commands::Scan {
             detailed,
-            categories: _,
+            ref categories,
             dry_run: _,
             no_cache,
-        } => cmd_scan(&cli, detailed, no_cache),
+            time_budget,
+            max_files,
+            max_depth,
+            one_file_system,
+            ref save,
+            ref compare,
+            ref save_plan,
+        } => cmd_scan(
+            &cli,
+            detailed,
+            categories.as_deref(),
+            no_cache,
+            &ScanLimits {
+                time_budget_secs: time_budget,
+                max_files,
+                max_depth,
+                one_file_system,
+            },
+            &ScanFiles {
+                save: save.as_deref(),
+                compare: compare.as_deref(),
+                save_plan: save_plan.as_deref(),
+            },
+        ),
+
+        Commands::Diff {
+            ref old,
+            ref new,
+            detailed,
+        } => cmd_diff(&cli, old, new, detailed),
 
         Commands::Clean {
             hard,
             yes,
-            categories: _,
-            max_safety: _,
+            ref categories,
+            ref max_safety,
             dry_run,
-        } => cmd_clean(&cli, hard, yes, dry_run),
+            free,
+            ref plan,
+            ref stage,
+        } => cmd_clean(
+            &cli,
+            hard,
+            yes,
+            match plan {
+                Some(plan) => CleanSource::Plan(plan),
+                None => CleanSource::Scan {
+                    categories: categories.as_deref(),
+                    free,
+                },
+            },
+            max_safety.level(),
+            dry_run,
+            stage.as_ref().map(|s| s.store()),
+        ),
 
         Commands::Undo {
             last,
             ref session,
             list,
-        } => cmd_undo(&cli, last, session.clone(), list),
+            ref only,
+            ref to,
+            ref conflict,
+            ref action,
+        } => {
+            if let Some(action) = action {
+                return cmd_undo_history(&cli, action);
+            }
+            let options = cleaner::RestoreOptions {
+                only: only.clone(),
+                to: to.clone(),
+                on_conflict: conflict.policy(),
+            };
+            cmd_undo(&cli, last, session.clone(), list, &options)
+        }
 
         Commands::Purge {
             expired,
@@ -59,6 +141,12 @@ fn main() -> Result<()> {
             install_auto,
         } => cmd_purge(&cli, expired, all, session.clone(), yes, install_auto),
 
+        Commands::Recover {
+            rollback,
+            finalize,
+            ref session,
+        } => cmd_recover(&cli, rollback, finalize, session.as_deref()),
+
         Commands::Dup {
             ref path,
             perceptual,
@@ -81,6 +169,17 @@ fn main() -> Result<()> {
             yes,
         } => cmd_docker(&cli, prune, dry_run, yes),
 
+        Commands::Watch {
+            rescan_interval,
+            stop,
+        } => cmd_watch(&cli, rescan_interval, stop),
+
+        Commands::Trend {
+            days,
+            top,
+            runaway_gb,
+        } => cmd_trend(&cli, days, top, runaway_gb),
+
         Commands::Config { action } => cmd_config(action),
         Commands::Status { diagnostics } => cmd_status(diagnostics),
 
@@ -100,37 +199,148 @@ fn main() -> Result<()> {
 
 // ─── Scan ─────────────────────────────────────────────────────────────────────
 
-fn cmd_scan(cli: &Cli, detailed: bool, no_cache: bool) -> Result<()> {
+/// Parse `--categories` into a filter, rejecting unknown names up front
+fn parse_categories(categories: Option<&[String]>) -> Result<Option<CategoryFilter>> {
+    categories.map(CategoryFilter::parse).transpose()
+}
+
+/// Files `scan` reads or writes besides printing its results
+struct ScanFiles<'a> {
+    save: Option<&'a Path>,
+    compare: Option<&'a Path>,
+    save_plan: Option<&'a Path>,
+}
+
+fn cmd_scan(
+    cli: &Cli,
+    detailed: bool,
+    categories: Option<&[String]>,
+    no_cache: bool,
+    limits: &ScanLimits,
+    files: &ScanFiles,
+) -> Result<()> {
+    let categories = parse_categories(categories)?;
+    if files.compare.is_some() && matches!(cli.format, OutputFormat::Jsonl) {
+        anyhow::bail!("--compare can't be combined with --format jsonl");
+    }
+    // Load up front so a bad snapshot fails before the scan runs
+    let baseline = files.compare.map(Snapshot::load).transpose()?;
     let profile_name = cli.profile.as_deref().unwrap_or("quick");
     let profile = Profile::load(profile_name)?;
     let config = Config::load()?;
 
-    if !cli.quiet {
+    if !cli.quiet && matches!(cli.format, OutputFormat::Human) {
         output::print_profile_info(&profile);
     }
 
     let scan_targets = profile.enabled_targets();
     let show_progress = !cli.quiet && matches!(cli.format, OutputFormat::Human);
 
-    let results = scanner::run_scan_with_cache(
+    // JSON Lines output is the event stream itself
+    let events = match cli.format {
+        OutputFormat::Jsonl => EventSink::callback(|event| {
+            use std::io::Write;
+            let mut out = std::io::stdout().lock();
+            if let Ok(line) = serde_json::to_string(&event) {
+                let _ = writeln!(out, "{}", line);
+                let _ = out.flush();
+            }
+        }),
+        _ => EventSink::none(),
+    };
+
+    let results = scanner::run_scan_with_options(
         &scan_targets,
-        show_progress,
-        profile.includes_dev_projects(),
-        profile.thresholds.stale_days,
-        config.large_file_threshold_bytes(),
-        !no_cache,
-        profile_name,
+        &ScanOptions {
+            show_progress,
+            use_cache: !no_cache,
+            profile_name: profile_name.to_string(),
+            categories,
+            events,
+            limits: profile.limits.with_overrides(limits),
+            // Only cleaning, --detailed, snapshots and plans need every file
+            top_files: (!detailed
+                && files.save.is_none()
+                && files.compare.is_none()
+                && files.save_plan.is_none())
+            .then_some(DEFAULT_TOP_FILES),
+            record_history: true,
+            ..ScanOptions::for_profile(&profile, &config)?
+        },
     )?;
 
+    if let Some(path) = files.save_plan {
+        save_plan(cli, &results, profile_name, path)?;
+    }
+
+    if files.save.is_some() || baseline.is_some() {
+        let snapshot = Snapshot::new(results.clone(), profile_name);
+        if let Some(path) = files.save {
+            snapshot.save(path)?;
+            if !cli.quiet && matches!(cli.format, OutputFormat::Human) {
+                output::print_snapshot_saved(path);
+            }
+        }
+        if let Some(baseline) = baseline {
+            print_diff(cli, &diff::diff(&baseline, &snapshot), detailed);
+            return Ok(());
+        }
+    }
+
     match cli.format {
         OutputFormat::Human => output::print_scan_results(&results, detailed),
         OutputFormat::Json => output::print_scan_json(&results),
+        OutputFormat::Jsonl => {}
         OutputFormat::Quiet => output::print_scan_quiet(&results),
     }
 
     Ok(())
 }
 
+/// Write what `clean` would remove from `results` as a plan
+///
+/// The plan goes through the same exclusion and trim filtering as a clean,
+/// so it lists exactly the files a clean would take. Safety is checked when
+/// the plan runs, against that clean's `--max-safety`.
+fn save_plan(cli: &Cli, results: &ScanResults, profile_name: &str, path: &Path) -> Result<()> {
+    let config = Config::load()?;
+    let (items, _) = cleaner::drop_excluded(results.items.clone(), &config.exclusions()?);
+    let (items, _) = cleaner::apply_trim(items);
+    let mut planned = ScanResults::new();
+    planned.items = items;
+    let plan = SavedPlan::from_results(&planned, profile_name)?;
+    plan.save(path)?;
+    if !cli.quiet && matches!(cli.format, OutputFormat::Human) {
+        output::print_plan_saved(path, &plan);
+    }
+    Ok(())
+}
+
+// ─── Diff ─────────────────────────────────────────────────────────────────────
+
+fn cmd_diff(cli: &Cli, old: &Path, new: &Path, detailed: bool) -> Result<()> {
+    let old = Snapshot::load(old)?;
+    let new = Snapshot::load(new)?;
+    print_diff(cli, &diff::diff(&old, &new), detailed);
+    Ok(())
+}
+
+fn print_diff(cli: &Cli, diff: &diff::ScanDiff, detailed: bool) {
+    match cli.format {
+        OutputFormat::Human => output::print_diff(diff, detailed),
+        OutputFormat::Json | OutputFormat::Jsonl => output::print_diff_json(diff),
+        OutputFormat::Quiet => {
+            println!(
+                "{}  {}  {}  {}",
+                diff.count(diff::Change::Added),
+                diff.count(diff::Change::Removed),
+                diff.count(diff::Change::Grown) + diff.count(diff::Change::Shrunk),
+                diff.delta_bytes
+            );
+        }
+    }
+}
+
 // ─── Dup ──────────────────────────────────────────────────────────────────────
 
 fn cmd_dup(
@@ -181,13 +391,15 @@ fn cmd_dup(
         perceptual,
         threshold,
         show_progress,
+        excludes: Config::load()?.exclusions()?,
+        cancel: CancelToken::new(),
     };
 
     let results = tidymac::duplicates::find_duplicates(&config)?;
 
     match cli.format {
         OutputFormat::Human => output::print_dup_results(&results, detailed),
-        OutputFormat::Json => output::print_dup_json(&results),
+        OutputFormat::Json | OutputFormat::Jsonl => output::print_dup_json(&results),
         OutputFormat::Quiet => {
             println!(
                 "{}  {}  {}",
@@ -203,41 +415,118 @@ fn cmd_dup(
 
 // ─── Clean ────────────────────────────────────────────────────────────────────
 
-fn cmd_clean(cli: &Cli, hard: bool, yes: bool, dry_run: bool) -> Result<()> {
-    let profile_name = cli.profile.as_deref().unwrap_or("quick");
-    let profile = Profile::load(profile_name)?;
+/// What `clean` removes
+enum CleanSource<'a> {
+    /// Whatever a fresh scan finds, optionally only enough to free `free` bytes
+    Scan {
+        categories: Option<&'a [String]>,
+        free: Option<u64>,
+    },
+    /// Exactly the files in a plan saved by `scan --save-plan`
+    Plan(&'a Path),
+}
+
+fn cmd_clean(
+    cli: &Cli,
+    hard: bool,
+    yes: bool,
+    source: CleanSource,
+    max_safety: SafetyLevel,
+    dry_run: bool,
+    stage: Option<StagingStore>,
+) -> Result<()> {
     let config = Config::load()?;
+    let show_progress = !cli.quiet && matches!(cli.format, OutputFormat::Human);
 
-    if !cli.quiet {
-        output::print_profile_info(&profile);
-    }
+    let mut free = None;
+    // Planned files that changed since the plan was saved
+    let mut drifted = Vec::new();
+    let (profile_name, mut results) = match source {
+        CleanSource::Plan(path) => {
+            let plan = SavedPlan::load(path)?;
+            if !cli.quiet {
+                output::print_plan_info(path, &plan);
+            }
+            if !hard && !dry_run {
+                check_staging(cli)?;
+            }
 
-    // Check staging health before soft-delete
-    if !hard && !dry_run {
-        let health = cleaner::check_staging_health()?;
-        if !cli.quiet {
-            output::print_staging_health(&health);
+            let (items, changed) = plan.verify();
+            drifted = changed;
+            let mut results = ScanResults::new();
+            results.items = items;
+            results.recalculate();
+            (plan.profile, results)
         }
-    }
+        CleanSource::Scan { categories, free: goal } => {
+            free = goal;
+            let categories = parse_categories(categories)?;
+            let profile_name = cli.profile.as_deref().unwrap_or("quick");
+            let profile = Profile::load(profile_name)?;
 
-    let scan_targets = profile.enabled_targets();
-    let show_progress = !cli.quiet && matches!(cli.format, OutputFormat::Human);
+            if !cli.quiet {
+                output::print_profile_info(&profile);
+            }
+            if !hard && !dry_run {
+                check_staging(cli)?;
+            }
 
-    let results = scanner::run_scan(
-        &scan_targets,
-        show_progress,
-        profile.includes_dev_projects(),
-        profile.thresholds.stale_days,
-        config.large_file_threshold_bytes(),
-    )?;
+            let scan_targets = profile.enabled_targets();
+            let results = scanner::run_scan_with_options(
+                &scan_targets,
+                &ScanOptions {
+                    show_progress,
+                    use_cache: true,
+                    profile_name: profile_name.to_string(),
+                    categories,
+                    record_history: true,
+                    ..ScanOptions::for_profile(&profile, &config)?
+                },
+            )?;
+            (profile_name.to_string(), results)
+        }
+    };
+    let profile_name = profile_name.as_str();
+
+    // Hold back anything riskier than --max-safety before showing totals,
+    // and drop anything matched by exclude_paths
+    let (allowed, mut held_back) = cleaner::partition_by_safety(&results.items, &max_safety);
+    let (allowed, excluded) = cleaner::drop_excluded(allowed, &config.exclusions()?);
+    held_back.extend(excluded);
+    // Targets with a trim policy only give up what exceeds it
+    let (allowed, trimmed) = cleaner::apply_trim(allowed);
+    results.items = allowed;
+    results.recalculate();
+
+    if matches!(cli.format, OutputFormat::Human) && !cli.quiet {
+        output::print_held_back(&held_back);
+        output::print_trimmed(&trimmed);
+        output::print_drifted(&drifted);
+    }
 
     if results.items.is_empty() {
         println!("  {} Nothing to clean!", "✨");
         return Ok(());
     }
 
-    // Show what would be cleaned
-    if matches!(cli.format, OutputFormat::Human) {
+    // With a goal, clean only the least risky files that reach it
+    if let Some(goal) = free {
+        let plan = cleaner::plan_for_goal(&results.items, goal);
+        if matches!(cli.format, OutputFormat::Human) {
+            output::print_clean_plan(&plan);
+        }
+        if !plan.reachable {
+            anyhow::bail!(
+                "Goal of {} is unreachable within --max-safety '{}': at most {} can be freed",
+                format::format_size(goal),
+                max_safety,
+                format::format_size(plan.available_bytes)
+            );
+        }
+        results.items = plan.items;
+        results.recalculate();
+    } else if matches!(cli.format, OutputFormat::Human) {
+        // Show what would be cleaned
         output::print_scan_results(&results, false);
     }
 
@@ -251,7 +540,14 @@ fn cmd_clean(cli: &Cli, hard: bool, yes: bool, dry_run: bool) -> Result<()> {
     };
 
     if mode == CleanMode::DryRun {
-        let report = cleaner::clean(&results.items, mode, profile_name, false)?;
+        let report = cleaner::clean(
+            &results.items,
+            mode,
+            profile_name,
+            &max_safety,
+            false,
+            &CancelToken::new(),
+        )?;
         println!(
             "  {} Dry run — would clean {} files ({}). No files modified.",
             "ℹ️",
@@ -273,7 +569,7 @@ fn cmd_clean(cli: &Cli, hard: bool, yes: bool, dry_run: bool) -> Result<()> {
             "❓",
             mode_label,
             format::format_count(results.total_files),
-            format::format_size(results.total_reclaimable)
+            format::format_size(results.total_freeable)
         );
         use std::io::Write;
         std::io::stdout().flush()?;
@@ -286,17 +582,41 @@ fn cmd_clean(cli: &Cli, hard: bool, yes: bool, dry_run: bool) -> Result<()> {
         }
     }
 
-    let report = cleaner::clean(&results.items, mode, profile_name, show_progress)?;
+    // Ctrl-C stops after the current file; what was done stays undoable
+    let cancel = CancelToken::new();
+    signal_hook::flag::register(signal_hook::consts::SIGTERM, cancel.flag())?;
+    signal_hook::flag::register(signal_hook::consts::SIGINT, cancel.flag())?;
+
+    let mut report = cleaner::clean_with_store(
+        &results.items,
+        mode,
+        profile_name,
+        &max_safety,
+        stage,
+        show_progress,
+        &cancel,
+    )?;
+    held_back.append(&mut report.held_back);
+    report.held_back = held_back;
+    report.trimmed = trimmed;
 
     match cli.format {
         OutputFormat::Human => output::print_clean_report(&report),
-        OutputFormat::Json => {
+        OutputFormat::Json | OutputFormat::Jsonl => {
             let json = serde_json::json!({
                 "mode": format!("{}", report.mode),
                 "files_removed": report.files_removed,
                 "bytes_freed": report.bytes_freed,
+                "staged_bytes": report.staged_bytes,
                 "session_id": report.session_id,
                 "errors": report.errors,
+                "max_safety": max_safety.to_string(),
+                "held_back": report.held_back,
+                "cancelled": report.cancelled,
+                "goal_bytes": free,
+                "trimmed": report.trimmed,
+                "drifted": drifted,
+                "skipped": report.skipped,
             });
             println!("{}", serde_json::to_string_pretty(&json)?);
         }
@@ -313,6 +633,15 @@ fn cmd_clean(cli: &Cli, hard: bool, yes: bool, dry_run: bool) -> Result<()> {
     Ok(())
 }
 
+/// Check staging health before a soft delete
+fn check_staging(cli: &Cli) -> Result<()> {
+    let health = cleaner::check_staging_health()?;
+    if !cli.quiet {
+        output::print_staging_health(&health);
+    }
+    Ok(())
+}
+
 // ─── Privacy ──────────────────────────────────────────────────────────────────
 
 fn cmd_privacy(cli: &Cli, action: &tidymac::cli::args::PrivacyAction) -> Result<()> {
@@ -344,7 +673,7 @@ fn cmd_privacy(cli: &Cli, action: &tidymac::cli::args::PrivacyAction) -> Result<
 
             match cli.format {
                 OutputFormat::Human => output::print_privacy_report(&report),
-                OutputFormat::Json => {
+                OutputFormat::Json | OutputFormat::Jsonl => {
                     let json = serde_json::json!({
                         "browser_profiles": report.browser_profiles.iter().map(|p| {
                             serde_json::json!({
@@ -458,7 +787,7 @@ fn cmd_viz(cli: &Cli, _interactive: bool) -> Result<()> {
 
     match cli.format {
         OutputFormat::Human => tidymac::viz::print_viz(&usage),
-        OutputFormat::Json => tidymac::viz::print_viz_json(&usage),
+        OutputFormat::Json | OutputFormat::Jsonl => tidymac::viz::print_viz_json(&usage),
         OutputFormat::Quiet => {
             println!(
                 "{}  {}  {}",
@@ -525,7 +854,7 @@ fn cmd_docker(cli: &Cli, prune: bool, dry_run: bool, yes: bool) -> Result<()> {
 
         match cli.format {
             OutputFormat::Human => output::print_docker_usage(&usage),
-            OutputFormat::Json => match serde_json::to_string_pretty(&usage) {
+            OutputFormat::Json | OutputFormat::Jsonl => match serde_json::to_string_pretty(&usage) {
                 Ok(s) => println!("{}", s),
                 Err(e) => eprintln!("Error: {}", e),
             },
@@ -580,7 +909,7 @@ fn cmd_apps(cli: &Cli, action: &tidymac::cli::args::AppsAction) -> Result<()> {
 
             match cli.format {
                 OutputFormat::Human => output::print_app_list(&apps, *detailed),
-                OutputFormat::Json => {
+                OutputFormat::Json | OutputFormat::Jsonl => {
                     let json: Vec<_> = apps.iter().map(|a| {
                         serde_json::json!({
                             "name": a.name,
@@ -677,7 +1006,7 @@ fn cmd_startup(cli: &Cli, action: &tidymac::cli::args::StartupAction) -> Result<
 
             match cli.format {
                 OutputFormat::Human => output::print_startup_items(&items),
-                OutputFormat::Json => {
+                OutputFormat::Json | OutputFormat::Jsonl => {
                     let json: Vec<_> = items
                         .iter()
                         .map(|i| {
@@ -758,25 +1087,34 @@ fn cmd_startup(cli: &Cli, action: &tidymac::cli::args::StartupAction) -> Result<
 
 // ─── Undo ─────────────────────────────────────────────────────────────────────
 
-fn cmd_undo(cli: &Cli, last: bool, session: Option<String>, list: bool) -> Result<()> {
+fn cmd_undo(
+    cli: &Cli,
+    last: bool,
+    session: Option<String>,
+    list: bool,
+    options: &cleaner::RestoreOptions,
+) -> Result<()> {
     if list {
         let sessions = CleanManifest::list_sessions()?;
         match cli.format {
             OutputFormat::Human => output::print_sessions(&sessions),
-            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&sessions)?),
+            OutputFormat::Json | OutputFormat::Jsonl => println!("{}", serde_json::to_string_pretty(&sessions)?),
             OutputFormat::Quiet => {
                 for s in &sessions {
                     let status = if s.restored {
                         "restored"
+                    } else if s.restored_files > 0 {
+                        "partly-restored"
                     } else if s.is_expired {
                         "expired"
                     } else {
                         "active"
                     };
                     println!(
-                        "{}  {}  {}  {}",
+                        "{}  {}  {}  {}  {}",
                         s.session_id,
                         s.profile,
+                        format::format_size(s.total_bytes),
                         format::format_size(s.staged_size),
                         status
                     );
@@ -804,6 +1142,18 @@ fn cmd_undo(cli: &Cli, last: bool, session: Option<String>, list: bool) -> Resul
             "tidymac undo --session <ID>".cyan()
         );
         println!("    {} List all", "tidymac undo --list".cyan());
+        println!(
+            "    {} Find where a file went",
+            "tidymac undo find '<glob>'".cyan()
+        );
+        println!(
+            "    {} Restore part of a session",
+            "tidymac undo --last --only '~/Library/Caches/*'".cyan()
+        );
+        println!(
+            "    {} Restore somewhere else",
+            "tidymac undo --last --to ~/Recovered".cyan()
+        );
         println!();
 
         let sessions = CleanManifest::list_sessions()?;
@@ -834,32 +1184,34 @@ fn cmd_undo(cli: &Cli, last: bool, session: Option<String>, list: bool) -> Resul
         return Ok(());
     }
 
-    let restorable_count = manifest
-        .items
-        .iter()
-        .filter(|i| i.success && i.staged_path.is_some())
-        .count();
+    let selected = cleaner::select_for_restore(&manifest, options)?;
+    let selected_bytes: u64 = selected.iter().map(|i| i.size_bytes).sum();
 
     println!();
     println!(
         "  {} Restoring session '{}' — {} files ({})",
         "↩️",
         session_id.cyan(),
-        restorable_count,
-        format::format_size(manifest.total_bytes)
+        selected.len(),
+        format::format_size(selected_bytes)
     );
+    if let Some(ref to) = options.to {
+        println!("  {} Into: {}", "📁", format::format_path(to));
+    }
 
     let show_progress = !cli.quiet && matches!(cli.format, OutputFormat::Human);
-    let report = cleaner::restore_session(&session_id, show_progress)?;
+    let report = cleaner::restore_session_with_options(&session_id, options, show_progress)?;
 
     match cli.format {
         OutputFormat::Human => output::print_restore_report(&report),
-        OutputFormat::Json => {
+        OutputFormat::Json | OutputFormat::Jsonl => {
             let json = serde_json::json!({
                 "session_id": report.session_id,
                 "restored_count": report.restored_count,
                 "restored_bytes": report.restored_bytes,
                 "errors": report.errors,
+                "skipped": report.skipped,
+                "remaining": report.remaining,
             });
             println!("{}", serde_json::to_string_pretty(&json)?);
         }
@@ -876,6 +1228,252 @@ fn cmd_undo(cli: &Cli, last: bool, session: Option<String>, list: bool) -> Resul
     Ok(())
 }
 
+fn cmd_undo_history(cli: &Cli, action: &UndoAction) -> Result<()> {
+    let (query, heading) = match action {
+        UndoAction::Find {
+            pattern,
+            min_size,
+            max_size,
+        } => {
+            if pattern.is_none() && min_size.is_none() && max_size.is_none() {
+                anyhow::bail!("Give a pattern or a size range to search for, e.g. tidymac undo find '*.psd'");
+            }
+            let heading = match pattern {
+                Some(p) => format!("Matches for '{}'", p),
+                None => "Matches".to_string(),
+            };
+            let query = cleaner::HistoryQuery {
+                pattern: pattern.clone(),
+                min_size: *min_size,
+                max_size: *max_size,
+                ..Default::default()
+            };
+            (query, heading)
+        }
+        UndoAction::Show {
+            session,
+            only,
+            min_size,
+            max_size,
+            status,
+        } => {
+            let query = cleaner::HistoryQuery {
+                pattern: only.clone(),
+                min_size: *min_size,
+                max_size: *max_size,
+                session: Some(session.clone()),
+                status: status.as_ref().map(|s| s.status()),
+            };
+            (query, format!("Session {}", session))
+        }
+        UndoAction::Verify { session } => return cmd_undo_verify(cli, session),
+    };
+
+    let found = cleaner::history::find(&query)?;
+    match cli.format {
+        OutputFormat::Human => output::print_history(&heading, &found),
+        OutputFormat::Json | OutputFormat::Jsonl => {
+            println!("{}", serde_json::to_string_pretty(&found)?)
+        }
+        OutputFormat::Quiet => {
+            for item in &found {
+                println!(
+                    "{}  {}  {}  {}",
+                    item.status.label(),
+                    item.session_id,
+                    format::format_size(item.size_bytes),
+                    item.path.display()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cmd_undo_verify(cli: &Cli, session_id: &str) -> Result<()> {
+    let report = cleaner::verify_session(session_id)?;
+    match cli.format {
+        OutputFormat::Human => output::print_verify_report(&report),
+        OutputFormat::Json | OutputFormat::Jsonl => {
+            println!("{}", serde_json::to_string_pretty(&report)?)
+        }
+        OutputFormat::Quiet => {
+            for path in &report.missing {
+                println!("missing  {}", path.display());
+            }
+            for path in &report.corrupted {
+                println!("corrupted  {}", path.display());
+            }
+        }
+    }
+    if !report.is_ok() {
+        anyhow::bail!(
+            "Session '{}' has {} missing and {} corrupted staged files",
+            session_id,
+            report.missing.len(),
+            report.corrupted.len()
+        );
+    }
+    Ok(())
+}
+
+// ─── Recover ──────────────────────────────────────────────────────────────────
+
+/// Offer to recover cleans that were cut short, once per run
+fn check_interrupted_sessions(cli: &Cli) -> Result<()> {
+    use std::io::IsTerminal;
+
+    let sessions = cleaner::journal::incomplete_sessions()?;
+    if sessions.is_empty() {
+        return Ok(());
+    }
+    if !matches!(cli.format, OutputFormat::Human) || !std::io::stdin().is_terminal() {
+        eprintln!(
+            "  {} {} interrupted clean(s) left files in staging without a manifest; run {}",
+            "⚠".yellow(),
+            sessions.len(),
+            "tidymac recover".cyan()
+        );
+        return Ok(());
+    }
+
+    output::print_incomplete_sessions(&sessions);
+    print!(
+        "  {} [r]oll back, [f]inalize, or decide later? [r/f/N] ",
+        "❓"
+    );
+    use std::io::Write;
+    std::io::stdout().flush()?;
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    match input.trim().to_ascii_lowercase().as_str() {
+        "r" => recover_sessions(cli, &sessions, true),
+        "f" => recover_sessions(cli, &sessions, false),
+        _ => {
+            println!("  {} Later: {}", "💡", "tidymac recover".cyan());
+            println!();
+            Ok(())
+        }
+    }
+}
+
+fn cmd_recover(cli: &Cli, rollback: bool, finalize: bool, session: Option<&str>) -> Result<()> {
+    let mut sessions = cleaner::journal::incomplete_sessions()?;
+    if let Some(id) = session {
+        sessions.retain(|s| s.session_id == id);
+        if sessions.is_empty() {
+            anyhow::bail!("No interrupted session '{}' in staging", id);
+        }
+    }
+
+    if !rollback && !finalize {
+        let orphans = cleaner::journal::orphaned_files()?;
+        match cli.format {
+            OutputFormat::Human => {
+                output::print_incomplete_sessions(&sessions);
+                output::print_orphaned_files(&orphans);
+            }
+            OutputFormat::Json | OutputFormat::Jsonl => {
+                let json = serde_json::json!({
+                    "incomplete": sessions,
+                    "orphaned": orphans,
+                });
+                println!("{}", serde_json::to_string_pretty(&json)?);
+            }
+            OutputFormat::Quiet => {
+                for s in &sessions {
+                    println!(
+                        "{}  {}  {}  incomplete",
+                        s.session_id,
+                        s.staged_count(),
+                        format::format_size(s.staged_bytes())
+                    );
+                }
+                for o in &orphans {
+                    println!(
+                        "{}  {}  {}  orphaned",
+                        o.session_id,
+                        o.files,
+                        format::format_size(o.bytes)
+                    );
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    recover_sessions(cli, &sessions, rollback)
+}
+
+fn recover_sessions(
+    cli: &Cli,
+    sessions: &[cleaner::IncompleteSession],
+    rollback: bool,
+) -> Result<()> {
+    let mut results = Vec::new();
+    for session in sessions {
+        let result = if rollback {
+            let report = cleaner::journal::rollback(session)?;
+            if matches!(cli.format, OutputFormat::Human) {
+                output::print_restore_report(&report);
+            }
+            serde_json::json!({
+                "session_id": report.session_id,
+                "action": "rollback",
+                "restored_count": report.restored_count,
+                "restored_bytes": report.restored_bytes,
+                "remaining": report.remaining,
+                "errors": report.errors,
+            })
+        } else {
+            let manifest = cleaner::journal::finalize(session)?;
+            if matches!(cli.format, OutputFormat::Human) {
+                println!();
+                println!(
+                    "  {} Finalized session '{}' — {} files ({}) can be undone",
+                    "✓".green(),
+                    manifest.session_id.cyan(),
+                    manifest.total_files,
+                    format::format_size(manifest.total_bytes)
+                );
+                for err in &manifest.errors {
+                    println!("    {} {}", "→".dimmed(), err.dimmed());
+                }
+                println!();
+            }
+            serde_json::json!({
+                "session_id": manifest.session_id,
+                "action": "finalize",
+                "total_files": manifest.total_files,
+                "total_bytes": manifest.total_bytes,
+                "errors": manifest.errors,
+            })
+        };
+        results.push(result);
+    }
+
+    match cli.format {
+        OutputFormat::Human => {
+            if sessions.is_empty() {
+                println!("  {} No interrupted sessions.", "✓".green());
+            }
+        }
+        OutputFormat::Json | OutputFormat::Jsonl => {
+            println!("{}", serde_json::to_string_pretty(&results)?)
+        }
+        OutputFormat::Quiet => {
+            for r in &results {
+                println!(
+                    "{}  {}",
+                    r["session_id"].as_str().unwrap_or(""),
+                    r["action"].as_str().unwrap_or("")
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
 // ─── Purge ────────────────────────────────────────────────────────────────────
 
 fn cmd_purge(
@@ -991,6 +1589,13 @@ fn cmd_purge(
         health.session_count,
         format::format_size(health.total_size)
     );
+    if health.store_blobs > 0 {
+        println!(
+            "    {} of it in {} deduplicated files",
+            format::format_size(health.store_size),
+            health.store_blobs
+        );
+    }
     if health.expired_count > 0 {
         println!(
             "  {} {} expired ({}) — run {}",
@@ -1005,6 +1610,124 @@ fn cmd_purge(
     Ok(())
 }
 
+// ─── Watch ────────────────────────────────────────────────────────────────────
+
+fn cmd_watch(cli: &Cli, rescan_interval: u64, stop: bool) -> Result<()> {
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use tidymac::watch::{self, state::WatchState, WatchOptions};
+
+    let profile_name = cli.profile.as_deref().unwrap_or("quick");
+
+    if stop {
+        let Some(state) = WatchState::load(profile_name).filter(|s| s.is_live()) else {
+            anyhow::bail!("No watch daemon is running for profile '{}'", profile_name);
+        };
+        let rc = unsafe { libc::kill(state.pid as libc::pid_t, libc::SIGTERM) };
+        if rc != 0 {
+            anyhow::bail!(
+                "Failed to stop watch daemon (pid {}): {}",
+                state.pid,
+                std::io::Error::last_os_error()
+            );
+        }
+        if !cli.quiet {
+            println!("  {} Stopping watch daemon (pid {})", "✓".green(), state.pid);
+        }
+        return Ok(());
+    }
+
+    if rescan_interval == 0 {
+        anyhow::bail!("--rescan-interval must be at least 1 minute");
+    }
+    if let Some(running) = WatchState::load(profile_name).filter(|s| s.is_live()) {
+        anyhow::bail!(
+            "A watch daemon is already running for profile '{}' (pid {})",
+            profile_name,
+            running.pid
+        );
+    }
+
+    let profile = Profile::load(profile_name)?;
+    let config = Config::load()?;
+    let targets = profile.enabled_targets();
+
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::consts::SIGTERM, Arc::clone(&stop_flag))?;
+    signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(&stop_flag))?;
+
+    let backend = watch::backend::default_backend()?;
+    if !cli.quiet {
+        println!();
+        println!(
+            "  {} Watching {} targets for profile {} ({} backend)",
+            "👀",
+            targets.len(),
+            profile_name.cyan(),
+            backend.name()
+        );
+        println!(
+            "  {} Scans with this profile now read live results. Press Ctrl-C to stop.",
+            "💡"
+        );
+    }
+
+    let opts = WatchOptions {
+        profile_name: profile_name.to_string(),
+        excludes: config.exclusions()?,
+        rescan_interval: std::time::Duration::from_secs(rescan_interval * 60),
+        stop: stop_flag,
+    };
+    let state = watch::run(&targets, &opts, backend)?;
+
+    if !cli.quiet {
+        let total: u64 = state.targets.iter().map(|t| t.item.size_bytes).sum();
+        println!(
+            "  {} Watch stopped — state saved ({} across {} targets)",
+            "✓".green(),
+            format::format_size(total),
+            state.targets.len()
+        );
+    }
+    Ok(())
+}
+
+// ─── Trend ────────────────────────────────────────────────────────────────────
+
+fn cmd_trend(cli: &Cli, days: u32, top: usize, runaway_gb: f64) -> Result<()> {
+    use tidymac::scanner::history::{self, ScanHistory, TrendOptions};
+
+    if !runaway_gb.is_finite() || runaway_gb < 0.0 {
+        anyhow::bail!("--runaway-gb must be a positive number");
+    }
+
+    let records = ScanHistory::open().load(None)?;
+    let report = history::trend(
+        &records,
+        &TrendOptions {
+            days,
+            top,
+            runaway_bytes: (runaway_gb * 1024.0 * 1024.0 * 1024.0) as u64,
+            profile: cli.profile.clone(),
+        },
+    );
+
+    match cli.format {
+        OutputFormat::Human => output::print_trend(&report),
+        OutputFormat::Json | OutputFormat::Jsonl => output::print_trend_json(&report),
+        OutputFormat::Quiet => {
+            println!(
+                "{}  {}  {}",
+                report.scans,
+                report.fastest_growing.len(),
+                report.runaway.len()
+            );
+        }
+    }
+
+    Ok(())
+}
+
 // ─── Config ───────────────────────────────────────────────────────────────────
 
 fn cmd_config(action: ConfigAction) -> Result<()> {
@@ -1034,6 +1757,7 @@ fn cmd_config(action: ConfigAction) -> Result<()> {
                 "stale_days" => config.stale_days = value.parse()?,
                 "large_file_threshold_mb" => config.large_file_threshold_mb = value.parse()?,
                 "staging_retention_days" => config.staging_retention_days = value.parse()?,
+                "staging_store" => config.staging_store = value.parse()?,
                 "default_profile" => config.default_profile = value.clone(),
                 _ => anyhow::bail!("Unknown config key: {}", key),
             }
@@ -1085,6 +1809,13 @@ fn cmd_status(diagnostics: bool) -> Result<()> {
         health.session_count,
         format::format_size(health.total_size)
     );
+    if health.store_blobs > 0 {
+        println!(
+            "    {} of it in {} deduplicated files",
+            format::format_size(health.store_size),
+            health.store_blobs
+        );
+    }
     if health.expired_count > 0 {
         println!(
             "  {} {} expired ({}) — run {}",
@@ -1095,19 +1826,42 @@ fn cmd_status(diagnostics: bool) -> Result<()> {
         );
     }
     output::print_staging_health(&health);
+    let incomplete = cleaner::journal::incomplete_sessions()?;
+    if !incomplete.is_empty() {
+        println!(
+            "  {} {} interrupted clean(s) — run {}",
+            "⚠".yellow(),
+            incomplete.len(),
+            "tidymac recover".cyan()
+        );
+    }
+    let orphans = cleaner::journal::orphaned_files()?;
+    if !orphans.is_empty() {
+        println!(
+            "  {} {} orphaned staged files ({}) that no session records — see {}",
+            "⚠".yellow(),
+            orphans.iter().map(|o| o.files).sum::<usize>(),
+            format::format_size(orphans.iter().map(|o| o.bytes).sum()),
+            "tidymac recover".cyan()
+        );
+    }
 
     // Scan cache info
     if let Some(cache) = tidymac::scanner::cache::ScanCache::load(&config.default_profile) {
         println!(
-            "  {} Scan cache: {} entries, updated {}",
+            "  {} Scan cache: {} targets ({} files), updated {}",
             "⚡",
             cache.entry_count(),
+            format::format_count(cache.file_count()),
             cache.age_string()
         );
     } else {
         println!("  {} Scan cache: empty (first scan will populate)", "⚡");
     }
 
+    // Watch daemons
+    output::print_watch_states(&tidymac::watch::state::WatchState::list());
+
     // Recent sessions
     let sessions = CleanManifest::list_sessions()?;
     if !sessions.is_empty() {
@@ -1,7 +1,12 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-use crate::scanner::targets::{self, ScanTarget};
+use crate::common::config::StagingStore;
+use crate::scanner::targets::{self, Category, SafetyLevel, ScanTarget, TrimPolicy};
+use crate::scanner::walker::expand_paths;
+use crate::scanner::limits::ScanLimits;
+use crate::scanner::LargeFileScan;
 
 /// A smart cleanup profile
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,6 +15,15 @@ pub struct Profile {
     pub targets: ProfileTargets,
     #[serde(default)]
     pub thresholds: ProfileThresholds,
+    #[serde(default)]
+    pub large_files: LargeFileSettings,
+    #[serde(default)]
+    pub limits: ScanLimits,
+    /// Trim policies keyed by target name or category (`dev_cache.cargo`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub trim: BTreeMap<String, TrimPolicy>,
+    #[serde(default)]
+    pub staging: StagingSettings,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,6 +63,75 @@ pub struct ProfileTargets {
     pub large_files: bool,
     #[serde(default)]
     pub dev: DevTargets,
+    /// User-defined targets declared as `[[targets.custom]]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub custom: Vec<CustomTarget>,
+}
+
+/// A user-defined scan target declared in a profile TOML
+///
+/// ```toml
+/// [[targets.custom]]
+/// name = "Bazel Cache"
+/// paths = ["~/.cache/bazel", "~/build-cache/*"]
+/// category = { dev_cache = "cargo" }   # or "user_cache", "logs", ...
+/// safety = "safe"
+/// min_age_days = 14
+/// reason = "In-house build cache — regenerated on next build"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomTarget {
+    pub name: String,
+    /// Paths or glob patterns, with `~` expansion
+    pub paths: Vec<String>,
+    #[serde(default = "default_custom_category")]
+    pub category: Category,
+    #[serde(default = "default_custom_safety")]
+    pub safety: SafetyLevel,
+    #[serde(default = "default_true")]
+    pub recursive: bool,
+    #[serde(default)]
+    pub min_age_days: Option<u32>,
+    #[serde(default = "default_custom_reason")]
+    pub reason: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+impl CustomTarget {
+    /// Convert into a regular scan target
+    pub fn to_scan_target(&self) -> ScanTarget {
+        ScanTarget {
+            name: self.name.clone(),
+            category: self.category.clone(),
+            paths: self.paths.clone(),
+            safety: self.safety.clone(),
+            reason: self.reason.clone(),
+            recursive: self.recursive,
+            min_age_days: self.min_age_days,
+            trim: None,
+        }
+    }
+
+    /// Reject definitions that are empty or point at protected locations
+    fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            anyhow::bail!("Custom target is missing a name");
+        }
+        if self.paths.is_empty() {
+            anyhow::bail!("Custom target '{}' has no paths", self.name);
+        }
+        for path in crate::scanner::walker::expand_paths(&self.paths) {
+            if crate::common::safety::is_protected(&path) {
+                anyhow::bail!(
+                    "Custom target '{}' points at a protected path: {}",
+                    self.name,
+                    path.display()
+                );
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -81,6 +164,9 @@ pub struct DevTargets {
     pub gradle_cache: bool,
     #[serde(default)]
     pub conda_cache: bool,
+    /// Build output inside stale projects (target/, build/, .next/, ...)
+    #[serde(default)]
+    pub project_artifacts: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -100,6 +186,44 @@ impl Default for ProfileThresholds {
     }
 }
 
+/// Where and how the large-file pass looks, used when `targets.large_files` is on
+///
+/// ```toml
+/// [large_files]
+/// roots = ["~/Downloads", "~/Movies"]
+/// min_age_days = 30
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LargeFileSettings {
+    #[serde(default = "default_large_roots")]
+    pub roots: Vec<String>,
+    /// Only report files not modified for this many days
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_age_days: Option<u32>,
+}
+
+impl Default for LargeFileSettings {
+    fn default() -> Self {
+        Self {
+            roots: default_large_roots(),
+            min_age_days: None,
+        }
+    }
+}
+
+/// How soft deletes with this profile stage files
+///
+/// ```toml
+/// [staging]
+/// store = "compress"
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StagingSettings {
+    /// Overrides the `staging_store` setting; `clean --stage` overrides both
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub store: Option<StagingStore>,
+}
+
 fn default_true() -> bool {
     true
 }
@@ -109,6 +233,27 @@ fn default_stale() -> u32 {
 fn default_large() -> u64 {
     500
 }
+fn default_large_roots() -> Vec<String> {
+    [
+        "~/Downloads",
+        "~/Desktop",
+        "~/Documents",
+        "~/Movies",
+        "~/VirtualBox VMs",
+    ]
+    .iter()
+    .map(|r| r.to_string())
+    .collect()
+}
+fn default_custom_category() -> Category {
+    Category::UserCache
+}
+fn default_custom_safety() -> SafetyLevel {
+    SafetyLevel::Caution
+}
+fn default_custom_reason() -> String {
+    "User-defined scan target".to_string()
+}
 
 impl Profile {
     /// Load a profile by name
@@ -125,6 +270,9 @@ impl Profile {
                 .with_context(|| format!("Failed to read profile: {}", path.display()))?;
             let profile: Profile = toml::from_str(&contents)
                 .with_context(|| format!("Failed to parse profile: {}", path.display()))?;
+            profile
+                .validate()
+                .with_context(|| format!("Invalid profile: {}", path.display()))?;
             return Ok(profile);
         }
 
@@ -134,17 +282,71 @@ impl Profile {
         )
     }
 
+    /// Check that user-supplied parts of the profile are usable
+    pub fn validate(&self) -> Result<()> {
+        for custom in &self.targets.custom {
+            custom.validate()?;
+        }
+        for (key, policy) in &self.trim {
+            policy.validate(key)?;
+        }
+        Ok(())
+    }
+
     /// Check if dev project scanning is enabled
     pub fn includes_dev_projects(&self) -> bool {
         self.targets.dev.node_modules_stale || self.targets.dev.venv
     }
 
+    /// Check if build artifacts in stale projects should be scanned
+    pub fn includes_project_artifacts(&self) -> bool {
+        self.targets.dev.project_artifacts
+    }
+
+    /// Large-file pass settings, or `None` when the profile doesn't want one
+    pub fn large_file_scan(&self) -> Option<LargeFileScan> {
+        if !self.targets.large_files {
+            return None;
+        }
+        Some(LargeFileScan {
+            roots: expand_paths(&self.large_files.roots),
+            threshold_bytes: self.thresholds.large_file_mb * 1024 * 1024,
+            min_age_days: self.large_files.min_age_days,
+        })
+    }
+
     /// Get the list of scan targets enabled by this profile
+    ///
+    /// Custom targets are always included unless they set `enabled = false`;
+    /// the category toggles only apply to the built-in targets.
     pub fn enabled_targets(&self) -> Vec<ScanTarget> {
         let all = targets::all_targets();
-        all.into_iter()
+        let mut enabled: Vec<ScanTarget> = all
+            .into_iter()
             .filter(|t| self.is_target_enabled(t))
-            .collect()
+            .collect();
+        enabled.extend(
+            self.targets
+                .custom
+                .iter()
+                .filter(|c| c.enabled)
+                .map(|c| c.to_scan_target()),
+        );
+        for target in &mut enabled {
+            target.trim = self.trim_policy(target).cloned();
+        }
+        enabled
+    }
+
+    /// Trim policy for a target: by its name, else by its category
+    fn trim_policy(&self, target: &ScanTarget) -> Option<&TrimPolicy> {
+        self.trim.get(&target.name).or_else(|| {
+            let slug = target.category.slug();
+            self.trim.get(&slug).or_else(|| match target.category {
+                Category::DevCache(_) => self.trim.get("dev_cache.*"),
+                _ => None,
+            })
+        })
     }
 
     fn is_target_enabled(&self, target: &ScanTarget) -> bool {
@@ -181,6 +383,9 @@ impl Profile {
             CocoaPods => self.targets.dev.cocoapods_cache,
             Cargo => self.targets.dev.cargo_cache,
             Gradle => self.targets.dev.gradle_cache,
+            CargoTarget | GradleBuild | JsBuild | PythonCache | SwiftPmBuild | CMakeBuild => {
+                self.targets.dev.project_artifacts
+            }
             _ => false,
         }
     }
@@ -230,11 +435,16 @@ fn builtin_profile(name: &str) -> Option<Profile> {
                 downloaded_dmgs: false,
                 large_files: false,
                 dev: DevTargets::default(),
+                custom: Vec::new(),
             },
             thresholds: ProfileThresholds {
                 stale_days: 30,
                 large_file_mb: 500,
             },
+            large_files: LargeFileSettings::default(),
+            limits: ScanLimits::default(),
+            trim: BTreeMap::new(),
+            staging: StagingSettings::default(),
         }),
 
         "developer" | "dev" => Some(Profile {
@@ -269,12 +479,18 @@ fn builtin_profile(name: &str) -> Option<Profile> {
                     cargo_cache: true,
                     gradle_cache: true,
                     conda_cache: true,
+                    project_artifacts: true,
                 },
+                custom: Vec::new(),
             },
             thresholds: ProfileThresholds {
                 stale_days: 30,
                 large_file_mb: 500,
             },
+            large_files: LargeFileSettings::default(),
+            limits: ScanLimits::default(),
+            trim: BTreeMap::new(),
+            staging: StagingSettings::default(),
         }),
 
         "creative" => Some(Profile {
@@ -295,11 +511,16 @@ fn builtin_profile(name: &str) -> Option<Profile> {
                 downloaded_dmgs: true,
                 large_files: false,
                 dev: DevTargets::default(),
+                custom: Vec::new(),
             },
             thresholds: ProfileThresholds {
                 stale_days: 14,
                 large_file_mb: 200,
             },
+            large_files: LargeFileSettings::default(),
+            limits: ScanLimits::default(),
+            trim: BTreeMap::new(),
+            staging: StagingSettings::default(),
         }),
 
         "deep" | "deep_clean" => Some(Profile {
@@ -334,12 +555,25 @@ fn builtin_profile(name: &str) -> Option<Profile> {
                     cargo_cache: true,
                     gradle_cache: true,
                     conda_cache: true,
+                    project_artifacts: true,
                 },
+                custom: Vec::new(),
             },
             thresholds: ProfileThresholds {
                 stale_days: 14,
                 large_file_mb: 100,
             },
+            large_files: LargeFileSettings {
+                roots: default_large_roots(),
+                min_age_days: Some(30),
+            },
+            // Deep scans cover whole home folders; stay off mounted shares
+            limits: ScanLimits {
+                one_file_system: true,
+                ..ScanLimits::default()
+            },
+            trim: BTreeMap::new(),
+            staging: StagingSettings::default(),
         }),
 
         _ => None,
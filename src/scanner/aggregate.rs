@@ -0,0 +1,165 @@
+//! Memory-bounded aggregation of walked files
+//!
+//! A `FileCollector` keeps running totals for everything it is given but
+//! holds on to at most `limit` entries — the largest ones — so scanning a
+//! cache folder with millions of files stays small. Each walker thread owns
+//! its own collector and they are merged at the end, so nothing is locked
+//! per file.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use super::targets::{FileEntry, ScanItem};
+use crate::common::sizes::{DiskUsage, UsageTally};
+
+/// Files listed per item when only the largest are kept
+pub const DEFAULT_TOP_FILES: usize = 100;
+
+/// Running totals plus either every file or the `limit` largest
+#[derive(Debug, Default)]
+pub struct FileCollector {
+    limit: Option<usize>,
+    tally: UsageTally,
+    count: usize,
+    all: Vec<FileEntry>,
+    top: BinaryHeap<Reverse<BySize>>,
+}
+
+/// What a collector ends up with
+#[derive(Debug, Default)]
+pub struct Collected {
+    pub usage: DiskUsage,
+    pub file_count: usize,
+    /// Largest first
+    pub files: Vec<FileEntry>,
+    /// `files` holds only the largest `file_count` entries
+    pub partial: bool,
+}
+
+impl FileCollector {
+    /// Keep the `limit` largest files, or all of them when `None`
+    pub fn new(limit: Option<usize>) -> Self {
+        Self {
+            limit,
+            ..Self::default()
+        }
+    }
+
+    pub fn push(&mut self, file: FileEntry) {
+        self.tally.add(file.usage(), file.hard_link);
+        self.count += 1;
+        match self.limit {
+            None => self.all.push(file),
+            Some(limit) => {
+                self.top.push(Reverse(BySize(file)));
+                if self.top.len() > limit {
+                    self.top.pop();
+                }
+            }
+        }
+    }
+
+    /// Fold in another collector with the same limit
+    pub fn merge(&mut self, other: FileCollector) {
+        self.tally.merge(other.tally);
+        self.count += other.count;
+        self.all.extend(other.all);
+        for entry in other.top {
+            self.top.push(entry);
+            if self.limit.is_some_and(|limit| self.top.len() > limit) {
+                self.top.pop();
+            }
+        }
+    }
+
+    pub fn finish(self) -> Collected {
+        let mut files = match self.limit {
+            None => self.all,
+            Some(_) => self.top.into_iter().map(|Reverse(BySize(f))| f).collect(),
+        };
+        files.sort_by_key(|f| Reverse(f.size_bytes));
+        Collected {
+            usage: self.tally.finish(),
+            partial: files.len() < self.count,
+            file_count: self.count,
+            files,
+        }
+    }
+}
+
+/// Cut `item.files` down to its `limit` largest, leaving totals alone
+pub fn keep_largest(item: &mut ScanItem, limit: usize) {
+    if item.files.len() <= limit {
+        return;
+    }
+    item.files.sort_by_key(|f| Reverse(f.size_bytes));
+    item.files.truncate(limit);
+    item.files_partial = true;
+}
+
+/// Orders entries by allocated size only
+#[derive(Debug)]
+struct BySize(FileEntry);
+
+impl PartialEq for BySize {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.size_bytes == other.0.size_bytes
+    }
+}
+
+impl Eq for BySize {}
+
+impl PartialOrd for BySize {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BySize {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.size_bytes.cmp(&other.0.size_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, size: u64) -> FileEntry {
+        FileEntry {
+            path: name.into(),
+            size_bytes: size,
+            modified: None,
+            apparent_bytes: size,
+            freeable_bytes: size,
+            hard_link: None,
+            accessed: None,
+            file_id: None,
+        }
+    }
+
+    #[test]
+    fn test_top_files_keep_totals_for_everything() {
+        let mut left = FileCollector::new(Some(2));
+        let mut right = FileCollector::new(Some(2));
+        for (i, size) in [5u64, 1, 9, 3].iter().enumerate() {
+            left.push(entry(&format!("l{}", i), *size));
+        }
+        right.push(entry("r0", 7));
+        left.merge(right);
+
+        let collected = left.finish();
+        assert_eq!(collected.file_count, 5);
+        assert_eq!(collected.usage.allocated, 25);
+        assert!(collected.partial);
+        let sizes: Vec<u64> = collected.files.iter().map(|f| f.size_bytes).collect();
+        assert_eq!(sizes, vec![9, 7]);
+
+        let mut all = FileCollector::new(None);
+        all.push(entry("a", 1));
+        all.push(entry("b", 2));
+        let collected = all.finish();
+        assert!(!collected.partial);
+        assert_eq!(collected.files.len(), 2);
+    }
+}
@@ -1,90 +1,252 @@
+//! Incremental scan cache
+//!
+//! Stores every scanned target, including its per-file entries, in a
+//! gzip-compressed, versioned file at `~/.tidymac/cache/scan_<profile>.json.gz`.
+//! A target is served from cache while the mtimes of its roots and of every
+//! directory up to `MTIME_DEPTH` levels below them are unchanged.
+
 use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
 
 use crate::common::config::Config;
-use crate::scanner::targets::ScanItem;
+use crate::common::exclusions::ExclusionMatcher;
+use crate::common::permissions;
+use crate::scanner::targets::{ScanItem, ScanTarget};
+use crate::scanner::walker;
 
-/// Cache entry for a single scan target directory
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CacheEntry {
-    /// Path that was scanned
+/// On-disk format version; caches written with any other version are ignored
+pub const CACHE_VERSION: u32 = 7;
+
+/// How many directory levels below a target root are mtime-checked
+pub const MTIME_DEPTH: usize = 3;
+
+/// Entries older than this are rescanned regardless of mtimes, which bounds
+/// both missed changes below `MTIME_DEPTH` and drift in `min_age_days` filters
+pub const MAX_ENTRY_AGE_SECS: i64 = 24 * 3600;
+
+/// A directory paired with its modification time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirMtime {
     pub path: PathBuf,
-    /// Directory mtime at scan time
-    pub mtime_secs: u64,
-    /// Total size found
-    pub size_bytes: u64,
-    /// File count found
-    pub file_count: usize,
-    /// Category string
-    pub category: String,
-    /// Name of the scan item
-    pub name: String,
-    /// Safety level string
-    pub safety: String,
-    /// Reason string
-    pub reason: String,
+    pub mtime_secs: i64,
+    pub mtime_nsec: i64,
 }
 
-/// Complete scan cache
+/// Directory mtimes of a target, taken just before it is walked
+#[derive(Debug, Clone)]
+pub struct TargetSnapshot {
+    /// Cache key identifying the target definition
+    pub key: String,
+    /// Roots and their subdirectories up to `MTIME_DEPTH`
+    pub dirs: Vec<DirMtime>,
+}
+
+impl TargetSnapshot {
+    /// Snapshot the directories a target would walk
+    pub fn take(target: &ScanTarget, excludes: &ExclusionMatcher) -> Self {
+        let depth = if target.recursive { MTIME_DEPTH } else { 0 };
+        let mut dirs = Vec::new();
+        for root in walker::expand_paths(&target.paths) {
+            if permissions::is_sip_protected(&root) || excludes.is_excluded(&root) {
+                continue;
+            }
+            snapshot_dir(&root, depth, excludes, &mut dirs);
+        }
+        Self {
+            key: target_key(target),
+            dirs,
+        }
+    }
+}
+
+/// Stable key for a target definition
+///
+/// Includes everything that changes what a walk returns, so editing a custom
+/// target's paths or age filter never serves stale results.
+pub fn target_key(target: &ScanTarget) -> String {
+    format!(
+        "{}|{}|{}|{:?}",
+        target.name,
+        target.paths.join(":"),
+        target.recursive,
+        target.min_age_days
+    )
+}
+
+/// Copy what a target says about its results onto a stored item
+///
+/// Safety, category, reason and trim policy don't change what a walk finds,
+/// so they aren't part of `target_key`; they come from the current profile
+/// and built-in targets instead of from whenever the item was walked.
+pub fn restamp(item: &mut ScanItem, target: &ScanTarget) {
+    item.category = target.category.clone();
+    item.safety = target.safety.clone();
+    item.reason = target.reason.clone();
+    item.trim = target.trim.clone();
+}
+
+/// Mtimes of `root` and its subdirectories down to `depth` levels
+pub fn snapshot_tree(root: &Path, depth: usize, excludes: &ExclusionMatcher) -> Vec<DirMtime> {
+    let mut dirs = Vec::new();
+    snapshot_dir(root, depth, excludes, &mut dirs);
+    dirs
+}
+
+/// Record `dir` and its subdirectories down to `depth` more levels
+fn snapshot_dir(dir: &Path, depth: usize, excludes: &ExclusionMatcher, out: &mut Vec<DirMtime>) {
+    let Ok(meta) = std::fs::symlink_metadata(dir) else {
+        return;
+    };
+    if !meta.is_dir() {
+        return;
+    }
+    out.push(DirMtime {
+        path: dir.to_path_buf(),
+        mtime_secs: meta.mtime(),
+        mtime_nsec: meta.mtime_nsec(),
+    });
+    if depth == 0 {
+        return;
+    }
+
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut subdirs: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+        .map(|e| e.path())
+        .filter(|p| !excludes.is_entry_excluded(p, true))
+        .collect();
+    subdirs.sort();
+    for sub in subdirs {
+        snapshot_dir(&sub, depth - 1, excludes, out);
+    }
+}
+
+/// Read a gzip-compressed JSON file whose top-level `version` must match
+///
+/// The version is checked before deserializing so older layouts are
+/// dropped instead of misparsed.
+pub(crate) fn read_versioned<T: DeserializeOwned>(path: &Path, version: u32) -> Option<T> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut json = String::new();
+    GzDecoder::new(file).read_to_string(&mut json).ok()?;
+
+    let value: serde_json::Value = serde_json::from_str(&json).ok()?;
+    if value.get("version").and_then(|v| v.as_u64()) != Some(version as u64) {
+        tracing::debug!("Ignoring {} with another format version", path.display());
+        return None;
+    }
+    serde_json::from_value(value).ok()
+}
+
+/// Write `value` as gzip-compressed JSON, replacing `path` atomically
+pub(crate) fn write_compressed<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+
+    let json = serde_json::to_vec(value).context("Failed to serialize state")?;
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+    encoder.write_all(&json)?;
+    let compressed = encoder.finish().context("Failed to compress state")?;
+
+    // Write-then-rename so a crash never leaves a truncated file behind
+    let tmp = path.with_extension("gz.tmp");
+    std::fs::write(&tmp, compressed)
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    Ok(())
+}
+
+/// A cached target: the full scan item plus the mtimes it was valid for
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ScanCache {
-    /// Profile name this cache was built with
-    pub profile: String,
-    /// When the cache was last updated
-    pub timestamp: chrono::DateTime<chrono::Utc>,
-    /// Cached entries keyed by path string
-    pub entries: HashMap<String, CacheEntry>,
-    /// Cache hit statistics
-    #[serde(default)]
-    pub stats: CacheStats,
+pub struct CacheEntry {
+    pub scanned_at: chrono::DateTime<chrono::Utc>,
+    pub dirs: Vec<DirMtime>,
+    pub item: ScanItem,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+/// Cache hit statistics for one scan
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CacheStats {
+    /// Targets served from cache
     pub hits: usize,
+    /// Targets with no cache entry
     pub misses: usize,
+    /// Targets whose entry was stale and had to be rescanned
     pub invalidated: usize,
+    /// Targets served from a running `tidymac watch` daemon
+    #[serde(default)]
+    pub watched: usize,
 }
 
 impl CacheStats {
     pub fn hit_rate(&self) -> f64 {
-        let total = self.hits + self.misses;
+        let total = self.hits + self.watched + self.misses + self.invalidated;
         if total == 0 {
             0.0
         } else {
-            self.hits as f64 / total as f64 * 100.0
+            (self.hits + self.watched) as f64 / total as f64 * 100.0
         }
     }
 }
 
+/// Complete scan cache for one profile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanCache {
+    /// On-disk format version
+    pub version: u32,
+    /// Profile name this cache was built with
+    pub profile: String,
+    /// When the cache was last updated
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// `exclude_paths` patterns the cached items were computed with
+    pub excludes: Vec<String>,
+    /// Cached entries keyed by `target_key`
+    pub entries: HashMap<String, CacheEntry>,
+    /// Statistics for the current run; never persisted
+    #[serde(skip)]
+    pub stats: CacheStats,
+}
+
 impl ScanCache {
     /// Create a new empty cache for a profile
     pub fn new(profile: &str) -> Self {
         Self {
+            version: CACHE_VERSION,
             profile: profile.to_string(),
             timestamp: chrono::Utc::now(),
+            excludes: Vec::new(),
             entries: HashMap::new(),
             stats: CacheStats::default(),
         }
     }
 
-    /// Get cache file path
-    pub fn cache_path() -> PathBuf {
-        Config::data_dir().join("scan_cache.json")
+    /// Get the cache file path for a profile
+    pub fn cache_path(profile: &str) -> PathBuf {
+        Config::cache_dir().join(format!("scan_{}.json.gz", profile))
     }
 
-    /// Load cache from disk, returns None if missing or invalid
+    /// Load the cache for a profile, returns None if missing, unreadable or
+    /// written by a different format version
     pub fn load(profile: &str) -> Option<Self> {
-        let path = Self::cache_path();
-        if !path.exists() {
-            return None;
-        }
+        Self::load_from(&Self::cache_path(profile), profile)
+    }
 
-        let contents = std::fs::read_to_string(&path).ok()?;
-        let cache: ScanCache = serde_json::from_str(&contents).ok()?;
+    fn load_from(path: &Path, profile: &str) -> Option<Self> {
+        let cache: ScanCache = read_versioned(path, CACHE_VERSION)?;
 
         // Invalidate if profile changed
         if cache.profile != profile {
@@ -96,74 +258,77 @@ impl ScanCache {
 
     /// Save cache to disk
     pub fn save(&mut self) -> Result<()> {
-        self.timestamp = chrono::Utc::now();
-        let path = Self::cache_path();
-
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
+        let path = Self::cache_path(&self.profile);
+        self.save_to(&path)
+    }
 
-        let json = serde_json::to_string_pretty(self).context("Failed to serialize scan cache")?;
-        std::fs::write(&path, json)
-            .with_context(|| format!("Failed to write cache: {}", path.display()))?;
+    fn save_to(&mut self, path: &Path) -> Result<()> {
+        self.timestamp = chrono::Utc::now();
 
-        Ok(())
+        write_compressed(path, self)
     }
 
-    /// Check if a path has changed since last scan
-    /// Returns the cached entry if still valid, None if needs re-scan
-    pub fn check(&mut self, path: &Path) -> Option<&CacheEntry> {
-        let key = path.display().to_string();
-
-        // Get current directory mtime
-        let current_mtime = dir_mtime(path)?;
+    /// Return the cached item for a target if its directories are unchanged
+    ///
+    /// With `full_files` an entry that only kept its largest files counts as
+    /// a miss, so the target is walked again for its complete list.
+    pub fn check(&mut self, snapshot: &TargetSnapshot, full_files: bool) -> Option<ScanItem> {
+        let Some(entry) = self
+            .entries
+            .get(&snapshot.key)
+            .filter(|e| !(full_files && e.item.files_partial))
+        else {
+            self.stats.misses += 1;
+            return None;
+        };
 
-        if let Some(entry) = self.entries.get(&key) {
-            if entry.mtime_secs == current_mtime {
-                self.stats.hits += 1;
-                return Some(entry);
-            } else {
-                self.stats.invalidated += 1;
-                return None;
-            }
+        let age = chrono::Utc::now() - entry.scanned_at;
+        if entry.dirs != snapshot.dirs || age.num_seconds() > MAX_ENTRY_AGE_SECS {
+            self.stats.invalidated += 1;
+            self.entries.remove(&snapshot.key);
+            return None;
         }
 
-        self.stats.misses += 1;
-        None
+        self.stats.hits += 1;
+        Some(entry.item.clone())
     }
 
-    /// Store a scan result in the cache
-    pub fn store(&mut self, item: &ScanItem) {
-        let key = item.path.display().to_string();
-        let mtime = dir_mtime(&item.path).unwrap_or(0);
-
+    /// Store a freshly walked item under the snapshot taken before the walk
+    pub fn store(&mut self, snapshot: TargetSnapshot, item: &ScanItem) {
         self.entries.insert(
-            key,
+            snapshot.key,
             CacheEntry {
-                path: item.path.clone(),
-                mtime_secs: mtime,
-                size_bytes: item.size_bytes,
-                file_count: item.file_count,
-                category: format!("{}", item.category),
-                name: item.name.clone(),
-                safety: format!("{:?}", item.safety),
-                reason: item.reason.clone(),
+                scanned_at: chrono::Utc::now(),
+                dirs: snapshot.dirs,
+                item: item.clone(),
             },
         );
     }
 
-    /// Remove a path from cache (after cleaning)
+    /// Drop every entry covering `path` (after cleaning)
     pub fn invalidate(&mut self, path: &Path) {
-        let key = path.display().to_string();
-        self.entries.remove(&key);
+        self.entries
+            .retain(|_, e| !e.dirs.iter().any(|d| path.starts_with(&d.path)));
     }
 
-    /// Clear the entire cache
+    /// Remove the cache files of every profile
     pub fn clear() -> Result<()> {
-        let path = Self::cache_path();
-        if path.exists() {
-            std::fs::remove_file(&path)
-                .with_context(|| format!("Failed to remove cache: {}", path.display()))?;
+        let dir = Config::cache_dir();
+        if let Ok(entries) = std::fs::read_dir(&dir) {
+            for entry in entries.filter_map(|e| e.ok()) {
+                if entry.file_name().to_string_lossy().starts_with("scan_") {
+                    std::fs::remove_file(entry.path()).with_context(|| {
+                        format!("Failed to remove cache: {}", entry.path().display())
+                    })?;
+                }
+            }
+        }
+
+        // Pre-v2 caches lived directly in the data dir
+        let legacy = Config::data_dir().join("scan_cache.json");
+        if legacy.exists() {
+            std::fs::remove_file(&legacy)
+                .with_context(|| format!("Failed to remove cache: {}", legacy.display()))?;
         }
         Ok(())
     }
@@ -189,15 +354,112 @@ impl ScanCache {
     pub fn entry_count(&self) -> usize {
         self.entries.len()
     }
+
+    /// Number of per-file entries across all cached items
+    pub fn file_count(&self) -> usize {
+        self.entries.values().map(|e| e.item.files.len()).sum()
+    }
 }
 
-/// Get a directory's modification time as seconds since epoch
-fn dir_mtime(path: &Path) -> Option<u64> {
-    std::fs::metadata(path)
-        .ok()?
-        .modified()
-        .ok()?
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .ok()
-        .map(|d| d.as_secs())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::targets::{Category, SafetyLevel};
+    use tempfile::TempDir;
+
+    fn target(root: &Path) -> ScanTarget {
+        ScanTarget {
+            name: "Test Cache".into(),
+            category: Category::UserCache,
+            paths: vec![root.display().to_string()],
+            safety: SafetyLevel::Safe,
+            reason: "test".into(),
+            recursive: true,
+            min_age_days: None,
+            trim: None,
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_keeps_file_entries() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path().join("root");
+        std::fs::create_dir_all(root.join("a")).unwrap();
+        std::fs::write(root.join("a/one.bin"), b"data").unwrap();
+
+        let t = target(&root);
+        let excludes = ExclusionMatcher::empty();
+        let snap = TargetSnapshot::take(&t, &excludes);
+        let item = walker::walk_target(&t, &excludes).unwrap();
+        assert_eq!(item.files.len(), 1);
+
+        let cache_file = tmp.path().join("scan_test.json.gz");
+        let mut cache = ScanCache::new("test");
+        cache.store(snap, &item);
+        cache.save_to(&cache_file).unwrap();
+
+        let mut loaded = ScanCache::load_from(&cache_file, "test").unwrap();
+        let hit = loaded.check(&TargetSnapshot::take(&t, &excludes), true).unwrap();
+        assert_eq!(hit.files.len(), 1);
+        assert_eq!(hit.files[0].path, root.join("a/one.bin"));
+        assert_eq!(loaded.stats.hits, 1);
+
+        assert!(ScanCache::load_from(&cache_file, "other").is_none());
+    }
+
+    #[test]
+    fn test_hits_take_metadata_from_the_current_target() {
+        let tmp = TempDir::new().unwrap();
+        std::fs::write(tmp.path().join("one.bin"), b"data").unwrap();
+
+        let mut t = target(tmp.path());
+        let excludes = ExclusionMatcher::empty();
+        let mut cache = ScanCache::new("test");
+        let item = walker::walk_target(&t, &excludes).unwrap();
+        cache.store(TargetSnapshot::take(&t, &excludes), &item);
+
+        t.safety = SafetyLevel::Caution;
+        t.reason = "edited".into();
+        let snapshot = TargetSnapshot::take(&t, &excludes);
+        let mut hit = cache.check(&snapshot, true).unwrap();
+        assert_eq!(hit.safety, SafetyLevel::Safe);
+        restamp(&mut hit, &t);
+        assert_eq!(hit.safety, SafetyLevel::Caution);
+        assert_eq!(hit.reason, "edited");
+    }
+
+    #[test]
+    fn test_nested_change_invalidates() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path().join("root");
+        std::fs::create_dir_all(root.join("a/b")).unwrap();
+
+        let t = target(&root);
+        let excludes = ExclusionMatcher::empty();
+        let item = walker::walk_target(&t, &excludes).unwrap();
+        let mut cache = ScanCache::new("test");
+        cache.store(TargetSnapshot::take(&t, &excludes), &item);
+
+        // A file appearing two levels down changes only that dir's mtime
+        std::fs::write(root.join("a/b/new.bin"), b"new").unwrap();
+        assert!(cache.check(&TargetSnapshot::take(&t, &excludes), true).is_none());
+        assert_eq!(cache.stats.invalidated, 1);
+        assert!(cache.check(&TargetSnapshot::take(&t, &excludes), true).is_none());
+        assert_eq!(cache.stats.misses, 1);
+    }
+
+    #[test]
+    fn test_other_version_is_ignored() {
+        let tmp = TempDir::new().unwrap();
+        let cache_file = tmp.path().join("scan_test.json.gz");
+        let mut cache = ScanCache::new("test");
+        cache.version = CACHE_VERSION + 1;
+        cache.save_to(&cache_file).unwrap();
+        assert!(ScanCache::load_from(&cache_file, "test").is_none());
+    }
+
+    #[test]
+    fn test_load_nonexistent_returns_none() {
+        assert!(ScanCache::load("__no_such_profile__").is_none());
+    }
 }
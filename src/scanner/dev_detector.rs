@@ -1,7 +1,14 @@
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
-use super::targets::{Category, DevTool, SafetyLevel, ScanItem};
+use super::limits::{self, ScanBudget, TruncationReason};
+use super::staleness::StalenessOracle;
+use super::PASS_ARTIFACTS;
+use super::targets::{self, Category, DevTool, FileEntry, SafetyLevel, ScanItem};
 use super::walker;
+use crate::common::cancel::CancelToken;
+use crate::common::exclusions::ExclusionMatcher;
 
 /// Detect installed developer tools and their cache sizes
 pub fn detect_dev_environment() -> Vec<DevToolInfo> {
@@ -112,7 +119,13 @@ pub fn detect_dev_environment() -> Vec<DevToolInfo> {
 }
 
 /// Scan for stale node_modules across project directories
-pub fn scan_node_modules(stale_days: u32) -> ScanItem {
+pub fn scan_node_modules(
+    oracle: &StalenessOracle,
+    excludes: &ExclusionMatcher,
+    cancel: &CancelToken,
+    budget: &ScanBudget,
+) -> ScanItem {
+    let stale_days = oracle.stale_days();
     let home = dirs::home_dir().unwrap_or_default();
     let search_roots = vec![
         home.join("Projects"),
@@ -129,16 +142,18 @@ pub fn scan_node_modules(stale_days: u32) -> ScanItem {
     ];
 
     let existing_roots: Vec<PathBuf> = search_roots.into_iter().filter(|p| p.exists()).collect();
-    let files = walker::find_node_modules(&existing_roots, stale_days);
+    let files = walker::find_node_modules(&existing_roots, oracle, excludes, cancel, budget);
 
-    let total_size: u64 = files.iter().map(|f| f.size_bytes).sum();
+    let usage = targets::file_totals(&files);
     let count = files.len();
 
     ScanItem {
         name: format!("Stale node_modules (>{} days)", stale_days),
         category: Category::DevCache(DevTool::NodeModules),
         path: home.join("**/node_modules"),
-        size_bytes: total_size,
+        size_bytes: usage.allocated,
+        apparent_bytes: usage.apparent,
+        freeable_bytes: usage.freeable,
         file_count: count,
         safety: SafetyLevel::Safe,
         reason: format!(
@@ -146,11 +161,20 @@ pub fn scan_node_modules(stale_days: u32) -> ScanItem {
             stale_days
         ),
         files,
+        files_partial: false,
+        trim: None,
+        roots: existing_roots,
     }
 }
 
 /// Scan for stale Python virtual environments
-pub fn scan_venvs(stale_days: u32) -> ScanItem {
+pub fn scan_venvs(
+    oracle: &StalenessOracle,
+    excludes: &ExclusionMatcher,
+    cancel: &CancelToken,
+    budget: &ScanBudget,
+) -> ScanItem {
+    let stale_days = oracle.stale_days();
     let home = dirs::home_dir().unwrap_or_default();
     let search_roots = vec![
         home.join("Projects"),
@@ -166,25 +190,362 @@ pub fn scan_venvs(stale_days: u32) -> ScanItem {
     ];
 
     let existing_roots: Vec<PathBuf> = search_roots.into_iter().filter(|p| p.exists()).collect();
-    let files = walker::find_venvs(&existing_roots, stale_days);
+    let files = walker::find_venvs(&existing_roots, oracle, excludes, cancel, budget);
 
-    let total_size: u64 = files.iter().map(|f| f.size_bytes).sum();
+    let usage = targets::file_totals(&files);
     let count = files.len();
 
     ScanItem {
         name: format!("Stale Python venvs (>{} days)", stale_days),
         category: Category::DevCache(DevTool::Venv),
         path: home.join("**/.venv"),
-        size_bytes: total_size,
+        size_bytes: usage.allocated,
+        apparent_bytes: usage.apparent,
+        freeable_bytes: usage.freeable,
         file_count: count,
         safety: SafetyLevel::Safe,
         reason: format!(
             "Python virtualenvs in stale projects — recreate with 'python -m venv .venv'",
         ),
         files,
+        files_partial: false,
+        trim: None,
+        roots: existing_roots,
     }
 }
 
+// ─── Project build artifacts ──────────────────────────────────────────────────
+
+/// Maximum depth below a code root at which projects are discovered
+const PROJECT_MAX_DEPTH: usize = 6;
+
+/// Maximum depth below a project root searched for nested artifacts
+const NESTED_MAX_DEPTH: usize = 8;
+
+/// A marker-file → artifact-directory rule for one ecosystem
+#[derive(Debug, Clone)]
+pub struct ArtifactRule {
+    pub tool: DevTool,
+    /// Files whose presence marks a project root
+    pub markers: &'static [&'static str],
+    /// Directories (relative to the project root) that are build output
+    pub artifacts: &'static [&'static str],
+    /// Artifact names also collected anywhere below the project root
+    pub nested: &'static [&'static str],
+    /// Extra check an artifact directory must pass, to avoid hand-made dirs
+    pub verify: Option<fn(&Path) -> bool>,
+    pub safety: SafetyLevel,
+    pub rebuild_hint: &'static str,
+}
+
+/// Built-in marker → artifact rules
+pub fn artifact_rules() -> Vec<ArtifactRule> {
+    vec![
+        ArtifactRule {
+            tool: DevTool::CargoTarget,
+            markers: &["Cargo.toml"],
+            artifacts: &["target"],
+            nested: &[],
+            verify: Some(is_cargo_target),
+            safety: SafetyLevel::Safe,
+            rebuild_hint: "rebuild with 'cargo build'",
+        },
+        ArtifactRule {
+            tool: DevTool::GradleBuild,
+            markers: &[
+                "build.gradle",
+                "build.gradle.kts",
+                "settings.gradle",
+                "settings.gradle.kts",
+            ],
+            artifacts: &["build", ".gradle"],
+            nested: &[],
+            verify: None,
+            safety: SafetyLevel::Safe,
+            rebuild_hint: "rebuild with './gradlew build'",
+        },
+        ArtifactRule {
+            tool: DevTool::JsBuild,
+            markers: &["package.json"],
+            artifacts: &[".next", ".nuxt", "dist", ".turbo"],
+            nested: &[],
+            verify: None,
+            // dist/ is occasionally committed or hand-curated
+            safety: SafetyLevel::Caution,
+            rebuild_hint: "rebuild with your framework's build script",
+        },
+        ArtifactRule {
+            tool: DevTool::PythonCache,
+            markers: &["pyproject.toml", "setup.py", "setup.cfg"],
+            artifacts: &["__pycache__", ".pytest_cache", ".tox", ".mypy_cache", ".ruff_cache"],
+            nested: &["__pycache__"],
+            verify: None,
+            safety: SafetyLevel::Safe,
+            rebuild_hint: "regenerated automatically by the tools",
+        },
+        ArtifactRule {
+            tool: DevTool::SwiftPmBuild,
+            markers: &["Package.swift"],
+            artifacts: &[".build"],
+            nested: &[],
+            verify: None,
+            safety: SafetyLevel::Safe,
+            rebuild_hint: "rebuild with 'swift build'",
+        },
+        ArtifactRule {
+            tool: DevTool::CMakeBuild,
+            markers: &["CMakeLists.txt"],
+            artifacts: &["build", "cmake-build-debug", "cmake-build-release"],
+            nested: &[],
+            verify: Some(is_cmake_build),
+            safety: SafetyLevel::Safe,
+            rebuild_hint: "re-run cmake to regenerate",
+        },
+    ]
+}
+
+/// Cargo writes CACHEDIR.TAG into target/ (and always has debug/ or release/)
+fn is_cargo_target(dir: &Path) -> bool {
+    dir.join("CACHEDIR.TAG").exists() || dir.join("debug").is_dir() || dir.join("release").is_dir()
+}
+
+/// Only treat a directory as CMake output if CMake configured it
+fn is_cmake_build(dir: &Path) -> bool {
+    dir.join("CMakeCache.txt").exists()
+}
+
+/// Directories that hold code projects
+pub fn code_roots() -> Vec<PathBuf> {
+    let home = dirs::home_dir().unwrap_or_default();
+    [
+        "Projects",
+        "projects",
+        "Code",
+        "code",
+        "Development",
+        "dev",
+        "workspace",
+        "repos",
+        "src",
+        "Documents",
+        "Desktop",
+    ]
+    .iter()
+    .map(|d| home.join(d))
+    .filter(|p| p.exists())
+    .collect()
+}
+
+/// Scan the code roots for build artifacts of stale projects
+///
+/// Returns one `ScanItem` per ecosystem in `tools`, skipping empty ones.
+pub fn scan_project_artifacts(
+    oracle: &StalenessOracle,
+    excludes: &ExclusionMatcher,
+    tools: &[DevTool],
+    cancel: &CancelToken,
+    budget: &ScanBudget,
+) -> Vec<ScanItem> {
+    find_project_artifacts(&code_roots(), oracle, excludes, tools, cancel, budget)
+}
+
+/// Find artifact directories of stale projects under `roots`
+pub fn find_project_artifacts(
+    roots: &[PathBuf],
+    oracle: &StalenessOracle,
+    excludes: &ExclusionMatcher,
+    tools: &[DevTool],
+    cancel: &CancelToken,
+    budget: &ScanBudget,
+) -> Vec<ScanItem> {
+    let stale_days = oracle.stale_days();
+    let rules: Vec<ArtifactRule> = artifact_rules()
+        .into_iter()
+        .filter(|r| tools.contains(&r.tool))
+        .collect();
+    if rules.is_empty() {
+        return Vec::new();
+    }
+
+    // Never descend into artifact dirs themselves, only into source trees
+    let skip_names: Vec<&str> = artifact_rules()
+        .iter()
+        .flat_map(|r| r.artifacts.iter().copied())
+        .chain(["node_modules", "Library"])
+        .collect();
+    let mut found: HashMap<DevTool, Vec<FileEntry>> = HashMap::new();
+    let mut claimed: HashSet<PathBuf> = HashSet::new();
+
+    let one_file_system = budget.limits().one_file_system;
+    for root in roots {
+        if excludes.is_excluded(root) {
+            continue;
+        }
+        if one_file_system && limits::is_network_filesystem(root) {
+            budget.record(
+                PASS_ARTIFACTS,
+                TruncationReason::NetworkFilesystem { path: root.clone() },
+            );
+            continue;
+        }
+
+        let projects = walkdir::WalkDir::new(root)
+            .follow_links(false)
+            .same_file_system(one_file_system)
+            .max_depth(PROJECT_MAX_DEPTH)
+            .into_iter()
+            .filter_entry(|e| {
+                if e.depth() == 0 {
+                    return true;
+                }
+                let name = e.file_name().to_string_lossy();
+                e.file_type().is_dir()
+                    && !name.starts_with('.')
+                    && !skip_names.contains(&name.as_ref())
+                    && !excludes.is_entry_excluded(e.path(), true)
+            })
+            .filter_map(|e| e.ok());
+
+        for project in projects {
+            if cancel.is_cancelled() {
+                break;
+            }
+            if budget.expired() {
+                budget.record_expired(PASS_ARTIFACTS);
+                break;
+            }
+            let dir = project.path();
+            for rule in &rules {
+                let Some(marker_mtime) = newest_marker_mtime(dir, rule.markers) else {
+                    continue;
+                };
+                if !oracle.is_stale(dir, Some(marker_mtime)) {
+                    continue;
+                }
+
+                let mut candidates: Vec<PathBuf> =
+                    rule.artifacts.iter().map(|a| dir.join(a)).collect();
+                if !rule.nested.is_empty() {
+                    candidates.extend(find_nested_artifacts(
+                        dir,
+                        rule,
+                        &skip_names,
+                        excludes,
+                        one_file_system,
+                    ));
+                }
+                for path in candidates {
+                    let is_real_dir = std::fs::symlink_metadata(&path)
+                        .map(|m| m.is_dir())
+                        .unwrap_or(false);
+                    if !is_real_dir
+                        || !rule.verify.map(|v| v(&path)).unwrap_or(true)
+                        || excludes.is_excluded(&path)
+                        || excludes.contains_excluded(&path)
+                    {
+                        continue;
+                    }
+
+                    // e.g. build/ matched by both the Gradle and CMake rules
+                    if !claimed.insert(path.clone()) {
+                        continue;
+                    }
+                    let modified = std::fs::metadata(&path).ok().and_then(|m| m.modified().ok());
+                    found
+                        .entry(rule.tool.clone())
+                        .or_default()
+                        .push(FileEntry::from_dir(path, modified));
+                }
+            }
+        }
+    }
+
+    let home = dirs::home_dir().unwrap_or_default();
+    let mut items = Vec::new();
+    for rule in rules {
+        let Some(mut files) = found.remove(&rule.tool) else {
+            continue;
+        };
+        files.sort_by_key(|f| std::cmp::Reverse(f.size_bytes));
+        let usage = targets::file_totals(&files);
+        if usage.allocated == 0 {
+            continue;
+        }
+
+        items.push(ScanItem {
+            name: format!("Stale {} (>{} days)", rule.tool, stale_days),
+            category: Category::DevCache(rule.tool.clone()),
+            path: home.join(format!("**/{}", rule.artifacts[0])),
+            size_bytes: usage.allocated,
+            apparent_bytes: usage.apparent,
+            freeable_bytes: usage.freeable,
+            file_count: files.len(),
+            safety: rule.safety.clone(),
+            reason: format!(
+                "Build output in projects not modified for {}+ days — {}",
+                stale_days, rule.rebuild_hint
+            ),
+            files,
+            files_partial: false,
+            trim: None,
+            roots: roots.to_vec(),
+        });
+    }
+    items
+}
+
+/// Directories named in `rule.nested` anywhere below `project`
+///
+/// Doesn't descend into artifact or hidden directories, virtualenvs, or
+/// nested projects with their own markers, which are judged on their own.
+fn find_nested_artifacts(
+    project: &Path,
+    rule: &ArtifactRule,
+    skip_names: &[&str],
+    excludes: &ExclusionMatcher,
+    one_file_system: bool,
+) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    let mut walk = walkdir::WalkDir::new(project)
+        .follow_links(false)
+        .same_file_system(one_file_system)
+        .max_depth(NESTED_MAX_DEPTH)
+        .into_iter();
+    while let Some(entry) = walk.next() {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        if entry.depth() == 0 || !entry.file_type().is_dir() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy();
+        if rule.nested.contains(&name.as_ref()) {
+            found.push(entry.path().to_path_buf());
+            walk.skip_current_dir();
+            continue;
+        }
+        let path = entry.path();
+        if name.starts_with('.')
+            || skip_names.contains(&name.as_ref())
+            || excludes.is_entry_excluded(path, true)
+            || path.join("pyvenv.cfg").exists()
+            || newest_marker_mtime(path, rule.markers).is_some()
+        {
+            walk.skip_current_dir();
+        }
+    }
+    found
+}
+
+/// Most recent mtime among the marker files present in `dir`
+fn newest_marker_mtime(dir: &Path, markers: &[&str]) -> Option<SystemTime> {
+    markers
+        .iter()
+        .filter_map(|m| std::fs::metadata(dir.join(m)).ok())
+        .filter_map(|m| m.modified().ok())
+        .max()
+}
+
 #[derive(Debug, Clone)]
 pub struct DevToolInfo {
     pub tool: DevTool,
@@ -0,0 +1,349 @@
+//! Comparing two scans
+//!
+//! Items are matched by category, name and base path; files within a
+//! matched item by path. Only what changed is reported.
+
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use crate::scanner::snapshot::Snapshot;
+use crate::scanner::targets::{Category, FileEntry, ScanItem};
+
+/// How an item or file changed between two scans
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Change {
+    Added,
+    Removed,
+    Grown,
+    Shrunk,
+}
+
+impl Change {
+    fn between(old: Option<u64>, new: Option<u64>) -> Option<Self> {
+        match (old, new) {
+            (None, Some(_)) => Some(Change::Added),
+            (Some(_), None) => Some(Change::Removed),
+            (Some(old), Some(new)) if new > old => Some(Change::Grown),
+            (Some(old), Some(new)) if new < old => Some(Change::Shrunk),
+            _ => None,
+        }
+    }
+}
+
+/// Where one side of the comparison came from
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotInfo {
+    pub taken_at: chrono::DateTime<chrono::Utc>,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub profile: String,
+    /// 0 for plain `scan --format json` output
+    pub snapshot_version: u32,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub tidymac_version: String,
+}
+
+impl SnapshotInfo {
+    fn of(snapshot: &Snapshot) -> Self {
+        Self {
+            taken_at: snapshot.taken_at(),
+            profile: snapshot.profile.clone(),
+            snapshot_version: snapshot.snapshot_version,
+            tidymac_version: snapshot.tidymac_version.clone(),
+        }
+    }
+}
+
+/// Byte delta for one category
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryDelta {
+    pub category: Category,
+    pub old_bytes: u64,
+    pub new_bytes: u64,
+    pub delta_bytes: i64,
+}
+
+/// A file that changed within an item
+#[derive(Debug, Clone, Serialize)]
+pub struct FileChange {
+    pub path: PathBuf,
+    pub change: Change,
+    pub old_bytes: u64,
+    pub new_bytes: u64,
+    pub delta_bytes: i64,
+}
+
+/// An item that was added, removed, or changed size
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemChange {
+    pub name: String,
+    pub category: Category,
+    pub path: PathBuf,
+    pub change: Change,
+    pub old_bytes: u64,
+    pub new_bytes: u64,
+    pub delta_bytes: i64,
+    pub old_files: usize,
+    pub new_files: usize,
+    /// Largest change first
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<FileChange>,
+    /// False when either side only listed its largest files, so per-file
+    /// changes could not be worked out
+    pub files_compared: bool,
+}
+
+/// Everything that differs between two scans
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanDiff {
+    pub old: SnapshotInfo,
+    pub new: SnapshotInfo,
+    pub old_bytes: u64,
+    pub new_bytes: u64,
+    pub delta_bytes: i64,
+    /// Largest absolute change first; unchanged categories are left out
+    pub categories: Vec<CategoryDelta>,
+    /// Largest absolute change first
+    pub items: Vec<ItemChange>,
+}
+
+impl ScanDiff {
+    pub fn count(&self, change: Change) -> usize {
+        self.items.iter().filter(|i| i.change == change).count()
+    }
+}
+
+/// Compare `old` with `new`
+pub fn diff(old: &Snapshot, new: &Snapshot) -> ScanDiff {
+    let old_items = index_items(&old.results.items);
+    let new_items = index_items(&new.results.items);
+
+    let mut keys: Vec<&ItemKey> = old_items.keys().chain(new_items.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    let mut items = Vec::new();
+    for key in keys {
+        let (old_item, new_item) = (old_items.get(key), new_items.get(key));
+        let old_bytes = old_item.map(|i| i.size_bytes);
+        let new_bytes = new_item.map(|i| i.size_bytes);
+        let Some(change) = Change::between(old_bytes, new_bytes) else {
+            continue;
+        };
+        let files_compared = !old_item.is_some_and(|i| i.files_partial)
+            && !new_item.is_some_and(|i| i.files_partial);
+        let files = if files_compared {
+            diff_files(
+                old_item.map_or(&[][..], |i| &i.files),
+                new_item.map_or(&[][..], |i| &i.files),
+            )
+        } else {
+            Vec::new()
+        };
+        let item = new_item.or(old_item).unwrap();
+        items.push(ItemChange {
+            name: item.name.clone(),
+            category: item.category.clone(),
+            path: item.path.clone(),
+            change,
+            old_bytes: old_bytes.unwrap_or(0),
+            new_bytes: new_bytes.unwrap_or(0),
+            delta_bytes: delta(old_bytes.unwrap_or(0), new_bytes.unwrap_or(0)),
+            old_files: old_item.map_or(0, |i| i.file_count),
+            new_files: new_item.map_or(0, |i| i.file_count),
+            files,
+            files_compared,
+        });
+    }
+    items.sort_by_key(|i| Reverse(i.delta_bytes.unsigned_abs()));
+
+    ScanDiff {
+        old: SnapshotInfo::of(old),
+        new: SnapshotInfo::of(new),
+        old_bytes: old.results.total_reclaimable,
+        new_bytes: new.results.total_reclaimable,
+        delta_bytes: delta(old.results.total_reclaimable, new.results.total_reclaimable),
+        categories: category_deltas(&old.results.items, &new.results.items),
+        items,
+    }
+}
+
+type ItemKey = (String, String, PathBuf);
+
+fn index_items(items: &[ScanItem]) -> HashMap<ItemKey, &ScanItem> {
+    items
+        .iter()
+        .map(|i| ((i.category.slug(), i.name.clone(), i.path.clone()), i))
+        .collect()
+}
+
+fn diff_files(old: &[FileEntry], new: &[FileEntry]) -> Vec<FileChange> {
+    let old_sizes: HashMap<&PathBuf, u64> = old.iter().map(|f| (&f.path, f.size_bytes)).collect();
+    let new_sizes: HashMap<&PathBuf, u64> = new.iter().map(|f| (&f.path, f.size_bytes)).collect();
+
+    let mut changes: Vec<FileChange> = old_sizes
+        .keys()
+        .chain(new_sizes.keys().filter(|p| !old_sizes.contains_key(*p)))
+        .filter_map(|path| {
+            let (old_bytes, new_bytes) =
+                (old_sizes.get(path).copied(), new_sizes.get(path).copied());
+            let change = Change::between(old_bytes, new_bytes)?;
+            Some(FileChange {
+                path: (*path).clone(),
+                change,
+                old_bytes: old_bytes.unwrap_or(0),
+                new_bytes: new_bytes.unwrap_or(0),
+                delta_bytes: delta(old_bytes.unwrap_or(0), new_bytes.unwrap_or(0)),
+            })
+        })
+        .collect();
+    changes.sort_by(|a, b| {
+        b.delta_bytes
+            .unsigned_abs()
+            .cmp(&a.delta_bytes.unsigned_abs())
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    changes
+}
+
+fn category_deltas(old: &[ScanItem], new: &[ScanItem]) -> Vec<CategoryDelta> {
+    let mut by_slug: BTreeMap<String, CategoryDelta> = BTreeMap::new();
+    for (items, is_new) in [(old, false), (new, true)] {
+        for item in items {
+            let entry = by_slug
+                .entry(item.category.slug())
+                .or_insert_with(|| CategoryDelta {
+                    category: item.category.clone(),
+                    old_bytes: 0,
+                    new_bytes: 0,
+                    delta_bytes: 0,
+                });
+            if is_new {
+                entry.new_bytes += item.size_bytes;
+            } else {
+                entry.old_bytes += item.size_bytes;
+            }
+        }
+    }
+    let mut deltas: Vec<CategoryDelta> = by_slug
+        .into_values()
+        .map(|mut c| {
+            c.delta_bytes = delta(c.old_bytes, c.new_bytes);
+            c
+        })
+        .filter(|c| c.delta_bytes != 0)
+        .collect();
+    deltas.sort_by_key(|c| Reverse(c.delta_bytes.unsigned_abs()));
+    deltas
+}
+
+fn delta(old: u64, new: u64) -> i64 {
+    new as i64 - old as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::targets::{SafetyLevel, ScanResults};
+
+    fn file(path: &str, size: u64) -> FileEntry {
+        FileEntry {
+            path: path.into(),
+            size_bytes: size,
+            modified: None,
+            apparent_bytes: size,
+            freeable_bytes: size,
+            hard_link: None,
+            accessed: None,
+            file_id: None,
+        }
+    }
+
+    fn item(name: &str, category: Category, files: Vec<FileEntry>) -> ScanItem {
+        let size = files.iter().map(|f| f.size_bytes).sum();
+        ScanItem {
+            name: name.to_string(),
+            category,
+            path: format!("/data/{}", name).into(),
+            size_bytes: size,
+            apparent_bytes: size,
+            freeable_bytes: size,
+            file_count: files.len(),
+            safety: SafetyLevel::Safe,
+            reason: String::new(),
+            files,
+            files_partial: false,
+            trim: None,
+            roots: Vec::new(),
+        }
+    }
+
+    fn snapshot(items: Vec<ScanItem>) -> Snapshot {
+        let mut results = ScanResults::new();
+        results.items = items;
+        results.recalculate();
+        Snapshot::new(results, "quick")
+    }
+
+    #[test]
+    fn test_diff_reports_items_files_and_categories() {
+        let old = snapshot(vec![
+            item(
+                "Logs",
+                Category::Logs,
+                vec![file("/l/a", 100), file("/l/b", 50)],
+            ),
+            item("Trash", Category::Trash, vec![file("/t/x", 10)]),
+            item("Same", Category::UserCache, vec![file("/s/x", 5)]),
+        ]);
+        let new = snapshot(vec![
+            item(
+                "Logs",
+                Category::Logs,
+                vec![file("/l/a", 400), file("/l/c", 20)],
+            ),
+            item("Npm", Category::UserCache, vec![file("/n/x", 30)]),
+            item("Same", Category::UserCache, vec![file("/s/x", 5)]),
+        ]);
+
+        let d = diff(&old, &new);
+        assert_eq!(d.delta_bytes, 455 - 165);
+        assert_eq!(d.items.len(), 3);
+        assert_eq!(d.count(Change::Added), 1);
+        assert_eq!(d.count(Change::Removed), 1);
+
+        let logs = &d.items[0];
+        assert_eq!(logs.name, "Logs");
+        assert_eq!(logs.change, Change::Grown);
+        assert_eq!(logs.delta_bytes, 270);
+        let kinds: Vec<(&str, Change)> = logs
+            .files
+            .iter()
+            .map(|f| (f.path.to_str().unwrap(), f.change))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("/l/a", Change::Grown),
+                ("/l/b", Change::Removed),
+                ("/l/c", Change::Added)
+            ]
+        );
+
+        let logs_delta = d.categories.iter().find(|c| c.category == Category::Logs);
+        assert_eq!(logs_delta.unwrap().delta_bytes, 270);
+        assert_eq!(d.categories.len(), 3);
+    }
+
+    #[test]
+    fn test_diff_skips_files_of_partial_items() {
+        let old = snapshot(vec![item("Logs", Category::Logs, vec![file("/l/a", 100)])]);
+        let mut grown = item("Logs", Category::Logs, vec![file("/l/b", 300)]);
+        grown.files_partial = true;
+        let d = diff(&old, &snapshot(vec![grown]));
+        assert!(!d.items[0].files_compared);
+        assert!(d.items[0].files.is_empty());
+    }
+}
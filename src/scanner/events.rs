@@ -0,0 +1,183 @@
+//! Typed progress events emitted while a scan runs
+//!
+//! Pass an `EventSink` in `ScanOptions::events` to receive every event as it
+//! happens, either through a callback or a channel. Events may be emitted
+//! from several worker threads at once, but all events for one target
+//! arrive in order: `TargetStarted`, any `FileBatch`es, `TargetFinished`,
+//! then `ItemFound` if the target turned up anything.
+
+use serde::Serialize;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+
+use super::cache::CacheStats;
+use super::limits::Truncation;
+use super::targets::{Category, FileEntry, SafetyLevel, ScanItem};
+
+/// Files are streamed in batches of this size
+pub const FILE_BATCH: usize = 500;
+
+/// Something that happened during a scan
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ScanEvent {
+    /// The scan began; `steps` counts targets plus extra passes
+    Started { profile: String, steps: usize },
+    /// A target or pass is being looked at
+    TargetStarted { target: String },
+    /// Files found so far for a target
+    FileBatch {
+        target: String,
+        files: Vec<FileEntry>,
+    },
+    /// A target or pass is done
+    TargetFinished {
+        target: String,
+        size_bytes: u64,
+        file_count: usize,
+        /// Served from the scan cache or watch daemon rather than walked
+        cached: bool,
+    },
+    /// A reportable item, without its file list (see `FileBatch`)
+    ItemFound {
+        name: String,
+        category: Category,
+        path: std::path::PathBuf,
+        size_bytes: u64,
+        apparent_bytes: u64,
+        freeable_bytes: u64,
+        file_count: usize,
+        safety: SafetyLevel,
+        reason: String,
+    },
+    /// A non-fatal problem
+    Error {
+        target: Option<String>,
+        message: String,
+    },
+    /// The scan completed
+    Done {
+        total_reclaimable: u64,
+        total_freeable: u64,
+        total_files: usize,
+        items: usize,
+        duration_secs: f64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache: Option<CacheStats>,
+        /// Targets whose results are partial because of scan limits
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        truncated: Vec<Truncation>,
+    },
+}
+
+impl ScanEvent {
+    /// Summary event for an item
+    pub fn item_found(item: &ScanItem) -> Self {
+        ScanEvent::ItemFound {
+            name: item.name.clone(),
+            category: item.category.clone(),
+            path: item.path.clone(),
+            size_bytes: item.size_bytes,
+            apparent_bytes: item.apparent_bytes,
+            freeable_bytes: item.freeable_bytes,
+            file_count: item.file_count,
+            safety: item.safety.clone(),
+            reason: item.reason.clone(),
+        }
+    }
+}
+
+type Callback = dyn Fn(ScanEvent) + Send + Sync;
+
+/// Where scan events go; the default sink drops them
+#[derive(Clone, Default)]
+pub struct EventSink {
+    callback: Option<Arc<Callback>>,
+}
+
+impl std::fmt::Debug for EventSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventSink")
+            .field("active", &self.is_active())
+            .finish()
+    }
+}
+
+impl EventSink {
+    /// A sink that drops every event
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Deliver events to `f`, which may be called from several threads
+    pub fn callback(f: impl Fn(ScanEvent) + Send + Sync + 'static) -> Self {
+        Self {
+            callback: Some(Arc::new(f)),
+        }
+    }
+
+    /// Deliver events to a channel; the receiver sees them in emission order
+    pub fn channel() -> (Self, Receiver<ScanEvent>) {
+        let (tx, rx) = mpsc::channel();
+        let tx = std::sync::Mutex::new(tx);
+        let sink = Self::callback(move |event| {
+            // A dropped receiver just means nobody is listening any more
+            let _ = tx.lock().unwrap().send(event);
+        });
+        (sink, rx)
+    }
+
+    /// Whether anyone is listening; lets emitters skip building events
+    pub fn is_active(&self) -> bool {
+        self.callback.is_some()
+    }
+
+    pub fn emit(&self, event: ScanEvent) {
+        if let Some(ref callback) = self.callback {
+            callback(event);
+        }
+    }
+
+    /// Emit `files` as `FileBatch` events
+    pub fn emit_files(&self, target: &str, files: &[FileEntry]) {
+        if !self.is_active() {
+            return;
+        }
+        for chunk in files.chunks(FILE_BATCH) {
+            self.emit(ScanEvent::FileBatch {
+                target: target.to_string(),
+                files: chunk.to_vec(),
+            });
+        }
+    }
+
+    /// Emit the rest of a target's sequence once its `items` are known
+    ///
+    /// Used for targets served from cache and for passes that don't stream
+    /// their files while walking; multi-item passes report one finished
+    /// event with the totals and one `ItemFound` per item.
+    pub fn emit_finished(&self, target: &str, items: &[ScanItem], cached: bool) {
+        if !self.is_active() {
+            return;
+        }
+        for item in items {
+            self.emit_files(target, &item.files);
+        }
+        self.emit(ScanEvent::TargetFinished {
+            target: target.to_string(),
+            size_bytes: items.iter().map(|i| i.size_bytes).sum(),
+            file_count: items.iter().map(|i| i.file_count).sum(),
+            cached,
+        });
+        for item in items.iter().filter(|i| i.size_bytes > 0) {
+            self.emit(ScanEvent::item_found(item));
+        }
+    }
+
+    /// Announce that `target` is starting
+    pub fn emit_started(&self, target: &str) {
+        self.emit(ScanEvent::TargetStarted {
+            target: target.to_string(),
+        });
+    }
+}
@@ -0,0 +1,493 @@
+//! Minimal read-only access to a git repository's metadata.
+//!
+//! Everything here reads files under `.git` directly — HEAD, refs, the
+//! reflog, the index and commit objects — so staleness checks never shell
+//! out to `git`. Anything unexpected (delta-compressed commits, unknown
+//! index versions) yields `None` rather than an error; callers treat missing
+//! information conservatively.
+
+use flate2::read::ZlibDecoder;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Symbolic refs followed before giving up, as in git
+const MAX_SYMREF_DEPTH: usize = 5;
+
+/// Location of a repository on disk
+#[derive(Debug, Clone)]
+pub struct GitRepo {
+    /// Top of the working tree
+    pub work_tree: PathBuf,
+    /// Per-worktree git dir (HEAD, index, logs/)
+    pub git_dir: PathBuf,
+    /// Shared git dir (objects/, refs/, packed-refs)
+    pub common_dir: PathBuf,
+}
+
+impl GitRepo {
+    /// Find the repository enclosing `path`, if any
+    ///
+    /// The home directory itself is never considered: with a dotfiles repo
+    /// there, every project without its own repo would be judged, and
+    /// walked for untracked files, as part of the whole home directory.
+    pub fn discover(path: &Path) -> Option<Self> {
+        Self::discover_below(path, dirs::home_dir().as_deref())
+    }
+
+    fn discover_below(path: &Path, boundary: Option<&Path>) -> Option<Self> {
+        path.ancestors()
+            .take_while(|dir| Some(*dir) != boundary)
+            .find_map(Self::open)
+    }
+
+    /// Open `dir` as a working tree root (it must contain `.git`)
+    pub fn open(dir: &Path) -> Option<Self> {
+        let dot_git = dir.join(".git");
+        let meta = std::fs::symlink_metadata(&dot_git).ok()?;
+
+        let git_dir = if meta.is_dir() {
+            dot_git
+        } else {
+            // Worktrees and submodules use a `gitdir: <path>` file
+            let contents = std::fs::read_to_string(&dot_git).ok()?;
+            let target = contents.trim().strip_prefix("gitdir:")?.trim();
+            dir.join(target)
+        };
+        if !git_dir.join("HEAD").is_file() {
+            return None;
+        }
+
+        let common_dir = std::fs::read_to_string(git_dir.join("commondir"))
+            .ok()
+            .map(|c| git_dir.join(c.trim()))
+            .unwrap_or_else(|| git_dir.clone());
+
+        Some(Self {
+            work_tree: dir.to_path_buf(),
+            git_dir,
+            common_dir,
+        })
+    }
+
+    /// Hex object id HEAD points at, following symbolic refs
+    pub fn head_oid(&self) -> Option<String> {
+        let head = std::fs::read_to_string(self.git_dir.join("HEAD")).ok()?;
+        let head = head.trim();
+        match head.strip_prefix("ref:") {
+            Some(name) => self.resolve_ref(name.trim(), 0),
+            None => is_hex_oid(head).then(|| head.to_string()),
+        }
+    }
+
+    /// Object id of ref `name`; `depth` counts the symbolic refs followed
+    /// so far, so refs pointing at each other end instead of recursing
+    fn resolve_ref(&self, name: &str, depth: usize) -> Option<String> {
+        if depth >= MAX_SYMREF_DEPTH {
+            return None;
+        }
+        for dir in [&self.git_dir, &self.common_dir] {
+            if let Ok(contents) = std::fs::read_to_string(dir.join(name)) {
+                let value = contents.trim();
+                if let Some(next) = value.strip_prefix("ref:") {
+                    return self.resolve_ref(next.trim(), depth + 1);
+                }
+                if is_hex_oid(value) {
+                    return Some(value.to_string());
+                }
+            }
+        }
+
+        let packed = std::fs::read_to_string(self.common_dir.join("packed-refs")).ok()?;
+        packed
+            .lines()
+            .filter(|l| !l.starts_with('#') && !l.starts_with('^'))
+            .filter_map(|l| l.split_once(' '))
+            .find(|(_, r)| *r == name)
+            .filter(|(oid, _)| is_hex_oid(oid))
+            .map(|(oid, _)| oid.to_string())
+    }
+
+    /// Committer time of the HEAD commit
+    pub fn head_commit_time(&self) -> Option<SystemTime> {
+        let oid = self.head_oid()?;
+        let commit = self.read_commit(&oid)?;
+        parse_committer_time(&commit)
+    }
+
+    /// Time of the newest entry in the HEAD reflog (commits, checkouts, ...)
+    pub fn last_reflog_time(&self) -> Option<SystemTime> {
+        let tail = read_tail(&self.git_dir.join("logs/HEAD"), 8192)?;
+        let line = tail.lines().rev().find(|l| !l.trim().is_empty())?;
+        let (who, _msg) = line.split_once('\t').unwrap_or((line, ""));
+        let mut parts = who.rsplit(' ');
+        let _tz = parts.next()?;
+        parts.next()?.parse().ok().map(epoch_secs)
+    }
+
+    /// Whether the working tree has uncommitted changes
+    ///
+    /// Tracked files are compared against the index by size and mtime (whole
+    /// seconds), and any untracked file that isn't ignored counts as a
+    /// change, since new files are work in progress too. An unreadable index
+    /// reports the tree dirty. Index entries are never rehashed, so an edit
+    /// that keeps a file's size within the second it was staged goes
+    /// unnoticed, unlike with `git status`.
+    pub fn is_dirty(&self) -> bool {
+        let Some(entries) = read_index(&self.git_dir.join("index")) else {
+            return true;
+        };
+
+        let mut tracked: HashSet<&[u8]> = HashSet::with_capacity(entries.len());
+        for entry in &entries {
+            tracked.insert(entry.path.as_slice());
+            if entry.is_gitlink() || entry.skip_worktree {
+                continue;
+            }
+            let path = self
+                .work_tree
+                .join(std::ffi::OsStr::from_bytes(&entry.path));
+            match std::fs::symlink_metadata(&path) {
+                Ok(meta) => {
+                    if meta.size() as u32 != entry.size || meta.mtime() as u32 != entry.mtime_secs {
+                        return true;
+                    }
+                }
+                Err(_) => return true,
+            }
+        }
+
+        self.has_untracked_files(&tracked)
+    }
+
+    fn has_untracked_files(&self, tracked: &HashSet<&[u8]>) -> bool {
+        let root = self.work_tree.clone();
+        let walker = ignore::WalkBuilder::new(&self.work_tree)
+            .hidden(false)
+            .ignore(false)
+            .git_ignore(true)
+            .git_exclude(true)
+            .git_global(true)
+            .require_git(false)
+            .parents(false)
+            .filter_entry(move |e| {
+                // Skip our own .git and any nested repositories
+                e.file_name() != ".git" && (e.path() == root || !e.path().join(".git").exists())
+            })
+            .build();
+
+        for entry in walker.filter_map(|e| e.ok()) {
+            if !entry.file_type().map(|t| !t.is_dir()).unwrap_or(false) {
+                continue;
+            }
+            let Ok(rel) = entry.path().strip_prefix(&self.work_tree) else {
+                continue;
+            };
+            if !tracked.contains(rel.as_os_str().as_bytes()) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Decompressed body of a commit object, from loose or packed storage
+    fn read_commit(&self, oid: &str) -> Option<Vec<u8>> {
+        if !is_hex_oid(oid) {
+            return None;
+        }
+        let objects = self.common_dir.join("objects");
+        let loose = objects.join(&oid[..2]).join(&oid[2..]);
+        if let Ok(file) = File::open(&loose) {
+            let mut raw = Vec::new();
+            ZlibDecoder::new(file).read_to_end(&mut raw).ok()?;
+            let nul = raw.iter().position(|&b| b == 0)?;
+            if !raw.starts_with(b"commit ") {
+                return None;
+            }
+            return Some(raw[nul + 1..].to_vec());
+        }
+
+        let oid_bytes = decode_hex(oid)?;
+        let packs = std::fs::read_dir(objects.join("pack")).ok()?;
+        for pack in packs.filter_map(|e| e.ok()) {
+            let idx_path = pack.path();
+            if idx_path.extension().map(|e| e != "idx").unwrap_or(true) {
+                continue;
+            }
+            if let Some(offset) = find_in_pack_index(&idx_path, &oid_bytes) {
+                return read_packed_commit(&idx_path.with_extension("pack"), offset);
+            }
+        }
+        None
+    }
+}
+
+// ─── Index ────────────────────────────────────────────────────────────────────
+
+/// The parts of an index entry needed to spot modifications
+#[derive(Debug, Clone)]
+pub struct IndexEntry {
+    pub path: Vec<u8>,
+    pub mtime_secs: u32,
+    pub size: u32,
+    pub mode: u32,
+    pub skip_worktree: bool,
+}
+
+impl IndexEntry {
+    fn is_gitlink(&self) -> bool {
+        self.mode & 0o170000 == 0o160000
+    }
+}
+
+/// Parse a version 2, 3 or 4 index file
+pub fn read_index(path: &Path) -> Option<Vec<IndexEntry>> {
+    let data = std::fs::read(path).ok()?;
+    if data.len() < 12 || &data[..4] != b"DIRC" {
+        return None;
+    }
+    let version = be_u32(&data[4..8]);
+    if !(2..=4).contains(&version) {
+        return None;
+    }
+    let count = be_u32(&data[8..12]) as usize;
+
+    let mut entries = Vec::with_capacity(count);
+    let mut pos = 12;
+    let mut prev_path: Vec<u8> = Vec::new();
+
+    for _ in 0..count {
+        let start = pos;
+        let fixed = data.get(pos..pos + 62)?;
+        let mtime_secs = be_u32(&fixed[8..12]);
+        let mode = be_u32(&fixed[24..28]);
+        let size = be_u32(&fixed[36..40]);
+        let flags = u16::from_be_bytes([fixed[60], fixed[61]]);
+        pos += 62;
+
+        let mut skip_worktree = false;
+        if version >= 3 && flags & 0x4000 != 0 {
+            let ext = data.get(pos..pos + 2)?;
+            skip_worktree = u16::from_be_bytes([ext[0], ext[1]]) & 0x4000 != 0;
+            pos += 2;
+        }
+
+        let path = if version == 4 {
+            let (strip, used) = read_offset_varint(data.get(pos..)?)?;
+            pos += used;
+            let end = pos + data.get(pos..)?.iter().position(|&b| b == 0)?;
+            let keep = prev_path.len().checked_sub(strip)?;
+            let mut path = prev_path[..keep].to_vec();
+            path.extend_from_slice(&data[pos..end]);
+            pos = end + 1;
+            path
+        } else {
+            let end = pos + data.get(pos..)?.iter().position(|&b| b == 0)?;
+            let path = data[pos..end].to_vec();
+            // Entries are NUL-padded to a multiple of 8 bytes
+            pos = start + ((end - start + 8) & !7);
+            path
+        };
+
+        prev_path.clone_from(&path);
+        entries.push(IndexEntry {
+            path,
+            mtime_secs,
+            size,
+            mode,
+            skip_worktree,
+        });
+    }
+
+    Some(entries)
+}
+
+/// Git's offset varint, used for index v4 path prefix lengths
+fn read_offset_varint(buf: &[u8]) -> Option<(usize, usize)> {
+    let mut i = 0;
+    let mut byte = *buf.get(i)?;
+    let mut value = (byte & 0x7f) as usize;
+    while byte & 0x80 != 0 {
+        i += 1;
+        byte = *buf.get(i)?;
+        value = ((value + 1) << 7) | (byte & 0x7f) as usize;
+    }
+    Some((value, i + 1))
+}
+
+// ─── Pack files ───────────────────────────────────────────────────────────────
+
+/// Look up an object's pack offset in a version 2 `.idx` file
+fn find_in_pack_index(idx_path: &Path, oid: &[u8]) -> Option<u64> {
+    let idx = std::fs::read(idx_path).ok()?;
+    if idx.len() < 8 + 256 * 4 || idx[..4] != [0xff, b't', b'O', b'c'] || be_u32(&idx[4..8]) != 2 {
+        return None;
+    }
+
+    let fanout = |i: usize| be_u32(&idx[8 + i * 4..12 + i * 4]) as usize;
+    let total = fanout(255);
+    let first = oid[0] as usize;
+    let lo = if first == 0 { 0 } else { fanout(first - 1) };
+    let hi = fanout(first);
+
+    let names = 8 + 256 * 4;
+    let sha_at = |i: usize| idx.get(names + i * 20..names + i * 20 + 20);
+    let mut range = lo..hi;
+    let pos = loop {
+        if range.is_empty() {
+            return None;
+        }
+        let mid = range.start + range.len() / 2;
+        match sha_at(mid)?.cmp(oid) {
+            std::cmp::Ordering::Equal => break mid,
+            std::cmp::Ordering::Less => range.start = mid + 1,
+            std::cmp::Ordering::Greater => range.end = mid,
+        }
+    };
+
+    let offsets = names + total * 20 + total * 4;
+    let offset = be_u32(idx.get(offsets + pos * 4..offsets + pos * 4 + 4)?);
+    if offset & 0x8000_0000 == 0 {
+        return Some(offset as u64);
+    }
+    let large = offsets + total * 4 + (offset & 0x7fff_ffff) as usize * 8;
+    let bytes = idx.get(large..large + 8)?;
+    Some(u64::from_be_bytes(bytes.try_into().ok()?))
+}
+
+/// Read a non-deltified commit object at `offset` in a pack
+fn read_packed_commit(pack_path: &Path, offset: u64) -> Option<Vec<u8>> {
+    let mut file = File::open(pack_path).ok()?;
+    file.seek(SeekFrom::Start(offset)).ok()?;
+
+    let mut byte = [0u8; 1];
+    file.read_exact(&mut byte).ok()?;
+    let kind = (byte[0] >> 4) & 0x7;
+    while byte[0] & 0x80 != 0 {
+        file.read_exact(&mut byte).ok()?;
+    }
+    // 1 = commit; deltified objects (6, 7) would need their base chain
+    if kind != 1 {
+        return None;
+    }
+
+    let mut body = Vec::new();
+    ZlibDecoder::new(file).read_to_end(&mut body).ok()?;
+    Some(body)
+}
+
+// ─── Helpers ──────────────────────────────────────────────────────────────────
+
+fn parse_committer_time(commit: &[u8]) -> Option<SystemTime> {
+    let text = String::from_utf8_lossy(commit);
+    let line = text
+        .lines()
+        .take_while(|l| !l.is_empty())
+        .find(|l| l.starts_with("committer "))?;
+    let mut parts = line.rsplit(' ');
+    let _tz = parts.next()?;
+    parts.next()?.parse().ok().map(epoch_secs)
+}
+
+fn read_tail(path: &Path, max_bytes: u64) -> Option<String> {
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(max_bytes)))
+        .ok()?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf).ok()?;
+    Some(String::from_utf8_lossy(&buf).into_owned())
+}
+
+fn epoch_secs(secs: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+fn be_u32(b: &[u8]) -> u32 {
+    u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+}
+
+fn is_hex_oid(s: &str) -> bool {
+    s.len() == 40 && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !is_hex_oid(s) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset_varint() {
+        assert_eq!(read_offset_varint(&[0x05]), Some((5, 1)));
+        // 0x80 0x00 encodes 128 in git's offset encoding
+        assert_eq!(read_offset_varint(&[0x80, 0x00]), Some((128, 2)));
+    }
+
+    #[test]
+    fn test_parse_committer_time() {
+        let commit =
+            b"tree abc\nauthor A <a@x> 1600000000 +0000\ncommitter C <c@x> 1700000000 +0100\n\nmsg";
+        assert_eq!(
+            parse_committer_time(commit),
+            Some(epoch_secs(1_700_000_000))
+        );
+    }
+
+    /// A work tree whose `.git` holds just HEAD and `files`
+    fn bare_repo(files: &[(&str, &str)]) -> (tempfile::TempDir, GitRepo) {
+        let dir = tempfile::tempdir().unwrap();
+        let git_dir = dir.path().join(".git");
+        std::fs::create_dir_all(&git_dir).unwrap();
+        std::fs::write(git_dir.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+        for (name, contents) in files {
+            let path = git_dir.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+        let repo = GitRepo::open(dir.path()).unwrap();
+        (dir, repo)
+    }
+
+    #[test]
+    fn test_damaged_packed_refs_yield_nothing() {
+        for oid in ["ab", "é", "zz3456789012345678901234567890123456789z"] {
+            let packed = format!("{} refs/heads/main\n", oid);
+            let (_dir, repo) = bare_repo(&[("packed-refs", &packed)]);
+            assert_eq!(repo.head_oid(), None);
+            assert_eq!(repo.head_commit_time(), None);
+        }
+        let (_dir, repo) = bare_repo(&[]);
+        assert_eq!(repo.read_commit("é"), None);
+    }
+
+    #[test]
+    fn test_discovery_stops_below_the_boundary() {
+        let (dir, _repo) = bare_repo(&[]);
+        let project = dir.path().join("code/project");
+        std::fs::create_dir_all(&project).unwrap();
+        assert!(GitRepo::discover_below(&project, None).is_some());
+        assert!(GitRepo::discover_below(&project, Some(dir.path())).is_none());
+    }
+
+    #[test]
+    fn test_symbolic_ref_loops_yield_nothing() {
+        let (_dir, repo) = bare_repo(&[
+            ("refs/heads/main", "ref: refs/heads/other\n"),
+            ("refs/heads/other", "ref: refs/heads/main\n"),
+        ]);
+        assert_eq!(repo.head_oid(), None);
+    }
+}
@@ -0,0 +1,432 @@
+//! Scan history and growth trends
+//!
+//! Every recorded scan appends one JSON line of per-target totals to
+//! `~/.tidymac/history/scans.jsonl`. `trend` reads the lines back and works
+//! out how each target and category grew, which caches grow fastest, and
+//! which targets grew by more than a threshold within a single day.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use crate::common::config::Config;
+use crate::scanner::targets::{Category, ScanResults};
+
+/// On-disk format version; lines written with any other version are skipped
+pub const HISTORY_VERSION: u32 = 1;
+
+/// Records older than this are dropped when the file is compacted
+pub const RETENTION_DAYS: i64 = 365;
+
+/// The history file is compacted once it grows past this size
+const COMPACT_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Totals for one target in one scan
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TargetTotal {
+    pub name: String,
+    pub category: Category,
+    pub size_bytes: u64,
+    pub file_count: usize,
+    /// Scan limits cut this target short, so its size is a lower bound
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+/// One scan as kept in the history file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanRecord {
+    pub version: u32,
+    pub timestamp: DateTime<Utc>,
+    pub profile: String,
+    pub duration_secs: f64,
+    pub targets: Vec<TargetTotal>,
+}
+
+impl ScanRecord {
+    /// Per-target totals of `results`; items sharing a name and category
+    /// are summed
+    pub fn from_results(results: &ScanResults, profile: &str) -> Self {
+        let mut totals: BTreeMap<(String, String), TargetTotal> = BTreeMap::new();
+        for item in &results.items {
+            let truncated = results.truncated.iter().any(|t| t.target == item.name);
+            let total = totals
+                .entry((item.category.slug(), item.name.clone()))
+                .or_insert_with(|| TargetTotal {
+                    name: item.name.clone(),
+                    category: item.category.clone(),
+                    size_bytes: 0,
+                    file_count: 0,
+                    truncated,
+                });
+            total.size_bytes += item.size_bytes;
+            total.file_count += item.file_count;
+        }
+        Self {
+            version: HISTORY_VERSION,
+            timestamp: results.timestamp,
+            profile: profile.to_string(),
+            duration_secs: results.duration_secs,
+            targets: totals.into_values().collect(),
+        }
+    }
+}
+
+/// The append-only history file
+#[derive(Debug, Clone)]
+pub struct ScanHistory {
+    path: PathBuf,
+}
+
+impl ScanHistory {
+    /// History under `Config::data_dir()`
+    pub fn open() -> Self {
+        Self::at(Config::data_dir().join("history").join("scans.jsonl"))
+    }
+
+    /// History kept in a specific file
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append one scan, compacting the file first if it has grown large
+    pub fn append(&self, record: &ScanRecord) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create history dir: {}", dir.display()))?;
+        }
+        if std::fs::metadata(&self.path).is_ok_and(|m| m.len() > COMPACT_BYTES) {
+            self.compact(Utc::now() - Duration::days(RETENTION_DAYS))?;
+        }
+
+        let mut line = serde_json::to_string(record).context("Failed to serialize scan record")?;
+        line.push('\n');
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open history: {}", self.path.display()))?;
+        // One write per record, so concurrent scans never interleave lines
+        file.write_all(line.as_bytes())
+            .with_context(|| format!("Failed to write history: {}", self.path.display()))?;
+        Ok(())
+    }
+
+    /// Records taken at or after `since`, oldest first
+    ///
+    /// Lines that can't be parsed (a torn write, an older format) are skipped.
+    pub fn load(&self, since: Option<DateTime<Utc>>) -> Result<Vec<ScanRecord>> {
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read history: {}", self.path.display()))
+            }
+        };
+        let mut records: Vec<ScanRecord> = BufReader::new(file)
+            .lines()
+            .map_while(|line| line.ok())
+            .filter_map(|line| serde_json::from_str::<ScanRecord>(&line).ok())
+            .filter(|r| r.version == HISTORY_VERSION)
+            .filter(|r| since.is_none_or(|since| r.timestamp >= since))
+            .collect();
+        records.sort_by_key(|r| r.timestamp);
+        Ok(records)
+    }
+
+    /// Rewrite the file without records older than `cutoff`
+    pub fn compact(&self, cutoff: DateTime<Utc>) -> Result<()> {
+        let records = self.load(Some(cutoff))?;
+        let mut contents = String::new();
+        for record in &records {
+            contents.push_str(&serde_json::to_string(record)?);
+            contents.push('\n');
+        }
+        let tmp = self.path.with_extension("jsonl.tmp");
+        std::fs::write(&tmp, contents)
+            .with_context(|| format!("Failed to write history: {}", tmp.display()))?;
+        std::fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Failed to replace history: {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+/// Append `results` to the default history, logging rather than failing
+pub fn record(results: &ScanResults, profile: &str) {
+    if let Err(e) = ScanHistory::open().append(&ScanRecord::from_results(results, profile)) {
+        tracing::warn!("Could not record scan history: {}", e);
+    }
+}
+
+// ─── Trends ──────────────────────────────────────────────────────────────────
+
+/// What `trend` looks at
+#[derive(Debug, Clone)]
+pub struct TrendOptions {
+    /// Only records from this many days back
+    pub days: u32,
+    /// How many of the fastest-growing targets to rank
+    pub top: usize,
+    /// Growth within 24 hours that marks a target as a runaway writer
+    pub runaway_bytes: u64,
+    /// Only records of this profile (all profiles when `None`)
+    pub profile: Option<String>,
+}
+
+/// Growth of one category over the window
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryTrend {
+    pub category: Category,
+    pub first_bytes: u64,
+    pub last_bytes: u64,
+    pub growth_bytes: i64,
+}
+
+/// Growth of one target over the window
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetTrend {
+    pub name: String,
+    pub category: Category,
+    pub first_bytes: u64,
+    pub last_bytes: u64,
+    pub growth_bytes: i64,
+    /// Growth averaged over the days between the first and last sample
+    /// (at least one day)
+    pub growth_per_day: i64,
+    pub samples: usize,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// A target that grew by more than the threshold within 24 hours
+#[derive(Debug, Clone, Serialize)]
+pub struct RunawayWriter {
+    pub name: String,
+    pub category: Category,
+    pub growth_bytes: u64,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+/// Everything `tidymac trend` reports
+#[derive(Debug, Clone, Serialize)]
+pub struct TrendReport {
+    pub since: DateTime<Utc>,
+    pub scans: usize,
+    pub runaway_threshold_bytes: u64,
+    /// Largest growth first
+    pub categories: Vec<CategoryTrend>,
+    /// Fastest-growing first, at most `TrendOptions::top`
+    pub fastest_growing: Vec<TargetTrend>,
+    /// Largest one-day growth first
+    pub runaway: Vec<RunawayWriter>,
+}
+
+/// One target's sizes over time
+struct Series {
+    category: Category,
+    samples: Vec<(DateTime<Utc>, u64)>,
+}
+
+/// Work out growth from history records (oldest first)
+///
+/// Samples of truncated targets are left out since their sizes are only
+/// lower bounds. A target missing from a scan (another profile, a
+/// `--categories` filter) simply has no sample there.
+pub fn trend(records: &[ScanRecord], opts: &TrendOptions) -> TrendReport {
+    let since = Utc::now() - Duration::days(i64::from(opts.days));
+    let records: Vec<&ScanRecord> = records
+        .iter()
+        .filter(|r| r.timestamp >= since)
+        .filter(|r| opts.profile.as_ref().is_none_or(|p| *p == r.profile))
+        .collect();
+
+    let mut series: BTreeMap<(String, String), Series> = BTreeMap::new();
+    for record in &records {
+        for total in record.targets.iter().filter(|t| !t.truncated) {
+            series
+                .entry((total.category.slug(), total.name.clone()))
+                .or_insert_with(|| Series {
+                    category: total.category.clone(),
+                    samples: Vec::new(),
+                })
+                .samples
+                .push((record.timestamp, total.size_bytes));
+        }
+    }
+
+    let mut categories: BTreeMap<String, CategoryTrend> = BTreeMap::new();
+    let mut targets = Vec::new();
+    let mut runaway = Vec::new();
+    for ((slug, name), s) in &series {
+        let (first_seen, first_bytes) = s.samples[0];
+        let (last_seen, last_bytes) = s.samples[s.samples.len() - 1];
+        let growth_bytes = last_bytes as i64 - first_bytes as i64;
+
+        let category = categories
+            .entry(slug.clone())
+            .or_insert_with(|| CategoryTrend {
+                category: s.category.clone(),
+                first_bytes: 0,
+                last_bytes: 0,
+                growth_bytes: 0,
+            });
+        category.first_bytes += first_bytes;
+        category.last_bytes += last_bytes;
+        category.growth_bytes += growth_bytes;
+
+        if s.samples.len() < 2 {
+            continue;
+        }
+        let days = ((last_seen - first_seen).num_seconds() as f64 / 86400.0).max(1.0);
+        targets.push(TargetTrend {
+            name: name.clone(),
+            category: s.category.clone(),
+            first_bytes,
+            last_bytes,
+            growth_bytes,
+            growth_per_day: (growth_bytes as f64 / days) as i64,
+            samples: s.samples.len(),
+            first_seen,
+            last_seen,
+        });
+
+        if let Some((growth, from, to)) = max_daily_growth(&s.samples) {
+            if growth > opts.runaway_bytes {
+                runaway.push(RunawayWriter {
+                    name: name.clone(),
+                    category: s.category.clone(),
+                    growth_bytes: growth,
+                    from,
+                    to,
+                });
+            }
+        }
+    }
+
+    let mut categories: Vec<CategoryTrend> = categories.into_values().collect();
+    categories.sort_by_key(|c| Reverse(c.growth_bytes));
+    targets.retain(|t| t.growth_bytes > 0);
+    targets.sort_by_key(|t| Reverse(t.growth_per_day));
+    targets.truncate(opts.top);
+    runaway.sort_by_key(|r| Reverse(r.growth_bytes));
+
+    TrendReport {
+        since,
+        scans: records.len(),
+        runaway_threshold_bytes: opts.runaway_bytes,
+        categories,
+        fastest_growing: targets,
+        runaway,
+    }
+}
+
+/// Largest growth between two samples at most 24 hours apart
+fn max_daily_growth(
+    samples: &[(DateTime<Utc>, u64)],
+) -> Option<(u64, DateTime<Utc>, DateTime<Utc>)> {
+    let mut best: Option<(u64, DateTime<Utc>, DateTime<Utc>)> = None;
+    for (i, &(to, to_bytes)) in samples.iter().enumerate() {
+        for &(from, from_bytes) in samples[..i].iter().rev() {
+            if to - from > Duration::hours(24) {
+                break;
+            }
+            let growth = to_bytes.saturating_sub(from_bytes);
+            if growth > 0 && best.is_none_or(|(b, _, _)| growth > b) {
+                best = Some((growth, from, to));
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GB: u64 = 1024 * 1024 * 1024;
+
+    fn record(hours_ago: i64, targets: &[(&str, Category, u64)]) -> ScanRecord {
+        ScanRecord {
+            version: HISTORY_VERSION,
+            timestamp: Utc::now() - Duration::hours(hours_ago),
+            profile: "quick".to_string(),
+            duration_secs: 1.0,
+            targets: targets
+                .iter()
+                .map(|(name, category, size)| TargetTotal {
+                    name: name.to_string(),
+                    category: category.clone(),
+                    size_bytes: *size,
+                    file_count: 1,
+                    truncated: false,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_trend_ranks_growth_and_flags_runaway_writers() {
+        let records = vec![
+            record(72, &[("Logs", Category::Logs, GB), ("Caches", Category::UserCache, GB)]),
+            record(48, &[("Logs", Category::Logs, GB), ("Caches", Category::UserCache, 2 * GB)]),
+            // Logs jump by 6 GB overnight
+            record(30, &[("Logs", Category::Logs, GB), ("Caches", Category::UserCache, 3 * GB)]),
+            record(20, &[("Logs", Category::Logs, 7 * GB), ("Caches", Category::UserCache, 4 * GB)]),
+        ];
+        let report = trend(
+            &records,
+            &TrendOptions {
+                days: 30,
+                top: 10,
+                runaway_bytes: 5 * GB,
+                profile: None,
+            },
+        );
+
+        assert_eq!(report.scans, 4);
+        assert_eq!(report.fastest_growing[0].name, "Logs");
+        assert_eq!(report.fastest_growing[0].growth_bytes, 6 * GB as i64);
+        assert_eq!(report.fastest_growing[1].name, "Caches");
+        assert_eq!(report.categories[0].category, Category::Logs);
+
+        assert_eq!(report.runaway.len(), 1);
+        assert_eq!(report.runaway[0].name, "Logs");
+        assert_eq!(report.runaway[0].growth_bytes, 6 * GB);
+    }
+
+    #[test]
+    fn test_trend_ignores_truncated_samples_and_other_profiles() {
+        let mut partial = record(1, &[("Caches", Category::UserCache, 0)]);
+        partial.targets[0].truncated = true;
+        let mut other = record(2, &[("Caches", Category::UserCache, 100 * GB)]);
+        other.profile = "deep".to_string();
+        let records = vec![
+            record(10, &[("Caches", Category::UserCache, GB)]),
+            other,
+            partial,
+        ];
+        let report = trend(
+            &records,
+            &TrendOptions {
+                days: 30,
+                top: 10,
+                runaway_bytes: GB,
+                profile: Some("quick".to_string()),
+            },
+        );
+        assert_eq!(report.scans, 2);
+        assert!(report.fastest_growing.is_empty());
+        assert!(report.runaway.is_empty());
+        assert_eq!(report.categories[0].last_bytes, GB);
+    }
+}
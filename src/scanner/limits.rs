@@ -0,0 +1,239 @@
+//! Resource limits for a scan run
+//!
+//! `ScanLimits` is what a profile's `[limits]` table or the scan flags ask
+//! for; `ScanBudget` applies them to one run and records every target that
+//! stopped early, so partial results can be labelled as such.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Bounds on how much work a scan may do
+///
+/// ```toml
+/// [limits]
+/// time_budget_secs = 120
+/// max_files = 200000      # per target
+/// max_depth = 12
+/// one_file_system = true
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanLimits {
+    /// Wall-clock budget for the whole scan
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_budget_secs: Option<u64>,
+    /// Files looked at per target before its walk stops
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_files: Option<usize>,
+    /// Directory levels below each root that are walked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<usize>,
+    /// Don't cross mount points, and skip roots on network filesystems
+    #[serde(default)]
+    pub one_file_system: bool,
+}
+
+impl ScanLimits {
+    /// `self` with every limit set in `overrides` taking precedence
+    pub fn with_overrides(&self, overrides: &ScanLimits) -> ScanLimits {
+        ScanLimits {
+            time_budget_secs: overrides.time_budget_secs.or(self.time_budget_secs),
+            max_files: overrides.max_files.or(self.max_files),
+            max_depth: overrides.max_depth.or(self.max_depth),
+            one_file_system: self.one_file_system || overrides.one_file_system,
+        }
+    }
+}
+
+/// Why a target's results are incomplete
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum TruncationReason {
+    /// The scan's time budget ran out
+    TimeBudget { secs: u64 },
+    /// The target had more files than `max_files`
+    MaxFiles { limit: usize },
+    /// Directories below `max_depth` were not walked
+    MaxDepth { limit: usize },
+    /// Mount points inside the target were not crossed
+    OtherFilesystem,
+    /// A root lives on a network filesystem and was skipped
+    NetworkFilesystem { path: PathBuf },
+}
+
+impl std::fmt::Display for TruncationReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TruncationReason::TimeBudget { secs } => {
+                write!(f, "time budget of {}s ran out", secs)
+            }
+            TruncationReason::MaxFiles { limit } => write!(f, "stopped after {} files", limit),
+            TruncationReason::MaxDepth { limit } => {
+                write!(f, "directories deeper than {} levels skipped", limit)
+            }
+            TruncationReason::OtherFilesystem => write!(f, "other filesystems not crossed"),
+            TruncationReason::NetworkFilesystem { path } => {
+                write!(f, "network filesystem skipped: {}", path.display())
+            }
+        }
+    }
+}
+
+/// A target whose results are incomplete
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Truncation {
+    pub target: String,
+    #[serde(flatten)]
+    pub reason: TruncationReason,
+}
+
+/// `ScanLimits` applied to one scan run
+#[derive(Debug)]
+pub struct ScanBudget {
+    limits: ScanLimits,
+    deadline: Option<Instant>,
+    truncated: Mutex<Vec<Truncation>>,
+}
+
+impl Default for ScanBudget {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
+
+impl ScanBudget {
+    /// Start the clock on `limits`
+    pub fn new(limits: &ScanLimits) -> Self {
+        Self {
+            limits: limits.clone(),
+            deadline: limits
+                .time_budget_secs
+                .map(|secs| Instant::now() + Duration::from_secs(secs)),
+            truncated: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn unlimited() -> Self {
+        Self::new(&ScanLimits::default())
+    }
+
+    pub fn limits(&self) -> &ScanLimits {
+        &self.limits
+    }
+
+    /// Whether the time budget has run out
+    pub fn expired(&self) -> bool {
+        self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+
+    /// Record that the time budget stopped `target`
+    pub fn record_expired(&self, target: &str) {
+        let secs = self.limits.time_budget_secs.unwrap_or_default();
+        self.record(target, TruncationReason::TimeBudget { secs });
+    }
+
+    /// Record that `target` stopped early; repeats are ignored
+    pub fn record(&self, target: &str, reason: TruncationReason) {
+        let mut truncated = self.truncated.lock().unwrap();
+        if !truncated
+            .iter()
+            .any(|t| t.target == target && t.reason == reason)
+        {
+            tracing::info!("Scan of '{}' truncated: {}", target, reason);
+            truncated.push(Truncation {
+                target: target.to_string(),
+                reason,
+            });
+        }
+    }
+
+    pub fn is_truncated(&self, target: &str) -> bool {
+        self.truncated
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|t| t.target == target)
+    }
+
+    /// Everything recorded so far, in order
+    pub fn truncations(&self) -> Vec<Truncation> {
+        self.truncated.lock().unwrap().clone()
+    }
+}
+
+/// Whether `path` lives on a network filesystem (SMB, NFS, AFP, WebDAV, ...)
+#[cfg(target_os = "macos")]
+pub fn is_network_filesystem(path: &Path) -> bool {
+    const MNT_LOCAL: u32 = 0x0000_1000;
+    statfs(path).is_some_and(|st| st.f_flags & MNT_LOCAL == 0)
+}
+
+/// Whether `path` lives on a network filesystem (SMB, NFS, AFP, WebDAV, ...)
+#[cfg(target_os = "linux")]
+pub fn is_network_filesystem(path: &Path) -> bool {
+    const NETWORK_MAGIC: &[i64] = &[
+        0x6969,      // NFS
+        0x517B,      // SMB
+        0xFF53_4D42, // CIFS
+        0xFE53_4D42, // SMB2
+        0x0102_1997, // 9P
+        0x7375_7245, // Coda
+        0x5346_414F, // AFS
+    ];
+    // f_type is narrower than i64 on some architectures
+    #[allow(clippy::useless_conversion)]
+    statfs(path).is_some_and(|st| NETWORK_MAGIC.contains(&i64::from(st.f_type)))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+pub fn is_network_filesystem(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn statfs(path: &Path) -> Option<libc::statfs> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut st: libc::statfs = unsafe { std::mem::zeroed() };
+    let rc = unsafe { libc::statfs(c_path.as_ptr(), &mut st) };
+    (rc == 0).then_some(st)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_win_and_repeats_are_ignored() {
+        let profile = ScanLimits {
+            time_budget_secs: Some(60),
+            max_depth: Some(4),
+            ..Default::default()
+        };
+        let cli = ScanLimits {
+            max_depth: Some(2),
+            one_file_system: true,
+            ..Default::default()
+        };
+        let merged = profile.with_overrides(&cli);
+        assert_eq!(merged.time_budget_secs, Some(60));
+        assert_eq!(merged.max_depth, Some(2));
+        assert!(merged.one_file_system);
+
+        let budget = ScanBudget::new(&merged);
+        assert!(!budget.expired());
+        budget.record("Logs", TruncationReason::MaxDepth { limit: 2 });
+        budget.record("Logs", TruncationReason::MaxDepth { limit: 2 });
+        assert_eq!(budget.truncations().len(), 1);
+        assert!(budget.is_truncated("Logs"));
+        assert!(!budget.is_truncated("Trash"));
+
+        let expired = ScanBudget::new(&ScanLimits {
+            time_budget_secs: Some(0),
+            ..Default::default()
+        });
+        assert!(expired.expired());
+    }
+}
@@ -1,15 +1,129 @@
+pub mod aggregate;
 pub mod cache;
 pub mod dev_detector;
+pub mod diff;
 pub mod docker;
+pub mod events;
+pub mod git;
+pub mod history;
+pub mod limits;
+pub mod snapshot;
+pub mod staleness;
 pub mod targets;
 pub mod walker;
 
 use anyhow::Result;
 use indicatif::{ProgressBar, ProgressStyle};
-use std::time::Instant;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
 
-use cache::ScanCache;
-use targets::{ScanResults, ScanTarget};
+use crate::common::cancel::CancelToken;
+use crate::common::config::Config;
+use crate::common::exclusions::ExclusionMatcher;
+use crate::profiles::loader::Profile;
+use crate::watch::state::WatchState;
+use cache::{ScanCache, TargetSnapshot};
+use events::{EventSink, ScanEvent};
+use limits::{ScanBudget, ScanLimits};
+use staleness::StalenessOracle;
+use targets::{Category, CategoryFilter, DevTool, LargeFileKind, ScanResults, ScanTarget};
+
+/// Target names used in events for the passes that aren't scan targets
+pub const PASS_NODE_MODULES: &str = "Stale node_modules";
+pub const PASS_VENVS: &str = "Stale Python venvs";
+pub const PASS_ARTIFACTS: &str = "Stale project build artifacts";
+pub const PASS_LARGE_FILES: &str = "Large files";
+
+/// Settings for the large-file pass
+#[derive(Debug, Clone)]
+pub struct LargeFileScan {
+    /// Directories to search (already `~`-expanded)
+    pub roots: Vec<PathBuf>,
+    /// Minimum file size in bytes
+    pub threshold_bytes: u64,
+    /// Only report files not modified for at least this many days
+    pub min_age_days: Option<u32>,
+}
+
+/// Options controlling a scan run
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// Show an indicatif progress bar
+    pub show_progress: bool,
+    /// Look for stale node_modules / venvs in project directories
+    pub include_dev_projects: bool,
+    /// Look for build artifacts (target/, build/, ...) of stale projects
+    pub include_project_artifacts: bool,
+    /// Days without changes before a project directory counts as stale
+    pub stale_days: u32,
+    /// Large-file pass settings (skipped when `None`)
+    pub large_files: Option<LargeFileScan>,
+    /// Serve unchanged targets from the scan cache
+    pub use_cache: bool,
+    /// Profile name the cache is keyed by
+    pub profile_name: String,
+    /// Only scan these categories (everything when `None`)
+    pub categories: Option<CategoryFilter>,
+    /// Paths pruned from every walk (from `Config.exclude_paths`)
+    pub excludes: ExclusionMatcher,
+    /// Receives progress events while the scan runs
+    pub events: EventSink,
+    /// Stops the scan early; a cancelled scan returns `TidyError::Cancelled`
+    pub cancel: CancelToken,
+    /// Time, file-count, depth and filesystem bounds; targets cut short are
+    /// listed in `ScanResults::truncated`
+    pub limits: ScanLimits,
+    /// Keep only this many of the largest files per target item, so memory
+    /// stays bounded on huge trees; `None` keeps every file (needed to clean)
+    pub top_files: Option<usize>,
+    /// Append per-target totals to the scan history used by `trend`
+    pub record_history: bool,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            show_progress: false,
+            include_dev_projects: false,
+            include_project_artifacts: false,
+            stale_days: 30,
+            large_files: None,
+            use_cache: true,
+            profile_name: "quick".to_string(),
+            categories: None,
+            excludes: ExclusionMatcher::empty(),
+            events: EventSink::none(),
+            cancel: CancelToken::new(),
+            limits: ScanLimits::default(),
+            top_files: None,
+            record_history: false,
+        }
+    }
+}
+
+impl ScanOptions {
+    /// Options for scanning with `profile`, honouring the configured exclusions
+    pub fn for_profile(profile: &Profile, config: &Config) -> Result<Self> {
+        Ok(Self {
+            include_dev_projects: profile.includes_dev_projects(),
+            include_project_artifacts: profile.includes_project_artifacts(),
+            stale_days: profile.thresholds.stale_days,
+            large_files: profile.large_file_scan(),
+            profile_name: profile.profile.name.clone(),
+            excludes: config.exclusions()?,
+            limits: profile.limits.clone(),
+            ..Self::default()
+        })
+    }
+
+    /// Whether a category passes the `categories` filter
+    pub fn wants(&self, category: &Category) -> bool {
+        self.categories
+            .as_ref()
+            .map(|f| f.matches(category))
+            .unwrap_or(true)
+    }
+}
 
 /// Main scan orchestrator - runs all targets and collects results
 pub fn run_scan(
@@ -30,7 +144,7 @@ pub fn run_scan(
     )
 }
 
-/// Main scan with cache control
+/// Main scan with cache control, honouring the configured `exclude_paths`
 pub fn run_scan_with_cache(
     targets: &[ScanTarget],
     show_progress: bool,
@@ -40,18 +154,95 @@ pub fn run_scan_with_cache(
     use_cache: bool,
     profile_name: &str,
 ) -> Result<ScanResults> {
-    let start = Instant::now();
-    let mut results = ScanResults::new();
+    run_scan_with_options(
+        targets,
+        &ScanOptions {
+            show_progress,
+            include_dev_projects,
+            include_project_artifacts: false,
+            stale_days,
+            large_files: Some(LargeFileScan {
+                roots: vec![dirs::home_dir().unwrap_or_default()],
+                threshold_bytes: large_file_threshold,
+                min_age_days: None,
+            }),
+            use_cache,
+            profile_name: profile_name.to_string(),
+            categories: None,
+            excludes: Config::load()?.exclusions()?,
+            events: EventSink::none(),
+            cancel: CancelToken::new(),
+            limits: ScanLimits::default(),
+            top_files: None,
+            record_history: false,
+        },
+    )
+}
 
-    // Load or create cache
-    let mut scan_cache = if use_cache {
-        ScanCache::load(profile_name).unwrap_or_else(|| ScanCache::new(profile_name))
+/// Main scan with full option control
+pub fn run_scan_with_options(targets: &[ScanTarget], opts: &ScanOptions) -> Result<ScanResults> {
+    let show_progress = opts.show_progress;
+    let stale_days = opts.stale_days;
+    let use_cache = opts.use_cache;
+    let profile_name = opts.profile_name.as_str();
+    let events = &opts.events;
+    let cancel = &opts.cancel;
+    let budget = &ScanBudget::new(&opts.limits);
+
+    // Drop targets outside the category filter before anything is walked
+    let targets: Vec<ScanTarget> = targets
+        .iter()
+        .filter(|t| opts.wants(&t.category))
+        .cloned()
+        .collect();
+    let targets = targets.as_slice();
+    let scan_node_modules =
+        opts.include_dev_projects && opts.wants(&Category::DevCache(DevTool::NodeModules));
+    let scan_venvs = opts.include_dev_projects && opts.wants(&Category::DevCache(DevTool::Venv));
+    let artifact_tools: Vec<DevTool> = if opts.include_project_artifacts {
+        dev_detector::artifact_rules()
+            .into_iter()
+            .map(|r| r.tool)
+            .filter(|t| opts.wants(&Category::DevCache(t.clone())))
+            .collect()
     } else {
-        ScanCache::new(profile_name)
+        Vec::new()
     };
+    let large_files = opts
+        .large_files
+        .as_ref()
+        .filter(|_| opts.wants(&Category::LargeFile));
+
+    let start = Instant::now();
+    let mut results = ScanResults::new();
+    let oracle = StalenessOracle::new(stale_days);
+
+    // Load or create cache; cached items are only valid for the same excludes
+    let mut scan_cache = use_cache.then(|| {
+        let mut cache = ScanCache::load(profile_name)
+            .filter(|c| c.excludes == opts.excludes.patterns())
+            .unwrap_or_else(|| ScanCache::new(profile_name));
+        cache.excludes = opts.excludes.patterns().to_vec();
+        cache
+    });
+
+    // A running `tidymac watch` daemon already has current results
+    let watch = scan_cache
+        .as_ref()
+        .and_then(|_| WatchState::load(profile_name))
+        .filter(|w| w.is_live() && w.excludes == opts.excludes.patterns());
 
     // Set up progress bar
-    let total_steps = targets.len() + if include_dev_projects { 2 } else { 0 } + 1;
+    let total_steps = targets.len()
+        + scan_node_modules as usize
+        + scan_venvs as usize
+        + !artifact_tools.is_empty() as usize
+        + large_files.is_some() as usize;
+
+    events.emit(ScanEvent::Started {
+        profile: profile_name.to_string(),
+        steps: total_steps,
+    });
 
     let pb = if show_progress {
         let pb = ProgressBar::new(total_steps as u64);
@@ -65,76 +256,95 @@ pub fn run_scan_with_cache(
     } else {
         None
     };
+    // Partial results are never cached or returned
+    let check_cancelled = |pb: &Option<ProgressBar>| -> Result<()> {
+        if cancel.is_cancelled() {
+            if let Some(ref pb) = pb {
+                pb.finish_and_clear();
+            }
+        }
+        cancel.check()
+    };
 
     // 1. Scan all defined targets (with cache)
     if let Some(ref pb) = pb {
         pb.set_message("Scanning system locations...");
     }
 
-    if use_cache {
-        // Check cache for each target individually
-        let mut uncached_targets = Vec::new();
-
-        for target in targets {
-            let expanded = walker::expand_paths(&target.paths);
-            let mut all_cached = true;
-            let mut cached_items = Vec::new();
-
-            for path in &expanded {
-                if let Some(cached) = scan_cache.check(path) {
-                    // Store cached result temporarily
-                    if cached.size_bytes > 0 {
-                        cached_items.push(targets::ScanItem {
-                            name: cached.name.clone(),
-                            category: target.category.clone(),
-                            path: cached.path.clone(),
-                            size_bytes: cached.size_bytes,
-                            file_count: cached.file_count,
-                            safety: target.safety.clone(),
-                            reason: cached.reason.clone(),
-                            files: Vec::new(), // Cached results don't store individual files
-                        });
-                    }
-                } else {
-                    all_cached = false;
-                }
+    // Serve unchanged targets from cache; snapshots are taken before walking
+    // so changes made during the walk invalidate the entry next time
+    let mut uncached = Vec::new();
+    for target in targets {
+        let watched = watch
+            .as_ref()
+            .and_then(|w| w.item(&cache::target_key(target)));
+        if let (Some(item), Some(cache)) = (watched, scan_cache.as_mut()) {
+            cache.stats.watched += 1;
+            let mut item = item.clone();
+            cache::restamp(&mut item, target);
+            if let Some(limit) = opts.top_files {
+                aggregate::keep_largest(&mut item, limit);
             }
-
-            if all_cached {
-                // Only add cached results if target is fully cached
-                results.items.extend(cached_items);
-            } else {
-                uncached_targets.push(target.clone());
+            events.emit_started(&target.name);
+            events.emit_finished(&target.name, std::slice::from_ref(&item), true);
+            if item.size_bytes > 0 {
+                results.items.push(item);
             }
+            continue;
         }
-
-        // Only scan uncached targets
-        if !uncached_targets.is_empty() {
-            let scan_results = walker::walk_targets(&uncached_targets);
-            for result in scan_results {
-                match result {
-                    Ok(item) => {
-                        scan_cache.store(&item);
+        match scan_cache.as_mut() {
+            Some(cache) => {
+                let snapshot = TargetSnapshot::take(target, &opts.excludes);
+                match cache.check(&snapshot, opts.top_files.is_none()) {
+                    Some(mut item) => {
+                        cache::restamp(&mut item, target);
+                        if let Some(limit) = opts.top_files {
+                            aggregate::keep_largest(&mut item, limit);
+                        }
+                        events.emit_started(&target.name);
+                        events.emit_finished(&target.name, std::slice::from_ref(&item), true);
                         if item.size_bytes > 0 {
                             results.items.push(item);
                         }
                     }
-                    Err(e) => results.errors.push(format!("Scan error: {}", e)),
+                    None => uncached.push((target.clone(), Some(snapshot))),
                 }
             }
+            None => uncached.push((target.clone(), None)),
         }
-    } else {
-        // No cache — scan everything
-        let scan_results = walker::walk_targets(targets);
-        for result in scan_results {
-            match result {
-                Ok(item) => {
-                    scan_cache.store(&item);
-                    if item.size_bytes > 0 {
-                        results.items.push(item);
+    }
+
+    let to_walk: Vec<ScanTarget> = uncached.iter().map(|(t, _)| t.clone()).collect();
+    let walked = walker::walk_targets_streaming(
+        &to_walk,
+        &opts.excludes,
+        events,
+        cancel,
+        budget,
+        opts.top_files,
+    );
+    check_cancelled(&pb)?;
+    for ((target, snapshot), result) in uncached.into_iter().zip(walked) {
+        match result {
+            Ok(item) => {
+                // Partial walks would hide files on the next cached scan
+                if let (Some(cache), Some(snapshot)) = (scan_cache.as_mut(), snapshot) {
+                    if !budget.is_truncated(&target.name) {
+                        cache.store(snapshot, &item);
                     }
                 }
-                Err(e) => results.errors.push(format!("Scan error: {}", e)),
+                if item.size_bytes > 0 {
+                    events.emit(ScanEvent::item_found(&item));
+                    results.items.push(item);
+                }
+            }
+            Err(e) => {
+                let message = format!("Scan error: {}", e);
+                events.emit(ScanEvent::Error {
+                    target: Some(target.name),
+                    message: message.clone(),
+                });
+                results.errors.push(message);
             }
         }
     }
@@ -143,20 +353,30 @@ pub fn run_scan_with_cache(
         pb.set_position(targets.len() as u64);
     }
 
-    // 2. Scan for stale node_modules and venvs
-    if include_dev_projects {
+    // 2. Scan for stale node_modules, venvs and project build artifacts
+    if scan_node_modules {
         if let Some(ref pb) = pb {
             pb.set_message("Scanning for stale node_modules...");
         }
-        let nm_item = dev_detector::scan_node_modules(stale_days);
+        events.emit_started(PASS_NODE_MODULES);
+        let nm_item = dev_detector::scan_node_modules(&oracle, &opts.excludes, cancel, budget);
+        check_cancelled(&pb)?;
+        events.emit_finished(PASS_NODE_MODULES, std::slice::from_ref(&nm_item), false);
         if nm_item.size_bytes > 0 {
             results.items.push(nm_item);
         }
         if let Some(ref pb) = pb {
             pb.inc(1);
+        }
+    }
+    if scan_venvs {
+        if let Some(ref pb) = pb {
             pb.set_message("Scanning for stale Python venvs...");
         }
-        let venv_item = dev_detector::scan_venvs(stale_days);
+        events.emit_started(PASS_VENVS);
+        let venv_item = dev_detector::scan_venvs(&oracle, &opts.excludes, cancel, budget);
+        check_cancelled(&pb)?;
+        events.emit_finished(PASS_VENVS, std::slice::from_ref(&venv_item), false);
         if venv_item.size_bytes > 0 {
             results.items.push(venv_item);
         }
@@ -165,31 +385,39 @@ pub fn run_scan_with_cache(
         }
     }
 
-    // 3. Scan for large files
-    if let Some(ref pb) = pb {
-        pb.set_message("Scanning for large files...");
-    }
-    let home = dirs::home_dir().unwrap_or_default();
-    let large_files = walker::find_large_files(&home, large_file_threshold);
-    if !large_files.is_empty() {
-        let total_size: u64 = large_files.iter().map(|f| f.size_bytes).sum();
-        let count = large_files.len();
-        results.items.push(targets::ScanItem {
-            name: format!(
-                "Large files (>{})",
-                crate::common::format::format_size(large_file_threshold)
-            ),
-            category: targets::Category::LargeFile,
-            path: home,
-            size_bytes: total_size,
-            file_count: count,
-            safety: targets::SafetyLevel::Caution,
-            reason: "Large files that may no longer be needed".into(),
-            files: large_files,
-        });
+    if !artifact_tools.is_empty() {
+        if let Some(ref pb) = pb {
+            pb.set_message("Scanning for stale project build artifacts...");
+        }
+        events.emit_started(PASS_ARTIFACTS);
+        let artifacts = dev_detector::scan_project_artifacts(
+            &oracle,
+            &opts.excludes,
+            &artifact_tools,
+            cancel,
+            budget,
+        );
+        check_cancelled(&pb)?;
+        events.emit_finished(PASS_ARTIFACTS, &artifacts, false);
+        results.items.extend(artifacts);
+        if let Some(ref pb) = pb {
+            pb.inc(1);
+        }
     }
-    if let Some(ref pb) = pb {
-        pb.inc(1);
+
+    // 3. Scan for large files
+    if let Some(large) = large_files {
+        if let Some(ref pb) = pb {
+            pb.set_message("Scanning for large files...");
+        }
+        events.emit_started(PASS_LARGE_FILES);
+        let large_items = scan_large_files(large, &opts.excludes, cancel, budget);
+        check_cancelled(&pb)?;
+        events.emit_finished(PASS_LARGE_FILES, &large_items, false);
+        results.items.extend(large_items);
+        if let Some(ref pb) = pb {
+            pb.inc(1);
+        }
     }
 
     // Sort by size descending
@@ -200,28 +428,102 @@ pub fn run_scan_with_cache(
     // Calculate totals
     results.recalculate();
     results.duration_secs = start.elapsed().as_secs_f64();
+    results.truncated = budget.truncations();
 
     // Save cache
-    if use_cache {
-        if let Err(e) = scan_cache.save() {
+    if let Some(mut cache) = scan_cache {
+        if let Err(e) = cache.save() {
             results.errors.push(format!("Cache save warning: {}", e));
         }
+        results.cache = Some(cache.stats);
+    }
 
-        // Add cache stats to results
-        if show_progress && (scan_cache.stats.hits > 0 || scan_cache.stats.misses > 0) {
-            results.errors.push(format!(
-                "Cache: {} hits, {} misses, {} invalidated ({:.0}% hit rate)",
-                scan_cache.stats.hits,
-                scan_cache.stats.misses,
-                scan_cache.stats.invalidated,
-                scan_cache.stats.hit_rate()
-            ));
-        }
+    if opts.record_history {
+        history::record(&results, profile_name);
     }
 
+    events.emit(ScanEvent::Done {
+        total_reclaimable: results.total_reclaimable,
+        total_freeable: results.total_freeable,
+        total_files: results.total_files,
+        items: results.items.len(),
+        duration_secs: results.duration_secs,
+        cache: results.cache.clone(),
+        truncated: results.truncated.clone(),
+    });
+
     if let Some(ref pb) = pb {
         pb.finish_and_clear();
     }
 
     Ok(results)
 }
+
+/// Run the large-file pass and group the hits by kind
+///
+/// Each kind becomes its own `ScanItem` so the advice in `reason` fits
+/// every file listed under it.
+fn scan_large_files(
+    large: &LargeFileScan,
+    excludes: &ExclusionMatcher,
+    cancel: &CancelToken,
+    budget: &ScanBudget,
+) -> Vec<targets::ScanItem> {
+    let min_age = large
+        .min_age_days
+        .map(|d| Duration::from_secs(d as u64 * 86400));
+    let mut seen = std::collections::HashSet::new();
+    let mut by_kind: std::collections::HashMap<LargeFileKind, Vec<targets::FileEntry>> =
+        std::collections::HashMap::new();
+
+    for root in &large.roots {
+        for file in walker::find_large_files(root, large.threshold_bytes, excludes, cancel, budget) {
+            let old_enough = match (min_age, file.modified) {
+                (Some(min), Some(modified)) => {
+                    SystemTime::now().duration_since(modified).unwrap_or_default() >= min
+                }
+                _ => true,
+            };
+            if old_enough && seen.insert(file.path.clone()) {
+                by_kind
+                    .entry(LargeFileKind::classify(&file.path))
+                    .or_default()
+                    .push(file);
+            }
+        }
+    }
+
+    let threshold = crate::common::format::format_size(large.threshold_bytes);
+    let path = match large.roots.as_slice() {
+        [root] => root.clone(),
+        _ => dirs::home_dir().unwrap_or_default(),
+    };
+    let mut items = Vec::new();
+    for kind in LargeFileKind::ALL {
+        let Some(mut files) = by_kind.remove(kind) else {
+            continue;
+        };
+        files.sort_by_key(|f| std::cmp::Reverse(f.size_bytes));
+        let name = match kind {
+            LargeFileKind::Other => format!("Large files (>{})", threshold),
+            _ => format!("Large {} files (>{})", kind, threshold),
+        };
+        let usage = targets::file_totals(&files);
+        items.push(targets::ScanItem {
+            name,
+            category: Category::LargeFile,
+            path: path.clone(),
+            size_bytes: usage.allocated,
+            apparent_bytes: usage.apparent,
+            freeable_bytes: usage.freeable,
+            file_count: files.len(),
+            safety: targets::SafetyLevel::Caution,
+            reason: kind.advice().to_string(),
+            files,
+            files_partial: false,
+            trim: None,
+            roots: large.roots.clone(),
+        });
+    }
+    items
+}
@@ -0,0 +1,94 @@
+//! Saved scan results
+//!
+//! `tidymac scan --save <file>` writes a `Snapshot`: the full `ScanResults`
+//! wrapped with a format version and the tidymac release that wrote it.
+//! Plain `scan --format json` output is also accepted when loading and is
+//! treated as version 0, so results saved before snapshots existed can
+//! still be compared.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::scanner::targets::ScanResults;
+
+/// Format version written by this release; newer snapshots are rejected
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Scan results as saved to disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub snapshot_version: u32,
+    /// Release of tidymac that wrote the snapshot
+    pub tidymac_version: String,
+    pub profile: String,
+    pub results: ScanResults,
+}
+
+impl Snapshot {
+    pub fn new(results: ScanResults, profile: &str) -> Self {
+        Self {
+            snapshot_version: SNAPSHOT_VERSION,
+            tidymac_version: env!("CARGO_PKG_VERSION").to_string(),
+            profile: profile.to_string(),
+            results,
+        }
+    }
+
+    /// When the snapshotted scan ran
+    pub fn taken_at(&self) -> DateTime<Utc> {
+        self.results.timestamp
+    }
+
+    /// Write the snapshot, replacing `path` only once it is complete
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+        }
+        let tmp = path.with_extension("tmp");
+        let file = std::fs::File::create(&tmp)
+            .with_context(|| format!("Failed to write snapshot: {}", tmp.display()))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer(&mut writer, self).context("Failed to serialize snapshot")?;
+        writer
+            .flush()
+            .with_context(|| format!("Failed to write snapshot: {}", tmp.display()))?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to write snapshot: {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Read a snapshot, or bare `scan --format json` output
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Failed to read snapshot: {}", path.display()))?;
+        let value: serde_json::Value = serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("Not a JSON file: {}", path.display()))?;
+
+        let Some(version) = value.get("snapshot_version") else {
+            let results: ScanResults = serde_json::from_value(value)
+                .with_context(|| format!("Not a tidymac scan snapshot: {}", path.display()))?;
+            return Ok(Self {
+                snapshot_version: 0,
+                tidymac_version: String::new(),
+                profile: String::new(),
+                results,
+            });
+        };
+
+        let version = version.as_u64().unwrap_or(u64::MAX);
+        if version > u64::from(SNAPSHOT_VERSION) {
+            bail!(
+                "Snapshot {} has format version {}, this tidymac reads up to {}; upgrade tidymac to compare it",
+                path.display(),
+                version,
+                SNAPSHOT_VERSION
+            );
+        }
+        serde_json::from_value(value)
+            .with_context(|| format!("Failed to parse snapshot: {}", path.display()))
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use super::git::GitRepo;
+
+/// What the enclosing git repository says about recent activity
+#[derive(Debug, Clone)]
+pub struct RepoActivity {
+    pub work_tree: PathBuf,
+    pub head_commit_time: Option<SystemTime>,
+    pub last_reflog_time: Option<SystemTime>,
+    pub dirty: bool,
+}
+
+impl RepoActivity {
+    /// Read activity for a repository straight from its `.git` files
+    pub fn read(repo: &GitRepo) -> Self {
+        Self {
+            work_tree: repo.work_tree.clone(),
+            head_commit_time: repo.head_commit_time(),
+            last_reflog_time: repo.last_reflog_time(),
+            dirty: repo.is_dirty(),
+        }
+    }
+
+    /// Most recent of the HEAD commit and the last reflog entry
+    pub fn last_activity(&self) -> Option<SystemTime> {
+        self.head_commit_time.max(self.last_reflog_time)
+    }
+}
+
+/// Decides whether a project directory has gone stale
+///
+/// Projects inside a git repository are judged by the repo's HEAD commit
+/// time and last reflog entry, and are never stale while the working tree
+/// has uncommitted changes. Projects outside git fall back to the mtime the
+/// caller supplies (e.g. `package.json`). Repository lookups are cached, so
+/// one oracle can be shared across finders and threads.
+#[derive(Debug)]
+pub struct StalenessOracle {
+    stale_days: u32,
+    repos: Mutex<HashMap<PathBuf, RepoActivity>>,
+}
+
+impl StalenessOracle {
+    pub fn new(stale_days: u32) -> Self {
+        Self {
+            stale_days,
+            repos: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Days without activity before a project counts as stale
+    pub fn stale_days(&self) -> u32 {
+        self.stale_days
+    }
+
+    /// Whether the project at `project_dir` is stale
+    ///
+    /// `fallback` is used when the project isn't in a git repository or the
+    /// repository yields no timestamps; with neither, the project is stale.
+    pub fn is_stale(&self, project_dir: &Path, fallback: Option<SystemTime>) -> bool {
+        let last_activity = match self.activity(project_dir) {
+            Some(repo) if repo.dirty => return false,
+            Some(repo) => repo.last_activity().or(fallback),
+            None => fallback,
+        };
+
+        let threshold = Duration::from_secs(self.stale_days as u64 * 86400);
+        last_activity
+            .map(|t| SystemTime::now().duration_since(t).unwrap_or_default() > threshold)
+            .unwrap_or(true)
+    }
+
+    /// Activity of the repository enclosing `project_dir`, if any
+    pub fn activity(&self, project_dir: &Path) -> Option<RepoActivity> {
+        let repo = GitRepo::discover(project_dir)?;
+
+        if let Some(cached) = self.repos.lock().unwrap().get(&repo.work_tree) {
+            return Some(cached.clone());
+        }
+
+        // Read outside the lock; a duplicate read on a race is harmless
+        let activity = RepoActivity::read(&repo);
+        self.repos
+            .lock()
+            .unwrap()
+            .insert(repo.work_tree.clone(), activity.clone());
+        Some(activity)
+    }
+}
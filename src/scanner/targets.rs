@@ -2,10 +2,15 @@ use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::time::SystemTime;
 
+use crate::common::sizes::{self, DiskUsage, FileId, HardLink, UsageTally};
+
 // ─── Core types ───────────────────────────────────────────────────────────────
 
 /// Safety level for a file deletion
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// Variants are ordered from least to most risky, so `a <= b` means
+/// "`a` is at most as risky as `b`".
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum SafetyLevel {
     /// Caches, temp files — always safe to remove
@@ -42,6 +47,49 @@ pub enum DevTool {
     RubyGems,
     Terraform,
     Ivy2,
+    GradleBuild,
+    JsBuild,
+    PythonCache,
+    SwiftPmBuild,
+    CMakeBuild,
+}
+
+impl DevTool {
+    /// Every developer tool, in declaration order
+    pub const ALL: &'static [DevTool] = &[
+        DevTool::Xcode,
+        DevTool::XcodeArchives,
+        DevTool::XcodeSimulators,
+        DevTool::Docker,
+        DevTool::NodeModules,
+        DevTool::Venv,
+        DevTool::Conda,
+        DevTool::Homebrew,
+        DevTool::Pip,
+        DevTool::CocoaPods,
+        DevTool::Gradle,
+        DevTool::Maven,
+        DevTool::Cargo,
+        DevTool::CargoTarget,
+        DevTool::Npm,
+        DevTool::Yarn,
+        DevTool::YarnBerry,
+        DevTool::Pnpm,
+        DevTool::Go,
+        DevTool::RubyGems,
+        DevTool::Terraform,
+        DevTool::Ivy2,
+        DevTool::GradleBuild,
+        DevTool::JsBuild,
+        DevTool::PythonCache,
+        DevTool::SwiftPmBuild,
+        DevTool::CMakeBuild,
+    ];
+
+    /// Stable snake_case identifier, identical to the serialized form
+    pub fn slug(&self) -> String {
+        serde_slug(self)
+    }
 }
 
 impl std::fmt::Display for DevTool {
@@ -69,6 +117,11 @@ impl std::fmt::Display for DevTool {
             DevTool::RubyGems => write!(f, "Ruby Gems"),
             DevTool::Terraform => write!(f, "Terraform plugins"),
             DevTool::Ivy2 => write!(f, "JVM / Ivy2 cache"),
+            DevTool::GradleBuild => write!(f, "Gradle build/ dirs"),
+            DevTool::JsBuild => write!(f, "JS build output"),
+            DevTool::PythonCache => write!(f, "Python tool caches"),
+            DevTool::SwiftPmBuild => write!(f, "SwiftPM .build/ dirs"),
+            DevTool::CMakeBuild => write!(f, "CMake build dirs"),
         }
     }
 }
@@ -94,6 +147,138 @@ pub enum Category {
     OldDownload,
 }
 
+impl Category {
+    /// Every category that isn't parameterised by a dev tool
+    pub const SIMPLE: &'static [Category] = &[
+        Category::SystemCache,
+        Category::UserCache,
+        Category::Logs,
+        Category::TempFiles,
+        Category::CrashReports,
+        Category::LargeFile,
+        Category::Duplicate,
+        Category::MailAttachment,
+        Category::Trash,
+        Category::BrowserData,
+        Category::AppLeftover,
+        Category::StartupItem,
+        Category::DownloadedDmg,
+        Category::OldDownload,
+    ];
+
+    /// Stable selector name, e.g. `user_cache` or `dev_cache.cargo`
+    pub fn slug(&self) -> String {
+        match self {
+            Category::DevCache(tool) => format!("dev_cache.{}", tool.slug()),
+            other => serde_slug(other),
+        }
+    }
+
+    /// Every selector name accepted by `CategoryFilter`
+    pub fn all_slugs() -> Vec<String> {
+        let mut slugs: Vec<String> = Category::SIMPLE.iter().map(|c| c.slug()).collect();
+        slugs.push("dev_cache.*".to_string());
+        slugs.extend(
+            DevTool::ALL
+                .iter()
+                .map(|t| Category::DevCache(t.clone()).slug()),
+        );
+        slugs
+    }
+}
+
+/// Serialized name of a unit enum variant
+fn serde_slug<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Restricts a scan or clean to a set of categories
+///
+/// Selectors are category slugs (`user_cache`, `dev_cache.npm`) or the
+/// `dev_cache.*` wildcard, which matches every developer tool.
+#[derive(Debug, Clone, Default)]
+pub struct CategoryFilter {
+    selectors: Vec<String>,
+}
+
+impl CategoryFilter {
+    /// Parse selector names, rejecting anything that isn't a known category
+    pub fn parse(names: &[String]) -> anyhow::Result<Self> {
+        let valid = Category::all_slugs();
+        let mut selectors = Vec::new();
+
+        for name in names {
+            let name = name.trim().to_lowercase();
+            if name.is_empty() {
+                continue;
+            }
+            let name = if name == "dev_cache" {
+                "dev_cache.*".to_string()
+            } else {
+                name
+            };
+            if !valid.contains(&name) {
+                anyhow::bail!(
+                    "Unknown category '{}'. Valid values: {}",
+                    name,
+                    valid.join(", ")
+                );
+            }
+            if !selectors.contains(&name) {
+                selectors.push(name);
+            }
+        }
+
+        if selectors.is_empty() {
+            anyhow::bail!("No categories given. Valid values: {}", valid.join(", "));
+        }
+
+        Ok(Self { selectors })
+    }
+
+    /// Whether a category is selected
+    pub fn matches(&self, category: &Category) -> bool {
+        let slug = category.slug();
+        self.selectors.iter().any(|s| {
+            *s == slug || (s == "dev_cache.*" && matches!(category, Category::DevCache(_)))
+        })
+    }
+
+    /// The normalised selectors
+    pub fn selectors(&self) -> &[String] {
+        &self.selectors
+    }
+}
+
+impl std::fmt::Display for SafetyLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SafetyLevel::Safe => write!(f, "safe"),
+            SafetyLevel::Caution => write!(f, "caution"),
+            SafetyLevel::Dangerous => write!(f, "dangerous"),
+        }
+    }
+}
+
+impl std::str::FromStr for SafetyLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "safe" => Ok(SafetyLevel::Safe),
+            "caution" => Ok(SafetyLevel::Caution),
+            "dangerous" => Ok(SafetyLevel::Dangerous),
+            other => anyhow::bail!(
+                "Unknown safety level '{}'. Valid values: safe, caution, dangerous",
+                other
+            ),
+        }
+    }
+}
+
 impl std::fmt::Display for Category {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -116,6 +301,82 @@ impl std::fmt::Display for Category {
     }
 }
 
+/// Kind of file found by the large-file pass, used to tailor advice
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LargeFileKind {
+    Video,
+    DiskImage,
+    VmImage,
+    Archive,
+    Iso,
+    Other,
+}
+
+impl LargeFileKind {
+    /// Every kind, in the order results are listed
+    pub const ALL: &'static [LargeFileKind] = &[
+        LargeFileKind::VmImage,
+        LargeFileKind::DiskImage,
+        LargeFileKind::Iso,
+        LargeFileKind::Video,
+        LargeFileKind::Archive,
+        LargeFileKind::Other,
+    ];
+
+    /// Classify a file by its extension
+    pub fn classify(path: &std::path::Path) -> Self {
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        match ext.as_str() {
+            "mp4" | "mov" | "m4v" | "mkv" | "avi" | "wmv" | "flv" | "webm" | "mpg" | "mpeg"
+            | "mts" => LargeFileKind::Video,
+            "dmg" | "sparseimage" | "img" | "pkg" => LargeFileKind::DiskImage,
+            "vmdk" | "vdi" | "qcow2" | "vhd" | "vhdx" | "hdd" | "ova" | "utm" => {
+                LargeFileKind::VmImage
+            }
+            "zip" | "tar" | "gz" | "tgz" | "bz2" | "xz" | "zst" | "7z" | "rar" => {
+                LargeFileKind::Archive
+            }
+            "iso" => LargeFileKind::Iso,
+            _ => LargeFileKind::Other,
+        }
+    }
+
+    /// What to do about files of this kind
+    pub fn advice(&self) -> &'static str {
+        match self {
+            LargeFileKind::Video => {
+                "Videos — move to external storage or a media library if you still need them"
+            }
+            LargeFileKind::DiskImage => {
+                "Disk images and installers — safe to delete once the app is installed"
+            }
+            LargeFileKind::VmImage => {
+                "VM disks — remove unused VMs from within the VM app so its config stays consistent"
+            }
+            LargeFileKind::Archive => "Archives — delete if already extracted or backed up",
+            LargeFileKind::Iso => "ISO images — OS or installer media that can be re-downloaded",
+            LargeFileKind::Other => "Large files that may no longer be needed",
+        }
+    }
+}
+
+impl std::fmt::Display for LargeFileKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LargeFileKind::Video => write!(f, "video"),
+            LargeFileKind::DiskImage => write!(f, "disk image"),
+            LargeFileKind::VmImage => write!(f, "VM image"),
+            LargeFileKind::Archive => write!(f, "archive"),
+            LargeFileKind::Iso => write!(f, "ISO"),
+            LargeFileKind::Other => write!(f, "other"),
+        }
+    }
+}
+
 /// A single scan result entry
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScanItem {
@@ -128,9 +389,24 @@ pub struct ScanItem {
     /// Base path of the scan target
     pub path: PathBuf,
 
-    /// Total size in bytes
+    /// Directories the item's files were found under; cleaning refuses
+    /// files outside them. Empty means just `path`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roots: Vec<PathBuf>,
+
+    /// Bytes allocated on disk, each hard-linked file counted once
     pub size_bytes: u64,
 
+    /// Logical size (`st_size`); larger than `size_bytes` for sparse or
+    /// compressed files
+    #[serde(default)]
+    pub apparent_bytes: u64,
+
+    /// Bytes deleting this item would actually free, leaving out files with
+    /// hard links elsewhere and blocks shared with clones
+    #[serde(default)]
+    pub freeable_bytes: u64,
+
     /// Number of files found
     pub file_count: usize,
 
@@ -141,16 +417,102 @@ pub struct ScanItem {
     pub reason: String,
 
     /// Individual file paths (for detailed view)
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub files: Vec<FileEntry>,
+
+    /// `files` only lists the largest files; the totals still cover all of
+    /// them. Such items must be rescanned with full lists before cleaning.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub files_partial: bool,
+
+    /// Trim policy of the target; cleaning evicts only what exceeds it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trim: Option<TrimPolicy>,
 }
 
 /// Individual file entry within a scan item
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileEntry {
     pub path: PathBuf,
+    /// Bytes allocated on disk
     pub size_bytes: u64,
     pub modified: Option<SystemTime>,
+    /// Last access time, where the filesystem records it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accessed: Option<SystemTime>,
+    /// Logical size (`st_size`)
+    #[serde(default)]
+    pub apparent_bytes: u64,
+    /// Bytes deleting just this path would free; 0 for hard-linked files
+    #[serde(default)]
+    pub freeable_bytes: u64,
+    /// Set when the file has more than one hard link
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hard_link: Option<HardLink>,
+    /// Device and inode seen by the scan, so cleaning can tell if the path
+    /// was replaced since
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_id: Option<FileId>,
+}
+
+impl FileEntry {
+    pub fn from_metadata(path: PathBuf, meta: &std::fs::Metadata) -> Self {
+        let (usage, hard_link) = sizes::file_usage(&path, meta);
+        Self {
+            path,
+            size_bytes: usage.allocated,
+            modified: meta.modified().ok(),
+            accessed: meta.accessed().ok(),
+            apparent_bytes: usage.apparent,
+            freeable_bytes: usage.freeable,
+            hard_link,
+            file_id: Some(FileId::of(meta)),
+        }
+    }
+
+    /// Entry for a directory that is removed as a whole
+    pub fn from_dir(path: PathBuf, modified: Option<SystemTime>) -> Self {
+        let usage = sizes::dir_usage(&path);
+        let file_id = std::fs::symlink_metadata(&path)
+            .ok()
+            .map(|meta| FileId::of(&meta));
+        Self {
+            path,
+            size_bytes: usage.allocated,
+            modified,
+            accessed: None,
+            apparent_bytes: usage.apparent,
+            freeable_bytes: usage.freeable,
+            hard_link: None,
+            file_id,
+        }
+    }
+
+    /// When the file was last read or written: the later of atime and
+    /// mtime, so volumes mounted `noatime` fall back to mtime
+    pub fn last_used(&self) -> Option<SystemTime> {
+        self.accessed.max(self.modified)
+    }
+
+    pub fn usage(&self) -> DiskUsage {
+        DiskUsage {
+            apparent: self.apparent_bytes,
+            allocated: self.size_bytes,
+            freeable: self.freeable_bytes,
+        }
+    }
+}
+
+/// Totals for a set of files, counting each hard-linked file once
+///
+/// A hard-linked file only counts as freeable when every one of its links
+/// is in `files`.
+pub fn file_totals(files: &[FileEntry]) -> DiskUsage {
+    let mut tally = UsageTally::new();
+    for file in files {
+        tally.add(file.usage(), file.hard_link);
+    }
+    tally.finish()
 }
 
 /// Complete scan results
@@ -165,14 +527,30 @@ pub struct ScanResults {
     /// All scan items grouped by category
     pub items: Vec<ScanItem>,
 
-    /// Total reclaimable space in bytes
+    /// Total reclaimable space in bytes, as allocated on disk
     pub total_reclaimable: u64,
 
+    /// Total logical size of all items
+    #[serde(default)]
+    pub total_apparent: u64,
+
+    /// Space cleaning every item would actually free
+    #[serde(default)]
+    pub total_freeable: u64,
+
     /// Total files found
     pub total_files: usize,
 
     /// Errors encountered during scan
     pub errors: Vec<String>,
+
+    /// Scan cache statistics, when the cache was used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<super::cache::CacheStats>,
+
+    /// Targets cut short by scan limits; their items are partial
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub truncated: Vec<super::limits::Truncation>,
 }
 
 impl ScanResults {
@@ -186,6 +564,8 @@ impl ScanResults {
     /// Recalculate totals from items
     pub fn recalculate(&mut self) {
         self.total_reclaimable = self.items.iter().map(|i| i.size_bytes).sum();
+        self.total_apparent = self.items.iter().map(|i| i.apparent_bytes).sum();
+        self.total_freeable = self.items.iter().map(|i| i.freeable_bytes).sum();
         self.total_files = self.items.iter().map(|i| i.file_count).sum();
     }
 
@@ -218,6 +598,57 @@ pub struct ScanTarget {
     pub reason: String,
     pub recursive: bool,
     pub min_age_days: Option<u32>, // only flag if older than N days
+    /// Keep part of the target when cleaning instead of wiping it
+    pub trim: Option<TrimPolicy>,
+}
+
+/// How much of a cache to keep when cleaning it
+///
+/// Files not used within `keep_days` are evicted, then the least recently
+/// used of the rest until the target fits in `max_gb`.
+///
+/// ```toml
+/// [trim]
+/// "dev_cache.cargo" = { max_gb = 10 }
+/// "dev_cache.homebrew" = { keep_days = 30 }
+/// "Bazel Cache" = { max_gb = 20, keep_days = 14 }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrimPolicy {
+    /// Keep at most this many GB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_gb: Option<f64>,
+    /// Keep files used within this many days
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_days: Option<u32>,
+}
+
+impl TrimPolicy {
+    pub fn max_bytes(&self) -> Option<u64> {
+        self.max_gb.map(|gb| (gb * 1024.0 * 1024.0 * 1024.0) as u64)
+    }
+
+    /// Reject policies that keep nothing or can't be understood
+    pub fn validate(&self, key: &str) -> anyhow::Result<()> {
+        if self.max_gb.is_none() && self.keep_days.is_none() {
+            anyhow::bail!("Trim policy '{}' sets neither max_gb nor keep_days", key);
+        }
+        if self.max_gb.is_some_and(|gb| !gb.is_finite() || gb < 0.0) {
+            anyhow::bail!("Trim policy '{}' has an invalid max_gb", key);
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for TrimPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.max_gb, self.keep_days) {
+            (Some(gb), Some(days)) => write!(f, "keep ≤ {} GB used in the last {} days", gb, days),
+            (Some(gb), None) => write!(f, "keep ≤ {} GB", gb),
+            (None, Some(days)) => write!(f, "keep files used in the last {} days", days),
+            (None, None) => write!(f, "keep everything"),
+        }
+    }
 }
 
 /// Get all system junk scan targets
@@ -231,6 +662,7 @@ pub fn system_junk_targets() -> Vec<ScanTarget> {
             reason: "Application caches that will be regenerated automatically".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
         ScanTarget {
             name: "System Log Files".into(),
@@ -240,6 +672,7 @@ pub fn system_junk_targets() -> Vec<ScanTarget> {
             reason: "System logs — old entries are safe to remove".into(),
             recursive: true,
             min_age_days: Some(7),
+            trim: None,
         },
         ScanTarget {
             name: "User Log Files".into(),
@@ -249,6 +682,7 @@ pub fn system_junk_targets() -> Vec<ScanTarget> {
             reason: "Application logs that can be safely removed".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
         ScanTarget {
             name: "Temporary Files".into(),
@@ -258,6 +692,7 @@ pub fn system_junk_targets() -> Vec<ScanTarget> {
             reason: "Temporary files created by the system and apps".into(),
             recursive: true,
             min_age_days: Some(1),
+            trim: None,
         },
         ScanTarget {
             name: "Crash Reports".into(),
@@ -267,6 +702,7 @@ pub fn system_junk_targets() -> Vec<ScanTarget> {
             reason: "Application crash reports — safe to remove unless debugging".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
         ScanTarget {
             name: "QuickLook Thumbnails".into(),
@@ -276,6 +712,7 @@ pub fn system_junk_targets() -> Vec<ScanTarget> {
             reason: "Thumbnail preview caches — regenerated on demand".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
         ScanTarget {
             name: "Downloaded DMG Files".into(),
@@ -285,6 +722,7 @@ pub fn system_junk_targets() -> Vec<ScanTarget> {
             reason: "Installer disk images — usually safe to remove after installation".into(),
             recursive: false,
             min_age_days: Some(7),
+            trim: None,
         },
     ]
 }
@@ -300,6 +738,7 @@ pub fn developer_targets() -> Vec<ScanTarget> {
             reason: "Build artifacts that Xcode regenerates on next build".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
         ScanTarget {
             name: "Xcode Archives".into(),
@@ -310,6 +749,7 @@ pub fn developer_targets() -> Vec<ScanTarget> {
                 .into(),
             recursive: true,
             min_age_days: Some(90),
+            trim: None,
         },
         ScanTarget {
             name: "iOS Simulators".into(),
@@ -319,6 +759,7 @@ pub fn developer_targets() -> Vec<ScanTarget> {
             reason: "iOS simulator data — can be re-downloaded".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
         ScanTarget {
             name: "Docker Data".into(),
@@ -332,6 +773,7 @@ pub fn developer_targets() -> Vec<ScanTarget> {
                 .into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
         ScanTarget {
             name: "Homebrew Cache".into(),
@@ -341,6 +783,7 @@ pub fn developer_targets() -> Vec<ScanTarget> {
             reason: "Downloaded package archives — re-downloaded on demand".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
         ScanTarget {
             name: "pip Cache".into(),
@@ -350,6 +793,7 @@ pub fn developer_targets() -> Vec<ScanTarget> {
             reason: "Python package download cache — re-downloaded on demand".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
         ScanTarget {
             name: "npm Cache".into(),
@@ -359,6 +803,7 @@ pub fn developer_targets() -> Vec<ScanTarget> {
             reason: "npm package cache — re-downloaded on demand".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
         ScanTarget {
             name: "Yarn Cache".into(),
@@ -368,6 +813,7 @@ pub fn developer_targets() -> Vec<ScanTarget> {
             reason: "Yarn package cache — re-downloaded on demand".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
         ScanTarget {
             name: "CocoaPods Cache".into(),
@@ -377,6 +823,7 @@ pub fn developer_targets() -> Vec<ScanTarget> {
             reason: "CocoaPods spec and download cache".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
         ScanTarget {
             name: "Cargo Registry Cache".into(),
@@ -389,6 +836,7 @@ pub fn developer_targets() -> Vec<ScanTarget> {
             reason: "Rust crate download cache — re-downloaded on demand".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
         ScanTarget {
             name: "Gradle Cache".into(),
@@ -398,6 +846,7 @@ pub fn developer_targets() -> Vec<ScanTarget> {
             reason: "Gradle build cache and dependency downloads".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
         ScanTarget {
             name: "Maven Local Repository".into(),
@@ -407,6 +856,7 @@ pub fn developer_targets() -> Vec<ScanTarget> {
             reason: "Maven dependency cache — may include locally installed artifacts".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
         ScanTarget {
             name: "Conda Package Cache".into(),
@@ -416,6 +866,7 @@ pub fn developer_targets() -> Vec<ScanTarget> {
             reason: "Conda downloaded packages — re-downloaded on demand".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
         // ── New: pnpm store ───────────────────────────────────────────────────
         ScanTarget {
@@ -429,6 +880,7 @@ pub fn developer_targets() -> Vec<ScanTarget> {
             reason: "pnpm content-addressable package store — re-installed on demand".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
         // ── New: Yarn Berry (v2+) cache ───────────────────────────────────────
         ScanTarget {
@@ -439,6 +891,7 @@ pub fn developer_targets() -> Vec<ScanTarget> {
             reason: "Yarn v2+ (Berry) global package cache — re-downloaded on demand".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
         // ── New: Go module cache ──────────────────────────────────────────────
         ScanTarget {
@@ -449,6 +902,7 @@ pub fn developer_targets() -> Vec<ScanTarget> {
             reason: "Go module download cache — re-downloaded with `go get`".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
         // ── New: Ruby Gems ────────────────────────────────────────────────────
         ScanTarget {
@@ -459,6 +913,7 @@ pub fn developer_targets() -> Vec<ScanTarget> {
             reason: "Ruby gem installations — reinstall with `gem install`".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
         // ── New: Terraform plugin cache ───────────────────────────────────────
         ScanTarget {
@@ -469,6 +924,7 @@ pub fn developer_targets() -> Vec<ScanTarget> {
             reason: "Terraform provider plugins — re-downloaded with `terraform init`".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
         // ── New: JVM / Ivy2 cache ─────────────────────────────────────────────
         ScanTarget {
@@ -479,6 +935,7 @@ pub fn developer_targets() -> Vec<ScanTarget> {
             reason: "Ivy/SBT dependency download cache — re-downloaded on next build".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
     ]
 }
@@ -494,6 +951,7 @@ pub fn trash_targets() -> Vec<ScanTarget> {
             reason: "Files in your trash bin".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
         ScanTarget {
             name: "External Drive Trash".into(),
@@ -503,6 +961,7 @@ pub fn trash_targets() -> Vec<ScanTarget> {
             reason: "Trash from external drives".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
     ]
 }
@@ -518,6 +977,7 @@ pub fn mail_targets() -> Vec<ScanTarget> {
             reason: "Cached mail attachments — re-downloaded from mail server".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
         ScanTarget {
             name: "Mail Container Data".into(),
@@ -527,6 +987,7 @@ pub fn mail_targets() -> Vec<ScanTarget> {
             reason: "Sandboxed mail attachment cache".into(),
             recursive: true,
             min_age_days: None,
+            trim: None,
         },
     ]
 }
@@ -3,21 +3,169 @@ use jwalk::{WalkDir, Parallelism};
 use rayon::prelude::*;
 use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use std::collections::HashSet;
 use std::time::{SystemTime, Duration};
 
+use super::aggregate::FileCollector;
+use super::events::{EventSink, ScanEvent, FILE_BATCH};
+use super::limits::{self, ScanBudget, ScanLimits, TruncationReason};
+use super::staleness::StalenessOracle;
+use super::{PASS_LARGE_FILES, PASS_NODE_MODULES, PASS_VENVS};
 use super::targets::{FileEntry, ScanItem, ScanTarget};
+use crate::common::cancel::CancelToken;
+use crate::common::exclusions::ExclusionMatcher;
 use crate::common::permissions;
-use crate::ffi::CANCEL_FLAG;
+use crate::common::sizes;
+
+/// The `max_depth` and `one_file_system` limits for one walk root
+///
+/// Directories past the fence are yielded but not read; the fence remembers
+/// whether that hid anything so the walk can be reported as truncated.
+struct WalkFence {
+    max_depth: Option<usize>,
+    /// Device of the walk root, when mount points must not be crossed
+    root_dev: Option<u64>,
+    depth_hit: AtomicBool,
+    mount_hit: AtomicBool,
+}
+
+impl WalkFence {
+    fn new(root: &Path, limits: &ScanLimits) -> Arc<Self> {
+        let root_dev = if limits.one_file_system {
+            std::fs::metadata(root).ok().map(|m| m.dev())
+        } else {
+            None
+        };
+        Arc::new(Self {
+            max_depth: limits.max_depth,
+            root_dev,
+            depth_hit: AtomicBool::new(false),
+            mount_hit: AtomicBool::new(false),
+        })
+    }
+
+    fn is_open(&self) -> bool {
+        self.max_depth.is_none() && self.root_dev.is_none()
+    }
+
+    /// Whether jwalk may read the entries of `dir`
+    fn admits(&self, dir: &jwalk::DirEntry<((), ())>) -> bool {
+        if self.max_depth.is_some_and(|max| dir.depth >= max) {
+            if !self.depth_hit.load(Ordering::Relaxed) && has_entries(&dir.path()) {
+                self.depth_hit.store(true, Ordering::Relaxed);
+            }
+            return false;
+        }
+        if let Some(root_dev) = self.root_dev {
+            if dir.metadata().is_ok_and(|m| m.dev() != root_dev) {
+                self.mount_hit.store(true, Ordering::Relaxed);
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Record in `budget` anything the fence kept out of `target`
+    fn report(&self, budget: &ScanBudget, target: &str) {
+        if let (true, Some(limit)) = (self.depth_hit.load(Ordering::Relaxed), self.max_depth) {
+            budget.record(target, TruncationReason::MaxDepth { limit });
+        }
+        if self.mount_hit.load(Ordering::Relaxed) {
+            budget.record(target, TruncationReason::OtherFilesystem);
+        }
+    }
+}
+
+fn has_entries(dir: &Path) -> bool {
+    std::fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some())
+}
+
+/// Whether a walk for `target` has to stop now, recording why in `budget`
+fn should_stop(cancel: &CancelToken, budget: &ScanBudget, target: &str) -> bool {
+    if cancel.is_cancelled() {
+        return true;
+    }
+    if budget.expired() {
+        budget.record_expired(target);
+        return true;
+    }
+    false
+}
+
+/// Whether `root` may be walked at all under `budget`'s limits
+fn root_allowed(root: &Path, budget: &ScanBudget, target: &str) -> bool {
+    if budget.limits().one_file_system && limits::is_network_filesystem(root) {
+        budget.record(
+            target,
+            TruncationReason::NetworkFilesystem {
+                path: root.to_path_buf(),
+            },
+        );
+        return false;
+    }
+    true
+}
+
+/// Prune excluded entries, and yield but don't descend into `no_descend`
+/// dirs or dirs outside `fence`
+fn prune_dirs(
+    walker: WalkDir,
+    excludes: &ExclusionMatcher,
+    no_descend: &'static [&'static str],
+    fence: &Arc<WalkFence>,
+) -> WalkDir {
+    if excludes.is_empty() && no_descend.is_empty() && fence.is_open() {
+        return walker;
+    }
+    let excludes = excludes.clone();
+    let fence = Arc::clone(fence);
+    walker.process_read_dir(move |_depth, _path, _state, children| {
+        children.retain(|child| match child {
+            Ok(e) => !excludes.is_entry_excluded(&e.path(), e.file_type.is_dir()),
+            Err(_) => true,
+        });
+        for child in children.iter_mut().flatten() {
+            if no_descend.iter().any(|n| child.file_name == *n)
+                || (child.file_type.is_dir() && !fence.admits(child))
+            {
+                child.read_children_path = None;
+            }
+        }
+    })
+}
 
 /// Walk a scan target and collect file information
-pub fn walk_target(target: &ScanTarget) -> Result<ScanItem> {
-    let total_size = AtomicU64::new(0);
-    let total_count = AtomicUsize::new(0);
-    let files = Arc::new(Mutex::new(Vec::new()));
-    let visited_inodes = Arc::new(Mutex::new(HashSet::new()));
+pub fn walk_target(target: &ScanTarget, excludes: &ExclusionMatcher) -> Result<ScanItem> {
+    walk_target_streaming(
+        target,
+        excludes,
+        &EventSink::none(),
+        &CancelToken::new(),
+        &ScanBudget::unlimited(),
+        None,
+    )
+}
+
+/// Walk a scan target, streaming `TargetStarted`, `FileBatch` and
+/// `TargetFinished` events to `events` as files are found
+///
+/// Stops early once `cancel` fires, returning what was found so far. Walks
+/// cut short by `budget`'s limits are recorded there under the target name.
+/// With `top_files` set only that many of the largest files are kept in the
+/// item (see `aggregate`); the totals always cover every file.
+pub fn walk_target_streaming(
+    target: &ScanTarget,
+    excludes: &ExclusionMatcher,
+    events: &EventSink,
+    cancel: &CancelToken,
+    budget: &ScanBudget,
+    top_files: Option<usize>,
+) -> Result<ScanItem> {
+    let collectors = Mutex::new(Vec::new());
+    let files_seen = AtomicUsize::new(0);
+    let max_files = budget.limits().max_files;
+    events.emit_started(&target.name);
 
     let expanded_paths = expand_paths(&target.paths);
 
@@ -27,21 +175,30 @@ pub fn walk_target(target: &ScanTarget) -> Result<ScanItem> {
             return;
         }
 
-        if permissions::is_sip_protected(base_path) {
+        if permissions::is_sip_protected(base_path) || excludes.is_excluded(base_path) {
+            return;
+        }
+        if !root_allowed(base_path, budget, &target.name) {
             return;
         }
 
+        // Each root is consumed on one thread, so its collector and event
+        // batch need no locking until they're handed over at the end
+        let mut collector = FileCollector::new(top_files);
+        let mut batch = Vec::new();
+        let fence = WalkFence::new(base_path, budget.limits());
         let mut walker = WalkDir::new(base_path)
             .follow_links(false)
             .sort(false)
             .parallelism(Parallelism::RayonDefaultPool { busy_timeout: Duration::from_secs(1) });
+        walker = prune_dirs(walker, excludes, &[], &fence);
 
         if !target.recursive {
             walker = walker.max_depth(1);
         }
 
         for entry_res in walker {
-            if CANCEL_FLAG.load(Ordering::Relaxed) {
+            if should_stop(cancel, budget, &target.name) {
                 break;
             }
 
@@ -60,18 +217,13 @@ pub fn walk_target(target: &ScanTarget) -> Result<ScanItem> {
             };
 
             if entry.file_type.is_file() {
-                let path = entry.path();
-                
-                // Apply DMG filter for download targets
-                if target.category == crate::scanner::targets::Category::DownloadedDmg {
-                    if let Some(ext) = path.extension() {
-                        if ext != "dmg" && ext != "pkg" {
-                            continue;
-                        }
-                    } else {
-                        continue;
+                if let Some(limit) = max_files {
+                    if files_seen.fetch_add(1, Ordering::Relaxed) >= limit {
+                        budget.record(&target.name, TruncationReason::MaxFiles { limit });
+                        break;
                     }
                 }
+                let path = entry.path();
 
                 let metadata = match entry.metadata() {
                     Ok(m) => m,
@@ -81,61 +233,111 @@ pub fn walk_target(target: &ScanTarget) -> Result<ScanItem> {
                     }
                 };
 
-                // Symlink loop detection (inode check)
-                let inode = metadata.ino();
-                {
-                    let mut visited = visited_inodes.lock().unwrap();
-                    if !visited.insert(inode) {
-                        tracing::trace!("Already visited inode {}, skipping {:?}", inode, path);
-                        continue; 
-                    }
-                }
-
-                let size = metadata.blocks() * 512;
-                let modified = metadata.modified().ok();
+                let Some(file) = target_file_entry(target, &path, &metadata) else {
+                    continue;
+                };
 
-                if let Some(min_days) = target.min_age_days {
-                    if let Some(mod_time) = modified {
-                        let age = SystemTime::now()
-                            .duration_since(mod_time)
-                            .unwrap_or_default();
-                        if age.as_secs() < (min_days as u64 * 86400) {
-                            continue;
-                        }
+                if events.is_active() {
+                    batch.push(file.clone());
+                    if batch.len() >= FILE_BATCH {
+                        events.emit_files(&target.name, &std::mem::take(&mut batch));
                     }
                 }
-
-                total_size.fetch_add(size, Ordering::Relaxed);
-                total_count.fetch_add(1, Ordering::Relaxed);
-
-                files.lock().unwrap().push(FileEntry {
-                    path: path.to_path_buf(),
-                    size_bytes: size,
-                    modified,
-                });
+                collector.push(file);
             }
         }
+        fence.report(budget, &target.name);
+        events.emit_files(&target.name, &batch);
+        collectors.lock().unwrap().push(collector);
     });
 
-    let size = total_size.load(Ordering::Relaxed);
-    let count = total_count.load(Ordering::Relaxed);
-    let collected_files = Arc::try_unwrap(files).unwrap().into_inner().unwrap();
+    // Every link of a hard-linked file is listed (cleaning must see each
+    // path), but the totals count it once by (dev, ino)
+    let mut collector = FileCollector::new(top_files);
+    for other in collectors.into_inner().unwrap() {
+        collector.merge(other);
+    }
+    let collected = collector.finish();
+    let usage = collected.usage;
+    let count = collected.file_count;
+
+    events.emit(ScanEvent::TargetFinished {
+        target: target.name.clone(),
+        size_bytes: usage.allocated,
+        file_count: count,
+        cached: false,
+    });
 
     Ok(ScanItem {
         name: target.name.clone(),
         category: target.category.clone(),
         path: expanded_paths.first().cloned().unwrap_or_default(),
-        size_bytes: size,
+        size_bytes: usage.allocated,
+        apparent_bytes: usage.apparent,
+        freeable_bytes: usage.freeable,
         file_count: count,
         safety: target.safety.clone(),
         reason: target.reason.clone(),
-        files: collected_files,
+        files: collected.files,
+        files_partial: collected.partial,
+        trim: target.trim.clone(),
+        roots: expanded_paths,
     })
 }
 
+/// The entry a target records for a regular file, or `None` if the target
+/// filters it out (wrong extension for DMG targets, younger than `min_age_days`)
+pub fn target_file_entry(
+    target: &ScanTarget,
+    path: &Path,
+    metadata: &std::fs::Metadata,
+) -> Option<FileEntry> {
+    // Apply DMG filter for download targets
+    if target.category == crate::scanner::targets::Category::DownloadedDmg {
+        let ext = path.extension()?;
+        if ext != "dmg" && ext != "pkg" {
+            return None;
+        }
+    }
+
+    let modified = metadata.modified().ok();
+    if let (Some(min_days), Some(mod_time)) = (target.min_age_days, modified) {
+        let age = SystemTime::now()
+            .duration_since(mod_time)
+            .unwrap_or_default();
+        if age.as_secs() < (min_days as u64 * 86400) {
+            return None;
+        }
+    }
+
+    Some(FileEntry::from_metadata(path.to_path_buf(), metadata))
+}
+
 /// Walk multiple targets in parallel
-pub fn walk_targets(targets: &[ScanTarget]) -> Vec<Result<ScanItem>> {
-    targets.par_iter().map(|t| walk_target(t)).collect()
+pub fn walk_targets(targets: &[ScanTarget], excludes: &ExclusionMatcher) -> Vec<Result<ScanItem>> {
+    walk_targets_streaming(
+        targets,
+        excludes,
+        &EventSink::none(),
+        &CancelToken::new(),
+        &ScanBudget::unlimited(),
+        None,
+    )
+}
+
+/// Walk multiple targets in parallel, streaming events for each
+pub fn walk_targets_streaming(
+    targets: &[ScanTarget],
+    excludes: &ExclusionMatcher,
+    events: &EventSink,
+    cancel: &CancelToken,
+    budget: &ScanBudget,
+    top_files: Option<usize>,
+) -> Vec<Result<ScanItem>> {
+    targets
+        .par_iter()
+        .map(|t| walk_target_streaming(t, excludes, events, cancel, budget, top_files))
+        .collect()
 }
 
 /// Expand ~ and glob patterns in paths
@@ -158,22 +360,35 @@ pub fn expand_paths(paths: &[String]) -> Vec<PathBuf> {
     expanded
 }
 
-/// Find node_modules directories in common project locations
-pub fn find_node_modules(search_roots: &[PathBuf], stale_days: u32) -> Vec<FileEntry> {
+/// Find stale node_modules directories in common project locations
+///
+/// Staleness of the owning project is decided by `oracle`, falling back to
+/// the mtime of its `package.json` outside git.
+pub fn find_node_modules(
+    search_roots: &[PathBuf],
+    oracle: &StalenessOracle,
+    excludes: &ExclusionMatcher,
+    cancel: &CancelToken,
+    budget: &ScanBudget,
+) -> Vec<FileEntry> {
     let results = Arc::new(Mutex::new(Vec::new()));
-    let stale_threshold = Duration::from_secs(stale_days as u64 * 86400);
 
     search_roots.par_iter().for_each(|root| {
-        if !root.exists() {
+        if !root.exists() || excludes.is_excluded(root) {
+            return;
+        }
+        if !root_allowed(root, budget, PASS_NODE_MODULES) {
             return;
         }
+        let fence = WalkFence::new(root, budget.limits());
 
         let walker = WalkDir::new(root)
             .follow_links(false)
             .parallelism(Parallelism::RayonDefaultPool { busy_timeout: Duration::from_secs(1) });
+        let walker = prune_dirs(walker, excludes, &["node_modules", "Library"], &fence);
 
         for entry_res in walker {
-            if CANCEL_FLAG.load(Ordering::Relaxed) {
+            if should_stop(cancel, budget, PASS_NODE_MODULES) {
                 break;
             }
             let entry = match entry_res {
@@ -181,60 +396,62 @@ pub fn find_node_modules(search_roots: &[PathBuf], stale_days: u32) -> Vec<FileE
                 Err(_) => continue,
             };
 
-            let name = entry.file_name.to_string_lossy();
-            if (name == "node_modules" && entry.depth > 0)
-                || name.starts_with('.')
-                || name == "Library"
-            {
+            if entry.depth == 0 || !entry.file_type.is_dir() || entry.file_name != "node_modules" {
                 continue;
             }
 
             let path = entry.path();
-            if name == "node_modules" && entry.file_type.is_dir() {
-                let package_json = path.parent().map(|p| p.join("package.json"));
-                let is_stale = package_json
-                    .and_then(|pj| std::fs::metadata(&pj).ok())
-                    .and_then(|m| m.modified().ok())
-                    .map(|mod_time| {
-                        SystemTime::now()
-                            .duration_since(mod_time)
-                            .unwrap_or_default()
-                            > stale_threshold
-                    })
-                    .unwrap_or(true);
-
-                if is_stale {
-                    results.lock().unwrap().push(FileEntry {
-                        path: path.to_path_buf(),
-                        size_bytes: dir_size(&path),
-                        modified: std::fs::metadata(&path).ok().and_then(|m| m.modified().ok()),
-                    });
-                }
+            let Some(project) = path.parent() else {
+                continue;
+            };
+            let package_json_mtime = std::fs::metadata(project.join("package.json"))
+                .and_then(|m| m.modified())
+                .ok();
+
+            // A directory is removed wholesale, so skip it if anything inside is excluded
+            if oracle.is_stale(project, package_json_mtime) && !excludes.contains_excluded(&path) {
+                let modified = std::fs::metadata(&path).ok().and_then(|m| m.modified().ok());
+                results.lock().unwrap().push(FileEntry::from_dir(path, modified));
             }
         }
+        fence.report(budget, PASS_NODE_MODULES);
     });
 
     Arc::try_unwrap(results).unwrap().into_inner().unwrap()
 }
 
-/// Find Python virtual environments in common locations
-pub fn find_venvs(search_roots: &[PathBuf], stale_days: u32) -> Vec<FileEntry> {
+/// Find stale Python virtual environments in common locations
+///
+/// Staleness of the owning project is decided by `oracle`, falling back to
+/// the venv directory's mtime outside git.
+pub fn find_venvs(
+    search_roots: &[PathBuf],
+    oracle: &StalenessOracle,
+    excludes: &ExclusionMatcher,
+    cancel: &CancelToken,
+    budget: &ScanBudget,
+) -> Vec<FileEntry> {
+    const VENV_NAMES: &[&str] = &[".venv", "venv", ".env", "env"];
     let results = Arc::new(Mutex::new(Vec::new()));
-    let stale_threshold = Duration::from_secs(stale_days as u64 * 86400);
-    let venv_names = [".venv", "venv", ".env", "env"];
 
     search_roots.par_iter().for_each(|root| {
-        if !root.exists() {
+        if !root.exists() || excludes.is_excluded(root) {
+            return;
+        }
+        if !root_allowed(root, budget, PASS_VENVS) {
             return;
         }
+        let fence = WalkFence::new(root, budget.limits());
 
         let walker = WalkDir::new(root)
             .follow_links(false)
+            .skip_hidden(false)
             .max_depth(3)
             .parallelism(Parallelism::RayonDefaultPool { busy_timeout: Duration::from_secs(1) });
+        let walker = prune_dirs(walker, excludes, &[".git", "node_modules", "Library"], &fence);
 
         for entry_res in walker {
-            if CANCEL_FLAG.load(Ordering::Relaxed) {
+            if should_stop(cancel, budget, PASS_VENVS) {
                 break;
             }
             let entry = match entry_res {
@@ -243,64 +460,60 @@ pub fn find_venvs(search_roots: &[PathBuf], stale_days: u32) -> Vec<FileEntry> {
             };
 
             let name = entry.file_name.to_string_lossy();
-            if entry.file_type.is_dir() && venv_names.contains(&name.as_ref()) {
+            if entry.file_type.is_dir() && VENV_NAMES.contains(&name.as_ref()) {
                 let path = entry.path();
                 let is_venv = path.join("pyvenv.cfg").exists()
                     || path.join("bin/python").exists()
                     || path.join("Scripts/python.exe").exists();
+                let Some(project) = path.parent() else {
+                    continue;
+                };
 
                 if is_venv {
-                    let is_stale = std::fs::metadata(&path)
-                        .ok()
-                        .and_then(|m| m.modified().ok())
-                        .map(|mod_time| {
-                            SystemTime::now()
-                                .duration_since(mod_time)
-                                .unwrap_or_default()
-                                > stale_threshold
-                        })
-                        .unwrap_or(true);
-
-                    if is_stale {
-                        results.lock().unwrap().push(FileEntry {
-                            path: path.to_path_buf(),
-                            size_bytes: dir_size(&path),
-                            modified: std::fs::metadata(&path).ok().and_then(|m| m.modified().ok()),
-                        });
+                    let venv_mtime = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+                    if oracle.is_stale(project, venv_mtime) && !excludes.contains_excluded(&path) {
+                        results.lock().unwrap().push(FileEntry::from_dir(path, venv_mtime));
                     }
                 }
             }
         }
+        fence.report(budget, PASS_VENVS);
     });
 
     Arc::try_unwrap(results).unwrap().into_inner().unwrap()
 }
 
-/// Calculate total size of a directory (physical disk usage)
+/// Calculate total size of a directory (physical disk usage, hard links
+/// counted once)
 pub fn dir_size(path: &Path) -> u64 {
-    WalkDir::new(path)
-        .follow_links(false)
-        .sort(false)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type.is_file())
-        .map(|e| e.metadata().map(|m| m.blocks() * 512).unwrap_or(0))
-        .sum()
+    sizes::dir_usage(path).allocated
 }
 
 /// Find large files in a directory
-pub fn find_large_files(root: &Path, threshold_bytes: u64) -> Vec<FileEntry> {
+pub fn find_large_files(
+    root: &Path,
+    threshold_bytes: u64,
+    excludes: &ExclusionMatcher,
+    cancel: &CancelToken,
+    budget: &ScanBudget,
+) -> Vec<FileEntry> {
     let mut results = Vec::new();
-    if !root.exists() {
+    if !root.exists() || excludes.is_excluded(root) {
+        return results;
+    }
+    if !root_allowed(root, budget, PASS_LARGE_FILES) {
         return results;
     }
 
+    let fence = WalkFence::new(root, budget.limits());
     let walker = WalkDir::new(root)
         .follow_links(false)
         .parallelism(Parallelism::RayonDefaultPool { busy_timeout: Duration::from_secs(1) });
+    let walker = prune_dirs(walker, excludes, &["node_modules", "Library"], &fence);
 
+    let mut files_seen = 0usize;
     for entry_res in walker {
-        if CANCEL_FLAG.load(Ordering::Relaxed) {
+        if should_stop(cancel, budget, PASS_LARGE_FILES) {
             break;
         }
         let entry = match entry_res {
@@ -308,25 +521,24 @@ pub fn find_large_files(root: &Path, threshold_bytes: u64) -> Vec<FileEntry> {
             Err(_) => continue,
         };
 
-        let name = entry.file_name.to_string_lossy();
-        if (name.starts_with('.') || name == "node_modules" || name == "Library") && entry.file_type.is_dir() {
-            continue;
-        }
-
         if entry.file_type.is_file() {
+            if let Some(limit) = budget.limits().max_files {
+                if files_seen >= limit {
+                    budget.record(PASS_LARGE_FILES, TruncationReason::MaxFiles { limit });
+                    break;
+                }
+                files_seen += 1;
+            }
             if let Ok(metadata) = entry.metadata() {
-                let size = metadata.blocks() * 512;
-                if size >= threshold_bytes {
-                    results.push(FileEntry {
-                        path: entry.path().to_path_buf(),
-                        size_bytes: size,
-                        modified: metadata.modified().ok(),
-                    });
+                if sizes::allocated_bytes(&metadata) >= threshold_bytes {
+                    results.push(FileEntry::from_metadata(entry.path(), &metadata));
                 }
             }
         }
     }
 
+    fence.report(budget, PASS_LARGE_FILES);
+
     results.sort_by(|a, b| b.size_bytes.cmp(&a.size_bytes));
     results
 }
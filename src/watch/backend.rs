@@ -0,0 +1,52 @@
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::common::exclusions::ExclusionMatcher;
+
+/// A filesystem change reported by a backend
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchEvent {
+    /// Something at `path` was created, modified, moved or removed
+    ///
+    /// When `path` is a directory, `recursive` says whether its whole
+    /// subtree must be re-read or just its direct entries.
+    Changed { path: PathBuf, recursive: bool },
+    /// The backend dropped events; every watched tree must be rescanned
+    Overflow,
+}
+
+/// Source of filesystem change events
+///
+/// Backends only need to report *which* path changed; the daemon stats the
+/// path itself to work out what happened. Implemented by inotify on Linux and
+/// by a polling fallback everywhere else — an FSEvents backend on macOS would
+/// slot in the same way.
+pub trait WatchBackend: Send {
+    /// Short name shown in `status`
+    fn name(&self) -> &'static str;
+
+    /// Start watching `root` and everything beneath it
+    fn watch_tree(&mut self, root: &Path, excludes: &ExclusionMatcher) -> Result<()>;
+
+    /// Stop watching `root` and everything beneath it
+    fn unwatch_tree(&mut self, root: &Path);
+
+    /// Wait up to `timeout` for changes; a zero timeout reports whatever
+    /// has changed by now without waiting
+    fn poll(&mut self, timeout: Duration) -> Result<Vec<WatchEvent>>;
+}
+
+/// The best backend available on this platform
+pub fn default_backend() -> Result<Box<dyn WatchBackend>> {
+    #[cfg(target_os = "linux")]
+    {
+        Ok(Box::new(super::inotify::InotifyBackend::new()?))
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        Ok(Box::new(super::poll::PollBackend::new(Duration::from_secs(
+            30,
+        ))))
+    }
+}
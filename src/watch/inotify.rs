@@ -0,0 +1,187 @@
+//! inotify backend (Linux)
+//!
+//! inotify watches single directories, so every directory below a watched
+//! root gets its own watch descriptor. New subdirectories are picked up when
+//! the daemon calls `watch_tree` on them after a `Changed` event.
+
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::backend::{WatchBackend, WatchEvent};
+use crate::common::exclusions::ExclusionMatcher;
+
+const WATCH_MASK: u32 = libc::IN_CREATE
+    | libc::IN_DELETE
+    | libc::IN_MODIFY
+    | libc::IN_CLOSE_WRITE
+    | libc::IN_ATTRIB
+    | libc::IN_MOVED_FROM
+    | libc::IN_MOVED_TO
+    | libc::IN_DELETE_SELF
+    | libc::IN_MOVE_SELF
+    | libc::IN_ONLYDIR;
+
+/// Size of `struct inotify_event` without the trailing name
+const EVENT_HEADER: usize = 16;
+
+pub struct InotifyBackend {
+    fd: OwnedFd,
+    by_wd: HashMap<i32, PathBuf>,
+    by_path: HashMap<PathBuf, i32>,
+    buf: Vec<u8>,
+}
+
+impl InotifyBackend {
+    pub fn new() -> Result<Self> {
+        let raw = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if raw < 0 {
+            bail!(
+                "Failed to initialise inotify: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+        Ok(Self {
+            fd: unsafe { OwnedFd::from_raw_fd(raw) },
+            by_wd: HashMap::new(),
+            by_path: HashMap::new(),
+            buf: vec![0u8; 64 * 1024],
+        })
+    }
+
+    fn add_watch(&mut self, dir: &Path) -> Result<()> {
+        let c_path = CString::new(dir.as_os_str().as_bytes())?;
+        let wd = unsafe { libc::inotify_add_watch(self.fd.as_raw_fd(), c_path.as_ptr(), WATCH_MASK) };
+        if wd < 0 {
+            let err = std::io::Error::last_os_error();
+            if err.raw_os_error() == Some(libc::ENOSPC) {
+                bail!(
+                    "inotify watch limit reached at {} (raise fs.inotify.max_user_watches)",
+                    dir.display()
+                );
+            }
+            // Vanished or unreadable directories are simply not watched
+            tracing::debug!("inotify_add_watch {}: {}", dir.display(), err);
+            return Ok(());
+        }
+        self.by_wd.insert(wd, dir.to_path_buf());
+        self.by_path.insert(dir.to_path_buf(), wd);
+        Ok(())
+    }
+
+    fn parse_events(&mut self, len: usize, events: &mut Vec<WatchEvent>) {
+        let mut offset = 0;
+        while offset + EVENT_HEADER <= len {
+            let field = |at: usize| {
+                let bytes: [u8; 4] = self.buf[offset + at..offset + at + 4].try_into().unwrap();
+                bytes
+            };
+            let wd = i32::from_ne_bytes(field(0));
+            let mask = u32::from_ne_bytes(field(4));
+            let name_len = u32::from_ne_bytes(field(12)) as usize;
+            let name_start = offset + EVENT_HEADER;
+            let name_end = (name_start + name_len).min(len);
+            let name: Vec<u8> = self.buf[name_start..name_end]
+                .iter()
+                .copied()
+                .take_while(|b| *b != 0)
+                .collect();
+            offset = name_end;
+
+            if mask & libc::IN_Q_OVERFLOW != 0 {
+                events.push(WatchEvent::Overflow);
+                continue;
+            }
+            let Some(dir) = self.by_wd.get(&wd).cloned() else {
+                continue;
+            };
+            if mask & libc::IN_IGNORED != 0 {
+                self.by_wd.remove(&wd);
+                self.by_path.remove(&dir);
+                continue;
+            }
+
+            let path = if name.is_empty() {
+                dir
+            } else {
+                dir.join(std::ffi::OsString::from_vec(name))
+            };
+            // A directory moved in arrives as one event, so read all of it
+            events.push(WatchEvent::Changed {
+                path,
+                recursive: true,
+            });
+        }
+    }
+}
+
+impl WatchBackend for InotifyBackend {
+    fn name(&self) -> &'static str {
+        "inotify"
+    }
+
+    fn watch_tree(&mut self, root: &Path, excludes: &ExclusionMatcher) -> Result<()> {
+        let walker = walkdir::WalkDir::new(root)
+            .follow_links(false)
+            .into_iter()
+            .filter_entry(|e| {
+                e.file_type().is_dir() && !excludes.is_entry_excluded(e.path(), true)
+            });
+        for entry in walker.filter_map(|e| e.ok()) {
+            if !self.by_path.contains_key(entry.path()) {
+                self.add_watch(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn unwatch_tree(&mut self, root: &Path) {
+        let doomed: Vec<(PathBuf, i32)> = self
+            .by_path
+            .iter()
+            .filter(|(p, _)| p.starts_with(root))
+            .map(|(p, wd)| (p.clone(), *wd))
+            .collect();
+        for (path, wd) in doomed {
+            unsafe { libc::inotify_rm_watch(self.fd.as_raw_fd(), wd) };
+            self.by_wd.remove(&wd);
+            self.by_path.remove(&path);
+        }
+    }
+
+    fn poll(&mut self, timeout: Duration) -> Result<Vec<WatchEvent>> {
+        let mut pfd = libc::pollfd {
+            fd: self.fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let ready = unsafe { libc::poll(&mut pfd, 1, timeout.as_millis() as libc::c_int) };
+        if ready < 0 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() == std::io::ErrorKind::Interrupted {
+                return Ok(Vec::new());
+            }
+            bail!("poll on inotify failed: {}", err);
+        }
+
+        let mut events = Vec::new();
+        loop {
+            let n = unsafe {
+                libc::read(
+                    self.fd.as_raw_fd(),
+                    self.buf.as_mut_ptr() as *mut libc::c_void,
+                    self.buf.len(),
+                )
+            };
+            if n <= 0 {
+                break;
+            }
+            self.parse_events(n as usize, &mut events);
+        }
+        Ok(events)
+    }
+}
@@ -0,0 +1,393 @@
+//! `tidymac watch` — keep scan results warm from filesystem events
+//!
+//! The daemon walks every target once, then applies change events to the
+//! per-target file lists instead of rescanning. Its state is written to
+//! `~/.tidymac/watch/<profile>.json.gz` when results change, where `scan`
+//! picks it up while the daemon's heartbeat is fresh. Between writes the
+//! heartbeat is kept by touching `<profile>.heartbeat` next to it.
+
+pub mod backend;
+#[cfg(target_os = "linux")]
+pub mod inotify;
+pub mod poll;
+pub mod state;
+
+use anyhow::Result;
+use chrono::Utc;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::common::exclusions::ExclusionMatcher;
+use crate::common::permissions;
+use crate::scanner::cache::{self, TargetSnapshot};
+use crate::scanner::targets::{file_totals, FileEntry, ScanItem, ScanTarget};
+use crate::scanner::walker;
+use backend::{WatchBackend, WatchEvent};
+use state::{WatchState, WatchedTarget, HEARTBEAT_SECS, STATE_VERSION};
+
+/// Unwatched targets are rescanned at least this often
+const UNWATCHED_RESCAN: Duration = Duration::from_secs(300);
+
+/// Batch changes for this long before writing the state file
+const SAVE_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Options for a watch run
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    /// Profile the state is stored under; must match what `scan` uses
+    pub profile_name: String,
+    pub excludes: ExclusionMatcher,
+    /// Full rescan interval, which also corrects `min_age_days` drift
+    pub rescan_interval: Duration,
+    /// Set to stop the daemon
+    pub stop: Arc<AtomicBool>,
+}
+
+/// A target as tracked in memory by the daemon
+struct LiveTarget {
+    target: ScanTarget,
+    key: String,
+    roots: Vec<PathBuf>,
+    files: BTreeMap<PathBuf, FileEntry>,
+    synced_at: chrono::DateTime<Utc>,
+    full_scan_at: chrono::DateTime<Utc>,
+    last_full_scan: Instant,
+    pending: bool,
+    watched: bool,
+}
+
+impl LiveTarget {
+    fn new(target: &ScanTarget, excludes: &ExclusionMatcher) -> Self {
+        let roots = walker::expand_paths(&target.paths)
+            .into_iter()
+            .filter(|r| !permissions::is_sip_protected(r) && !excludes.is_excluded(r))
+            .collect();
+        Self {
+            target: target.clone(),
+            key: cache::target_key(target),
+            roots,
+            files: BTreeMap::new(),
+            synced_at: Utc::now(),
+            full_scan_at: Utc::now(),
+            last_full_scan: Instant::now(),
+            pending: true,
+            watched: true,
+        }
+    }
+
+    /// Whether files at `path` can belong to this target
+    fn covers(&self, path: &Path) -> bool {
+        self.roots.iter().any(|root| {
+            if self.target.recursive {
+                path.starts_with(root)
+            } else {
+                path == root || path.parent() == Some(root.as_path())
+            }
+        })
+    }
+
+    fn full_scan(&mut self, excludes: &ExclusionMatcher) {
+        match walker::walk_target(&self.target, excludes) {
+            Ok(item) => {
+                self.files = item
+                    .files
+                    .into_iter()
+                    .map(|f| (f.path.clone(), f))
+                    .collect();
+                self.pending = false;
+                self.synced_at = Utc::now();
+                self.full_scan_at = self.synced_at;
+                self.last_full_scan = Instant::now();
+            }
+            Err(e) => tracing::warn!("Rescan of {} failed: {}", self.target.name, e),
+        }
+    }
+
+    fn remove_under(&mut self, path: &Path) {
+        let doomed: Vec<PathBuf> = self
+            .files
+            .range(path.to_path_buf()..)
+            .take_while(|(p, _)| p.starts_with(path))
+            .map(|(p, _)| p.clone())
+            .collect();
+        for p in doomed {
+            self.files.remove(&p);
+        }
+    }
+
+    fn add_file(&mut self, path: &Path, meta: &std::fs::Metadata) {
+        match walker::target_file_entry(&self.target, path, meta) {
+            Some(entry) => {
+                self.files.insert(path.to_path_buf(), entry);
+            }
+            None => {
+                self.files.remove(path);
+            }
+        }
+    }
+
+    /// Bring the entries at or below `path` in line with the filesystem
+    fn apply(&mut self, path: &Path, recursive: bool, excludes: &ExclusionMatcher) {
+        if !self.covers(path) {
+            return;
+        }
+        if excludes.is_excluded(path) {
+            self.remove_under(path);
+            return;
+        }
+
+        match std::fs::symlink_metadata(path) {
+            Ok(meta) if meta.is_file() => self.add_file(path, &meta),
+            Ok(meta) if meta.is_dir() => {
+                let deep = recursive && self.target.recursive;
+                if deep {
+                    self.remove_under(path);
+                } else {
+                    let direct: Vec<PathBuf> = self
+                        .files
+                        .range(path.to_path_buf()..)
+                        .take_while(|(p, _)| p.starts_with(path))
+                        .filter(|(p, _)| p.parent() == Some(path))
+                        .map(|(p, _)| p.clone())
+                        .collect();
+                    for p in direct {
+                        self.files.remove(&p);
+                    }
+                }
+                if !self.target.recursive && !self.roots.iter().any(|r| r == path) {
+                    return;
+                }
+
+                let walker = walkdir::WalkDir::new(path)
+                    .follow_links(false)
+                    .min_depth(1)
+                    .max_depth(if deep { usize::MAX } else { 1 })
+                    .into_iter()
+                    .filter_entry(|e| {
+                        !excludes.is_entry_excluded(e.path(), e.file_type().is_dir())
+                    });
+                for entry in walker.filter_map(|e| e.ok()) {
+                    if entry.file_type().is_file() {
+                        if let Ok(meta) = entry.metadata() {
+                            self.add_file(entry.path(), &meta);
+                        }
+                    }
+                }
+            }
+            // Gone, or no longer a regular file
+            _ => self.remove_under(path),
+        }
+        self.synced_at = Utc::now();
+    }
+
+    fn to_watched(&self) -> WatchedTarget {
+        let files: Vec<FileEntry> = self.files.values().cloned().collect();
+        let usage = file_totals(&files);
+        WatchedTarget {
+            key: self.key.clone(),
+            item: ScanItem {
+                name: self.target.name.clone(),
+                category: self.target.category.clone(),
+                path: self.roots.first().cloned().unwrap_or_default(),
+                size_bytes: usage.allocated,
+                apparent_bytes: usage.apparent,
+                freeable_bytes: usage.freeable,
+                file_count: files.len(),
+                safety: self.target.safety.clone(),
+                reason: self.target.reason.clone(),
+                files,
+                files_partial: false,
+                trim: self.target.trim.clone(),
+                roots: self.roots.clone(),
+            },
+            synced_at: self.synced_at,
+            full_scan_at: self.full_scan_at,
+            pending: self.pending,
+            watched: self.watched,
+            dirs: Vec::new(),
+        }
+    }
+}
+
+/// Run the watch daemon until `opts.stop` is set
+///
+/// Returns the final state, which has also been saved with `stopped` set.
+pub fn run(
+    targets: &[ScanTarget],
+    opts: &WatchOptions,
+    mut backend: Box<dyn WatchBackend>,
+) -> Result<WatchState> {
+    let excludes = &opts.excludes;
+    let previous = WatchState::load(&opts.profile_name)
+        .filter(|s| s.excludes == excludes.patterns() && s.stopped);
+
+    let mut live: Vec<LiveTarget> = targets
+        .iter()
+        .map(|t| LiveTarget::new(t, excludes))
+        .collect();
+
+    // Subscribe before the first walk so nothing changes unseen in between
+    for t in live.iter_mut() {
+        for root in &t.roots {
+            if !root.is_dir() {
+                continue;
+            }
+            if let Err(e) = backend.watch_tree(root, excludes) {
+                tracing::warn!("Not watching {}: {}", root.display(), e);
+                t.watched = false;
+            }
+        }
+    }
+
+    let mut state = WatchState {
+        version: STATE_VERSION,
+        profile: opts.profile_name.clone(),
+        pid: std::process::id(),
+        backend: backend.name().to_string(),
+        excludes: excludes.patterns().to_vec(),
+        started_at: Utc::now(),
+        heartbeat_at: Utc::now(),
+        stopped: false,
+        targets: Vec::new(),
+    };
+
+    // Reuse results from a clean shutdown when nothing moved since
+    for t in live.iter_mut() {
+        if opts.stop.load(Ordering::Relaxed) {
+            break;
+        }
+        let restored = previous
+            .as_ref()
+            .and_then(|p| p.targets.iter().find(|w| w.key == t.key));
+        match restored {
+            Some(w)
+                if !w.dirs.is_empty()
+                    && w.dirs == TargetSnapshot::take(&t.target, excludes).dirs =>
+            {
+                t.files = w
+                    .item
+                    .files
+                    .iter()
+                    .map(|f| (f.path.clone(), f.clone()))
+                    .collect();
+                t.full_scan_at = w.full_scan_at;
+                t.pending = false;
+            }
+            _ => t.full_scan(excludes),
+        }
+    }
+    state.targets = live.iter().map(|t| t.to_watched()).collect();
+    state.save()?;
+
+    let mut dirty = false;
+    let mut last_save = Instant::now();
+    let mut last_beat = Instant::now();
+    while !opts.stop.load(Ordering::Relaxed) {
+        let events = backend.poll(Duration::from_secs(1))?;
+        dirty |= !events.is_empty();
+        apply_events(&mut live, backend.as_mut(), events, excludes);
+
+        let due: Vec<usize> = live
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| {
+                let interval = if t.watched {
+                    opts.rescan_interval
+                } else {
+                    opts.rescan_interval.min(UNWATCHED_RESCAN)
+                };
+                t.pending || t.last_full_scan.elapsed() >= interval
+            })
+            .map(|(i, _)| i)
+            .collect();
+        if !due.is_empty() {
+            // Publish pending flags first so `scan` stops trusting those targets
+            if due.iter().any(|&i| live[i].pending) {
+                state.targets = live.iter().map(|t| t.to_watched()).collect();
+                if let Err(e) = state.save() {
+                    tracing::warn!("Failed to save watch state: {:#}", e);
+                }
+            }
+            for i in due {
+                live[i].full_scan(excludes);
+            }
+            dirty = true;
+        }
+
+        // A failed write stays dirty and is retried after the debounce
+        if dirty && last_save.elapsed() >= SAVE_DEBOUNCE {
+            state.targets = live.iter().map(|t| t.to_watched()).collect();
+            match state.save() {
+                Ok(()) => {
+                    dirty = false;
+                    last_beat = Instant::now();
+                }
+                Err(e) => tracing::warn!("Failed to save watch state: {:#}", e),
+            }
+            last_save = Instant::now();
+        } else if last_beat.elapsed() >= Duration::from_secs(HEARTBEAT_SECS as u64) {
+            if let Err(e) = state.beat() {
+                tracing::warn!("Failed to write watch heartbeat: {:#}", e);
+            }
+            last_beat = Instant::now();
+        }
+    }
+
+    // Record directory mtimes so the next run can skip unchanged targets.
+    // Changes that land after the snapshot make it mismatch, which only
+    // costs a rescan; changes before it are drained and applied here.
+    let snapshots: Vec<_> = live
+        .iter()
+        .map(|t| TargetSnapshot::take(&t.target, excludes).dirs)
+        .collect();
+    let events = backend.poll(Duration::ZERO)?;
+    apply_events(&mut live, backend.as_mut(), events, excludes);
+
+    state.targets = live
+        .iter()
+        .zip(snapshots)
+        .map(|(t, dirs)| WatchedTarget {
+            dirs: if t.pending { Vec::new() } else { dirs },
+            ..t.to_watched()
+        })
+        .collect();
+    state.stopped = true;
+    state.save()?;
+    Ok(state)
+}
+
+/// Route backend events to the targets they affect
+fn apply_events(
+    live: &mut [LiveTarget],
+    backend: &mut dyn WatchBackend,
+    events: Vec<WatchEvent>,
+    excludes: &ExclusionMatcher,
+) {
+    for event in events {
+        match event {
+            WatchEvent::Overflow => {
+                tracing::warn!("Watch events overflowed; rescanning every target");
+                for t in live.iter_mut() {
+                    t.pending = true;
+                }
+            }
+            WatchEvent::Changed { path, recursive } => {
+                // New directories need their own watches before being read
+                if recursive && path.is_dir() && live.iter().any(|t| t.covers(&path)) {
+                    if let Err(e) = backend.watch_tree(&path, excludes) {
+                        tracing::warn!("Not watching {}: {}", path.display(), e);
+                        for t in live.iter_mut().filter(|t| t.covers(&path)) {
+                            t.watched = false;
+                        }
+                    }
+                }
+                for t in live.iter_mut() {
+                    t.apply(&path, recursive, excludes);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Polling backend
+//!
+//! Every `interval` re-reads the size and mtime of every directory and file
+//! under the watched roots, and reports entries that appeared, vanished or
+//! changed. Files are compared as well as directories because a file that
+//! grows in place, like a log, leaves its directory's mtime alone. Every
+//! entry is tracked on its own, so events are never recursive. Used where
+//! no native event API is wired up.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::backend::{WatchBackend, WatchEvent};
+use crate::common::exclusions::ExclusionMatcher;
+
+/// What a poll compares: mtime seconds, mtime nanoseconds and size
+type Stamp = (i64, i64, u64);
+
+pub struct PollBackend {
+    interval: Duration,
+    roots: Vec<(PathBuf, ExclusionMatcher)>,
+    entries: HashMap<PathBuf, Stamp>,
+    last_snapshot: Instant,
+}
+
+impl PollBackend {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            roots: Vec::new(),
+            entries: HashMap::new(),
+            last_snapshot: Instant::now(),
+        }
+    }
+
+    /// Record `path` and, for a directory, everything below it
+    fn snapshot(path: &Path, excludes: &ExclusionMatcher, out: &mut HashMap<PathBuf, Stamp>) {
+        let Ok(meta) = std::fs::symlink_metadata(path) else {
+            return;
+        };
+        out.insert(
+            path.to_path_buf(),
+            (meta.mtime(), meta.mtime_nsec(), meta.size()),
+        );
+        if !meta.is_dir() {
+            return;
+        }
+        let Ok(entries) = std::fs::read_dir(path) else {
+            return;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            let child = entry.path();
+            if !excludes.is_entry_excluded(&child, is_dir) {
+                Self::snapshot(&child, excludes, out);
+            }
+        }
+    }
+}
+
+impl WatchBackend for PollBackend {
+    fn name(&self) -> &'static str {
+        "poll"
+    }
+
+    fn watch_tree(&mut self, root: &Path, excludes: &ExclusionMatcher) -> Result<()> {
+        if self.roots.iter().any(|(r, _)| root.starts_with(r)) {
+            return Ok(());
+        }
+        Self::snapshot(root, excludes, &mut self.entries);
+        self.roots.push((root.to_path_buf(), excludes.clone()));
+        Ok(())
+    }
+
+    fn unwatch_tree(&mut self, root: &Path) {
+        self.roots.retain(|(r, _)| !r.starts_with(root));
+        self.entries.retain(|p, _| !p.starts_with(root));
+    }
+
+    fn poll(&mut self, timeout: Duration) -> Result<Vec<WatchEvent>> {
+        // Walking every root is the expensive part, so it happens at most
+        // once per interval however often the caller polls; a zero timeout
+        // (draining before shutdown) checks right away
+        let due = self.interval.saturating_sub(self.last_snapshot.elapsed());
+        if !timeout.is_zero() {
+            if due > timeout {
+                std::thread::sleep(timeout);
+                return Ok(Vec::new());
+            }
+            std::thread::sleep(due);
+        }
+        self.last_snapshot = Instant::now();
+
+        let mut current = HashMap::new();
+        for (root, excludes) in &self.roots {
+            Self::snapshot(root, excludes, &mut current);
+        }
+
+        let mut events: Vec<WatchEvent> = current
+            .iter()
+            .filter(|(p, m)| self.entries.get(*p) != Some(m))
+            .map(|(p, _)| WatchEvent::Changed {
+                path: p.clone(),
+                recursive: false,
+            })
+            .collect();
+        events.extend(
+            self.entries
+                .keys()
+                .filter(|p| !current.contains_key(*p))
+                .map(|p| WatchEvent::Changed {
+                    path: p.clone(),
+                    recursive: false,
+                }),
+        );
+        self.entries = current;
+        Ok(events)
+    }
+}
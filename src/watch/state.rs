@@ -0,0 +1,171 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::common::config::Config;
+use crate::scanner::cache::{self, DirMtime};
+use crate::scanner::targets::ScanItem;
+
+/// On-disk format version of the daemon state
+pub const STATE_VERSION: u32 = 4;
+
+/// How often a running daemon touches its heartbeat file
+pub const HEARTBEAT_SECS: i64 = 10;
+
+/// A daemon that hasn't written a heartbeat for this long is treated as gone
+pub const LIVE_WINDOW_SECS: i64 = 3 * HEARTBEAT_SECS;
+
+/// Current results for one scan target, as kept by the daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchedTarget {
+    /// `scanner::cache::target_key` of the target
+    pub key: String,
+    pub item: ScanItem,
+    /// When `item` last reflected the filesystem
+    pub synced_at: DateTime<Utc>,
+    /// When the target was last walked in full
+    pub full_scan_at: DateTime<Utc>,
+    /// Waiting for a full rescan, e.g. after the backend dropped events
+    pub pending: bool,
+    /// Whether change events arrive for this target; unwatched targets are
+    /// only refreshed by periodic rescans
+    pub watched: bool,
+    /// Directory mtimes at shutdown, used to reuse `item` after a restart
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dirs: Vec<DirMtime>,
+}
+
+/// Everything a `tidymac watch` daemon knows, persisted per profile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchState {
+    pub version: u32,
+    pub profile: String,
+    pub pid: u32,
+    /// Event backend in use (`inotify`, `poll`, ...)
+    pub backend: String,
+    /// `exclude_paths` patterns the items were computed with
+    pub excludes: Vec<String>,
+    pub started_at: DateTime<Utc>,
+    /// Last sign of life: the later of the last save and the heartbeat
+    /// file's mtime
+    pub heartbeat_at: DateTime<Utc>,
+    /// Set when the daemon shut down cleanly
+    pub stopped: bool,
+    pub targets: Vec<WatchedTarget>,
+}
+
+impl WatchState {
+    /// Directory holding daemon state files
+    pub fn state_dir() -> PathBuf {
+        Config::data_dir().join("watch")
+    }
+
+    /// State file for a profile
+    pub fn state_path(profile: &str) -> PathBuf {
+        Self::state_dir().join(format!("{}.json.gz", profile))
+    }
+
+    /// Heartbeat file for a profile, touched between state saves
+    pub fn heartbeat_path(profile: &str) -> PathBuf {
+        Self::state_dir().join(format!("{}.heartbeat", profile))
+    }
+
+    /// Load the last state written for a profile
+    pub fn load(profile: &str) -> Option<Self> {
+        Self::load_from(&Self::state_path(profile))
+    }
+
+    fn load_from(path: &Path) -> Option<Self> {
+        let mut state: Self = cache::read_versioned(path, STATE_VERSION)?;
+        let touched = std::fs::metadata(Self::heartbeat_path(&state.profile))
+            .and_then(|m| m.modified())
+            .map(DateTime::<Utc>::from);
+        if let Ok(touched) = touched {
+            state.heartbeat_at = state.heartbeat_at.max(touched);
+        }
+        Some(state)
+    }
+
+    /// State of every profile that has ever been watched
+    pub fn list() -> Vec<Self> {
+        let Ok(entries) = std::fs::read_dir(Self::state_dir()) else {
+            return Vec::new();
+        };
+        let mut states: Vec<Self> = entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().ends_with(".json.gz"))
+            .filter_map(|e| Self::load_from(&e.path()))
+            .collect();
+        states.sort_by(|a, b| a.profile.cmp(&b.profile));
+        states
+    }
+
+    /// Save the state, refreshing the heartbeat
+    pub fn save(&mut self) -> Result<()> {
+        self.heartbeat_at = Utc::now();
+        cache::write_compressed(&Self::state_path(&self.profile), self)
+    }
+
+    /// Refresh the heartbeat without rewriting the state
+    pub fn beat(&mut self) -> Result<()> {
+        let path = Self::heartbeat_path(&self.profile);
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        let now = Utc::now();
+        file.set_modified(now.into())
+            .with_context(|| format!("Failed to touch {}", path.display()))?;
+        self.heartbeat_at = now;
+        Ok(())
+    }
+
+    /// Whether a daemon is currently keeping this state up to date
+    pub fn is_live(&self) -> bool {
+        !self.stopped && (Utc::now() - self.heartbeat_at).num_seconds() <= LIVE_WINDOW_SECS
+    }
+
+    /// Up-to-date item for a target, if the daemon has one
+    ///
+    /// Unwatched targets are left out: they are only as fresh as the last
+    /// periodic rescan, so a scan walks them itself.
+    pub fn item(&self, key: &str) -> Option<&ScanItem> {
+        self.targets
+            .iter()
+            .find(|t| t.key == key && t.watched && !t.pending)
+            .map(|t| &t.item)
+    }
+}
+
+/// Human-readable staleness of a target in `state`
+pub fn staleness(state: &WatchState, target: &WatchedTarget) -> String {
+    if target.pending {
+        "rescan pending".to_string()
+    } else if !state.is_live() {
+        // Nothing was tracked after the daemon's last heartbeat
+        format!(
+            "{} old, daemon not running",
+            age_string(state.heartbeat_at)
+        )
+    } else if target.watched {
+        "live".to_string()
+    } else {
+        format!("{} old, not watched", age_string(target.full_scan_at))
+    }
+}
+
+fn age_string(since: DateTime<Utc>) -> String {
+    let secs = (Utc::now() - since).num_seconds().max(0);
+    if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 3600 {
+        format!("{}m", secs / 60)
+    } else if secs < 86400 {
+        format!("{}h", secs / 3600)
+    } else {
+        format!("{}d", secs / 86400)
+    }
+}
@@ -50,6 +50,32 @@ fn test_scan_json_output() {
         .stdout(predicate::str::contains("total_reclaimable"));
 }
 
+#[test]
+fn test_scan_jsonl_output() {
+    let output = tidymac()
+        .args(["scan", "--format", "jsonl", "--no-cache"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let events: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("every line is one JSON event"))
+        .collect();
+    assert_eq!(events.first().unwrap()["event"], "started");
+    assert_eq!(events.last().unwrap()["event"], "done");
+}
+
+#[test]
+fn test_jsonl_rejected_outside_scan() {
+    tidymac()
+        .args(["status", "--format", "jsonl"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("only supported by scan"));
+}
+
 #[test]
 fn test_scan_with_profile() {
     tidymac()
@@ -89,6 +115,71 @@ fn test_status() {
         .stdout(predicate::str::contains("TidyMac Status"));
 }
 
+// ─── Diff command ────────────────────────────────────────────────────────────
+
+#[test]
+fn test_diff_json_output() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let item = |size: u64| {
+        format!(
+            r#"{{"name":"Logs","category":"logs","path":"/tmp/logs","size_bytes":{size},"file_count":1,"safety":"safe","reason":"test"}}"#
+        )
+    };
+    let results = |size: u64| {
+        format!(
+            r#"{{"timestamp":"2026-01-01T00:00:00Z","duration_secs":1.0,"items":[{}],"total_reclaimable":{size},"total_files":1,"errors":[]}}"#,
+            item(size)
+        )
+    };
+    let old = dir.path().join("old.json");
+    let new = dir.path().join("new.json");
+    std::fs::write(&old, results(100)).unwrap();
+    std::fs::write(
+        &new,
+        format!(r#"{{"snapshot_version":1,"tidymac_version":"1.0.0","profile":"quick","results":{}}}"#, results(400)),
+    )
+    .unwrap();
+
+    tidymac()
+        .arg("diff")
+        .arg(&old)
+        .arg(&new)
+        .args(["--format", "json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"delta_bytes\": 300"))
+        .stdout(predicate::str::contains("\"change\": \"grown\""));
+}
+
+#[test]
+fn test_diff_missing_snapshot() {
+    tidymac()
+        .args(["diff", "/nonexistent/old.json", "/nonexistent/new.json"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to read snapshot"));
+}
+
+// ─── Trend command ───────────────────────────────────────────────────────────
+
+#[test]
+fn test_trend_json_output() {
+    tidymac()
+        .args(["trend", "--format", "json", "--days", "7"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("fastest_growing"))
+        .stdout(predicate::str::contains("runaway"));
+}
+
+#[test]
+fn test_trend_rejects_negative_threshold() {
+    tidymac()
+        .args(["trend", "--runaway-gb=-1"])
+        .assert()
+        .failure();
+}
+
 // ─── Undo command ────────────────────────────────────────────────────────────
 
 #[test]
@@ -105,6 +196,33 @@ fn test_undo_no_flags_shows_help() {
         .stdout(predicate::str::contains("Undo"));
 }
 
+#[test]
+fn test_undo_find_needs_something_to_search_for() {
+    tidymac()
+        .args(["undo", "find"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("pattern or a size range"));
+}
+
+#[test]
+fn test_undo_find_json_lists_matches() {
+    tidymac()
+        .args(["undo", "find", "*.no-such-extension", "--format", "json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("["));
+}
+
+#[test]
+fn test_undo_show_unknown_session() {
+    tidymac()
+        .args(["undo", "show", "1999-01-01T00-00-00"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("not found"));
+}
+
 // ─── Purge command ───────────────────────────────────────────────────────────
 
 #[test]
@@ -207,6 +325,85 @@ fn test_clean_dry_run() {
         .success();
 }
 
+#[test]
+fn test_scan_unknown_category() {
+    tidymac()
+        .args(["scan", "--categories", "bogus"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Valid values"));
+}
+
+#[test]
+fn test_clean_invalid_max_safety() {
+    tidymac()
+        .args(["clean", "--dry-run", "--max-safety", "reckless"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_clean_free_invalid_size() {
+    tidymac()
+        .args(["clean", "--dry-run", "--free", "12parsecs"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown size unit"));
+}
+
+#[test]
+fn test_clean_free_dry_run() {
+    tidymac()
+        .args(["clean", "--dry-run", "--free", "1B"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_clean_plan_dry_run_skips_changed_files() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let kept = dir.path().join("kept.log");
+    std::fs::write(&kept, "keep me").unwrap();
+    let modified: chrono::DateTime<chrono::Utc> =
+        std::fs::metadata(&kept).unwrap().modified().unwrap().into();
+    let plan = serde_json::json!({
+        "plan_version": 1,
+        "tidymac_version": "1.0.0",
+        "profile": "quick",
+        "created_at": "2026-01-01T00:00:00Z",
+        "items": [{
+            "name": "Logs",
+            "category": "logs",
+            "safety": "safe",
+            "path": dir.path(),
+            "files": [
+                { "path": kept, "size_bytes": 7, "modified": modified },
+                { "path": dir.path().join("gone.log"), "size_bytes": 10, "modified": null }
+            ]
+        }]
+    });
+    let plan_path = dir.path().join("plan.json");
+    std::fs::write(&plan_path, plan.to_string()).unwrap();
+
+    tidymac()
+        .args(["clean", "--dry-run", "--plan"])
+        .arg(&plan_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("no longer exists"))
+        .stdout(predicate::str::contains("would clean 1 files"));
+    assert!(kept.exists());
+}
+
+#[test]
+fn test_clean_plan_conflicts_with_free() {
+    tidymac()
+        .args(["clean", "--plan", "plan.json", "--free", "1GB"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
 // ─── Dup command ─────────────────────────────────────────────────────────────
 
 #[test]
@@ -1,5 +1,6 @@
 use tempfile::TempDir;
 
+use tidymac::common::cancel::CancelToken;
 use tidymac::duplicates::hasher;
 
 #[test]
@@ -106,7 +107,7 @@ fn test_group_by_size() {
         dir.path().join("c.txt"),
     ];
 
-    let groups = hasher::group_by_size(&files);
+    let groups = hasher::group_by_size(&files, &CancelToken::new());
 
     // Only the 5-byte group should remain (2+ files)
     assert_eq!(groups.len(), 1, "Should have 1 size group");
@@ -128,7 +129,7 @@ fn test_group_by_size_no_duplicates() {
         dir.path().join("c.txt"),
     ];
 
-    let groups = hasher::group_by_size(&files);
+    let groups = hasher::group_by_size(&files, &CancelToken::new());
     assert_eq!(groups.len(), 0, "No size groups when all sizes are unique");
 }
 
@@ -146,7 +147,7 @@ fn test_group_by_full_hash() {
         dir.path().join("c.txt"),
     ];
 
-    let groups = hasher::group_by_full_hash(&files);
+    let groups = hasher::group_by_full_hash(&files, &CancelToken::new());
     assert_eq!(
         groups.len(),
         1,
@@ -172,3 +173,16 @@ fn test_empty_file_hashing() {
         "Quick and full hash of empty file should be identical"
     );
 }
+
+#[test]
+fn test_cancel_token_only_stops_its_own_operation() {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("a.txt"), "same").unwrap();
+    std::fs::write(dir.path().join("b.txt"), "same").unwrap();
+    let files = vec![dir.path().join("a.txt"), dir.path().join("b.txt")];
+
+    let cancelled = CancelToken::new();
+    cancelled.cancel();
+    assert!(hasher::group_by_full_hash(&files, &cancelled).is_empty());
+    assert_eq!(hasher::group_by_full_hash(&files, &CancelToken::new()).len(), 1);
+}
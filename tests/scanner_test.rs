@@ -1,9 +1,20 @@
+use std::path::Path;
 use tempfile::TempDir;
 
+use tidymac::common::cancel::CancelToken;
 use tidymac::common::config::Config;
+use tidymac::common::exclusions::ExclusionMatcher;
 use tidymac::common::format;
 use tidymac::common::permissions;
 use tidymac::profiles::loader::Profile;
+use tidymac::scanner::dev_detector;
+use tidymac::scanner::{self, LargeFileScan, ScanOptions};
+use tidymac::scanner::git::GitRepo;
+use tidymac::scanner::history::{ScanHistory, ScanRecord};
+use tidymac::scanner::limits::{ScanBudget, ScanLimits, TruncationReason};
+use tidymac::scanner::snapshot::{Snapshot, SNAPSHOT_VERSION};
+use tidymac::scanner::diff::{self, Change};
+use tidymac::scanner::staleness::StalenessOracle;
 use tidymac::scanner::targets;
 use tidymac::scanner::walker;
 
@@ -189,6 +200,19 @@ fn test_deep_profile_enables_everything() {
     assert!(profile.targets.dev.docker_dangling);
 }
 
+#[test]
+fn test_large_file_scan_follows_profile() {
+    let quick = Profile::load("quick").unwrap();
+    assert!(quick.large_file_scan().is_none(), "quick must skip the large-file pass");
+
+    let deep = Profile::load("deep").unwrap();
+    let scan = deep.large_file_scan().expect("deep enables large files");
+    assert_eq!(scan.threshold_bytes, 100 * 1024 * 1024);
+    assert_eq!(scan.min_age_days, Some(30));
+    assert!(!scan.roots.is_empty());
+    assert!(scan.roots.iter().all(|r| !r.to_string_lossy().contains('~')));
+}
+
 #[test]
 fn test_profile_enabled_targets_returns_correct_subset() {
     let quick = Profile::load("quick").unwrap();
@@ -212,6 +236,161 @@ fn test_available_profiles_includes_builtins() {
     assert!(profiles.contains(&"deep".to_string()));
 }
 
+#[test]
+fn test_profile_custom_targets_parse_and_enable() {
+    let toml_str = r#"
+        [profile]
+        name = "team"
+        description = "Team profile"
+        aggression = "low"
+
+        [targets]
+        user_caches = false
+
+        [[targets.custom]]
+        name = "Bazel Cache"
+        paths = ["/tmp/tidymac-test-bazel"]
+        category = { dev_cache = "cargo" }
+        safety = "safe"
+        min_age_days = 14
+        reason = "Bazel output base"
+
+        [[targets.custom]]
+        name = "Disabled Cache"
+        paths = ["/tmp/tidymac-test-disabled"]
+        enabled = false
+    "#;
+    let profile: Profile = toml::from_str(toml_str).unwrap();
+    assert!(profile.validate().is_ok());
+
+    let enabled = profile.enabled_targets();
+    let bazel = enabled
+        .iter()
+        .find(|t| t.name == "Bazel Cache")
+        .expect("custom target should be enabled even with user_caches = false");
+    assert_eq!(
+        bazel.category,
+        targets::Category::DevCache(targets::DevTool::Cargo)
+    );
+    assert_eq!(bazel.safety, targets::SafetyLevel::Safe);
+    assert_eq!(bazel.min_age_days, Some(14));
+    assert!(bazel.recursive);
+    assert!(!enabled.iter().any(|t| t.name == "Disabled Cache"));
+}
+
+#[test]
+fn test_profile_custom_target_rejects_protected_path() {
+    let toml_str = r#"
+        [profile]
+        name = "bad"
+        description = "Points at a protected path"
+        aggression = "low"
+
+        [targets]
+
+        [[targets.custom]]
+        name = "Oops"
+        paths = ["/System"]
+    "#;
+    let profile: Profile = toml::from_str(toml_str).unwrap();
+    assert!(profile.validate().is_err());
+}
+
+#[test]
+fn test_profile_trim_policies_apply_by_name_and_category() {
+    let toml_str = r#"
+        [profile]
+        name = "trimmed"
+        description = "Caps caches"
+        aggression = "low"
+
+        [targets]
+        user_caches = true
+
+        [targets.dev]
+        cargo_cache = true
+        npm_cache = true
+
+        [trim]
+        "dev_cache.cargo" = { max_gb = 10 }
+        "dev_cache.*" = { keep_days = 30 }
+        "User Cache Files" = { max_gb = 2, keep_days = 7 }
+    "#;
+    let profile: Profile = toml::from_str(toml_str).unwrap();
+    assert!(profile.validate().is_ok());
+
+    let enabled = profile.enabled_targets();
+    let trim_of = |category: targets::Category| {
+        enabled
+            .iter()
+            .find(|t| t.category == category)
+            .and_then(|t| t.trim.clone())
+    };
+    let cargo = trim_of(targets::Category::DevCache(targets::DevTool::Cargo)).unwrap();
+    assert_eq!(cargo.max_gb, Some(10.0));
+    assert_eq!(cargo.keep_days, None);
+    let npm = trim_of(targets::Category::DevCache(targets::DevTool::Npm)).unwrap();
+    assert_eq!(npm.keep_days, Some(30));
+    let user = enabled
+        .iter()
+        .find(|t| t.name == "User Cache Files")
+        .and_then(|t| t.trim.clone())
+        .unwrap();
+    assert_eq!(user.max_bytes(), Some(2 * 1024 * 1024 * 1024));
+}
+
+#[test]
+fn test_profile_trim_policy_rejects_empty_policy() {
+    let toml_str = r#"
+        [profile]
+        name = "bad"
+        description = "Trims nothing"
+        aggression = "low"
+
+        [targets]
+
+        [trim]
+        "dev_cache.cargo" = {}
+    "#;
+    let profile: Profile = toml::from_str(toml_str).unwrap();
+    assert!(profile.validate().is_err());
+}
+
+#[test]
+fn test_custom_target_walks_like_builtin() {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("blob.bin"), "x".repeat(4096)).unwrap();
+
+    let toml_str = format!(
+        r#"
+        [profile]
+        name = "team"
+        description = "Team profile"
+        aggression = "low"
+
+        [targets]
+
+        [[targets.custom]]
+        name = "Team Cache"
+        paths = ["{}"]
+        safety = "safe"
+        "#,
+        dir.path().display()
+    );
+    let profile: Profile = toml::from_str(&toml_str).unwrap();
+    let target = profile
+        .enabled_targets()
+        .into_iter()
+        .find(|t| t.name == "Team Cache")
+        .unwrap();
+
+    let item = walker::walk_target(&target, &ExclusionMatcher::empty()).unwrap();
+    assert_eq!(item.name, "Team Cache");
+    assert_eq!(item.category, targets::Category::UserCache);
+    assert_eq!(item.file_count, 1);
+    assert_eq!(item.files.len(), 1);
+}
+
 // ─── Scanner targets tests ────────────────────────────────────────────────────
 
 #[test]
@@ -252,6 +431,51 @@ fn test_developer_targets() {
     assert!(names.iter().any(|n| n.contains("Cargo")));
 }
 
+#[test]
+fn test_category_filter_selectors() {
+    let names: Vec<String> = ["user_cache", "dev_cache.cargo"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let filter = targets::CategoryFilter::parse(&names).unwrap();
+
+    assert!(filter.matches(&targets::Category::UserCache));
+    assert!(filter.matches(&targets::Category::DevCache(targets::DevTool::Cargo)));
+    assert!(!filter.matches(&targets::Category::DevCache(targets::DevTool::Npm)));
+    assert!(!filter.matches(&targets::Category::Logs));
+
+    for name in ["dev_cache", "dev_cache.*", " DEV_CACHE.* "] {
+        let filter = targets::CategoryFilter::parse(&[name.to_string()]).unwrap();
+        assert!(filter.matches(&targets::Category::DevCache(targets::DevTool::Npm)));
+        assert!(filter.matches(&targets::Category::DevCache(targets::DevTool::Xcode)));
+        assert!(!filter.matches(&targets::Category::UserCache));
+    }
+}
+
+#[test]
+fn test_category_filter_rejects_unknown() {
+    let err = targets::CategoryFilter::parse(&["dev_cache.nope".to_string()])
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("dev_cache.nope"));
+    assert!(err.contains("Valid values"));
+    assert!(err.contains("user_cache"));
+
+    assert!(targets::CategoryFilter::parse(&[]).is_err());
+}
+
+#[test]
+fn test_category_slugs_cover_all_targets() {
+    let slugs = targets::Category::all_slugs();
+    for t in targets::all_targets() {
+        assert!(
+            slugs.contains(&t.category.slug()),
+            "Missing slug for {}",
+            t.name
+        );
+    }
+}
+
 // ─── Walker tests ─────────────────────────────────────────────────────────────
 
 #[test]
@@ -317,6 +541,70 @@ fn test_dir_size_nonexistent() {
     assert_eq!(size, 0);
 }
 
+fn walk_dir_target(root: &Path) -> targets::ScanItem {
+    let target = targets::ScanTarget {
+        name: "Test".to_string(),
+        category: targets::Category::UserCache,
+        paths: vec![root.display().to_string()],
+        safety: targets::SafetyLevel::Safe,
+        recursive: true,
+        min_age_days: None,
+        reason: "test".to_string(),
+        trim: None,
+    };
+    walker::walk_target(&target, &ExclusionMatcher::empty()).unwrap()
+}
+
+#[test]
+fn test_walk_counts_hard_links_once() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path().join("target");
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("a.bin"), vec![7u8; 64 * 1024]).unwrap();
+    std::fs::hard_link(root.join("a.bin"), root.join("b.bin")).unwrap();
+
+    let item = walk_dir_target(&root);
+    // Both paths are listed so cleaning removes both...
+    assert_eq!(item.file_count, 2);
+    assert!(item.files.iter().all(|f| f.hard_link.is_some()));
+    // ...but the blocks are only counted once, and all of them come back
+    let single = item.files[0].size_bytes;
+    assert_eq!(item.size_bytes, single);
+    assert_eq!(item.apparent_bytes, 64 * 1024);
+    assert_eq!(item.freeable_bytes, single);
+    assert_eq!(walker::dir_size(&root), single);
+}
+
+#[test]
+fn test_walk_hard_link_outside_target_frees_nothing() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path().join("target");
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("a.bin"), vec![7u8; 64 * 1024]).unwrap();
+    std::fs::hard_link(root.join("a.bin"), dir.path().join("keep.bin")).unwrap();
+
+    let item = walk_dir_target(&root);
+    assert_eq!(item.file_count, 1);
+    assert!(item.size_bytes > 0);
+    assert_eq!(item.freeable_bytes, 0, "The other link keeps the data alive");
+}
+
+#[test]
+fn test_walk_reports_sparse_files_by_allocation() {
+    let dir = TempDir::new().unwrap();
+    let file = std::fs::File::create(dir.path().join("disk.img")).unwrap();
+    file.set_len(100 * 1024 * 1024).unwrap();
+
+    let item = walk_dir_target(dir.path());
+    assert_eq!(item.apparent_bytes, 100 * 1024 * 1024);
+    assert!(
+        item.size_bytes < 1024 * 1024,
+        "A sparse file should allocate almost nothing, got {}",
+        item.size_bytes
+    );
+    assert_eq!(item.freeable_bytes, item.size_bytes);
+}
+
 #[test]
 fn test_find_large_files() {
     let dir = TempDir::new().unwrap();
@@ -327,7 +615,7 @@ fn test_find_large_files() {
     std::fs::write(scan_dir.join("small.txt"), "tiny").unwrap();
     std::fs::write(scan_dir.join("big.txt"), "x".repeat(2000)).unwrap();
 
-    let large = walker::find_large_files(&scan_dir, 1000);
+    let large = walker::find_large_files(&scan_dir, 1000, &ExclusionMatcher::empty(), &CancelToken::new(), &ScanBudget::unlimited());
     // Both files may exceed 1000 bytes in physical blocks, so filter by name
     let big_files: Vec<_> = large
         .iter()
@@ -345,7 +633,7 @@ fn test_find_large_files_empty_dir() {
     let dir = TempDir::new().unwrap();
     let scan_dir = dir.path().join("testdir");
     std::fs::create_dir_all(&scan_dir).unwrap();
-    let large = walker::find_large_files(&scan_dir, 1000);
+    let large = walker::find_large_files(&scan_dir, 1000, &ExclusionMatcher::empty(), &CancelToken::new(), &ScanBudget::unlimited());
     assert!(large.is_empty());
 }
 
@@ -359,7 +647,7 @@ fn test_find_large_files_sorted_descending() {
     std::fs::write(scan_dir.join("large.txt"), "x".repeat(5000)).unwrap();
     std::fs::write(scan_dir.join("xlarge.txt"), "x".repeat(9000)).unwrap();
 
-    let large = walker::find_large_files(&scan_dir, 1000);
+    let large = walker::find_large_files(&scan_dir, 1000, &ExclusionMatcher::empty(), &CancelToken::new(), &ScanBudget::unlimited());
     assert_eq!(large.len(), 3);
     assert!(
         large[0].size_bytes >= large[1].size_bytes && large[1].size_bytes >= large[2].size_bytes,
@@ -367,6 +655,362 @@ fn test_find_large_files_sorted_descending() {
     );
 }
 
+#[test]
+fn test_large_file_kind_classification() {
+    use targets::LargeFileKind;
+    assert_eq!(LargeFileKind::classify(Path::new("/a/clip.MOV")), LargeFileKind::Video);
+    assert_eq!(LargeFileKind::classify(Path::new("/a/App.dmg")), LargeFileKind::DiskImage);
+    assert_eq!(LargeFileKind::classify(Path::new("/a/win.vmdk")), LargeFileKind::VmImage);
+    assert_eq!(LargeFileKind::classify(Path::new("/a/src.tar.gz")), LargeFileKind::Archive);
+    assert_eq!(LargeFileKind::classify(Path::new("/a/ubuntu.iso")), LargeFileKind::Iso);
+    assert_eq!(LargeFileKind::classify(Path::new("/a/data.bin")), LargeFileKind::Other);
+    assert_eq!(LargeFileKind::classify(Path::new("/a/noext")), LargeFileKind::Other);
+}
+
+#[test]
+fn test_large_file_pass_groups_by_kind() {
+    let dir = TempDir::new().unwrap();
+    let scan_dir = dir.path().join("testdir");
+    std::fs::create_dir_all(scan_dir.join("node_modules")).unwrap();
+    std::fs::write(scan_dir.join("movie.mp4"), "x".repeat(5000)).unwrap();
+    std::fs::write(scan_dir.join("disk.vmdk"), "x".repeat(5000)).unwrap();
+    std::fs::write(scan_dir.join("backup.zip"), "x".repeat(5000)).unwrap();
+    std::fs::write(scan_dir.join("node_modules/huge.zip"), "x".repeat(5000)).unwrap();
+
+    let opts = ScanOptions {
+        use_cache: false,
+        categories: Some(targets::CategoryFilter::parse(&["large_file".to_string()]).unwrap()),
+        large_files: Some(LargeFileScan {
+            roots: vec![scan_dir.clone()],
+            threshold_bytes: 4000,
+            min_age_days: None,
+        }),
+        ..ScanOptions::default()
+    };
+    let results = scanner::run_scan_with_options(&[], &opts).unwrap();
+
+    let names: Vec<&str> = results.items.iter().map(|i| i.name.as_str()).collect();
+    assert_eq!(results.items.len(), 3, "one item per kind, got {:?}", names);
+    assert!(names.iter().any(|n| n.starts_with("Large video files")));
+    assert!(names.iter().any(|n| n.starts_with("Large VM image files")));
+    let archives = results
+        .items
+        .iter()
+        .find(|i| i.name.starts_with("Large archive files"))
+        .unwrap();
+    assert_eq!(archives.file_count, 1, "node_modules must not be searched");
+    assert_eq!(archives.reason, targets::LargeFileKind::Archive.advice());
+
+    // Everything here was just written, so a minimum age hides it all
+    let aged = ScanOptions {
+        large_files: Some(LargeFileScan {
+            roots: vec![scan_dir],
+            threshold_bytes: 4000,
+            min_age_days: Some(1),
+        }),
+        ..opts
+    };
+    assert!(scanner::run_scan_with_options(&[], &aged).unwrap().items.is_empty());
+}
+
+#[test]
+fn test_exclusions_prune_walks() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path().join("testdir");
+    std::fs::create_dir_all(root.join("keep/deep")).unwrap();
+    std::fs::create_dir_all(root.join("other")).unwrap();
+    std::fs::write(root.join("keep/deep/big.bin"), "x".repeat(5000)).unwrap();
+    std::fs::write(root.join("other/big.bin"), "x".repeat(5000)).unwrap();
+    std::fs::write(root.join("other/disk.vmdk"), "x".repeat(5000)).unwrap();
+
+    let excludes = ExclusionMatcher::new(&[
+        root.join("keep").display().to_string(),
+        "*.vmdk".to_string(),
+    ])
+    .unwrap();
+
+    let large = walker::find_large_files(&root, 1000, &excludes, &CancelToken::new(), &ScanBudget::unlimited());
+    assert_eq!(large.len(), 1);
+    assert!(large[0].path.ends_with("other/big.bin"));
+
+    let target = targets::ScanTarget {
+        name: "Test".to_string(),
+        category: targets::Category::UserCache,
+        paths: vec![root.display().to_string()],
+        safety: targets::SafetyLevel::Safe,
+        recursive: true,
+        min_age_days: None,
+        reason: "test".to_string(),
+        trim: None,
+    };
+    let item = walker::walk_target(&target, &excludes).unwrap();
+    assert_eq!(item.file_count, 1);
+
+    // An excluded target root is never walked at all
+    let excludes = ExclusionMatcher::new(&[root.display().to_string()]).unwrap();
+    let item = walker::walk_target(&target, &excludes).unwrap();
+    assert_eq!(item.file_count, 0);
+}
+
+#[test]
+fn test_exclusions_skip_venv_with_excluded_contents() {
+    let dir = TempDir::new().unwrap();
+    let venv = dir.path().join("projects/app/venv");
+    std::fs::create_dir_all(venv.join("pinned")).unwrap();
+    std::fs::write(venv.join("pyvenv.cfg"), "home = /usr/bin").unwrap();
+    std::fs::write(venv.join("pinned/wheel.whl"), "x").unwrap();
+
+    let roots = vec![dir.path().join("projects")];
+    let found = walker::find_venvs(&roots, &StalenessOracle::new(0), &ExclusionMatcher::empty(), &CancelToken::new(), &ScanBudget::unlimited());
+    assert_eq!(found.len(), 1);
+
+    let excludes = ExclusionMatcher::new(&["**/pinned".to_string()]).unwrap();
+    let found = walker::find_venvs(&roots, &StalenessOracle::new(0), &excludes, &CancelToken::new(), &ScanBudget::unlimited());
+    assert!(found.is_empty());
+}
+
+// ─── Project artifact tests ───────────────────────────────────────────────────
+
+#[test]
+fn test_project_artifacts_by_marker() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path().join("code");
+
+    let rust = root.join("rusty");
+    std::fs::create_dir_all(rust.join("target/debug")).unwrap();
+    std::fs::write(rust.join("Cargo.toml"), "[package]").unwrap();
+    std::fs::write(rust.join("target/debug/app"), "x".repeat(4000)).unwrap();
+
+    let web = root.join("nested/web");
+    std::fs::create_dir_all(web.join(".next/cache")).unwrap();
+    std::fs::write(web.join("package.json"), "{}").unwrap();
+    std::fs::write(web.join(".next/cache/chunk.js"), "x".repeat(4000)).unwrap();
+
+    // A hand-made build/ dir next to CMakeLists.txt is not CMake output
+    let cmake = root.join("native");
+    std::fs::create_dir_all(cmake.join("build")).unwrap();
+    std::fs::write(cmake.join("CMakeLists.txt"), "project(x)").unwrap();
+    std::fs::write(cmake.join("build/notes.txt"), "x".repeat(4000)).unwrap();
+
+    let items = dev_detector::find_project_artifacts(
+        std::slice::from_ref(&root),
+        &StalenessOracle::new(0),
+        &ExclusionMatcher::empty(),
+        targets::DevTool::ALL,
+        &CancelToken::new(),
+        &ScanBudget::unlimited(),
+    );
+
+    let cargo = items
+        .iter()
+        .find(|i| i.category == targets::Category::DevCache(targets::DevTool::CargoTarget))
+        .expect("Cargo target/ should be found");
+    assert_eq!(cargo.files.len(), 1);
+    assert_eq!(cargo.files[0].path, rust.join("target"));
+
+    let js = items
+        .iter()
+        .find(|i| i.category == targets::Category::DevCache(targets::DevTool::JsBuild))
+        .expect(".next should be found");
+    assert_eq!(js.files[0].path, web.join(".next"));
+
+    assert!(!items
+        .iter()
+        .any(|i| i.category == targets::Category::DevCache(targets::DevTool::CMakeBuild)));
+}
+
+#[test]
+fn test_python_caches_are_found_throughout_the_project() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path().join("code");
+    let project = root.join("tool");
+    for cache in [
+        "__pycache__",
+        "pkg/__pycache__",
+        "pkg/sub/__pycache__",
+        "venv/lib/__pycache__",
+    ] {
+        std::fs::create_dir_all(project.join(cache)).unwrap();
+        std::fs::write(project.join(cache).join("mod.pyc"), "x".repeat(4000)).unwrap();
+    }
+    std::fs::write(project.join("pyproject.toml"), "[project]").unwrap();
+    std::fs::write(project.join("venv/pyvenv.cfg"), "home = /usr/bin").unwrap();
+
+    let items = dev_detector::find_project_artifacts(
+        std::slice::from_ref(&root),
+        &StalenessOracle::new(0),
+        &ExclusionMatcher::empty(),
+        &[targets::DevTool::PythonCache],
+        &CancelToken::new(),
+        &ScanBudget::unlimited(),
+    );
+
+    assert_eq!(items.len(), 1);
+    let mut paths: Vec<_> = items[0].files.iter().map(|f| f.path.clone()).collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        vec![
+            project.join("__pycache__"),
+            project.join("pkg/__pycache__"),
+            project.join("pkg/sub/__pycache__"),
+        ]
+    );
+}
+
+#[test]
+fn test_project_artifacts_respect_staleness_and_tools() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path().join("code");
+    let rust = root.join("fresh");
+    std::fs::create_dir_all(rust.join("target/release")).unwrap();
+    std::fs::write(rust.join("Cargo.toml"), "[package]").unwrap();
+    std::fs::write(rust.join("target/release/app"), "x".repeat(4000)).unwrap();
+    let roots = vec![root];
+
+    // Recently touched project is not stale
+    let items = dev_detector::find_project_artifacts(
+        &roots,
+        &StalenessOracle::new(30),
+        &ExclusionMatcher::empty(),
+        targets::DevTool::ALL,
+        &CancelToken::new(),
+        &ScanBudget::unlimited(),
+    );
+    assert!(items.is_empty());
+
+    // Tool not requested
+    let items = dev_detector::find_project_artifacts(
+        &roots,
+        &StalenessOracle::new(0),
+        &ExclusionMatcher::empty(),
+        &[targets::DevTool::JsBuild],
+        &CancelToken::new(),
+        &ScanBudget::unlimited(),
+    );
+    assert!(items.is_empty());
+}
+
+// ─── Git staleness tests ──────────────────────────────────────────────────────
+
+/// Run git in `dir` with a fixed identity and commit date (test setup only)
+fn git(dir: &std::path::Path, args: &[&str], date: &str) {
+    let status = std::process::Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_AUTHOR_NAME", "t")
+        .env("GIT_AUTHOR_EMAIL", "t@example.com")
+        .env("GIT_COMMITTER_NAME", "t")
+        .env("GIT_COMMITTER_EMAIL", "t@example.com")
+        .env("GIT_AUTHOR_DATE", date)
+        .env("GIT_COMMITTER_DATE", date)
+        .env("GIT_CONFIG_GLOBAL", "/dev/null")
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .output()
+        .expect("git must be installed to run this test")
+        .status;
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+/// A committed Rust project whose last commit is a year old
+fn old_rust_repo(dir: &std::path::Path) -> std::path::PathBuf {
+    let repo = dir.join("code/old");
+    std::fs::create_dir_all(repo.join("src")).unwrap();
+    std::fs::write(repo.join("Cargo.toml"), "[package]").unwrap();
+    std::fs::write(repo.join("src/main.rs"), "fn main() {}").unwrap();
+    std::fs::write(repo.join(".gitignore"), "/target\n").unwrap();
+    let date = "2020-01-01T00:00:00Z";
+    git(&repo, &["init", "-q", "-b", "main"], date);
+    git(&repo, &["add", "."], date);
+    git(&repo, &["commit", "-q", "-m", "init"], date);
+
+    std::fs::create_dir_all(repo.join("target/debug")).unwrap();
+    std::fs::write(repo.join("target/debug/app"), "x".repeat(4000)).unwrap();
+    repo
+}
+
+#[test]
+fn test_git_repo_reads_head_and_reflog() {
+    let dir = TempDir::new().unwrap();
+    let repo_dir = old_rust_repo(dir.path());
+    let expected = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_577_836_800);
+
+    let repo = GitRepo::discover(&repo_dir.join("src")).expect("repo should be found");
+    assert_eq!(repo.work_tree, repo_dir);
+    assert_eq!(repo.head_commit_time(), Some(expected));
+    assert_eq!(repo.last_reflog_time(), Some(expected));
+    assert!(!repo.is_dirty());
+
+    // Commits moved into a pack are still readable
+    git(&repo_dir, &["gc", "-q"], "2020-01-01T00:00:00Z");
+    assert_eq!(repo.head_commit_time(), Some(expected));
+}
+
+#[test]
+fn test_git_repo_detects_uncommitted_changes() {
+    let dir = TempDir::new().unwrap();
+    let repo_dir = old_rust_repo(dir.path());
+    let repo = GitRepo::discover(&repo_dir).unwrap();
+
+    // Ignored build output doesn't count
+    assert!(!repo.is_dirty());
+
+    std::fs::write(repo_dir.join("notes.txt"), "untracked").unwrap();
+    assert!(repo.is_dirty());
+    std::fs::remove_file(repo_dir.join("notes.txt")).unwrap();
+    assert!(!repo.is_dirty());
+
+    std::fs::write(repo_dir.join("src/main.rs"), "fn main() { todo!() }").unwrap();
+    assert!(repo.is_dirty());
+}
+
+#[test]
+fn test_git_repo_counts_untracked_files_unless_ignored() {
+    let dir = TempDir::new().unwrap();
+    let repo_dir = old_rust_repo(dir.path());
+    let repo = GitRepo::discover(&repo_dir).unwrap();
+
+    std::fs::write(repo_dir.join(".git/info/exclude"), "*.swp\n").unwrap();
+    std::fs::write(repo_dir.join("src/.main.rs.swp"), "editor state").unwrap();
+    assert!(!repo.is_dirty());
+
+    // A new file in a new directory, with nothing else changed
+    std::fs::create_dir_all(repo_dir.join("src/parser")).unwrap();
+    std::fs::write(repo_dir.join("src/parser/mod.rs"), "pub fn parse() {}").unwrap();
+    assert!(repo.is_dirty());
+}
+
+#[test]
+fn test_staleness_uses_git_activity() {
+    let dir = TempDir::new().unwrap();
+    let repo_dir = old_rust_repo(dir.path());
+    let roots = vec![dir.path().join("code")];
+
+    // Cargo.toml was just written, but the repo hasn't moved since 2020
+    let items = dev_detector::find_project_artifacts(
+        &roots,
+        &StalenessOracle::new(30),
+        &ExclusionMatcher::empty(),
+        &[targets::DevTool::CargoTarget],
+        &CancelToken::new(),
+        &ScanBudget::unlimited(),
+    );
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].files[0].path, repo_dir.join("target"));
+
+    // Work in progress is never flagged, whatever the age
+    std::fs::write(repo_dir.join("src/main.rs"), "fn main() { todo!() }").unwrap();
+    let items = dev_detector::find_project_artifacts(
+        &roots,
+        &StalenessOracle::new(0),
+        &ExclusionMatcher::empty(),
+        &[targets::DevTool::CargoTarget],
+        &CancelToken::new(),
+        &ScanBudget::unlimited(),
+    );
+    assert!(items.is_empty());
+}
+
 // ─── Scan results tests ──────────────────────────────────────────────────────
 
 #[test]
@@ -378,10 +1022,15 @@ fn test_scan_results_recalculate() {
         category: targets::Category::UserCache,
         path: std::path::PathBuf::from("/tmp/test1"),
         size_bytes: 1000,
+        apparent_bytes: 1000,
+        freeable_bytes: 1000,
         file_count: 5,
         safety: targets::SafetyLevel::Safe,
         reason: "test".to_string(),
         files: Vec::new(),
+        files_partial: false,
+        trim: None,
+        roots: Vec::new(),
     });
 
     results.items.push(targets::ScanItem {
@@ -389,10 +1038,15 @@ fn test_scan_results_recalculate() {
         category: targets::Category::Logs,
         path: std::path::PathBuf::from("/tmp/test2"),
         size_bytes: 2000,
+        apparent_bytes: 2000,
+        freeable_bytes: 2000,
         file_count: 3,
         safety: targets::SafetyLevel::Caution,
         reason: "test".to_string(),
         files: Vec::new(),
+        files_partial: false,
+        trim: None,
+        roots: Vec::new(),
     });
 
     results.recalculate();
@@ -410,10 +1064,15 @@ fn test_scan_results_filter_by_safety() {
         category: targets::Category::UserCache,
         path: std::path::PathBuf::from("/tmp/safe"),
         size_bytes: 1000,
+        apparent_bytes: 1000,
+        freeable_bytes: 1000,
         file_count: 1,
         safety: targets::SafetyLevel::Safe,
         reason: "test".to_string(),
         files: Vec::new(),
+        files_partial: false,
+        trim: None,
+        roots: Vec::new(),
     });
 
     results.items.push(targets::ScanItem {
@@ -421,10 +1080,15 @@ fn test_scan_results_filter_by_safety() {
         category: targets::Category::Logs,
         path: std::path::PathBuf::from("/tmp/caution"),
         size_bytes: 2000,
+        apparent_bytes: 2000,
+        freeable_bytes: 2000,
         file_count: 1,
         safety: targets::SafetyLevel::Caution,
         reason: "test".to_string(),
         files: Vec::new(),
+        files_partial: false,
+        trim: None,
+        roots: Vec::new(),
     });
 
     let safe = results.filter_by_safety(&targets::SafetyLevel::Safe);
@@ -435,3 +1099,386 @@ fn test_scan_results_filter_by_safety() {
     assert_eq!(caution.len(), 1);
     assert_eq!(caution[0].name, "Caution item");
 }
+
+// ─── Event stream tests ──────────────────────────────────────────────────────
+
+#[test]
+fn test_scan_events_stream_in_order() {
+    use tidymac::scanner::events::{EventSink, ScanEvent, FILE_BATCH};
+
+    let dir = TempDir::new().unwrap();
+    let root = dir.path().join("testdir");
+    std::fs::create_dir_all(&root).unwrap();
+    for i in 0..FILE_BATCH + 10 {
+        std::fs::write(root.join(format!("f{}.tmp", i)), "data").unwrap();
+    }
+
+    let target = targets::ScanTarget {
+        name: "Streamed".to_string(),
+        category: targets::Category::UserCache,
+        paths: vec![root.display().to_string()],
+        safety: targets::SafetyLevel::Safe,
+        recursive: true,
+        min_age_days: None,
+        reason: "test".to_string(),
+        trim: None,
+    };
+    let (events, rx) = EventSink::channel();
+    let opts = ScanOptions {
+        use_cache: false,
+        events,
+        ..ScanOptions::default()
+    };
+    let results = scanner::run_scan_with_options(&[target], &opts).unwrap();
+    drop(opts);
+    let received: Vec<ScanEvent> = rx.iter().collect();
+
+    assert!(matches!(received.first(), Some(ScanEvent::Started { steps: 1, .. })));
+    assert!(matches!(received[1], ScanEvent::TargetStarted { ref target } if target == "Streamed"));
+    let streamed: usize = received
+        .iter()
+        .map(|e| match e {
+            ScanEvent::FileBatch { files, .. } => {
+                assert!(files.len() <= FILE_BATCH);
+                files.len()
+            }
+            _ => 0,
+        })
+        .sum();
+    assert_eq!(streamed, FILE_BATCH + 10);
+
+    let finished = received
+        .iter()
+        .position(|e| matches!(e, ScanEvent::TargetFinished { cached: false, .. }))
+        .unwrap();
+    let found = received
+        .iter()
+        .position(|e| matches!(e, ScanEvent::ItemFound { .. }))
+        .unwrap();
+    assert!(finished < found);
+    match received.last() {
+        Some(ScanEvent::Done {
+            total_files, items, ..
+        }) => {
+            assert_eq!(*total_files, results.total_files);
+            assert_eq!(*items, 1);
+        }
+        other => panic!("expected done last, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_cancelled_scan_returns_cancelled_error() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path().join("testdir");
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("a.tmp"), "data").unwrap();
+    let target = targets::ScanTarget {
+        name: "Cancelled".to_string(),
+        category: targets::Category::UserCache,
+        paths: vec![root.display().to_string()],
+        safety: targets::SafetyLevel::Safe,
+        recursive: true,
+        min_age_days: None,
+        reason: "test".to_string(),
+        trim: None,
+    };
+
+    let cancel = CancelToken::new();
+    cancel.cancel();
+    let opts = ScanOptions {
+        use_cache: false,
+        cancel,
+        ..ScanOptions::default()
+    };
+    let err = scanner::run_scan_with_options(std::slice::from_ref(&target), &opts).unwrap_err();
+    assert!(tidymac::common::cancel::is_cancelled(&err));
+
+    // Another operation with its own token is unaffected
+    let opts = ScanOptions {
+        use_cache: false,
+        ..ScanOptions::default()
+    };
+    let results = scanner::run_scan_with_options(&[target], &opts).unwrap();
+    assert_eq!(results.total_files, 1);
+}
+
+// ─── Scan limit tests ────────────────────────────────────────────────────────
+
+fn limits_fixture() -> (TempDir, targets::ScanTarget) {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path().join("testdir");
+    std::fs::create_dir_all(root.join("a/b")).unwrap();
+    for i in 0..5 {
+        std::fs::write(root.join(format!("top{}.tmp", i)), "data").unwrap();
+    }
+    std::fs::write(root.join("a/mid.tmp"), "data").unwrap();
+    std::fs::write(root.join("a/b/deep.tmp"), "data").unwrap();
+    let target = targets::ScanTarget {
+        name: "Limited".to_string(),
+        category: targets::Category::UserCache,
+        paths: vec![root.display().to_string()],
+        safety: targets::SafetyLevel::Safe,
+        recursive: true,
+        min_age_days: None,
+        reason: "test".to_string(),
+        trim: None,
+    };
+    (dir, target)
+}
+
+fn scan_with_limits(target: &targets::ScanTarget, limits: ScanLimits) -> targets::ScanResults {
+    let opts = ScanOptions {
+        use_cache: false,
+        limits,
+        ..ScanOptions::default()
+    };
+    scanner::run_scan_with_options(std::slice::from_ref(target), &opts).unwrap()
+}
+
+#[test]
+fn test_scan_limits_truncate_and_label_targets() {
+    let (_dir, target) = limits_fixture();
+
+    let results = scan_with_limits(&target, ScanLimits::default());
+    assert_eq!(results.total_files, 7);
+    assert!(results.truncated.is_empty());
+
+    let results = scan_with_limits(
+        &target,
+        ScanLimits {
+            max_files: Some(3),
+            ..Default::default()
+        },
+    );
+    assert_eq!(results.total_files, 3);
+    assert_eq!(results.truncated.len(), 1);
+    assert_eq!(results.truncated[0].target, "Limited");
+    assert_eq!(results.truncated[0].reason, TruncationReason::MaxFiles { limit: 3 });
+
+    // Depth 1 is the root's own entries; a/ is listed but not read
+    let results = scan_with_limits(
+        &target,
+        ScanLimits {
+            max_depth: Some(1),
+            ..Default::default()
+        },
+    );
+    assert_eq!(results.total_files, 5);
+    assert_eq!(results.truncated[0].reason, TruncationReason::MaxDepth { limit: 1 });
+
+    // Deep enough for everything: nothing was hidden, so nothing is labelled
+    let results = scan_with_limits(
+        &target,
+        ScanLimits {
+            max_depth: Some(3),
+            one_file_system: true,
+            ..Default::default()
+        },
+    );
+    assert_eq!(results.total_files, 7);
+    assert!(results.truncated.is_empty());
+}
+
+#[test]
+fn test_scan_time_budget_returns_labelled_partial_results() {
+    let (_dir, target) = limits_fixture();
+    let results = scan_with_limits(
+        &target,
+        ScanLimits {
+            time_budget_secs: Some(0),
+            ..Default::default()
+        },
+    );
+    assert_eq!(results.total_files, 0);
+    assert_eq!(
+        results.truncated[0].reason,
+        TruncationReason::TimeBudget { secs: 0 }
+    );
+
+    let json = serde_json::to_value(&results).unwrap();
+    assert_eq!(json["truncated"][0]["target"], "Limited");
+    assert_eq!(json["truncated"][0]["reason"], "time_budget");
+}
+
+#[test]
+fn test_profile_limits_parse() {
+    let toml_str = r#"
+        [profile]
+        name = "bounded"
+        description = "Bounded profile"
+        aggression = "low"
+
+        [targets]
+
+        [limits]
+        time_budget_secs = 90
+        max_depth = 8
+        one_file_system = true
+    "#;
+    let profile: Profile = toml::from_str(toml_str).unwrap();
+    assert_eq!(profile.limits.time_budget_secs, Some(90));
+    assert_eq!(profile.limits.max_depth, Some(8));
+    assert_eq!(profile.limits.max_files, None);
+    assert!(profile.limits.one_file_system);
+    assert!(Profile::load("deep").unwrap().limits.one_file_system);
+    assert_eq!(Profile::load("quick").unwrap().limits, ScanLimits::default());
+}
+
+// ─── Aggregation tests ───────────────────────────────────────────────────────
+
+#[test]
+fn test_top_files_scan_keeps_totals_and_refetches_full_lists() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path().join("testdir");
+    std::fs::create_dir_all(root.join("sub")).unwrap();
+    for i in 1..=10 {
+        let parent = if i % 2 == 0 { root.join("sub") } else { root.clone() };
+        std::fs::write(parent.join(format!("f{}.bin", i)), vec![0u8; i * 8192]).unwrap();
+    }
+    let target = targets::ScanTarget {
+        name: "Aggregated".to_string(),
+        category: targets::Category::UserCache,
+        paths: vec![root.display().to_string()],
+        safety: targets::SafetyLevel::Safe,
+        recursive: true,
+        min_age_days: None,
+        reason: "test".to_string(),
+        trim: None,
+    };
+
+    let full = walker::walk_target(&target, &ExclusionMatcher::empty()).unwrap();
+    assert!(!full.files_partial);
+    assert_eq!(full.files.len(), 10);
+
+    let opts = ScanOptions {
+        use_cache: false,
+        top_files: Some(3),
+        ..ScanOptions::default()
+    };
+    let results = scanner::run_scan_with_options(std::slice::from_ref(&target), &opts).unwrap();
+    let item = &results.items[0];
+    assert!(item.files_partial);
+    assert_eq!(item.file_count, 10);
+    assert_eq!(item.size_bytes, full.size_bytes);
+    let names: Vec<String> = item
+        .files
+        .iter()
+        .map(|f| f.path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(names, vec!["f10.bin", "f9.bin", "f8.bin"]);
+
+    // A scan without top_files (as cleaning does) gets every file again
+    let opts = ScanOptions {
+        top_files: None,
+        ..opts
+    };
+    let results = scanner::run_scan_with_options(&[target], &opts).unwrap();
+    assert!(!results.items[0].files_partial);
+    assert_eq!(results.items[0].files.len(), 10);
+}
+
+// ─── History tests ───────────────────────────────────────────────────────────
+
+#[test]
+fn test_scan_history_appends_and_compacts() {
+    let dir = TempDir::new().unwrap();
+    let history = ScanHistory::at(dir.path().join("history").join("scans.jsonl"));
+    assert!(history.load(None).unwrap().is_empty());
+
+    let mut results = targets::ScanResults::new();
+    results.items = vec![
+        scan_item("Logs", targets::Category::Logs, 300),
+        scan_item("Logs", targets::Category::Logs, 200),
+        scan_item("Caches", targets::Category::UserCache, 1000),
+    ];
+    results.recalculate();
+
+    let mut old = ScanRecord::from_results(&results, "quick");
+    old.timestamp -= chrono::Duration::days(400);
+    history.append(&old).unwrap();
+    history
+        .append(&ScanRecord::from_results(&results, "quick"))
+        .unwrap();
+    // A torn line from an interrupted write is skipped
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(history.path())
+        .and_then(|mut f| std::io::Write::write_all(&mut f, b"{\"version\":1,\"ti"))
+        .unwrap();
+
+    let records = history.load(None).unwrap();
+    assert_eq!(records.len(), 2);
+    let logs = records[1].targets.iter().find(|t| t.name == "Logs").unwrap();
+    assert_eq!(logs.size_bytes, 500);
+    assert_eq!(logs.file_count, 2);
+
+    history
+        .compact(chrono::Utc::now() - chrono::Duration::days(365))
+        .unwrap();
+    assert_eq!(history.load(None).unwrap().len(), 1);
+}
+
+fn scan_item(name: &str, category: targets::Category, size: u64) -> targets::ScanItem {
+    targets::ScanItem {
+        name: name.to_string(),
+        category,
+        path: "/tmp".into(),
+        size_bytes: size,
+        apparent_bytes: size,
+        freeable_bytes: size,
+        file_count: 1,
+        safety: targets::SafetyLevel::Safe,
+        reason: "test".to_string(),
+        files: Vec::new(),
+        files_partial: false,
+        trim: None,
+        roots: Vec::new(),
+    }
+}
+
+// ─── Snapshot tests ──────────────────────────────────────────────────────────
+
+#[test]
+fn test_snapshot_round_trip_and_diff_against_plain_json() {
+    let dir = TempDir::new().unwrap();
+    let mut results = targets::ScanResults::new();
+    results.items = vec![scan_item("Logs", targets::Category::Logs, 300)];
+    results.recalculate();
+
+    // Output of `scan --format json` from before snapshots existed
+    let plain = dir.path().join("plain.json");
+    std::fs::write(&plain, serde_json::to_string(&results).unwrap()).unwrap();
+    let old = Snapshot::load(&plain).unwrap();
+    assert_eq!(old.snapshot_version, 0);
+
+    results.items.push(scan_item("Caches", targets::Category::UserCache, 1000));
+    results.recalculate();
+    let saved = dir.path().join("nested").join("after.json");
+    Snapshot::new(results, "developer").save(&saved).unwrap();
+    let new = Snapshot::load(&saved).unwrap();
+    assert_eq!(new.snapshot_version, SNAPSHOT_VERSION);
+    assert_eq!(new.profile, "developer");
+
+    let d = diff::diff(&old, &new);
+    assert_eq!(d.delta_bytes, 1000);
+    assert_eq!(d.items.len(), 1);
+    assert_eq!(d.items[0].change, Change::Added);
+    assert_eq!(d.categories[0].category, targets::Category::UserCache);
+}
+
+#[test]
+fn test_snapshot_from_newer_release_is_rejected() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("future.json");
+    std::fs::write(
+        &path,
+        format!(r#"{{"snapshot_version": {}, "results": {{}}}}"#, SNAPSHOT_VERSION + 1),
+    )
+    .unwrap();
+    let err = Snapshot::load(&path).unwrap_err().to_string();
+    assert!(err.contains("upgrade tidymac"));
+
+    std::fs::write(&path, r#"{"hello": "world"}"#).unwrap();
+    assert!(Snapshot::load(&path).is_err());
+}
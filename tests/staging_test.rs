@@ -1,7 +1,24 @@
+use std::sync::{Mutex, MutexGuard};
 use tempfile::TempDir;
 
+use tidymac::cleaner::journal::{self, Journal, MoveState};
 use tidymac::cleaner::manifest::{CleanManifest, ManifestItem};
-use tidymac::scanner::targets::{Category, FileEntry, SafetyLevel, ScanItem};
+use tidymac::cleaner::staging::stage_files;
+use tidymac::cleaner::{
+    apply_trim, clean, drop_excluded, partition_by_safety, purge_session,
+    restore_session_with_options, store, verify_session, CleanMode, OnConflict, RestoreOptions,
+};
+use tidymac::common::cancel::CancelToken;
+use tidymac::common::config::StagingStore;
+use tidymac::common::exclusions::ExclusionMatcher;
+use tidymac::scanner::targets::{Category, FileEntry, SafetyLevel, ScanItem, TrimPolicy};
+
+/// Session IDs are timestamps to the second, so tests that create sessions
+/// in the real staging area take turns
+fn session_lock() -> MutexGuard<'static, ()> {
+    static LOCK: Mutex<()> = Mutex::new(());
+    LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
 
 /// Helper to create a ScanItem with real files for testing
 fn create_test_scan_item(dir: &std::path::Path, name: &str, file_count: usize) -> ScanItem {
@@ -19,6 +36,11 @@ fn create_test_scan_item(dir: &std::path::Path, name: &str, file_count: usize) -
             path: file_path,
             size_bytes: size,
             modified: Some(std::time::SystemTime::now()),
+            apparent_bytes: size,
+            freeable_bytes: size,
+            hard_link: None,
+            accessed: None,
+            file_id: None,
         });
     }
 
@@ -27,10 +49,15 @@ fn create_test_scan_item(dir: &std::path::Path, name: &str, file_count: usize) -
         category: Category::TempFiles,
         path: dir.to_path_buf(),
         size_bytes: total_size,
+        apparent_bytes: total_size,
+        freeable_bytes: total_size,
         file_count,
         safety: SafetyLevel::Safe,
         reason: "Test item".to_string(),
         files,
+        files_partial: false,
+        trim: None,
+        roots: Vec::new(),
     }
 }
 
@@ -60,6 +87,10 @@ fn test_manifest_add_item() {
         is_dir: false,
         success: true,
         error: None,
+        restored: None,
+        content_hash: None,
+        archive_entry: None,
+        staged_bytes: None,
     });
 
     assert_eq!(manifest.total_files, 1);
@@ -80,6 +111,10 @@ fn test_manifest_failed_item_not_counted() {
         is_dir: false,
         success: false,
         error: Some("Permission denied".to_string()),
+        restored: None,
+        content_hash: None,
+        archive_entry: None,
+        staged_bytes: None,
     });
 
     assert_eq!(
@@ -138,6 +173,10 @@ fn test_staging_moves_files() {
             is_dir: false,
             success: true,
             error: None,
+            restored: None,
+            content_hash: None,
+            archive_entry: None,
+            staged_bytes: None,
         });
     }
 
@@ -200,6 +239,10 @@ fn test_manifest_serialization_roundtrip() {
         is_dir: true,
         success: true,
         error: None,
+        restored: None,
+        content_hash: None,
+        archive_entry: None,
+        staged_bytes: None,
     });
     manifest.add_error("Test warning".to_string());
 
@@ -221,3 +264,708 @@ fn test_manifest_serialization_roundtrip() {
     assert_eq!(loaded.errors.len(), 1);
     assert!(!loaded.restored);
 }
+
+#[test]
+fn test_partition_by_safety_holds_back_riskier_items() {
+    let dir = TempDir::new().unwrap();
+    let safe = create_test_scan_item(dir.path(), "safe", 1);
+    let mut caution = create_test_scan_item(dir.path(), "caution", 2);
+    caution.safety = SafetyLevel::Caution;
+    let mut dangerous = create_test_scan_item(dir.path(), "dangerous", 1);
+    dangerous.safety = SafetyLevel::Dangerous;
+    let items = vec![safe, caution, dangerous];
+
+    let (allowed, held_back) = partition_by_safety(&items, &SafetyLevel::Safe);
+    assert_eq!(allowed.len(), 1);
+    assert_eq!(allowed[0].name, "safe");
+    assert_eq!(held_back.len(), 2);
+    assert_eq!(held_back[0].name, "caution");
+    assert_eq!(held_back[0].file_count, 2);
+    assert!(held_back[0].reason.contains("caution"));
+
+    let (allowed, held_back) = partition_by_safety(&items, &SafetyLevel::Caution);
+    assert_eq!(allowed.len(), 2);
+    assert_eq!(held_back.len(), 1);
+    assert_eq!(held_back[0].safety, SafetyLevel::Dangerous);
+
+    let (allowed, held_back) = partition_by_safety(&items, &SafetyLevel::Dangerous);
+    assert_eq!(allowed.len(), 3);
+    assert!(held_back.is_empty());
+}
+
+#[test]
+fn test_drop_excluded_filters_files_and_items() {
+    let dir = TempDir::new().unwrap();
+    let partly = create_test_scan_item(dir.path(), "partly", 3);
+    let excluded_file = partly.files[0].path.display().to_string();
+
+    let whole_dir = dir.path().join("whole");
+    std::fs::create_dir_all(&whole_dir).unwrap();
+    let mut whole = create_test_scan_item(&whole_dir, "whole", 2);
+    whole.path = whole_dir.clone();
+
+    let excludes =
+        ExclusionMatcher::new(&[excluded_file, whole_dir.display().to_string()]).unwrap();
+    let (allowed, held_back) = drop_excluded(vec![partly, whole], &excludes);
+
+    assert_eq!(allowed.len(), 1);
+    assert_eq!(allowed[0].name, "partly");
+    assert_eq!(allowed[0].file_count, 2);
+    assert_eq!(allowed[0].files.len(), 2);
+
+    assert_eq!(held_back.len(), 2);
+    assert_eq!(held_back[0].file_count, 1);
+    assert_eq!(held_back[1].name, "whole");
+    assert_eq!(held_back[1].file_count, 2);
+    assert!(held_back[1].reason.contains("exclude_paths"));
+}
+
+#[test]
+fn test_drop_excluded_passes_items_with_no_matching_files() {
+    let dir = TempDir::new().unwrap();
+    let item = create_test_scan_item(dir.path(), "untouched", 3);
+    let excludes = ExclusionMatcher::new(&["/nowhere/near".to_string()]).unwrap();
+
+    let (allowed, held_back) = drop_excluded(vec![item.clone()], &excludes);
+    assert!(held_back.is_empty(), "{:?}", held_back);
+    assert_eq!(allowed.len(), 1);
+    assert_eq!(allowed[0].files.len(), 3);
+    assert_eq!(allowed[0].size_bytes, item.size_bytes);
+}
+
+#[test]
+fn test_cancelled_clean_records_consistent_manifest() {
+    let _lock = session_lock();
+    let dir = TempDir::new().unwrap();
+    let item = create_test_scan_item(dir.path(), "cancelled", 3);
+
+    let cancel = CancelToken::new();
+    cancel.cancel();
+    let report = clean(
+        std::slice::from_ref(&item),
+        CleanMode::SoftDelete,
+        "test_cancel",
+        &SafetyLevel::Safe,
+        false,
+        &cancel,
+    )
+    .unwrap();
+
+    assert!(report.cancelled);
+    assert_eq!(report.files_removed, 0);
+    for f in &item.files {
+        assert!(f.path.exists(), "nothing is touched after cancelling");
+    }
+
+    // The session is still written and says it was cut short
+    let session_id = report.session_id.unwrap();
+    let manifest = CleanManifest::load_from_session(&session_id).unwrap();
+    assert!(manifest.cancelled);
+    assert!(manifest.items.is_empty());
+    let _ = purge_session(&session_id);
+}
+
+#[test]
+fn test_clean_refuses_items_with_partial_file_lists() {
+    let dir = TempDir::new().unwrap();
+    let mut item = create_test_scan_item(dir.path(), "partial", 3);
+    item.files.truncate(1);
+    item.files_partial = true;
+
+    let err = clean(
+        std::slice::from_ref(&item),
+        CleanMode::SoftDelete,
+        "test_partial",
+        &SafetyLevel::Safe,
+        false,
+        &CancelToken::new(),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("largest files"));
+    assert!(dir.path().join("partial_2.txt").exists());
+
+    // A dry run still reports the whole item
+    let report = clean(
+        std::slice::from_ref(&item),
+        CleanMode::DryRun,
+        "test_partial",
+        &SafetyLevel::Safe,
+        false,
+        &CancelToken::new(),
+    )
+    .unwrap();
+    assert_eq!(report.files_removed, 3);
+}
+
+#[test]
+fn test_apply_trim_evicts_stale_and_least_recently_used_files() {
+    let dir = TempDir::new().unwrap();
+    let day = std::time::Duration::from_secs(86400);
+    let now = std::time::SystemTime::now();
+
+    let mut item = create_test_scan_item(dir.path(), "cache", 4);
+    for (file, days_ago) in item.files.iter_mut().zip([1u32, 3, 5, 60]) {
+        file.size_bytes = 100;
+        file.modified = Some(now - day * 90);
+        file.accessed = Some(now - day * days_ago);
+    }
+    item.trim = Some(TrimPolicy {
+        max_gb: Some(250.0 / 1073741824.0),
+        keep_days: Some(30),
+    });
+
+    let (selected, trimmed) = apply_trim(vec![item.clone()]);
+    assert_eq!(trimmed.len(), 1);
+    assert_eq!(trimmed[0].kept_files, 2);
+    assert_eq!(trimmed[0].evicted_files, 2);
+    assert_eq!(trimmed[0].evicted_bytes, 200);
+
+    // The two least recently used files are evicted
+    let evicted: Vec<_> = selected[0].files.iter().map(|f| f.path.clone()).collect();
+    assert_eq!(
+        evicted,
+        vec![dir.path().join("cache_2.txt"), dir.path().join("cache_3.txt")]
+    );
+    assert_eq!(selected[0].size_bytes, 200);
+    assert!(selected[0].trim.is_none());
+
+    // Trimming the result again changes nothing
+    let (again, trimmed) = apply_trim(selected.clone());
+    assert!(trimmed.is_empty());
+    assert_eq!(again[0].files.len(), 2);
+
+    // A policy that keeps everything leaves nothing to clean
+    item.trim = Some(TrimPolicy {
+        max_gb: Some(1.0),
+        keep_days: None,
+    });
+    let (selected, trimmed) = apply_trim(vec![item]);
+    assert!(selected.is_empty());
+    assert_eq!(trimmed[0].kept_files, 4);
+}
+
+#[test]
+fn test_clean_skips_files_that_changed_since_the_scan() {
+    let _lock = session_lock();
+    let dir = TempDir::new().unwrap();
+    let mut item = create_test_scan_item(dir.path(), "changed", 3);
+    for file in &mut item.files {
+        *file = FileEntry::from_metadata(file.path.clone(), &std::fs::metadata(&file.path).unwrap());
+    }
+    std::fs::write(dir.path().join("changed_1.txt"), "rewritten after the scan").unwrap();
+
+    let report = clean(
+        std::slice::from_ref(&item),
+        CleanMode::SoftDelete,
+        "test_changed",
+        &SafetyLevel::Safe,
+        false,
+        &CancelToken::new(),
+    )
+    .unwrap();
+    assert_eq!(report.files_removed, 2);
+    assert!(report.errors.is_empty());
+    assert_eq!(report.skipped.len(), 1);
+    assert!(report.skipped[0].reason.contains("size changed"));
+    assert!(dir.path().join("changed_1.txt").exists());
+    assert!(!dir.path().join("changed_0.txt").exists());
+
+    // The manifest records what was left alone and why
+    let session_id = report.session_id.unwrap();
+    let manifest = CleanManifest::load_from_session(&session_id).unwrap();
+    assert_eq!(manifest.items.len(), 2);
+    assert_eq!(manifest.skipped.len(), 1);
+    assert_eq!(manifest.skipped[0].path, dir.path().join("changed_1.txt"));
+    let _ = purge_session(&session_id);
+}
+
+#[test]
+fn test_clean_guards_whole_items_and_files_outside_the_item() {
+    let _lock = session_lock();
+    let dir = TempDir::new().unwrap();
+    let outside = TempDir::new().unwrap();
+    let mut item = as_scanned(&create_test_scan_item(dir.path(), "guarded", 1));
+    let stray = as_scanned(&create_test_scan_item(outside.path(), "stray", 1));
+    item.files.extend(stray.files);
+
+    let mut whole = create_test_scan_item(dir.path(), "whole", 0);
+    whole.path = dir.path().join("build");
+    let mut gone = whole.clone();
+    gone.path = dir.path().join("gone");
+    std::fs::create_dir(&whole.path).unwrap();
+
+    let report = clean(
+        &[item, whole, gone],
+        CleanMode::HardDelete,
+        "test_guarded",
+        &SafetyLevel::Safe,
+        false,
+        &CancelToken::new(),
+    )
+    .unwrap();
+    assert_eq!(report.files_removed, 2);
+    assert!(!dir.path().join("build").exists());
+    assert!(outside.path().join("stray_0.txt").exists());
+
+    let reasons: Vec<&str> = report.skipped.iter().map(|s| s.reason.as_str()).collect();
+    assert_eq!(reasons.len(), 2, "{:?}", reasons);
+    assert!(reasons[0].contains("is outside"), "{:?}", reasons);
+    assert_eq!(reasons[1], "no longer exists");
+}
+
+#[test]
+fn test_clean_accepts_files_under_any_root_of_a_target() {
+    let _lock = session_lock();
+    // Two roots, like pnpm's stores, whose only common parent is home
+    let home = TempDir::new().unwrap();
+    let store = home.path().join(".local/share/pnpm/store");
+    let library = home.path().join("Library/pnpm/store");
+    let stray = home.path().join("notes.txt");
+    std::fs::write(&stray, "not part of the target").unwrap();
+    let mut files = Vec::new();
+    for root in [&store, &library] {
+        std::fs::create_dir_all(root).unwrap();
+        std::fs::write(root.join("blob"), "cached package").unwrap();
+        files.push(root.join("blob"));
+    }
+    files.push(stray.clone());
+
+    let mut item = create_test_scan_item(home.path(), "pnpm", 0);
+    item.path = store.clone();
+    // As the walker records them, including a root that doesn't exist
+    item.roots = vec![store.clone(), library.clone(), home.path().join("missing")];
+    item.files = files
+        .into_iter()
+        .map(|p| FileEntry::from_metadata(p.clone(), &std::fs::metadata(&p).unwrap()))
+        .collect();
+
+    let report = clean(
+        &[item],
+        CleanMode::HardDelete,
+        "test_roots",
+        &SafetyLevel::Safe,
+        false,
+        &CancelToken::new(),
+    )
+    .unwrap();
+    assert_eq!(report.files_removed, 2, "{:?}", report.skipped);
+    assert!(!store.join("blob").exists());
+    assert!(!library.join("blob").exists());
+    assert!(stray.exists());
+    assert_eq!(report.skipped.len(), 1);
+    assert!(report.skipped[0].reason.contains("is outside"));
+}
+
+/// `item` with its files recorded as a scan would, so the check right
+/// before removal passes
+fn as_scanned(item: &ScanItem) -> ScanItem {
+    let mut item = item.clone();
+    for file in &mut item.files {
+        *file = FileEntry::from_metadata(file.path.clone(), &std::fs::metadata(&file.path).unwrap());
+    }
+    item
+}
+
+/// Soft-delete an item's files and return the session ID
+fn staged_session(item: &ScanItem, profile: &str) -> String {
+    let item = as_scanned(item);
+    let report = clean(
+        std::slice::from_ref(&item),
+        CleanMode::SoftDelete,
+        profile,
+        &SafetyLevel::Safe,
+        false,
+        &CancelToken::new(),
+    )
+    .unwrap();
+    assert!(report.errors.is_empty());
+    report.session_id.unwrap()
+}
+
+#[test]
+fn test_restore_part_of_a_session_then_the_rest_elsewhere() {
+    let _lock = session_lock();
+    let dir = TempDir::new().unwrap();
+    let item = create_test_scan_item(dir.path(), "partial", 3);
+    let session_id = staged_session(&item, "test_partial");
+
+    // Only the file matching the pattern comes back
+    let only = RestoreOptions {
+        only: vec![format!("{}/partial_1.*", dir.path().display())],
+        ..Default::default()
+    };
+    let report = restore_session_with_options(&session_id, &only, false).unwrap();
+    assert_eq!(report.restored_count, 1);
+    assert_eq!(report.remaining, 2);
+    assert!(dir.path().join("partial_1.txt").exists());
+    assert!(!dir.path().join("partial_0.txt").exists());
+
+    let manifest = CleanManifest::load_from_session(&session_id).unwrap();
+    assert!(!manifest.restored);
+    assert_eq!(manifest.restorable_items().count(), 2);
+
+    // Restoring the same file again finds nothing to do
+    let err = restore_session_with_options(&session_id, &only, false).unwrap_err();
+    assert!(err.to_string().contains("No restorable items"), "{}", err);
+
+    // The rest goes under another directory, keeping full paths
+    let elsewhere = TempDir::new().unwrap();
+    let to = RestoreOptions {
+        to: Some(elsewhere.path().to_path_buf()),
+        ..Default::default()
+    };
+    let report = restore_session_with_options(&session_id, &to, false).unwrap();
+    assert_eq!(report.restored_count, 2);
+    assert_eq!(report.remaining, 0);
+    let relocated = elsewhere
+        .path()
+        .join(dir.path().strip_prefix("/").unwrap())
+        .join("partial_0.txt");
+    assert!(relocated.exists());
+    assert!(!dir.path().join("partial_0.txt").exists());
+
+    let manifest = CleanManifest::load_from_session(&session_id).unwrap();
+    assert!(manifest.restored);
+    let item = manifest
+        .items
+        .iter()
+        .find(|i| i.original_path == dir.path().join("partial_0.txt"))
+        .unwrap();
+    assert_eq!(item.restored.as_ref().unwrap().path, relocated);
+    let _ = purge_session(&session_id);
+}
+
+#[test]
+fn test_restore_conflicts_error_skip_or_rename() {
+    let _lock = session_lock();
+    let dir = TempDir::new().unwrap();
+    let item = create_test_scan_item(dir.path(), "conflict", 1);
+    let session_id = staged_session(&item, "test_conflict");
+    let original = dir.path().join("conflict_0.txt");
+    std::fs::write(&original, "recreated since").unwrap();
+
+    let report = restore_session_with_options(&session_id, &RestoreOptions::default(), false)
+        .unwrap();
+    assert_eq!(report.restored_count, 0);
+    assert!(report.errors[0].contains("--conflict"), "{}", report.errors[0]);
+
+    let skip = RestoreOptions {
+        on_conflict: OnConflict::Skip,
+        ..Default::default()
+    };
+    let report = restore_session_with_options(&session_id, &skip, false).unwrap();
+    assert_eq!(report.skipped, vec![original.clone()]);
+    assert_eq!(report.remaining, 1);
+
+    let rename = RestoreOptions {
+        on_conflict: OnConflict::Rename,
+        ..Default::default()
+    };
+    let report = restore_session_with_options(&session_id, &rename, false).unwrap();
+    assert_eq!(report.restored_count, 1);
+    assert_eq!(std::fs::read_to_string(&original).unwrap(), "recreated since");
+    let renamed = dir.path().join("conflict_0.txt.restored");
+    assert!(std::fs::read_to_string(renamed).unwrap().contains("conflict"));
+    let _ = purge_session(&session_id);
+}
+
+#[test]
+fn test_overwrite_restore_keeps_the_existing_file_when_the_staged_copy_is_gone() {
+    let _lock = session_lock();
+    let dir = TempDir::new().unwrap();
+    let item = create_test_scan_item(dir.path(), "overwrite", 2);
+    let session_id = staged_session(&item, "test_overwrite");
+    let kept = dir.path().join("overwrite_0.txt");
+    let replaced = dir.path().join("overwrite_1.txt");
+    std::fs::write(&kept, "recreated since").unwrap();
+    std::fs::write(&replaced, "recreated since").unwrap();
+
+    let manifest = CleanManifest::load_from_session(&session_id).unwrap();
+    let lost = manifest
+        .items
+        .iter()
+        .find(|i| i.original_path == kept)
+        .unwrap();
+    std::fs::remove_file(lost.staged_path.as_ref().unwrap()).unwrap();
+
+    let overwrite = RestoreOptions {
+        on_conflict: OnConflict::Overwrite,
+        ..Default::default()
+    };
+    let report = restore_session_with_options(&session_id, &overwrite, false).unwrap();
+    assert_eq!(report.restored_count, 1);
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(std::fs::read_to_string(&kept).unwrap(), "recreated since");
+    assert!(std::fs::read_to_string(&replaced).unwrap().contains("overwrite"));
+
+    // Nothing left over from the swap
+    let names: Vec<_> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(names.len(), 2, "{:?}", names);
+    let _ = purge_session(&session_id);
+}
+
+/// Stage an item's files as a soft delete would, then "crash" before the
+/// manifest is saved
+fn interrupted_session(item: &ScanItem, profile: &str) -> journal::IncompleteSession {
+    let mut manifest = CleanManifest::new(profile, "soft_delete", 7);
+    let mut journal = Journal::create(&manifest).unwrap();
+    stage_files(
+        &[as_scanned(item)],
+        &mut manifest,
+        &mut journal,
+        StagingStore::Move,
+        false,
+        &CancelToken::new(),
+    )
+    .unwrap();
+    drop(journal);
+
+    journal::incomplete_sessions()
+        .unwrap()
+        .into_iter()
+        .find(|s| s.session_id == manifest.session_id)
+        .expect("the session is reported as interrupted")
+}
+
+#[test]
+fn test_interrupted_clean_can_be_rolled_back() {
+    let _lock = session_lock();
+    let dir = TempDir::new().unwrap();
+    let item = create_test_scan_item(dir.path(), "crashed", 3);
+    let session = interrupted_session(&item, "test_rollback");
+    assert_eq!(session.staged_count(), 3);
+    assert!(session.moves.iter().all(|m| m.state == MoveState::Staged));
+    assert!(!item.files[0].path.exists());
+
+    let report = journal::rollback(&session).unwrap();
+    assert_eq!(report.restored_count, 3);
+    assert_eq!(report.remaining, 0);
+    for file in &item.files {
+        assert!(file.path.exists());
+    }
+    assert!(journal::incomplete_sessions()
+        .unwrap()
+        .iter()
+        .all(|s| s.session_id != session.session_id));
+    let _ = purge_session(&session.session_id);
+}
+
+#[test]
+fn test_running_clean_is_not_offered_for_recovery() {
+    let _lock = session_lock();
+    let dir = TempDir::new().unwrap();
+    let item = create_test_scan_item(dir.path(), "running", 2);
+    let mut manifest = CleanManifest::new("test_running", "soft_delete", 7);
+    let mut journal = Journal::create(&manifest).unwrap();
+    stage_files(
+        &[as_scanned(&item)],
+        &mut manifest,
+        &mut journal,
+        StagingStore::Move,
+        false,
+        &CancelToken::new(),
+    )
+    .unwrap();
+
+    // Still holding the journal, as a clean in another process would be
+    let listed = |id: &str| {
+        journal::incomplete_sessions()
+            .unwrap()
+            .iter()
+            .any(|s| s.session_id == id)
+    };
+    assert!(!listed(&manifest.session_id));
+    assert!(Journal::create(&manifest).is_err());
+
+    drop(journal);
+    assert!(listed(&manifest.session_id));
+    let session = journal::incomplete_sessions()
+        .unwrap()
+        .into_iter()
+        .find(|s| s.session_id == manifest.session_id)
+        .unwrap();
+    journal::rollback(&session).unwrap();
+}
+
+#[test]
+fn test_interrupted_clean_can_be_finalized_and_undone() {
+    let _lock = session_lock();
+    let dir = TempDir::new().unwrap();
+    let item = create_test_scan_item(dir.path(), "finalized", 2);
+    let session = interrupted_session(&item, "test_finalize");
+
+    let manifest = journal::finalize(&session).unwrap();
+    assert_eq!(manifest.total_files, 2);
+    assert!(manifest.cancelled);
+
+    // Now an ordinary session
+    let loaded = CleanManifest::load_from_session(&session.session_id).unwrap();
+    assert_eq!(loaded.restorable_items().count(), 2);
+    let report =
+        restore_session_with_options(&session.session_id, &RestoreOptions::default(), false)
+            .unwrap();
+    assert_eq!(report.restored_count, 2);
+    assert!(item.files[1].path.exists());
+    let _ = purge_session(&session.session_id);
+}
+
+// ─── Dedup store ─────────────────────────────────────────────────────────────
+
+/// Soft-delete an item's files as session `id`, staging them with `store`
+fn stored_session(item: &ScanItem, id: &str, store: StagingStore) -> CleanManifest {
+    let mut manifest = CleanManifest::new("test_store", "soft_delete", 7);
+    manifest.session_id = id.to_string();
+    let mut journal = Journal::create(&manifest).unwrap();
+    stage_files(
+        &[as_scanned(item)],
+        &mut manifest,
+        &mut journal,
+        store,
+        false,
+        &CancelToken::new(),
+    )
+    .unwrap();
+    manifest.save().unwrap();
+    journal.finish().unwrap();
+    manifest
+}
+
+#[test]
+fn test_dedup_store_shares_verifies_and_sweeps_blobs() {
+    let _lock = session_lock();
+    let (dir_a, dir_b) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+    // Same names in different directories, so the same contents
+    let item_a = create_test_scan_item(dir_a.path(), "shared", 2);
+    let item_b = create_test_scan_item(dir_b.path(), "shared", 2);
+    let (id_a, id_b) = (
+        format!("dedup-{}-a", std::process::id()),
+        format!("dedup-{}-b", std::process::id()),
+    );
+
+    let a = stored_session(&item_a, &id_a, StagingStore::Dedup);
+    let b = stored_session(&item_b, &id_b, StagingStore::Dedup);
+    let blobs: Vec<_> = a
+        .items
+        .iter()
+        .map(|i| {
+            let hash = i.content_hash.clone().expect("stored by hash");
+            assert_eq!(i.staged_path.as_ref(), Some(&store::blob_path(&hash)));
+            store::blob_path(&hash)
+        })
+        .collect();
+    assert_eq!(
+        a.items.iter().map(|i| &i.content_hash).collect::<Vec<_>>(),
+        b.items.iter().map(|i| &i.content_hash).collect::<Vec<_>>()
+    );
+    assert!(!item_b.files[0].path.exists());
+
+    let report = verify_session(&id_a).unwrap();
+    assert_eq!((report.checked, report.verified), (2, 2));
+    assert!(report.is_ok());
+
+    // Restoring copies out, leaving the blobs for session b
+    let report = restore_session_with_options(&id_a, &RestoreOptions::default(), false).unwrap();
+    assert_eq!(report.restored_count, 2);
+    assert_eq!(
+        std::fs::read_to_string(&item_a.files[0].path).unwrap(),
+        "test content for file 0 in shared"
+    );
+    purge_session(&id_a).unwrap();
+    assert!(blobs.iter().all(|b| b.exists()));
+
+    // A damaged blob is reported, and never restored
+    std::fs::write(&blobs[1], "bit rot").unwrap();
+    let report = verify_session(&id_b).unwrap();
+    assert_eq!(report.corrupted, vec![item_b.files[1].path.clone()]);
+    let report = restore_session_with_options(&id_b, &RestoreOptions::default(), false).unwrap();
+    assert_eq!(report.restored_count, 1);
+    assert!(report.errors[0].contains("corrupted"), "{:?}", report.errors);
+    assert!(!item_b.files[1].path.exists());
+
+    // Nothing references the blobs once b is gone
+    purge_session(&id_b).unwrap();
+    assert!(blobs.iter().all(|b| !b.exists()));
+}
+
+// ─── Compressed staging ──────────────────────────────────────────────────────
+
+/// A log big enough to compress, and a photo that isn't worth it
+fn compressible_item(dir: &std::path::Path) -> ScanItem {
+    let mut item = create_test_scan_item(dir, "compressed", 0);
+    for (name, content) in [
+        ("app.log", "GET /index.html 200\n".repeat(5_000)),
+        ("photo.png", "not really a png".to_string()),
+    ] {
+        let path = dir.join(name);
+        std::fs::write(&path, &content).unwrap();
+        item.files.push(FileEntry::from_metadata(
+            path.clone(),
+            &std::fs::metadata(&path).unwrap(),
+        ));
+    }
+    item.file_count = 2;
+    item
+}
+
+#[test]
+fn test_compressed_staging_packs_and_restores_files() {
+    let _lock = session_lock();
+    let dir = TempDir::new().unwrap();
+    let item = compressible_item(dir.path());
+    let log = item.files[0].path.clone();
+    let original = std::fs::read(&log).unwrap();
+    let mtime = std::fs::metadata(&log).unwrap().modified().unwrap();
+    let id = format!("compress-{}", std::process::id());
+
+    let manifest = stored_session(&item, &id, StagingStore::Compress);
+    let (packed, photo) = (&manifest.items[0], &manifest.items[1]);
+    assert_eq!(packed.archive_entry.as_deref(), Some("000001"));
+    assert!(packed.staged_path.as_ref().unwrap().ends_with("archive.zip"));
+    assert!(packed.staged_bytes.unwrap() < packed.size_bytes / 10);
+    assert_eq!(photo.archive_entry, None, "already-compressed formats stay loose");
+    assert!(manifest.staged_bytes() < manifest.total_bytes);
+    assert!(!manifest.staging_files_dir().join("000001").exists());
+
+    assert!(verify_session(&id).unwrap().is_ok());
+    let report = restore_session_with_options(&id, &RestoreOptions::default(), false).unwrap();
+    assert_eq!(report.restored_count, 2);
+    assert_eq!(std::fs::read(&log).unwrap(), original);
+    let restored = std::fs::metadata(&log).unwrap().modified().unwrap();
+    let drift = restored.duration_since(mtime).unwrap_or_else(|e| e.duration());
+    assert!(drift.as_secs() <= 2, "mtime kept to zip's precision");
+    let _ = purge_session(&id);
+}
+
+#[test]
+fn test_interrupted_compressed_clean_rolls_back_from_the_archive() {
+    let _lock = session_lock();
+    let dir = TempDir::new().unwrap();
+    let item = compressible_item(dir.path());
+    let mut manifest = CleanManifest::new("test_compress_crash", "soft_delete", 7);
+    let mut journal = Journal::create(&manifest).unwrap();
+    stage_files(
+        &[as_scanned(&item)],
+        &mut manifest,
+        &mut journal,
+        StagingStore::Compress,
+        false,
+        &CancelToken::new(),
+    )
+    .unwrap();
+    drop(journal);
+
+    let session = journal::incomplete_sessions()
+        .unwrap()
+        .into_iter()
+        .find(|s| s.session_id == manifest.session_id)
+        .unwrap();
+    assert_eq!(session.staged_count(), 2);
+    assert_eq!(session.moves[0].item.archive_entry.as_deref(), Some("000001"));
+
+    let report = journal::rollback(&session).unwrap();
+    assert_eq!(report.restored_count, 2, "{:?}", report.errors);
+    assert!(item.files.iter().all(|f| f.path.exists()));
+}
@@ -0,0 +1,207 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+use tidymac::common::exclusions::ExclusionMatcher;
+use tidymac::scanner::targets::{Category, SafetyLevel, ScanTarget};
+use tidymac::watch::backend::{WatchBackend, WatchEvent};
+use tidymac::watch::poll::PollBackend;
+use tidymac::watch::state::{WatchState, WatchedTarget};
+use tidymac::watch::{self, WatchOptions};
+
+fn target(root: &Path) -> ScanTarget {
+    ScanTarget {
+        name: "Watched Cache".into(),
+        category: Category::UserCache,
+        paths: vec![root.display().to_string()],
+        safety: SafetyLevel::Safe,
+        reason: "test".into(),
+        recursive: true,
+        min_age_days: None,
+        trim: None,
+    }
+}
+
+fn wait_for(what: &str, mut check: impl FnMut() -> bool) {
+    let start = Instant::now();
+    while !check() {
+        assert!(start.elapsed() < Duration::from_secs(20), "timed out waiting for {}", what);
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+fn changed(events: &[WatchEvent], path: &Path) -> bool {
+    events
+        .iter()
+        .any(|e| matches!(e, WatchEvent::Changed { path: p, .. } if p == path))
+}
+
+// ─── Backend tests ────────────────────────────────────────────────────────────
+
+#[test]
+fn test_poll_backend_reports_changed_dirs() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path().join("root");
+    std::fs::create_dir_all(root.join("sub")).unwrap();
+
+    let mut backend = PollBackend::new(Duration::from_millis(10));
+    backend.watch_tree(&root, &ExclusionMatcher::empty()).unwrap();
+    assert!(backend.poll(Duration::ZERO).unwrap().is_empty());
+
+    std::fs::write(root.join("sub/new.bin"), b"data").unwrap();
+    std::fs::remove_dir(root.join("sub")).ok();
+    std::fs::create_dir_all(root.join("other")).unwrap();
+    let events = backend.poll(Duration::ZERO).unwrap();
+    assert!(changed(&events, &root.join("sub")));
+    assert!(changed(&events, &root.join("other")));
+}
+
+#[test]
+fn test_poll_backend_waits_for_its_interval_and_sees_files_grow() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path().join("root");
+    std::fs::create_dir_all(&root).unwrap();
+    let log = root.join("app.log");
+    std::fs::write(&log, "one\n").unwrap();
+
+    let mut backend = PollBackend::new(Duration::from_secs(3600));
+    backend.watch_tree(&root, &ExclusionMatcher::empty()).unwrap();
+
+    // Appending leaves the directory's mtime alone
+    let mut file = std::fs::OpenOptions::new().append(true).open(&log).unwrap();
+    std::io::Write::write_all(&mut file, b"two\n").unwrap();
+
+    let start = Instant::now();
+    assert!(backend.poll(Duration::from_millis(20)).unwrap().is_empty());
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    let events = backend.poll(Duration::ZERO).unwrap();
+    assert!(changed(&events, &log));
+    assert!(!changed(&events, &root));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_inotify_backend_reports_nested_files() {
+    use tidymac::watch::inotify::InotifyBackend;
+
+    let dir = TempDir::new().unwrap();
+    let root = dir.path().join("root");
+    std::fs::create_dir_all(root.join("a/b")).unwrap();
+
+    let mut backend = InotifyBackend::new().unwrap();
+    backend.watch_tree(&root, &ExclusionMatcher::empty()).unwrap();
+    std::fs::write(root.join("a/b/file.bin"), b"data").unwrap();
+
+    let mut events = Vec::new();
+    wait_for("inotify event", || {
+        events.extend(backend.poll(Duration::from_millis(100)).unwrap());
+        changed(&events, &root.join("a/b/file.bin"))
+    });
+}
+
+// ─── Daemon tests ─────────────────────────────────────────────────────────────
+
+#[test]
+fn test_watch_daemon_tracks_changes_and_restores() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path().join("cache");
+    std::fs::create_dir_all(root.join("old")).unwrap();
+    std::fs::write(root.join("old/keep.bin"), vec![1u8; 4096]).unwrap();
+    std::fs::write(root.join("old/drop.bin"), vec![1u8; 4096]).unwrap();
+
+    let profile = format!("__watch_test_{}__", std::process::id());
+    let targets = vec![target(&root)];
+    let key = tidymac::scanner::cache::target_key(&targets[0]);
+    let opts = WatchOptions {
+        profile_name: profile.clone(),
+        excludes: ExclusionMatcher::empty(),
+        rescan_interval: Duration::from_secs(3600),
+        stop: Arc::new(AtomicBool::new(false)),
+    };
+
+    let files_in_state = |path: &Path| {
+        WatchState::load(&profile)
+            .and_then(|s| s.item(&key).cloned())
+            .map(|item| item.files.iter().any(|f| f.path == path))
+            .unwrap_or(false)
+    };
+
+    let run_opts = opts.clone();
+    let run_targets = targets.clone();
+    let daemon = std::thread::spawn(move || {
+        let backend = Box::new(PollBackend::new(Duration::from_millis(50)));
+        watch::run(&run_targets, &run_opts, backend).unwrap()
+    });
+
+    wait_for("initial scan", || files_in_state(&root.join("old/keep.bin")));
+    assert!(WatchState::load(&profile).unwrap().is_live());
+
+    // Incremental updates: one file added in a new directory, one removed
+    std::fs::create_dir_all(root.join("new")).unwrap();
+    std::fs::write(root.join("new/added.bin"), vec![1u8; 4096]).unwrap();
+    std::fs::remove_file(root.join("old/drop.bin")).unwrap();
+    wait_for("incremental update", || {
+        files_in_state(&root.join("new/added.bin")) && !files_in_state(&root.join("old/drop.bin"))
+    });
+
+    opts.stop.store(true, Ordering::Relaxed);
+    let state = daemon.join().unwrap();
+    assert!(state.stopped);
+    assert!(!state.is_live());
+    let saved = &state.targets[0];
+    assert_eq!(saved.item.file_count, 2);
+    assert!(!saved.dirs.is_empty(), "clean shutdown records directory mtimes");
+
+    // Nothing changed while stopped, so a restart reuses the saved results
+    opts.stop.store(false, Ordering::Relaxed);
+    let run_opts = opts.clone();
+    let daemon = std::thread::spawn(move || {
+        let backend = Box::new(PollBackend::new(Duration::from_millis(50)));
+        watch::run(&targets, &run_opts, backend).unwrap()
+    });
+    wait_for("restart", || {
+        WatchState::load(&profile).map(|s| s.is_live()).unwrap_or(false)
+    });
+    opts.stop.store(true, Ordering::Relaxed);
+    let restarted = daemon.join().unwrap();
+    assert_eq!(restarted.targets[0].full_scan_at, saved.full_scan_at);
+    assert_eq!(restarted.targets[0].item.file_count, 2);
+
+    std::fs::remove_file(WatchState::state_path(&profile)).ok();
+    std::fs::remove_file(WatchState::heartbeat_path(&profile)).ok();
+}
+
+#[test]
+fn test_unwatched_targets_are_not_served_as_live() {
+    let dir = TempDir::new().unwrap();
+    let t = target(dir.path());
+    let key = tidymac::scanner::cache::target_key(&t);
+    let item = tidymac::scanner::walker::walk_target(&t, &ExclusionMatcher::empty()).unwrap();
+    let now = chrono::Utc::now();
+    let mut state = WatchState {
+        version: tidymac::watch::state::STATE_VERSION,
+        profile: "unwatched".into(),
+        pid: std::process::id(),
+        backend: "poll".into(),
+        excludes: Vec::new(),
+        started_at: now,
+        heartbeat_at: now,
+        stopped: false,
+        targets: vec![WatchedTarget {
+            key: key.clone(),
+            item,
+            synced_at: now,
+            full_scan_at: now,
+            pending: false,
+            watched: true,
+            dirs: Vec::new(),
+        }],
+    };
+    assert!(state.item(&key).is_some());
+
+    state.targets[0].watched = false;
+    assert!(state.item(&key).is_none());
+}